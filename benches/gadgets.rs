@@ -0,0 +1,56 @@
+// Proving and verifying time, and proof size, of PolyAddAir and PolyMulAir across N: cargo bench --bench gadgets
+// PolyAddAir has 89N+1 columns and O(N) constraints, while PolyMulAir has 6N-2 columns and O(N^2) terms in its 2N-1 constraints
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use p3_matrix::dense::RowMajorMatrix;
+use rand::{thread_rng, Rng};
+use verifiable_fhe_plonky3::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+use verifiable_fhe_plonky3::gadgets::config::{initialize_config, Val, ZkAir, ZkConfig};
+use verifiable_fhe_plonky3::gadgets::mul::{PolyMulAir, generate_polymul_trace};
+use verifiable_fhe_plonky3::io::{prove_air, serialize_proof, verify_air};
+use verifiable_fhe_plonky3::params::{N, P1};
+
+// Sizes benchmarked, up to the ring degree params::N
+const SIZES: [usize; 4] = [64, 256, 1024, N];
+
+fn random_poly(n: usize) -> Vec<u32> {
+    let mut rng = thread_rng();
+    (0..n).map(|_| rng.gen_range(0..P1)).collect()
+}
+
+// Benchmark prove_air() and verify_air() of `air` over `trace` at size n, and report the size of its proof
+fn bench_gadget<A: ZkAir>(c: &mut Criterion, zk_config: &ZkConfig, name: &str, n: usize, air: &A, trace: RowMajorMatrix<Val>) {
+    let proof = prove_air(zk_config, air, trace.clone());
+    let proof_bytes = serialize_proof(&proof).expect("proof serialization failed").len();
+    println!("{} N = {}: proof of {} bytes", name, n, proof_bytes);
+
+    c.benchmark_group(format!("prove/{}", name))
+        .sample_size(10)
+        .bench_with_input(BenchmarkId::from_parameter(n), &trace, |b, trace| {
+            b.iter_batched(|| trace.clone(), |trace| prove_air(zk_config, air, trace), BatchSize::LargeInput)
+        });
+
+    c.benchmark_group(format!("verify/{}", name))
+        .sample_size(10)
+        .bench_with_input(BenchmarkId::from_parameter(n), &proof, |b, proof| {
+            b.iter(|| verify_air(zk_config, air, proof).expect("verification failed"))
+        });
+}
+
+fn bench_add_vs_mul(c: &mut Criterion) {
+    let zk_config = initialize_config();
+    for n in SIZES {
+        let (a, b) = (random_poly(n), random_poly(n));
+
+        let air = PolyAddAir::with_n(a.clone(), b.clone(), P1, n).unwrap();
+        let trace = generate_polyadd_trace::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+        bench_gadget(c, &zk_config, "PolyAddAir", n, &air, trace);
+
+        let air = PolyMulAir::with_n(a.clone(), b.clone(), P1, n).unwrap();
+        let trace = generate_polymul_trace::<Val>(a, b, P1, n).unwrap();
+        bench_gadget(c, &zk_config, "PolyMulAir", n, &air, trace);
+    }
+}
+
+criterion_group!(benches, bench_add_vs_mul);
+criterion_main!(benches);
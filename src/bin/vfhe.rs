@@ -0,0 +1,159 @@
+/*
+Command line prover and verifier for the polynomial operation gadgets
+
+    vfhe prove  --op add|sub|mul --input a.json b.json --out proof.bin [--modulus M]
+    vfhe verify --op add|sub|mul --input a.json b.json --proof proof.bin --expected out.json [--modulus M]
+
+Polynomials are JSON arrays of coefficients, and the modulus defaults to params::P1.
+proof.bin only stores the operation next to the proof: the verifier passes the inputs and the modulus
+it expects, which the AIR pins, and verify checks the proof against the coefficients of out.json as public outputs.
+Exit codes: 0 on success, 1 when the proof does not verify, 2 on usage or input errors.
+*/
+use std::fs;
+use std::process::ExitCode;
+use serde::{Deserialize, Serialize};
+use p3_field::AbstractField;
+use p3_uni_stark::{prove, verify};
+use verifiable_fhe_plonky3::gadgets::config::{initialize_config, Challenger, Val, ZkAir};
+use verifiable_fhe_plonky3::gadgets::poly_op::PolynomialOpAir;
+use verifiable_fhe_plonky3::gadgets::registry::{BoxedAir, OPERATIONS};
+use verifiable_fhe_plonky3::io::ZkProof;
+use verifiable_fhe_plonky3::params::P1;
+
+// The proof and the operation it proves, which the verifier checks against its own inputs and expected output
+#[derive(Serialize, Deserialize)]
+struct ProofFile {
+    op: String,
+    proof: ZkProof,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("prove") => run_prove(&args[1..]),
+        Some("verify") => run_verify(&args[1..]),
+        _ => Err(usage()),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => {
+            eprintln!("verification failed");
+            ExitCode::from(1)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn usage() -> String {
+    let ops = OPERATIONS.join("|");
+    format!("usage:\n  vfhe prove --op {} --input a.json b.json --out proof.bin [--modulus M]\n  vfhe verify --op {} --input a.json b.json --proof proof.bin --expected out.json [--modulus M]", ops, ops)
+}
+
+// Values following `flag`, up to the next flag
+fn flag_values<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+    match args.iter().position(|arg| arg == flag) {
+        Some(i) => args[i+1..].iter().take_while(|arg| !arg.starts_with("--")).map(String::as_str).collect(),
+        None => vec![],
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Result<&'a str, String> {
+    match flag_values(args, flag).as_slice() {
+        [value] => Ok(value),
+        _ => Err(format!("expected exactly one value for {}\n{}", flag, usage())),
+    }
+}
+
+// Input polynomials read from the 2 files of --input
+fn read_inputs(args: &[String]) -> Result<(Vec<u32>, Vec<u32>), String> {
+    match flag_values(args, "--input").as_slice() {
+        [a, b] => Ok((read_polynomial(a)?, read_polynomial(b)?)),
+        _ => Err(format!("expected 2 input files for --input\n{}", usage())),
+    }
+}
+
+// Value of --modulus, params::P1 when it is not given
+fn read_modulus(args: &[String]) -> Result<u32, String> {
+    match flag_values(args, "--modulus").as_slice() {
+        [] => Ok(P1),
+        [m] => m.parse().map_err(|e| format!("invalid modulus {}: {}", m, e)),
+        _ => Err(format!("expected one value for --modulus\n{}", usage())),
+    }
+}
+
+fn read_polynomial(path: &str) -> Result<Vec<u32>, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    serde_json::from_str(&json).map_err(|e| format!("{} is not a JSON array of coefficients: {}", path, e))
+}
+
+fn check_op(op: &str) -> Result<(), String> {
+    if OPERATIONS.contains(&op) {
+        Ok(())
+    } else {
+        Err(format!("unsupported operation {}, expected one of {}", op, OPERATIONS.join(", ")))
+    }
+}
+
+// Prove `air` with its outputs as public values, returning the proof and the outputs
+fn prove_op<A: PolynomialOpAir + ZkAir>(air: &A) -> Result<(ZkProof, Vec<Val>), String> {
+    let zk_config = initialize_config();
+    let trace = air.generate_trace::<Val>().map_err(|e| e.to_string())?;
+    let public_values = air.public_outputs(&trace);
+    let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+    let proof = prove(&zk_config.config, air, &mut challenger, trace, &public_values);
+    Ok((proof, public_values))
+}
+
+fn verify_op<A: PolynomialOpAir + ZkAir>(air: &A, proof: &ZkProof, expected: &[u32]) -> bool {
+    if expected.len() != air.num_outputs() {
+        return false;
+    }
+    let zk_config = initialize_config();
+    let public_values: Vec<Val> = expected.iter().map(|&c| Val::from_wrapped_u32(c)).collect();
+    let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+    verify(&zk_config.config, air, &mut challenger, proof, &public_values).is_ok()
+}
+
+fn run_prove(args: &[String]) -> Result<bool, String> {
+    let op = flag_value(args, "--op")?;
+    check_op(op)?;
+    let out_path = flag_value(args, "--out")?;
+    let modulus = read_modulus(args)?;
+    let (a, b) = read_inputs(args)?;
+
+    let n = a.len();
+    let air = BoxedAir::from_name(op, a, b, modulus, n).map_err(|e| e.to_string())?;
+    let (proof, out) = prove_op(&air)?;
+
+    let file = ProofFile { op: op.to_string(), proof };
+    let bytes = bincode::serialize(&file).map_err(|e| format!("failed to encode the proof: {}", e))?;
+    fs::write(out_path, bytes).map_err(|e| format!("failed to write {}: {}", out_path, e))?;
+
+    // print the proven output, e.g. to be saved as the expected output of verify
+    let out: Vec<String> = out.iter().map(|c| c.to_string()).collect();
+    println!("[{}]", out.join(","));
+    Ok(true)
+}
+
+fn run_verify(args: &[String]) -> Result<bool, String> {
+    let op = flag_value(args, "--op")?;
+    check_op(op)?;
+    let proof_path = flag_value(args, "--proof")?;
+    let modulus = read_modulus(args)?;
+    let (a, b) = read_inputs(args)?;
+    let expected = read_polynomial(flag_value(args, "--expected")?)?;
+
+    let bytes = fs::read(proof_path).map_err(|e| format!("failed to read {}: {}", proof_path, e))?;
+    let file: ProofFile = bincode::deserialize(&bytes).map_err(|e| format!("{} is not a proof file: {}", proof_path, e))?;
+    if file.op != op {
+        return Err(format!("{} is a proof of {}, not {}", proof_path, file.op, op));
+    }
+
+    // the AIR of the verifier's own inputs and modulus, so a proof of other inputs does not verify
+    let n = a.len();
+    let air = BoxedAir::from_name(op, a, b, modulus, n).map_err(|e| e.to_string())?;
+    Ok(verify_op(&air, &file.proof, &expected))
+}
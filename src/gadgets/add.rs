@@ -1,56 +1,54 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use std::ops::Range;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
 use p3_field::{AbstractField, Field};
-use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
-use crate:: params::N;
-use std::ops::{Add, Sub};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::center::uncentered;
+use crate::gadgets::error::{check_air_inputs, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, eval_carry_chain, range_checked_limbs, MUL_CARRY_BITS};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_check, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{generate_elementwise_trace, pad_trace_to, trace_height};
+use crate::params::{FheParams, N};
 
 // Define AIR constraint inputs
+// The fields are only set through the checked constructors outside of the crate
 pub struct PolyAddAir {
-	pub a: Vec<u32>,
-	pub b: Vec<u32>,
-	pub modulus: u32
+    pub(crate) a: Vec<u32>,
+    pub(crate) b: Vec<u32>,
+    pub(crate) modulus: u32,
+    pub(crate) n: usize
 }
 
-/*
-Polynomial Addition Air
-Input:
-- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
-- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
-- mod: FHE ciphertext modulus
-Output:
-- out = out[0] + out[1] * X + ... + out[2N-2] * X^{2N-2}
+impl PolyAddAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Result<Self, GadgetError> {
+        Self::with_n(a, b, modulus, N)
+    }
 
-Note:
-- PolyAddAir does not have a state transition. Values required for constraints are all stored in one row.
-- While output polynomial `out` is calculated manually by generate_polyadd_trace(), we prove that this addition was done correctly, by enforcing a constraint such that a(x)+b(x) === out(x)  at x = [0..2N-1) based on Lagrange polynomial interpolation.
-*/
-impl<F: Field> BaseAir<F> for PolyAddAir {
-    // Air Table looks like this
-    // row:[      a: N      ][      b: N      ][mod:1][      out(x): N      ]
-    //     ^------------------inputs-----------------^^-calculated by generate_polyadd_trace
-    //     [0..............................................................0]
-    //     [0..............................................................0]
-    //     [0..............................................................0]
-    fn width(&self) -> usize {
-        3*N+1
+    // Construct the AIR with n coefficients, checking that a and b have n coefficients and that the modulus is nonzero,
+    // and below 2^31 for out to be range checked
+    pub fn with_n(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        check_air_inputs(&[&a, &b], n, modulus)?;
+        check_range_modulus(modulus)?;
+        Ok(Self { a, b, modulus, n })
     }
-}
 
-// Define constraints
-impl<AB: AirBuilder> Air<AB> for PolyAddAir {
-    fn eval(&self, builder: &mut AB) {
-        let main = builder.main();
-        let row = main.row_slice(0);
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`, checked as in with_n()
+    pub fn with_params(a: Vec<u32>, b: Vec<u32>, params: &FheParams, channel: usize) -> Result<Self, GadgetError> {
+        Self::with_n(a, b, params.modulus(channel)?, params.n)
+    }
 
-        // Enforce self.a and self.b as 2 input polynomials
-		for i in 0..N {
-			builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
-			builder.when_first_row().assert_eq(row[i+N], AB::Expr::from_canonical_u32(self.b[i]));
-		}
+    // Enforce out = (a + b) % mod over `row`, where inputs_low[i] = a[i]_0 + b[i]_0 is the sum of the lowest 8-bits limbs
+    // of the inputs: the ones of the constants in PolyAddAir, and of the range checked inputs in CommittedAddAir
+    pub(crate) fn eval_sum<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var], inputs_low: Vec<AB::Expr>) {
+        let n = self.n;
+        let (mod_col, out, q) = (2*n, 2*n+1, 3*n+1);
+        let (out_range, low_carry_bits) = (4*n+1, 4*n+1 + RANGE_CHECK_WIDTH*n);
 
         // Enforce self.modulus as mod
-        builder.when_first_row().assert_eq(row[2*N], AB::Expr::from_canonical_u32(self.modulus));
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
 
         /*
         We want to ensure a[i] + b[i]) === out[i] mod p
@@ -79,37 +77,193 @@ impl<AB: AirBuilder> Air<AB> for PolyAddAir {
         2) (3 + 4) % 7 evaluates to 0 === (1 * 5 + 2) % 7 evaluates to 0 (mod 7)
         */
 
+        // Enforce 2) with q_1 = q_2 = q[i] a bit, and 1) with t = 8 over the lowest limbs once out[i] is range checked into [0, mod):
+        // with a u32 a[i] and b[i], both sides differ by less than 2^33 < 2^8 * n, so they are equal once 1) and 2) hold
+        let sums = (0..n).map(|i| row[i] + row[i+n]).collect();
+        let quotients = (0..n).map(|i| row[q+i].into()).collect();
+        eval_reduced_sums(&mut builder.when_first_row(), sums, inputs_low, quotients, &row[out..out+n], &row[out_range..low_carry_bits + MUL_CARRY_BITS*n], self.modulus);
+    }
+}
+
+// Enforce lhs === q * mod + out for a bit q, with out range checked into [0, mod) by the RANGE_CHECK_WIDTH columns of out_range,
+// and the identity mod 2^8 over the lowest 8-bits limbs with a single offset carry in the MUL_CARRY_BITS columns of low_carry_bits:
+//     lhs_low - q * mod_0 - out_0 === 2^8 * c
+// where lhs_low === lhs mod 2^8 is lhs over the lowest limbs of its terms. As in PolyAddAir::eval_sum(), both give
+// lhs = q * mod + out over the integers when lhs is the sum of a few range checked values and constants.
+// Gadgets padded with zero rows call it with a builder gated on the first row, as eval_range_checks() does.
+pub(crate) fn eval_reduced_sum<AB: AirBuilder>(builder: &mut AB, lhs: AB::Expr, lhs_low: AB::Expr, q: AB::Expr, out: AB::Var, out_range: &[AB::Var], low_carry_bits: &[AB::Var], modulus: u32) {
+    builder.assert_bool(q.clone());
+    builder.assert_eq(lhs, q.clone() * AB::F::from_canonical_u32(modulus) + out);
+
+    eval_range_check(builder, out.into(), &out_range[..RANGE_CHECK_BITS], &out_range[RANGE_CHECK_BITS..RANGE_CHECK_WIDTH], modulus);
+
+    let mask = (1 << LIMB_BITS) - 1;
+    let out_low = reduced_low::<AB>(out_range);
+    eval_carry_chain(builder, vec![lhs_low - q * AB::F::from_canonical_u32(modulus & mask) - out_low], &low_carry_bits[..MUL_CARRY_BITS]);
+}
+
+// eval_reduced_sum() of the sums i = [0..out.len()), whose witness holds the out_range blocks of every sum, then their low_carry_bits,
+// as assigned by reduced_sums_witness()
+pub(crate) fn eval_reduced_sums<AB: AirBuilder>(builder: &mut AB, lhs: Vec<AB::Expr>, lhs_low: Vec<AB::Expr>, q: Vec<AB::Expr>, out: &[AB::Var], witness: &[AB::Var], modulus: u32) {
+    let low_carry_bits = RANGE_CHECK_WIDTH*out.len();
+    for (i, ((lhs, lhs_low), q)) in lhs.into_iter().zip(lhs_low).zip(q).enumerate() {
+        let block = i*RANGE_CHECK_WIDTH;
+        let bits = low_carry_bits + i*MUL_CARRY_BITS;
+        eval_reduced_sum(builder, lhs, lhs_low, q, out[i], &witness[block..block + RANGE_CHECK_WIDTH], &witness[bits..bits + MUL_CARRY_BITS], modulus);
+    }
+}
+
+// Columns of the witness of eval_reduced_sums() for `count` sums
+pub(crate) fn reduced_sums_width(count: usize) -> usize {
+    (RANGE_CHECK_WIDTH + MUL_CARRY_BITS)*count
+}
+
+// The lowest 8-bits limb of a value, read from its range check block
+pub(crate) fn reduced_low<AB: AirBuilder>(range: &[AB::Var]) -> AB::Expr {
+    range_checked_limbs::<AB>(&range[..RANGE_CHECK_BITS])[0].clone()
+}
+
+// Assign the range check of out and the low limb carry of eval_reduced_sum() to out_range and low_carry_bits,
+// where lhs_low is computed over the same limbs as in the AIR
+pub(crate) fn assign_reduced_sum<F: Field>(out_range: &mut [F], low_carry_bits: &mut [F], lhs_low: i64, q: bool, out: u32, modulus: u32) {
+    let mask = (1 << LIMB_BITS) - 1;
+    assign_range_check(out_range, out, modulus);
+    assign_carry_chain(low_carry_bits, &[lhs_low - q as i64 * (modulus & mask) as i64 - (out & mask) as i64]);
+}
+
+// The witness of eval_reduced_sums(): the out_range blocks of the sums i = [0..out.len()), then their low_carry_bits
+pub(crate) fn reduced_sums_witness<F: Field>(lhs_low: &[i64], q: &[bool], out: &[u32], modulus: u32) -> Vec<F> {
+    let count = out.len();
+    let mut values = vec![F::zero(); reduced_sums_width(count)];
+    let (out_range, low_carry_bits) = values.split_at_mut(RANGE_CHECK_WIDTH*count);
+    for i in 0..count {
+        assign_reduced_sum(&mut out_range[i*RANGE_CHECK_WIDTH..(i+1)*RANGE_CHECK_WIDTH], &mut low_carry_bits[i*MUL_CARRY_BITS..(i+1)*MUL_CARRY_BITS],
+            lhs_low[i], q[i], out[i], modulus);
+    }
+    values
+}
+
+/*
+Polynomial Addition Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[2N-2] * X^{2N-2}
+
+Note:
+- PolyAddAir does not have a state transition. Values required for constraints are all stored in one row.
+- While output polynomial `out` is calculated manually by generate_polyadd_trace(), we prove that this addition was done correctly,
+by enforcing a[i] + b[i] === q[i] * mod + out[i] for each coefficient, with a quotient bit q[i].
+- out[i] is range checked into [0, mod) and the sum is also enforced mod 2^8 over the lowest 8-bits limbs, see eval_sum().
+*/
+impl<F: Field> BaseAir<F> for PolyAddAir {
+    // Air Table looks like this
+    // row:[  a: N  ][  b: N  ][mod:1][ out(x): N ][ q: N ][ out_range: 62N ][ low_carry_bits: 23N ]
+    //     ^------------inputs---------^^---------------calculated by generate_polyadd_trace--------------^
+    //     [0......................................................................................0]
+    //     [0......................................................................................0]
+    //     [0......................................................................................0]
+    fn width(&self) -> usize {
+        polyadd_width(self.n)
+    }
+}
+
+fn polyadd_width(n: usize) -> usize {
+    4*n+1 + reduced_sums_width(n)
+}
+
+impl GadgetLayout for PolyAddAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+            .push("q", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PolyAddAir {
+    fn eval(&self, builder: &mut AB) {
+        self.eval_poly_op(builder);
+    }
+}
+
+impl PolynomialOpAir for PolyAddAir {
+    fn a(&self) -> &[u32] {
+        &self.a
+    }
+
+    fn b(&self) -> &[u32] {
+        &self.b
+    }
+
+    fn modulus(&self) -> u32 {
+        self.modulus
+    }
+
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn output_columns(&self) -> Range<usize> {
+        2*self.n+1..3*self.n+1
+    }
+
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        // The lowest limbs of the inputs are the ones of the constants they are pinned to
+        let mask = (1 << LIMB_BITS) - 1;
+        let inputs_low = (0..self.n).map(|i| AB::Expr::from_canonical_u32((self.a[i] & mask) + (self.b[i] & mask))).collect();
+        self.eval_sum(builder, row, inputs_low);
+    }
+
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError> {
+        generate_polyadd_trace(self.a.clone(), self.b.clone(), self.modulus, self.n)
     }
 }
 
 // Define a function to generate execution trace
-pub fn generate_polyadd_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32) -> RowMajorMatrix<F> {
-    let mut values: Vec<F>= Vec::with_capacity(4*(3*N+1)); // 4 is the minimum number of rows required
-
-	// Add input polynomials to values vector
-	for i in 0..N {
-        println!("a[{}]: {}", i, a[i]);
-		values.push(F::from_canonical_u32(a[i]));
-	}
-	for i in 0..N {
-        println!("b[{}]: {}", i, b[i]);
-		values.push(F::from_canonical_u32(b[i]));
-	}
-    // Add modulus to values vector
-    values.push(F::from_canonical_u32(modulus));
-
-	// Add the 2 polynomials and push it to values vector
-	for i in 0..N {
-		values.push(F::from_canonical_u32((a[i] + b[i]) % modulus));
-        println!("out[{}]: {}", i, (a[i] + b[i]) % modulus);
-	}
-
-	// Fill in the rest of the slots (last 3 rows) with 0
-	for _ in 0..3*(3*N+1) {
-		values.push(F::zero());
-	}
-    RowMajorMatrix::new(values, 3*N+1)
+pub fn generate_polyadd_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    generate_polyadd_trace_with_height(a, b, modulus, n, trace_height(1))
+}
+
+// generate_polyadd_trace() padded with zero rows up to `height`, a power of two of at least MIN_TRACE_HEIGHT
+pub fn generate_polyadd_trace_with_height<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize, height: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_range_modulus(modulus)?;
+
+    // With a zero operand, out is the other operand as is: its coefficients are already reduced once checked,
+    // and 0 + b[i] = 0 * mod + b[i] is the honest witness of the unchanged constraint
+    let zero_operand = a.iter().all(|&c| c == 0) || b.iter().all(|&c| c == 0);
+
+    // u64 keeps a[i] + b[i] from overflowing for 32-bits moduli
+    let elementwise = generate_elementwise_trace::<F>(a.clone(), b.clone(), modulus, n, move |x, y, m| if zero_operand { x + y } else { (x + y) % m as u64 })?;
+    let out: Vec<u32> = a.iter().zip(&b).map(|(&x, &y)| ((x as u64 + y as u64) % modulus as u64) as u32).collect();
+    let q: Vec<bool> = a.iter().zip(&b).map(|(&x, &y)| x as u64 + y as u64 >= modulus as u64).collect();
+
+    let mut values = elementwise.values;
+    values.truncate(3*n+1);
+    values.extend(polyadd_witness::<F>(&a, &b, &out, &q, modulus));
+    pad_trace_to(values, polyadd_width(n), height)
+}
+
+// Columns of the sum after out: the quotient bits q, the range checks of out and the carries of the low limb sums
+fn polyadd_witness<F: Field>(a: &[u32], b: &[u32], out: &[u32], q: &[bool], modulus: u32) -> Vec<F> {
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = a.iter().zip(b).map(|(&x, &y)| (x & mask) as i64 + (y & mask) as i64).collect();
+    let mut values: Vec<F> = q.iter().map(|&c| F::from_bool(c)).collect();
+    values.extend(reduced_sums_witness::<F>(&low, q, out, modulus));
+    values
+}
 
+// generate_polyadd_trace() of polynomials with signed coefficients in (-mod/2, mod/2], e.g. noise or messages centered at 0,
+// which are mapped to their representatives in [0, mod) first; the AIR takes the same uncentered() representatives
+pub fn generate_polyadd_trace_signed<F: Field>(a: Vec<i64>, b: Vec<i64>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    generate_polyadd_trace(uncentered(&a, modulus)?, uncentered(&b, modulus)?, modulus, n)
 }
 
 #[cfg(test)]
@@ -121,8 +275,14 @@ mod tests {
     use rand::{thread_rng, Rng};
     use p3_challenger::{HashChallenger, SerializingChallenger32};
     use p3_uni_stark::{prove, verify};
-    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
-    use crate::params::P1;
+    use p3_matrix::Matrix;
+    use p3_field::PrimeField32;
+    use crate::gadgets::center::centered;
+    use crate::gadgets::config::{goldilocks, initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::range_check::MAX_RANGE_CHECK_MODULUS;
+    use crate::gadgets::reference;
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::params::{ParamError, P1, P2, P3};
 
     #[test]
     fn test_poly_add() -> Result<(), impl Debug> {
@@ -139,9 +299,9 @@ mod tests {
             rng.gen_range(0..P1)
         }).collect();
 
-        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1 };
+        let air = PolyAddAir::new(random_poly1.clone(), random_poly2.clone(), P1).unwrap();
 
-        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1);
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, N).unwrap();
 
         let mut challenger: SerializingChallenger32<Mersenne31, HashChallenger<u8, Keccak256Hash, 32>> = Challenger::from_hasher(vec![], byte_hash);
         let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
@@ -149,5 +309,283 @@ mod tests {
         let mut challenger = Challenger::from_hasher(vec![], byte_hash);
         verify(&config, &air, &mut challenger, &proof, &vec![])
     }
-}
 
+    #[test]
+    fn test_poly_add_runtime_n() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let mut rng = thread_rng();
+        for n in [4, 16, 256] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| {
+                rng.gen_range(0..P1)
+            }).collect();
+
+            let random_poly2: Vec<u32> = (0..n).map(|_| {
+                rng.gen_range(0..P1)
+            }).collect();
+
+            let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+
+            let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_poly_add_with_height() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+
+        // the same AIR proves the sum at two padded heights, and rejects a height that is not a power of two
+        for height in [8, 32] {
+            let trace = generate_polyadd_trace_with_height::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n, height).unwrap();
+            assert_eq!(trace.height(), height);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+        assert_eq!(
+            generate_polyadd_trace_with_height::<Val>(random_poly1, random_poly2, P1, n, 6).unwrap_err(),
+            GadgetError::InvalidTraceHeight { height: 6, min: 4 }
+        );
+    }
+
+    #[test]
+    fn test_poly_add_zero_and_monomial_operands() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // a zero operand takes the fast path, a monomial (P1-1) * X^2 the general one with a wrap-around at X^2
+        let mut monomial = vec![0u32; n];
+        monomial[2] = P1 - 1;
+
+        for operand in [vec![0u32; n], monomial] {
+            for (a, b) in [(operand.clone(), random_poly.clone()), (random_poly.clone(), operand.clone())] {
+                let air = PolyAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+                let trace = generate_polyadd_trace::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+
+                let row = trace.row_slice(0);
+                for i in 0..n {
+                    let expected = (a[i] as u64 + b[i] as u64) % P1 as u64;
+                    assert_eq!(row[i+2*n+1], Val::from_canonical_u64(expected));
+                }
+                drop(row);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+            }
+        }
+    }
+
+    #[test]
+    fn test_poly_add_wide_modulus() {
+        // a 32-bits prime modulus above 2^31: (m-1) + (m-1) overflows u32.
+        // Goldilocks holds every such coefficient canonically, unlike Mersenne31.
+        let modulus: u32 = 4294967291;
+        let add = |x: u64, y: u64, m: u32| (x + y) % m as u64;
+        let trace = generate_elementwise_trace::<goldilocks::Val>(vec![modulus - 1; 4], vec![modulus - 1; 4], modulus, 4, add).unwrap();
+
+        // out[i] = 2 * (m-1) mod m = m-2
+        let row = trace.row_slice(0);
+        for i in 0..4 {
+            assert_eq!(row[i+2*4+1], goldilocks::Val::from_canonical_u32(modulus - 2));
+        }
+        drop(row);
+
+        // but out cannot be range checked into [0, m), so PolyAddAir does not take such a modulus
+        let too_large = GadgetError::ModulusTooLarge { modulus, max: MAX_RANGE_CHECK_MODULUS };
+        assert_eq!(generate_polyadd_trace::<goldilocks::Val>(vec![modulus - 1; 4], vec![modulus - 1; 4], modulus, 4).unwrap_err(), too_large);
+        assert_eq!(PolyAddAir::with_n(vec![0; 4], vec![0; 4], modulus, 4).err(), Some(too_large));
+    }
+
+    #[test]
+    fn test_poly_add_forged_quotient() {
+        // (P1-1) + (P1-1) is above n, and 1 + 2 below 2 * P1 - n: the other quotient bit with out shifted by n
+        // still holds mod n with out in [0, P1), but not mod 2^8
+        let order = Val::ORDER_U32 as u64;
+        for (x, y, q) in [(P1 - 1, P1 - 1, false), (1, 2, true)] {
+            let (a, b) = (vec![x; 4], vec![y; 4]);
+            let air = PolyAddAir::with_n(a.clone(), b.clone(), P1, 4).unwrap();
+            let forged = (x as u64 + y as u64 + order - q as u64 * P1 as u64) % order;
+            assert!(forged < P1 as u64);
+
+            let mut values: Vec<Val> = a.iter().chain(&b).chain(&[P1]).map(|&c| Val::from_canonical_u32(c)).collect();
+            let (out, quotients) = (vec![forged as u32; 4], vec![q; 4]);
+            values.extend(out.iter().map(|&c| Val::from_canonical_u32(c)));
+            values.extend(polyadd_witness::<Val>(&a, &b, &out, &quotients, P1));
+            assert_rejected(&air, pad_trace_to(values, polyadd_width(4), 4).unwrap(), &format!("a forged q = {}", q));
+        }
+    }
+
+    #[test]
+    fn test_poly_add_invalid_inputs() {
+        let poly: Vec<u32> = vec![1; 4];
+
+        // shorter and longer than n
+        assert_eq!(
+            generate_polyadd_trace::<Val>(vec![1; 3], poly.clone(), P1, 4).unwrap_err(),
+            GadgetError::LengthMismatch { expected: 4, actual: 3 }
+        );
+        assert_eq!(
+            generate_polyadd_trace::<Val>(poly.clone(), vec![1; 5], P1, 4).unwrap_err(),
+            GadgetError::LengthMismatch { expected: 4, actual: 5 }
+        );
+
+        // a coefficient equal to the modulus is out of range
+        assert_eq!(
+            generate_polyadd_trace::<Val>(poly.clone(), vec![0, 0, P1, 0], P1, 4).unwrap_err(),
+            GadgetError::CoefficientOutOfRange { index: 2, value: P1, modulus: P1 }
+        );
+    }
+
+    #[test]
+    fn test_poly_add_checked_constructor() {
+        let air = PolyAddAir::with_n(vec![1, 2, 3, 4], vec![5, 6, 7, 8], P1, 4).unwrap();
+        assert_eq!((air.n, air.modulus), (4, P1));
+
+        // a or b without n coefficients, a zero modulus, and no coefficient at all
+        assert_eq!(PolyAddAir::with_n(vec![1, 2, 3], vec![5, 6, 7, 8], P1, 4).err(), Some(GadgetError::LengthMismatch { expected: 4, actual: 3 }));
+        assert_eq!(PolyAddAir::with_n(vec![1, 2, 3, 4], vec![5; 5], P1, 4).err(), Some(GadgetError::LengthMismatch { expected: 4, actual: 5 }));
+        assert_eq!(PolyAddAir::with_n(vec![1, 2, 3, 4], vec![5, 6, 7, 8], 0, 4).err(), Some(GadgetError::ZeroModulus));
+        assert_eq!(PolyAddAir::with_n(vec![], vec![], P1, 0).err(), Some(GadgetError::EmptyPolynomial));
+
+        // new() expects params::N coefficients
+        assert_eq!(PolyAddAir::new(vec![0; 4], vec![0; 4], P1).err(), Some(GadgetError::LengthMismatch { expected: N, actual: 4 }));
+        assert!(PolyAddAir::new(vec![0; N], vec![0; N], P1).is_ok());
+    }
+
+    #[test]
+    fn test_poly_add_with_params() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 2 parameter sets in one process: N = 8 over P1, and N = 16 over (P2, 12289)
+        let small = FheParams::new(8, vec![P1]).unwrap();
+        let large = FheParams::new(16, vec![P2, 12289]).unwrap();
+
+        let mut rng = thread_rng();
+        for (params, channel) in [(&small, 0), (&large, 0), (&large, 1)] {
+            let modulus = params.modulus(channel).unwrap();
+            let random_poly1: Vec<u32> = (0..params.n).map(|_| rng.gen_range(0..modulus)).collect();
+            let random_poly2: Vec<u32> = (0..params.n).map(|_| rng.gen_range(0..modulus)).collect();
+
+            let air = PolyAddAir::with_params(random_poly1, random_poly2, params, channel).unwrap();
+            assert_eq!((air.n, air.modulus), (params.n, modulus));
+            let trace = air.generate_trace::<Val>().unwrap();
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+
+        // the default set is the one of the N and P1, P2, P3 constants
+        assert_eq!(FheParams::default(), FheParams::new(N, vec![P1, P2, P3]).unwrap());
+        assert_eq!(PolyAddAir::with_params(vec![0; 8], vec![0; 8], &small, 1).err(), Some(GadgetError::Params(ParamError::ChannelOutOfRange { channel: 1, channels: 1 })));
+        // the inputs are checked against the N of the set, as in with_n()
+        assert_eq!(PolyAddAir::with_params(vec![0; 8], vec![0; 16], &small, 0).err(), Some(GadgetError::LengthMismatch { expected: 8, actual: 16 }));
+        assert_eq!(FheParams::new(0, vec![P1]), Err(ParamError::EmptyParams));
+        assert_eq!(FheParams::new(8, vec![P1, 1 << 31]), Err(ParamError::InvalidModulus { modulus: 1 << 31 }));
+    }
+
+    #[test]
+    fn test_poly_add_signed() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // signed coefficients in (-P1/2, P1/2], including both ends and negative ones
+        let n = 16;
+        let half = (P1 / 2) as i64;
+        let mut rng = thread_rng();
+        let mut a: Vec<i64> = (0..n).map(|_| rng.gen_range(-half..=half)).collect();
+        let b: Vec<i64> = (0..n).map(|_| rng.gen_range(-half..=half)).collect();
+        (a[0], a[1], a[2]) = (-1, -half, half);
+
+        // out matches the reference sum of the representatives in [0, mod), and centers back to a + b mod P1
+        let (a_u, b_u) = (uncentered(&a, P1).unwrap(), uncentered(&b, P1).unwrap());
+        let air = PolyAddAir { a:a_u.clone(), b:b_u.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace_signed::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+        let out: Vec<u32> = trace.row_slice(0)[air.output_columns()].iter().map(|x| x.as_canonical_u32()).collect();
+        let widen = |poly: &[u32]| poly.iter().map(|&c| c as u128).collect::<Vec<_>>();
+        assert_eq!(widen(&out), reference::add(&widen(&a_u), &widen(&b_u), P1 as u128));
+        for i in 0..n {
+            assert_eq!(centered(out[i], P1), centered(((a[i] + b[i]).rem_euclid(P1 as i64)) as u32, P1));
+        }
+        assert_eq!(centered(a_u[0], P1), -1);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        // P1/2 + 1 and -(P1/2) - 1 are just outside of (-P1/2, P1/2]
+        assert_eq!(
+            generate_polyadd_trace_signed::<Val>(vec![half + 1; n], b.clone(), P1, n).unwrap_err(),
+            GadgetError::SignedCoefficientOutOfRange { index: 0, value: half + 1, modulus: P1 }
+        );
+        assert_eq!(
+            generate_polyadd_trace_signed::<Val>(a, vec![-half - 1; n], P1, n).unwrap_err(),
+            GadgetError::SignedCoefficientOutOfRange { index: 0, value: -half - 1, modulus: P1 }
+        );
+    }
+
+    #[test]
+    fn test_poly_add_elementwise_trace_unchanged() {
+        // the hand-written trace generation that generate_polyadd_trace used before generate_elementwise_trace
+        fn reference_trace(a: &[u32], b: &[u32], modulus: u32, n: usize) -> RowMajorMatrix<Val> {
+            let mut values: Vec<Val> = Vec::with_capacity(4*(3*n+1));
+            for i in 0..n {
+                values.push(Val::from_canonical_u32(a[i]));
+            }
+            for i in 0..n {
+                values.push(Val::from_canonical_u32(b[i]));
+            }
+            values.push(Val::from_canonical_u32(modulus));
+            for i in 0..n {
+                values.push(Val::from_canonical_u32(((a[i] as u64 + b[i] as u64) % modulus as u64) as u32));
+            }
+            for _ in 0..3*(3*n+1) {
+                values.push(Val::zero());
+            }
+            RowMajorMatrix::new(values, 3*n+1)
+        }
+
+        let mut rng = thread_rng();
+        for n in [1, 4, 256] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+            // the first 3N+1 columns are unchanged, and followed by the witness of the range checks
+            let trace = generate_polyadd_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+            let expected = reference_trace(&random_poly1, &random_poly2, P1, n);
+            assert_eq!((trace.width(), trace.height()), (polyadd_width(n), expected.height()));
+            for r in 0..trace.height() {
+                assert_eq!(&trace.row_slice(r)[..3*n+1], &*expected.row_slice(r));
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace, negacyclic_coeffs, negacyclic_layout, negacyclic_reduced, negacyclic_width};
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct AddThenMulAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub c: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl AddThenMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, c: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, c, modulus, n: N }
+    }
+
+    // Negacyclic product tmp * c, with tmp = a + b computed on the host
+    fn product(&self) -> NegacyclicMulAir {
+        NegacyclicMulAir { a: add_mod(&self.a, &self.b, self.modulus), b: self.c.clone(), modulus: self.modulus, n: self.n }
+    }
+}
+
+/*
+Add-then-Multiply Air
+Input:
+- a, b, c = c[0] + c[1] * X + ... + c[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- tmp = (a + b) % mod: intermediate sum
+- out = tmp * c in Z_mod[X]/(X^N+1)
+
+Note:
+- The row is the composition of the sum, with a carry bit per coefficient as in CiphertextAddAir:
+    a[i] + b[i] === carry[i] * mod + tmp[i]
+and a NegacyclicMulAir of (tmp, c) embedded through its eval_row(), as in CtMulAir.
+- a[i] + b[i] is above n for large moduli, so the sum goes through eval_reduced_sum() as in PolyAddAir:
+tmp is range checked into [0, mod) and the sum is also enforced mod 2^8 over the lowest limbs of the constant inputs.
+- The a input of the product is constrained equal to tmp on the first row, so the product uses the proven sum
+and (a + b) * c is proven in a single trace: tmp is neither exposed nor proven again in a second proof.
+*/
+impl<F: Field> BaseAir<F> for AddThenMulAir {
+    // Air Table looks like this
+    // row:[ a: N ][ b: N ][ tmp: N ][ carry: N ][ tmp_range: 62N ][ low_carry_bits: 23N ][ NegacyclicMulAir tmp*c ]
+    //     ^-----inputs-----^^-----------------------calculated by generate_add_then_mul_trace------------------------^
+    //     [0...................................................................................................0]
+    //     [0...................................................................................................0]
+    //     [0...................................................................................................0]
+    fn width(&self) -> usize {
+        add_then_mul_product(self.n) + negacyclic_width(self.n)
+    }
+}
+
+impl GadgetLayout for AddThenMulAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("tmp", self.n)
+            .push("carry", self.n)
+            .push("tmp_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+            .nest("mul", negacyclic_layout(self.n))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for AddThenMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a and self.b as the input polynomials
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+            builder.when_first_row().assert_eq(row[n+i], AB::Expr::from_canonical_u32(self.b[i]));
+        }
+
+        // Enforce a[i] + b[i] === carry[i] * mod + tmp[i], from the lowest limbs of the constant inputs
+        let (tmp, carry) = (2*n, 3*n);
+        let mul = add_then_mul_product(n);
+        let mask = (1 << LIMB_BITS) - 1;
+        let sums = (0..n).map(|i| row[i] + row[n+i]).collect();
+        let sums_low = (0..n).map(|i| AB::Expr::from_canonical_u32((self.a[i] & mask) + (self.b[i] & mask))).collect();
+        let carries = (0..n).map(|i| row[carry+i].into()).collect();
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &row[tmp..tmp+n], &row[4*n..mul], self.modulus);
+
+        // Enforce the negacyclic product tmp * c, with its a input === tmp
+        self.product().eval_row(builder, &row[mul..mul+negacyclic_width(n)]);
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[mul+i], row[tmp+i]);
+        }
+    }
+}
+
+// Column of out[0] = reduced[0] of the product in the add-then-multiply trace
+pub fn add_then_mul_output(n: usize) -> usize {
+    add_then_mul_product(n) + negacyclic_reduced(n)
+}
+
+// Column of the NegacyclicMulAir of the product, after the sum and its witness
+fn add_then_mul_product(n: usize) -> usize {
+    4*n + reduced_sums_width(n)
+}
+
+// (a + b) % mod, coefficient-wise
+fn add_mod(a: &[u32], b: &[u32], modulus: u32) -> Vec<u32> {
+    a.iter().zip(b).map(|(&x, &y)| ((x as u64 + y as u64) % modulus as u64) as u32).collect()
+}
+
+// (a + b) * c in Z_mod[X]/(X^N+1) computed on the host
+pub fn add_then_mul(a: &[u32], b: &[u32], c: &[u32], modulus: u32) -> Vec<u32> {
+    negacyclic_coeffs(&add_mod(a, b, modulus), c, modulus)
+}
+
+// Define a function to generate execution trace
+pub fn generate_add_then_mul_trace<F: Field>(a: Vec<u32>, b: Vec<u32>, c: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_poly(&c, n, modulus)?;
+    check_range_modulus(modulus)?;
+
+    let width = add_then_mul_product(n) + negacyclic_width(n);
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * width);
+
+    // Add input polynomials, their sum and the carries to values vector
+    let tmp = add_mod(&a, &b, modulus);
+    let carry: Vec<bool> = (0..n).map(|i| a[i] as u64 + b[i] as u64 >= modulus as u64).collect();
+    values.extend(a.iter().chain(b.iter()).chain(tmp.iter()).map(|&x| F::from_canonical_u32(x)));
+    values.extend(carry.iter().map(|&c| F::from_bool(c)));
+
+    // Add the range checks of tmp and the carries of the low limb sums
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = (0..n).map(|i| (a[i] & mask) as i64 + (b[i] & mask) as i64).collect();
+    values.extend(reduced_sums_witness::<F>(&low, &carry, &tmp, modulus));
+
+    // Add the first row of the product tmp * c
+    let product = generate_negacyclic_mul_trace::<F>(tmp, c, modulus, n)?;
+    values.extend_from_slice(&product.row_slice(0));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField32;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_add_then_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate 3 random input polynomials with n coefficients in the range of [0, P1)
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly = |rng: &mut rand::rngs::ThreadRng| -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let (a, b, c) = (random_poly(&mut rng), random_poly(&mut rng), random_poly(&mut rng));
+
+        // out matches the reference (a + b) * c in Z_P1[X]/(X^N+1)
+        let widen = |poly: &[u32]| poly.iter().map(|&x| x as u128).collect::<Vec<_>>();
+        let q = P1 as u128;
+        let expected = reference::negacyclic_mul(&reference::add(&widen(&a), &widen(&b), q), &widen(&c), q);
+        assert_eq!(widen(&add_then_mul(&a, &b, &c, P1)), expected);
+
+        let air = AddThenMulAir { a:a.clone(), b:b.clone(), c:c.clone(), modulus:P1, n };
+        let trace = generate_add_then_mul_trace::<Val>(a, b, c, P1, n).unwrap();
+        let out = add_then_mul_output(n);
+        let proven: Vec<u128> = trace.row_slice(0)[out..out+n].iter().map(|x| x.as_canonical_u32() as u128).collect();
+        assert_eq!(proven, expected);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_add_then_mul_wrong_sum() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 4;
+        let (a, b, c) = (vec![1, 2, 3, 4], vec![P1 - 1, 5, 6, 7], vec![1, 0, 0, 0]);
+        let air = AddThenMulAir { a:a.clone(), b:b.clone(), c:c.clone(), modulus:P1, n };
+
+        // a product of a + b + 1 instead of a + b, with a consistent NegacyclicMulAir sub-trace
+        let mut trace = generate_add_then_mul_trace::<Val>(a.clone(), b.clone(), c.clone(), P1, n).unwrap();
+        let wrong = add_mod(&add_mod(&a, &b, P1), &[1; 4], P1);
+        let product = generate_negacyclic_mul_trace::<Val>(wrong, c, P1, n).unwrap();
+        let mul = add_then_mul_product(n);
+        trace.values[mul..mul + negacyclic_width(n)].copy_from_slice(&product.row_slice(0));
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof of a product of the wrong sum was accepted");
+    }
+
+    #[test]
+    fn test_add_then_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let random_poly = |rng: &mut rand::rngs::ThreadRng| -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let (a, b, c) = (random_poly(&mut rng), random_poly(&mut rng), random_poly(&mut rng));
+        let air = AddThenMulAir { a:a.clone(), b:b.clone(), c:c.clone(), modulus:P1, n };
+
+        // the product tmp * c with a wrong raw product and re-solved quotients
+        assert_rejects_forged_product(&air, &generate_add_then_mul_trace(a, b, c, P1, n).unwrap(), |_| P1);
+    }
+}
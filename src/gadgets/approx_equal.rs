@@ -0,0 +1,202 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::noise_bound::center;
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct ApproxEqualAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    // inclusive bound on the centered difference, below mod / 2
+    pub bound: u32,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl ApproxEqualAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, bound: u32, modulus: u32) -> Self {
+        Self { a, b, bound, modulus, n: N }
+    }
+}
+
+/*
+Approximate Equality Air
+Input:
+- a, b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1} mod q (e.g. a computed and an expected ciphertext component)
+- bound: B < q/2
+Output:
+- none: proves -B <= centered(a[i] - b[i]) <= B for every coefficient
+
+Note:
+- The difference diff[i] = (a[i] - b[i]) mod q is proven with a borrow bit, as in PolySubAir:
+    a[i] + borrow[i] * q === b[i] + diff[i]
+- Its centered representative is written as a sign bit and a magnitude, and the magnitude is compared with B + 1,
+as in NoiseBoundAir:
+    diff[i] === mag[i] + neg[i] * (q - 2 * mag[i]),   0 <= mag[i] < B + 1
+- Every value in the identities is below 2q < n, so they hold over the integers, and B < q/2 keeps the decomposition unique.
+*/
+impl<F: Field> BaseAir<F> for ApproxEqualAir {
+    // Air Table looks like this
+    // row:[ a: N ][ b: N ][ diff: N ][ borrow: N ][ neg: N ][ mag: N ][ bits: 31N ][ eq: 31N ]
+    //     ^-----inputs-----^^-----------------calculated by generate_approx_equal_trace-----------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        (6 + 2*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for ApproxEqualAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("diff", self.n)
+            .push("borrow", self.n)
+            .push("neg", self.n)
+            .push("mag", self.n)
+            .push("bits", RANGE_CHECK_BITS*self.n)
+            .push("eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for ApproxEqualAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (a, b, diff, borrow, neg, mag) = (0, n, 2*n, 3*n, 4*n, 5*n);
+        let bits = 6*n;
+        let eq = bits + RANGE_CHECK_BITS*n;
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+
+        // Enforce self.a and self.b as the input polynomials
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[a+i], AB::Expr::from_canonical_u32(self.a[i]));
+            builder.when_first_row().assert_eq(row[b+i], AB::Expr::from_canonical_u32(self.b[i]));
+        }
+
+        for i in 0..n {
+            // Enforce a[i] + borrow[i] * q === b[i] + diff[i]
+            builder.assert_bool(row[borrow+i]);
+            builder.assert_eq(row[a+i] + row[borrow+i] * modulus, row[b+i] + row[diff+i]);
+
+            // Enforce diff[i] === mag[i] + neg[i] * (q - 2 * mag[i])
+            builder.assert_bool(row[neg+i]);
+            builder.assert_eq(row[diff+i], row[mag+i] + row[neg+i] * (AB::Expr::from(modulus) - row[mag+i] * AB::F::two()));
+
+            // Enforce 0 <= mag[i] < bound + 1
+            let bits = bits + i*RANGE_CHECK_BITS;
+            let eq = eq + i*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[mag+i].into(), &row[bits..bits+RANGE_CHECK_BITS], &row[eq..eq+RANGE_CHECK_BITS], self.bound + 1);
+        }
+    }
+}
+
+// Define a function to generate execution trace
+// The difference is not checked against the bound here: proving that is the job of this gadget
+pub fn generate_approx_equal_trace<F: Field>(a: Vec<u32>, b: Vec<u32>, bound: u32, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    // bound < q/2 keeps the balanced representation unique
+    check_poly(&[bound], 1, modulus / 2)?;
+
+    let width = (6 + 2*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomials, differences, borrows, signs and magnitudes to the row
+    let diff: Vec<u32> = (0..n).map(|i| ((a[i] as u64 + modulus as u64 - b[i] as u64) % modulus as u64) as u32).collect();
+    let centered: Vec<(bool, u32)> = diff.iter().map(|&d| center(d, modulus)).collect();
+    row.extend(a.iter().chain(b.iter()).chain(diff.iter()).map(|&c| F::from_canonical_u32(c)));
+    row.extend((0..n).map(|i| F::from_bool(a[i] < b[i])));
+    row.extend(centered.iter().map(|&(neg, _)| F::from_bool(neg)));
+    row.extend(centered.iter().map(|&(_, mag)| F::from_canonical_u32(mag)));
+
+    // Assign bits and prefix equality flags of every magnitude against bound + 1
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = centered.iter().map(|&(_, mag)| range_check_columns(mag, bound + 1)).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    // b = a + e mod q for a random a and a small e in [-bound, bound], with both ends
+    fn random_pair(n: usize, bound: u32) -> (Vec<u32>, Vec<u32>) {
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let mut e: Vec<i64> = (0..n).map(|_| rng.gen_range(-(bound as i64)..=bound as i64)).collect();
+        e[0] = bound as i64;
+        e[1] = -(bound as i64);
+        e[2] = 0;
+        let b = (0..n).map(|i| (a[i] as i64 + e[i]).rem_euclid(P1 as i64) as u32).collect();
+        (a, b)
+    }
+
+    #[test]
+    fn test_approx_equal() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let bound = 1 << 10;
+        let (a, b) = random_pair(n, bound);
+
+        let air = ApproxEqualAir { a:a.clone(), b:b.clone(), bound, modulus:P1, n };
+        let trace = generate_approx_equal_trace::<Val>(a, b, bound, P1, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_approx_equal_exceeded() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let bound = 1 << 10;
+        // one difference just past the bound on each side
+        for excess in [bound as i64 + 1, -(bound as i64) - 1] {
+            let (a, mut b) = random_pair(n, bound);
+            b[n/2] = (a[n/2] as i64 + excess).rem_euclid(P1 as i64) as u32;
+
+            let air = ApproxEqualAir { a:a.clone(), b:b.clone(), bound, modulus:P1, n };
+            let trace = generate_approx_equal_trace::<Val>(a, b, bound, P1, n).unwrap();
+
+            // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+            }));
+            assert!(!matches!(result, Ok(true)), "proof with a difference of {} outside [-{}, {}] was accepted", excess, bound, bound);
+        }
+    }
+}
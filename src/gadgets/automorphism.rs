@@ -0,0 +1,185 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct GaloisAutomorphismAir {
+    pub poly: Vec<u32>,
+    // odd exponent of the automorphism X -> X^k
+    pub k: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl GaloisAutomorphismAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, k: usize, modulus: u32) -> Self {
+        Self { poly, k, modulus, n: N }
+    }
+}
+
+/*
+Galois Automorphism Air
+Input:
+- poly = poly[0] + poly[1] * X + ... + poly[N-1] * X^{N-1}
+- k: odd exponent, so that X -> X^k is an automorphism of Z_mod[X]/(X^N+1)
+- mod: FHE ciphertext modulus
+Output:
+- out = poly(X^k) in Z_mod[X]/(X^N+1)
+
+Note:
+- poly[i] * X^i maps to poly[i] * X^{ik}, and since X^{2N} = 1 and X^N = -1, with j = ik mod 2N:
+    j < N:  out[j] = poly[i]
+    j >= N: out[j-N] = -poly[i] % mod
+- For odd k, i -> ik mod 2N is injective on [0, N) and hits every residue class mod N once, so out is a signed permutation of poly.
+- The permutation only depends on k and N, so it is baked into the constraints, and the negations are proven as in PolyNegAir:
+    poly[i] + out[j-N] === mod, or out[j-N] === 0 when poly[i] == 0
+- The output constraints are enforced on the first row only, where the inputs are pinned.
+*/
+impl<F: Field> BaseAir<F> for GaloisAutomorphismAir {
+    // Air Table looks like this
+    // row:[    poly: N    ][mod:1][      out: N      ]
+    //     ^--------inputs--------^^-calculated by generate_automorphism_trace
+    //     [0..............................................0]
+    //     [0..............................................0]
+    //     [0..............................................0]
+    fn width(&self) -> usize {
+        2*self.n+1
+    }
+}
+
+impl GadgetLayout for GaloisAutomorphismAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("poly", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for GaloisAutomorphismAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.poly as input polynomial and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+        builder.when_first_row().assert_eq(row[n], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce out[j] === poly[i], or the negation of poly[i] when X^{ik} wraps past X^N
+        let out = n+1;
+        for i in 0..n {
+            let (j, negate) = automorphism_index(i, self.k, n);
+            if !negate {
+                builder.when_first_row().assert_eq(row[out+j], row[i]);
+            } else if self.poly[i] == 0 {
+                builder.when_first_row().assert_zero(row[out+j]);
+            } else {
+                builder.when_first_row().assert_eq(row[i] + row[out+j], row[n]);
+            }
+        }
+    }
+}
+
+// Index of X^{ik} in Z[X]/(X^N+1), and whether the coefficient is negated
+pub fn automorphism_index(i: usize, k: usize, n: usize) -> (usize, bool) {
+    let j = (i * k) % (2*n);
+    if j < n { (j, false) } else { (j - n, true) }
+}
+
+// Define a function to generate execution trace
+pub fn generate_automorphism_trace<F: Field>(poly: Vec<u32>, k: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+    // an even k is not an automorphism: (X^k)^N = 1 != -1
+    if k % 2 == 0 {
+        return Err(GadgetError::InvalidAutomorphism { k, n });
+    }
+
+    let mut values: Vec<F>= Vec::with_capacity(MIN_TRACE_HEIGHT * (2*n+1));
+
+    // Add input polynomial and modulus to values vector
+    for i in 0..n {
+        values.push(F::from_canonical_u32(poly[i]));
+    }
+    values.push(F::from_canonical_u32(modulus));
+
+    // Permute the coefficients and push the image to values vector
+    let mut out = vec![0u32; n];
+    for i in 0..n {
+        let (j, negate) = automorphism_index(i, k, n);
+        out[j] = if negate { (modulus - poly[i]) % modulus } else { poly[i] };
+    }
+    for j in 0..n {
+        values.push(F::from_canonical_u32(out[j]));
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 2*n+1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_galois_automorphism() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate a random input polynomial, with a 0 coefficient that lands on a negated slot
+        let n = 8;
+        let k = 3;
+        let mut rng = thread_rng();
+        let mut poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        poly[3] = 0;
+
+        // X -> X^3 with N = 8: i = 0..8 maps to X^{0, 3, 6, 9, 12, 15, 18, 21}, i.e. X^{0, 3, 6}, -X^{1, 4, 7}, X^{2, 5}
+        let expected: Vec<u32> = vec![
+            poly[0],
+            (P1 - poly[3]) % P1,
+            poly[6],
+            poly[1],
+            (P1 - poly[4]) % P1,
+            poly[7],
+            poly[2],
+            (P1 - poly[5]) % P1,
+        ];
+
+        let air = GaloisAutomorphismAir { poly:poly.clone(), k, modulus:P1, n };
+        let trace = generate_automorphism_trace::<Val>(poly, k, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        for j in 0..n {
+            assert_eq!(row[n+1+j], Val::from_canonical_u32(expected[j]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_even_k_is_rejected() {
+        assert_eq!(
+            generate_automorphism_trace::<Val>(vec![0; 8], 2, P1, 8).unwrap_err(),
+            GadgetError::InvalidAutomorphism { k: 2, n: 8 }
+        );
+    }
+}
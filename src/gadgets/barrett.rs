@@ -0,0 +1,383 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::bit_decompose::{bits, eval_from_bits};
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Bit length k of the unreduced values: (q-1)^2 * N < 2^72 for the moduli of this crate
+pub const BARRETT_VALUE_BITS: usize = 80;
+
+// The integers are handled as little-endian 8-bits limbs, so that limb products and their sums stay far below n
+pub(crate) const LIMB_BITS: usize = 8;
+const VALUE_LIMBS: usize = BARRETT_VALUE_BITS / LIMB_BITS;
+
+// Every carry of the limb products is below 2^12: a position sums at most 10 products of 2 limbs
+pub(crate) const CARRY_BITS: usize = 12;
+
+// Define AIR constraint inputs
+pub struct BarrettReduceAir {
+    // unreduced values below 2^BARRETT_VALUE_BITS, e.g. accumulated coefficient products
+    pub value: Vec<u128>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl BarrettReduceAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(value: Vec<u128>, modulus: u32) -> Self {
+        Self { value, modulus, n: N }
+    }
+
+    fn offsets(&self) -> BarrettLayout {
+        BarrettLayout::new(self.modulus, self.n)
+    }
+}
+
+/*
+Barrett Reduction Air
+Input:
+- value[0], ..., value[N-1]: unreduced integers below 2^k with k = BARRETT_VALUE_BITS
+- mod: FHE ciphertext modulus
+Output:
+- out[i] = value[i] % mod
+
+Note:
+- With the precomputed reciprocal mu = floor(2^k / mod), the Barrett quotient
+    qhat = floor(value * mu / 2^k)
+is floor(value / mod) or one less, so value - qhat * mod is in [0, 2 * mod) and one conditional subtraction reduces it:
+    value === qhat * mod + c * mod + out,  c in {0, 1},  0 <= out < mod
+- value is up to 80 bits and value * mu up to 130 bits, far beyond n, so both identities are proven over 8-bits limbs
+with a carry chain, where sum_{j+l=m} x_j * y_l is the limb convolution at position m:
+    1) sum_{j+l=m} value_j * mu_l + carry_m === w_m + 2^8 * carry_{m+1}
+    2) sum_{j+l=m} qhat_j * mod_l + c * mod_m + out_m + carry'_m === value_m + 2^8 * carry'_{m+1}
+mu and mod are constants, so every term is of degree 1 or 2, and every limb and carry is range checked by its bits,
+so each equation is below 2^21 < n and holds over the integers.
+- Since k is a multiple of 8, the product limbs w split at limb k/8: the low limbs are value * mu mod 2^k (only range checked)
+and the high limbs are qhat, which 2) reads back as expressions of their bits.
+- out is range checked against mod with the bit comparison of range_check.rs, whose bits also give the limbs out_m.
+- The value limbs are pinned to self.value, and are assumed to be bytes when the row is embedded elsewhere.
+*/
+impl<F: Field> BaseAir<F> for BarrettReduceAir {
+    // Air Table looks like this, with M the number of product limbs and M' the number of positions of 2)
+    // row:[ value limbs: 10N ][ out: N ][ c: N ][ w bits: 8MN ][ carry bits: 12(M-1)N ][ out bits: 31N ][ out eq: 31N ][ carry' bits: 12(M'-1)N ]
+    //     ^-------input------^^------------------------------calculated by generate_barrett_trace---------------------------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        self.offsets().width
+    }
+}
+
+impl GadgetLayout for BarrettReduceAir {
+    fn layout(&self) -> TraceLayout {
+        let offsets = self.offsets();
+        TraceLayout::new()
+            .push("value", offsets.out - offsets.value)
+            .push("out", offsets.c - offsets.out)
+            .push("c", offsets.product_bits - offsets.c)
+            .push("w_bits", offsets.product_carry - offsets.product_bits)
+            .push("carry_bits", offsets.out_bits - offsets.product_carry)
+            .push("out_bits", offsets.out_eq - offsets.out_bits)
+            .push("out_eq", offsets.check_carry - offsets.out_eq)
+            .push("check_carry_bits", offsets.width - offsets.check_carry)
+    }
+}
+
+// Column offsets of the blocks, which depend on the number of limbs of mu and mod
+struct BarrettLayout {
+    mu: Vec<u64>,
+    modulus: Vec<u64>,
+    // number of limbs of value * mu
+    product_limbs: usize,
+    // number of positions of the identity 2)
+    check_limbs: usize,
+    value: usize,
+    out: usize,
+    c: usize,
+    product_bits: usize,
+    product_carry: usize,
+    out_bits: usize,
+    out_eq: usize,
+    check_carry: usize,
+    width: usize
+}
+
+impl BarrettLayout {
+    fn new(modulus: u32, n: usize) -> Self {
+        let mu = limbs(barrett_mu(modulus), limb_count(barrett_mu(modulus)));
+        let q = limbs(modulus as u128, limb_count(modulus as u128));
+        let product_limbs = VALUE_LIMBS + mu.len();
+        // qhat has as many limbs as mu, and out as many as a 31-bits integer
+        let check_limbs = VALUE_LIMBS.max(mu.len() + q.len() - 1).max(RANGE_CHECK_BITS.div_ceil(LIMB_BITS));
+
+        let value = 0;
+        let out = value + VALUE_LIMBS*n;
+        let c = out + n;
+        let product_bits = c + n;
+        let product_carry = product_bits + LIMB_BITS*product_limbs*n;
+        let out_bits = product_carry + CARRY_BITS*(product_limbs-1)*n;
+        let out_eq = out_bits + RANGE_CHECK_BITS*n;
+        let check_carry = out_eq + RANGE_CHECK_BITS*n;
+        let width = check_carry + CARRY_BITS*(check_limbs-1)*n;
+        Self { mu, modulus: q, product_limbs, check_limbs, value, out, c, product_bits, product_carry, out_bits, out_eq, check_carry, width }
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for BarrettReduceAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let layout = self.offsets();
+        let (m1, m2) = (layout.product_limbs, layout.check_limbs);
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let limb_base = AB::F::from_canonical_u32(1 << LIMB_BITS);
+
+        for i in 0..n {
+            // Enforce self.value as input limbs
+            let value = layout.value + i*VALUE_LIMBS;
+            for (l, limb) in limbs(self.value[i], VALUE_LIMBS).into_iter().enumerate() {
+                builder.when_first_row().assert_eq(row[value+l], AB::Expr::from_canonical_u64(limb));
+            }
+
+            let w_bits = layout.product_bits + i*LIMB_BITS*m1;
+            let w: Vec<AB::Expr> = (0..m1).map(|m| eval_from_bits(builder, &row[w_bits + m*LIMB_BITS..w_bits + (m+1)*LIMB_BITS])).collect();
+            let carry_bits = layout.product_carry + i*CARRY_BITS*(m1-1);
+            let carry: Vec<AB::Expr> = (0..m1-1).map(|m| eval_from_bits(builder, &row[carry_bits + m*CARRY_BITS..carry_bits + (m+1)*CARRY_BITS])).collect();
+
+            // 1) Enforce sum_{j+l=m} value_j * mu_l + carry_m === w_m + 2^8 * carry_{m+1}
+            for m in 0..m1 {
+                let mut lhs = if m > 0 { carry[m-1].clone() } else { AB::Expr::zero() };
+                for (l, &mu_l) in layout.mu.iter().enumerate() {
+                    if m >= l && m - l < VALUE_LIMBS {
+                        lhs = lhs + row[value + m - l] * AB::F::from_canonical_u64(mu_l);
+                    }
+                }
+                let rhs = if m < m1-1 { w[m].clone() + carry[m].clone() * limb_base } else { w[m].clone() };
+                builder.assert_eq(lhs, rhs);
+            }
+            let qhat = &w[VALUE_LIMBS..];
+
+            // Enforce 0 <= out < mod, and c to be a bit
+            let out_bits = layout.out_bits + i*RANGE_CHECK_BITS;
+            let out_eq = layout.out_eq + i*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[layout.out+i].into(), &row[out_bits..out_bits+RANGE_CHECK_BITS], &row[out_eq..out_eq+RANGE_CHECK_BITS], self.modulus);
+            builder.assert_bool(row[layout.c+i]);
+            let out_limb = |m: usize| -> AB::Expr {
+                let mut sum = AB::Expr::zero();
+                for b in 0..LIMB_BITS {
+                    if m*LIMB_BITS + b < RANGE_CHECK_BITS {
+                        sum = sum + row[out_bits + m*LIMB_BITS + b] * AB::F::from_canonical_u32(1 << b);
+                    }
+                }
+                sum
+            };
+
+            let carry_bits = layout.check_carry + i*CARRY_BITS*(m2-1);
+            let check_carry: Vec<AB::Expr> = (0..m2-1).map(|m| eval_from_bits(builder, &row[carry_bits + m*CARRY_BITS..carry_bits + (m+1)*CARRY_BITS])).collect();
+
+            // 2) Enforce sum_{j+l=m} qhat_j * mod_l + c * mod_m + out_m + carry'_m === value_m + 2^8 * carry'_{m+1}
+            for m in 0..m2 {
+                let mut lhs = if m > 0 { check_carry[m-1].clone() } else { AB::Expr::zero() };
+                for (l, &q_l) in layout.modulus.iter().enumerate() {
+                    if m >= l && m - l < qhat.len() {
+                        lhs = lhs + qhat[m-l].clone() * AB::F::from_canonical_u64(q_l);
+                    }
+                }
+                if m < layout.modulus.len() {
+                    lhs = lhs + row[layout.c+i] * AB::F::from_canonical_u64(layout.modulus[m]);
+                }
+                lhs = lhs + out_limb(m);
+                let mut rhs = if m < VALUE_LIMBS { row[value+m].into() } else { AB::Expr::zero() };
+                if m < m2-1 {
+                    rhs = rhs + check_carry[m].clone() * limb_base;
+                }
+                builder.assert_eq(lhs, rhs);
+            }
+        }
+    }
+}
+
+// mu = floor(2^k / mod)
+pub fn barrett_mu(modulus: u32) -> u128 {
+    (1u128 << BARRETT_VALUE_BITS) / modulus as u128
+}
+
+// Number of 8-bits limbs of x
+pub(crate) fn limb_count(x: u128) -> usize {
+    (128 - x.leading_zeros() as usize).div_ceil(LIMB_BITS)
+}
+
+// The first `count` little-endian 8-bits limbs of x
+pub(crate) fn limbs(x: u128, count: usize) -> Vec<u64> {
+    (0..count).map(|l| ((x >> (l*LIMB_BITS)) & 0xff) as u64).collect()
+}
+
+// Propagate the carries of the limbs conv - target: (limbs w_m, carries carry_{m+1} for m = [0..len-1))
+pub(crate) fn propagate(conv: &[u64], target: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let mut out = Vec::with_capacity(conv.len());
+    let mut carries = Vec::with_capacity(conv.len() - 1);
+    let mut carry = 0u64;
+    for m in 0..conv.len() {
+        let t = conv[m] + carry - target.get(m).copied().unwrap_or(0);
+        out.push(t & 0xff);
+        carry = t >> LIMB_BITS;
+        if m < conv.len() - 1 {
+            carries.push(carry);
+        }
+    }
+    (out, carries)
+}
+
+// Limb convolution sum_{j+l=m} x_j * y_l for m = [0..len)
+pub(crate) fn convolve(x: &[u64], y: &[u64], len: usize) -> Vec<u64> {
+    let mut conv = vec![0u64; len];
+    for (j, &x_j) in x.iter().enumerate() {
+        for (l, &y_l) in y.iter().enumerate() {
+            if j + l < len {
+                conv[j+l] += x_j * y_l;
+            }
+        }
+    }
+    conv
+}
+
+// Barrett quotient floor(value * mu / 2^k), computed with the limb product of the trace since value * mu overflows u128
+fn barrett_quotient(value: u128, modulus: u32) -> (u128, Vec<u64>, Vec<u64>) {
+    let mu = barrett_mu(modulus);
+    let mu_limbs = limbs(mu, limb_count(mu));
+    let conv = convolve(&limbs(value, VALUE_LIMBS), &mu_limbs, VALUE_LIMBS + mu_limbs.len());
+    let (w, carries) = propagate(&conv, &[]);
+    let qhat = w[VALUE_LIMBS..].iter().rev().fold(0u128, |acc, &limb| (acc << LIMB_BITS) | limb as u128);
+    (qhat, w, carries)
+}
+
+// value % mod by Barrett reduction: (out, c) with value = qhat * mod + c * mod + out
+pub fn barrett_reduce(value: u128, modulus: u32) -> (u32, bool) {
+    let (qhat, _, _) = barrett_quotient(value, modulus);
+    let r = value - qhat * modulus as u128;
+    if r >= modulus as u128 { ((r - modulus as u128) as u32, true) } else { (r as u32, false) }
+}
+
+// Define a function to generate execution trace
+pub fn generate_barrett_trace<F: Field>(value: Vec<u128>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if value.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: value.len() });
+    }
+    if let Some(index) = value.iter().position(|&v| v >> BARRETT_VALUE_BITS != 0) {
+        return Err(GadgetError::ValueOutOfRange { index, bits: BARRETT_VALUE_BITS });
+    }
+
+    let layout = BarrettLayout::new(modulus, n);
+    let m2 = layout.check_limbs;
+    let mut row: Vec<F> = vec![F::zero(); layout.width];
+    let assign_bits = |row: &mut Vec<F>, start: usize, x: u64, count: usize| {
+        for (b, bit) in bits(x, count).enumerate() {
+            row[start+b] = F::from_bool(bit);
+        }
+    };
+
+    for i in 0..n {
+        let v = limbs(value[i], VALUE_LIMBS);
+        for (l, &limb) in v.iter().enumerate() {
+            row[layout.value + i*VALUE_LIMBS + l] = F::from_canonical_u64(limb);
+        }
+
+        // 1) limbs of value * mu and their carries
+        let (qhat, w, carries) = barrett_quotient(value[i], modulus);
+        let m1 = layout.product_limbs;
+        for (m, &limb) in w.iter().enumerate() {
+            assign_bits(&mut row, layout.product_bits + i*LIMB_BITS*m1 + m*LIMB_BITS, limb, LIMB_BITS);
+        }
+        for (m, &carry) in carries.iter().enumerate() {
+            assign_bits(&mut row, layout.product_carry + i*CARRY_BITS*(m1-1) + m*CARRY_BITS, carry, CARRY_BITS);
+        }
+
+        // conditional subtraction, and the comparison of out with mod
+        let (out, c) = barrett_reduce(value[i], modulus);
+        row[layout.out+i] = F::from_canonical_u32(out);
+        row[layout.c+i] = F::from_bool(c);
+        let (out_bits, out_eq) = range_check_columns(out, modulus);
+        for b in 0..RANGE_CHECK_BITS {
+            row[layout.out_bits + i*RANGE_CHECK_BITS + b] = F::from_bool(out_bits[b]);
+            row[layout.out_eq + i*RANGE_CHECK_BITS + b] = F::from_bool(out_eq[b]);
+        }
+
+        // 2) carries of qhat * mod + c * mod + out - value, which propagates to 0 limbs
+        let mut conv = convolve(&limbs(qhat, layout.mu.len()), &layout.modulus, m2);
+        for (m, &q_m) in layout.modulus.iter().enumerate() {
+            conv[m] += c as u64 * q_m;
+        }
+        for (m, limb) in limbs(out as u128, RANGE_CHECK_BITS.div_ceil(LIMB_BITS)).into_iter().enumerate() {
+            conv[m] += limb;
+        }
+        let (_, carries) = propagate(&conv, &v);
+        for (m, &carry) in carries.iter().enumerate() {
+            assign_bits(&mut row, layout.check_carry + i*CARRY_BITS*(m2-1) + m*CARRY_BITS, carry, CARRY_BITS);
+        }
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::{P1, P2, P3};
+
+    // values up to (mod-1)^2 * N, the largest sum of N coefficient products, with both ends and the multiples of mod around 0
+    fn random_values(n: usize, modulus: u32) -> Vec<u128> {
+        let max = (modulus as u128 - 1).pow(2) * N as u128;
+        let mut rng = thread_rng();
+        let mut value: Vec<u128> = (0..n).map(|_| rng.gen_range(0..=max)).collect();
+        value[..5].copy_from_slice(&[0, modulus as u128 - 1, modulus as u128, 2*modulus as u128 - 1, max]);
+        value
+    }
+
+    #[test]
+    fn test_barrett_reduce() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        for modulus in [P1, P2, P3] {
+            let value = random_values(n, modulus);
+            for &v in value.iter() {
+                assert_eq!(barrett_reduce(v, modulus).0 as u128, v % modulus as u128);
+            }
+
+            let air = BarrettReduceAir { value:value.clone(), modulus, n };
+            let trace = generate_barrett_trace::<Val>(value.clone(), modulus, n).unwrap();
+
+            let layout = BarrettLayout::new(modulus, n);
+            let row = trace.row_slice(0);
+            for i in 0..n {
+                assert_eq!(row[layout.out+i], Val::from_canonical_u32((value[i] % modulus as u128) as u32));
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_barrett_value_too_large() {
+        assert_eq!(
+            generate_barrett_trace::<Val>(vec![0, 1u128 << BARRETT_VALUE_BITS], P1, 2).unwrap_err(),
+            GadgetError::ValueOutOfRange { index: 1, bits: BARRETT_VALUE_BITS }
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, eval_carry_chain, range_checked_limbs, COEFF_LIMBS, CRT_LIMBS, MUL_CARRY_BITS};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::gadgets::utils::mod_inv;
+use crate::params::{N, P1, P2, P3};
+
+// Define AIR constraint inputs
+pub struct BaseExtendAir {
+    // residues of x mod P1 and mod P2
+    pub r1: Vec<u32>,
+    pub r2: Vec<u32>,
+    pub n: usize
+}
+
+impl BaseExtendAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(r1: Vec<u32>, r2: Vec<u32>) -> Self {
+        Self { r1, r2, n: N }
+    }
+}
+
+// Number of range checked columns per coefficient: k, t, r3 and q3
+const CHECKED: usize = 4;
+
+// Carry columns of one identity mod 2^48
+const CARRY_COLUMNS: usize = CRT_LIMBS*MUL_CARRY_BITS;
+
+/*
+RNS Base Extension Air
+Input:
+- r1, r2: residues mod P1 and mod P2 of a polynomial x with coefficients in [0, P1 * P2)
+Output:
+- r3 = x mod P3: the residue in the new channel
+- k, t: mixed radix digits with x[i] = r1[i] + P1 * k[i] = r2[i] + P2 * t[i]
+- q3: quotient of x[i] by P3
+
+Note:
+- This is the exact base extension: x[i] is rebuilt from its residues with Garner's formula,
+    k[i] = (r2[i] - r1[i]) * P1^{-1} mod P2,   x[i] = r1[i] + P1 * k[i]
+so r3[i] is the residue of x[i] itself, and not of x[i] + alpha * P1 * P2 for some small alpha,
+as in the approximate (fast) base conversion, which needs a correction of alpha afterwards.
+- P1^{-1} mod P2 is only needed to compute k[i] on the host: the constraints check the result instead,
+    r1[i] + P1 * k[i] === r2[i] + P2 * t[i]
+    r1[i] + P1 * k[i] === q3[i] * P3 + r3[i]
+with 0 <= k[i] < P2, 0 <= t[i] < P1, 0 <= r3[i] < P3 and 0 <= q3[i] < P1 * P2 / P3 + 1 range checked.
+- Both sides of the identities are below P1 * P2 < 2^62, which overflows n: as in PolyMulAir, they are enforced
+mod n directly, and mod 2^48 with a carry chain over the 8-bits limbs of every side, read from the range checks,
+so that they hold over the integers.
+*/
+impl<F: Field> BaseAir<F> for BaseExtendAir {
+    // Air Table looks like this
+    // row:[ r1: N ][ r2: N ][ k: N ][ t: N ][ r3: N ][ q3: N ][ bits: 4 * 31N ][ eq: 4 * 31N ][ carry_bits: 2 * 138N ]
+    //     ^----inputs-----^^----------------------calculated by generate_base_extend_trace-----------------------^
+    //     ... the same row repeated 3 times, since every row must pass the range checks
+    fn width(&self) -> usize {
+        base_extend_width(self.n)
+    }
+}
+
+fn base_extend_width(n: usize) -> usize {
+    (6 + 2*CHECKED*RANGE_CHECK_BITS + 2*CARRY_COLUMNS)*n
+}
+
+impl GadgetLayout for BaseExtendAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("r1", self.n)
+            .push("r2", self.n)
+            .push("k", self.n)
+            .push("t", self.n)
+            .push("r3", self.n)
+            .push("q3", self.n)
+            .push("bits", CHECKED*RANGE_CHECK_BITS*self.n)
+            .push("eq", CHECKED*RANGE_CHECK_BITS*self.n)
+            .push("carry_bits", 2*CARRY_COLUMNS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for BaseExtendAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (r1, r2, k, t, r3, q3) = (0, n, 2*n, 3*n, 4*n, 5*n);
+        let bits = 6*n;
+        let eq = bits + CHECKED*RANGE_CHECK_BITS*n;
+        let carry_bits = eq + CHECKED*RANGE_CHECK_BITS*n;
+        let (p1, p2, p3) = (AB::F::from_canonical_u32(P1), AB::F::from_canonical_u32(P2), AB::F::from_canonical_u32(P3));
+
+        // Enforce self.r1 and self.r2 as the input residues
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[r1+i], AB::Expr::from_canonical_u32(self.r1[i]));
+            builder.when_first_row().assert_eq(row[r2+i], AB::Expr::from_canonical_u32(self.r2[i]));
+        }
+
+        for i in 0..n {
+            // Enforce r1[i] + P1 * k[i] === r2[i] + P2 * t[i] and r1[i] + P1 * k[i] === q3[i] * P3 + r3[i]
+            let x = row[r1+i] + row[k+i] * p1;
+            builder.assert_eq(x.clone(), row[r2+i] + row[t+i] * p2);
+            builder.assert_eq(x, row[q3+i] * p3 + row[r3+i]);
+
+            // Enforce 0 <= k[i] < P2, 0 <= t[i] < P1, 0 <= r3[i] < P3 and 0 <= q3[i] < P1 * P2 / P3 + 1
+            for (c, (col, bound)) in [(k, P2), (t, P1), (r3, P3), (q3, quotient_bound())].into_iter().enumerate() {
+                let offset = (i*CHECKED + c)*RANGE_CHECK_BITS;
+                let (b, e) = (bits + offset, eq + offset);
+                eval_range_check(builder, row[col+i].into(), &row[b..b+RANGE_CHECK_BITS], &row[e..e+RANGE_CHECK_BITS], bound);
+            }
+
+            // Enforce both identities mod 2^48, over the limbs of the constant residues and of the range checked (k, t, r3, q3)
+            let [k_limbs, t_limbs, r3_limbs, q3_limbs] = [0, 1, 2, 3].map(|c| {
+                let b = bits + (i*CHECKED + c)*RANGE_CHECK_BITS;
+                range_checked_limbs::<AB>(&row[b..b+RANGE_CHECK_BITS])
+            });
+            let (r1_limbs, r2_limbs) = (limbs(self.r1[i] as u128, CRT_LIMBS), limbs(self.r2[i] as u128, CRT_LIMBS));
+            let (k_p1, t_p2, q3_p3) = (limb_product::<AB>(&k_limbs, P1), limb_product::<AB>(&t_limbs, P2), limb_product::<AB>(&q3_limbs, P3));
+            let constant = |x: u64| AB::Expr::from_canonical_u64(x);
+            let terms = |rhs: &[AB::Expr], r: &[AB::Expr]| -> Vec<AB::Expr> {
+                (0..CRT_LIMBS).map(|m| {
+                    let term = constant(r1_limbs[m]) + k_p1[m].clone() - rhs[m].clone();
+                    if m < COEFF_LIMBS { term - r[m].clone() } else { term }
+                }).collect()
+            };
+            let r2_limbs: Vec<AB::Expr> = r2_limbs.into_iter().map(constant).collect();
+            let carries = carry_bits + 2*i*CARRY_COLUMNS;
+            eval_carry_chain(builder, terms(&t_p2, &r2_limbs), &row[carries..carries+CARRY_COLUMNS]);
+            eval_carry_chain(builder, terms(&q3_p3, &r3_limbs), &row[carries+CARRY_COLUMNS..carries+2*CARRY_COLUMNS]);
+        }
+    }
+}
+
+// Limb positions [0..CRT_LIMBS) of x * c, from the limbs of the range checked x and of the constant c
+fn limb_product<AB: AirBuilder>(x: &[AB::Expr], c: u32) -> Vec<AB::Expr> {
+    let c = limbs(c as u128, COEFF_LIMBS);
+    (0..CRT_LIMBS).map(|m| {
+        let mut sum = AB::Expr::zero();
+        for l in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+            sum = sum + x[l].clone() * AB::F::from_canonical_u64(c[m-l]);
+        }
+        sum
+    }).collect()
+}
+
+// Largest quotient floor((P1 * P2 - 1) / P3), plus 1: the bound q3[i] is range checked against
+fn quotient_bound() -> u32 {
+    ((P1 as u64 * P2 as u64 - 1) / P3 as u64 + 1) as u32
+}
+
+// Mixed radix digit k = (r2 - r1) * P1^{-1} mod P2, so that x = r1 + P1 * k
+fn garner_digit(r1: u32, r2: u32) -> u64 {
+    let inv = mod_inv(P1 as u64, P2 as u64);
+    let diff = (r2 as u64 + P2 as u64 - r1 as u64 % P2 as u64) % P2 as u64;
+    diff * inv % P2 as u64
+}
+
+// Residue mod P3 of the x in [0, P1 * P2) with x mod P1 = r1 and x mod P2 = r2, computed on the host
+pub fn base_extend(r1: u32, r2: u32) -> u32 {
+    ((r1 as u64 + P1 as u64 * garner_digit(r1, r2)) % P3 as u64) as u32
+}
+
+// Define a function to generate execution trace
+pub fn generate_base_extend_trace<F: Field>(r1: Vec<u32>, r2: Vec<u32>, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&r1, n, P1)?;
+    check_poly(&r2, n, P2)?;
+
+    // Compute the mixed radix digits, the new residue and its quotient
+    let xs: Vec<u64> = (0..n).map(|i| r1[i] as u64 + P1 as u64 * garner_digit(r1[i], r2[i])).collect();
+    let k: Vec<u32> = xs.iter().map(|&x| (x / P1 as u64) as u32).collect();
+    let t: Vec<u32> = xs.iter().map(|&x| (x / P2 as u64) as u32).collect();
+    let r3: Vec<u32> = xs.iter().map(|&x| (x % P3 as u64) as u32).collect();
+    let q3: Vec<u32> = xs.iter().map(|&x| (x / P3 as u64) as u32).collect();
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&base_extend_row(&r1, &r2, [&k, &t, &r3, &q3])))
+}
+
+// Row of the residues r1, r2 with the columns (k, t, r3, q3) and their witness
+fn base_extend_row<F: Field>(r1: &[u32], r2: &[u32], [k, t, r3, q3]: [&[u32]; CHECKED]) -> Vec<F> {
+    let n = r1.len();
+    let mut row: Vec<F> = Vec::with_capacity(base_extend_width(n));
+
+    // Assign input residues, the mixed radix digits, the new residue and its quotient to the row
+    for column in [r1, r2, k, t, r3, q3] {
+        row.extend(column.iter().map(|&c| F::from_canonical_u32(c)));
+    }
+
+    // Assign bits, then prefix equality flags, of (k, t, r3, q3) for every coefficient
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = (0..n).flat_map(|i| {
+        [(k[i], P2), (t[i], P1), (r3[i], P3), (q3[i], quotient_bound())].map(|(c, bound)| range_check_columns(c, bound))
+    }).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Assign the carries of r1 + P1 * k - r2 - P2 * t and of r1 + P1 * k - q3 * P3 - r3 at positions [0..CRT_LIMBS)
+    let product = |x: u32, c: u32| convolve(&limbs(x as u128, COEFF_LIMBS), &limbs(c as u128, COEFF_LIMBS), CRT_LIMBS);
+    for i in 0..n {
+        let (r1_limbs, k_p1) = (limbs(r1[i] as u128, CRT_LIMBS), product(k[i], P1));
+        for rhs in [[limbs(r2[i] as u128, CRT_LIMBS), product(t[i], P2)], [limbs(r3[i] as u128, CRT_LIMBS), product(q3[i], P3)]] {
+            let diff: Vec<i64> = (0..CRT_LIMBS).map(|m| {
+                r1_limbs[m] as i64 + k_p1[m] as i64 - rhs[0][m] as i64 - rhs[1][m] as i64
+            }).collect();
+            let mut carries = vec![F::zero(); CARRY_COLUMNS];
+            assign_carry_chain(&mut carries, &diff);
+            row.extend(carries);
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use p3_field::PrimeField32;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::rns::crt_recombine;
+    use crate::gadgets::soundness::assert_rejected;
+
+    #[test]
+    fn test_base_extend() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // random x in [0, P1 * P2), including both ends
+        let n = 8;
+        let mut rng = thread_rng();
+        let top = P1 as u64 * P2 as u64;
+        let mut xs: Vec<u64> = (0..n).map(|_| rng.gen_range(0..top)).collect();
+        xs[0] = 0;
+        xs[n-1] = top - 1;
+
+        let r1: Vec<u32> = xs.iter().map(|&x| (x % P1 as u64) as u32).collect();
+        let r2: Vec<u32> = xs.iter().map(|&x| (x % P2 as u64) as u32).collect();
+        for i in 0..n {
+            // the extended residue is the one of x itself, and the 3 residues recombine to x
+            let r3 = base_extend(r1[i], r2[i]);
+            assert_eq!(r3 as u64, xs[i] % P3 as u64);
+            assert_eq!(crt_recombine([r1[i], r2[i], r3]), xs[i] as u128);
+        }
+
+        let air = BaseExtendAir { r1:r1.clone(), r2:r2.clone(), n };
+        let trace = generate_base_extend_trace::<Val>(r1, r2, n).unwrap();
+        let r3 = air.layout().get("r3").unwrap();
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            assert_eq!(row[r3.start+i], Val::from_canonical_u64(xs[i] % P3 as u64));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_base_extend_forged_digits() {
+        let mut rng = thread_rng();
+        let x = rng.gen_range(0..P1 as u64 * P2 as u64);
+        let (r1, r2) = (vec![(x % P1 as u64) as u32], vec![(x % P2 as u64) as u32]);
+        let air = BaseExtendAir { r1: r1.clone(), r2: r2.clone(), n: 1 };
+
+        // k + d with t re-solved mod n, so that r1 + P1 * k === r2 + P2 * t still holds mod n but not over the integers,
+        // and r3, q3 the honest reduction of the forged x = r1 + P1 * (k + d)
+        let order = Val::ORDER_U32 as u64;
+        let p2_inv = mod_inv(P2 as u64, order);
+        let (k, t) = (x / P1 as u64, x / P2 as u64);
+        let (k, t) = (1..64).map(|d| (k + d, (t + d * P1 as u64 % order * p2_inv) % order))
+            .find(|&(k, t)| k < P2 as u64 && t < P1 as u64)
+            .expect("no forged digit below the bounds");
+        let forged = r1[0] as u64 + P1 as u64 * k;
+        let columns = [k as u32, t as u32, (forged % P3 as u64) as u32, (forged / P3 as u64) as u32].map(|c| vec![c]);
+        let row: Vec<Val> = base_extend_row(&r1, &r2, [&columns[0], &columns[1], &columns[2], &columns[3]]);
+
+        assert_rejected(&air, repeat_row(&row), "a forged k + d");
+    }
+}
@@ -0,0 +1,312 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::trace_height;
+
+// Define AIR constraint inputs
+pub struct BatchAddAir {
+    // k pairs of input polynomials, one addition a[j] + b[j] per row
+    pub a: Vec<Vec<u32>>,
+    pub b: Vec<Vec<u32>>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl BatchAddAir {
+    // number of rows: one per addition, padded up to trace_height(k)
+    fn height(&self) -> usize {
+        trace_height(self.a.len())
+    }
+}
+
+/*
+Batched Polynomial Addition Air
+Input:
+- a[j], b[j] for j = [0..k): k pairs of polynomials with N coefficients
+- mod: FHE ciphertext modulus
+Output (public values):
+- out[j] = a[j] + b[j] mod `mod` for j = [0..k), as k * N coefficients out[0][0], ..., out[k-1][N-1]
+
+Note:
+- k separate PolyAddAir proofs pay the commitment and FRI opening cost k times.
+BatchAddAir puts addition j on row j instead, so a single proof covers all of them.
+- Each row proves a[i] + b[i] === carry[i] * mod + out[i] with a carry bit, as in CiphertextAddAir.
+- The inputs and the public outputs differ per row, so every row carries a one-hot selector sel[0..H) of its index:
+    idx starts at 0 and increases by 1 per row
+    sel[j] are bits with sum_j sel[j] === 1 and sum_j j * sel[j] === idx
+which forces sel[j] = 1 exactly on row j. Then sel[j] * (a[i] - a[j][i]) === 0 pins the inputs of row j,
+and sel[j] * (out[i] - public_values[j*N+i]) === 0 its outputs, both of degree 2.
+- Rows j = [k..H) pad the trace to a power of two, and are pinned to the addition 0 + 0 = 0.
+- out[i] is range checked into [0, mod) and the sum is also enforced mod 2^8 through eval_reduced_sum(), as in PolyAddAir,
+on every row: the lowest limbs of the inputs of row j are the selected constants sum_j sel[j] * (a[j][i]_0 + b[j][i]_0).
+The padding rows hold the honest witness of 0 + 0 = 0, whose range checks pass.
+*/
+impl<F: Field> BaseAir<F> for BatchAddAir {
+    // Air Table looks like this
+    // row j:[idx = j][ sel: H ][ a[j]: N ][ b[j]: N ][ out[j]: N ][ carry[j]: N ][ out_range: 62N ][ low_carry_bits: 23N ]
+    //       ... one row per addition j = 0, 1, ..., k-1, then zero additions up to the height H
+    fn width(&self) -> usize {
+        batch_add_width(self.height(), self.n)
+    }
+}
+
+impl GadgetLayout for BatchAddAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("idx", 1)
+            .push("sel", self.height())
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("out", self.n)
+            .push("carry", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+    }
+}
+
+fn batch_add_width(height: usize, n: usize) -> usize {
+    1 + height + 4*n + reduced_sums_width(n)
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for BatchAddAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let k = self.a.len();
+        let height = self.height();
+
+        let public_values: Vec<AB::Expr> = builder.public_values().iter().map(|&v| v.into()).collect();
+
+        // Public values of another length than k * N are not the outputs of the batch: reject them instead of indexing past their end
+        if public_values.len() != k*n {
+            builder.when_first_row().assert_one(AB::Expr::zero());
+            return;
+        }
+
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        let idx = 0;
+        let sel = 1;
+        let (a, b) = (1 + height, 1 + height + n);
+        let (out, carry) = (1 + height + 2*n, 1 + height + 3*n);
+
+        // The row index starts at 0 and increases by 1 per row
+        builder.when_first_row().assert_zero(local[idx]);
+        builder.when_transition().assert_eq(next[idx], local[idx] + AB::Expr::one());
+
+        // Enforce sel as the one-hot encoding of idx
+        let mut sel_sum = AB::Expr::zero();
+        let mut sel_index = AB::Expr::zero();
+        for j in 0..height {
+            builder.assert_bool(local[sel+j]);
+            sel_sum = sel_sum + local[sel+j];
+            sel_index = sel_index + local[sel+j] * AB::F::from_canonical_usize(j);
+        }
+        builder.assert_one(sel_sum);
+        builder.assert_eq(sel_index, local[idx]);
+
+        // Enforce a[j], b[j] and out[j] on row j, and zeros on the padding rows
+        for j in 0..height {
+            let mut selected = builder.when(local[sel+j]);
+            for i in 0..n {
+                let (a_j, b_j) = if j < k { (self.a[j][i], self.b[j][i]) } else { (0, 0) };
+                let out_j = if j < k { public_values[j*n+i].clone() } else { AB::Expr::zero() };
+                selected.assert_eq(local[a+i], AB::Expr::from_canonical_u32(a_j));
+                selected.assert_eq(local[b+i], AB::Expr::from_canonical_u32(b_j));
+                selected.assert_eq(local[out+i], out_j);
+            }
+        }
+
+        // Enforce a[i] + b[i] === carry[i] * mod + out[i] on every row, with the lowest limbs of the selected inputs
+        let mask = (1 << LIMB_BITS) - 1;
+        let sums = (0..n).map(|i| local[a+i] + local[b+i]).collect();
+        let sums_low = (0..n).map(|i| {
+            (0..k).fold(AB::Expr::zero(), |low, j| low + local[sel+j] * AB::F::from_canonical_u32((self.a[j][i] & mask) + (self.b[j][i] & mask)))
+        }).collect();
+        let carries = (0..n).map(|i| local[carry+i].into()).collect();
+        eval_reduced_sums(builder, sums, sums_low, carries, &local[out..out+n], &local[carry+n..batch_add_width(height, n)], self.modulus);
+    }
+}
+
+// Define a function to generate execution trace
+// Returns the trace and the public values [out[0][0], ..., out[0][N-1], ..., out[k-1][N-1]]
+pub fn generate_batch_add_trace<F: Field>(a: Vec<Vec<u32>>, b: Vec<Vec<u32>>, modulus: u32, n: usize) -> Result<(RowMajorMatrix<F>, Vec<F>), GadgetError> {
+    if a.len() != b.len() || a.is_empty() {
+        return Err(GadgetError::LengthMismatch { expected: a.len().max(1), actual: b.len() });
+    }
+    for poly in a.iter().chain(b.iter()) {
+        check_poly(poly, n, modulus)?;
+    }
+    check_range_modulus(modulus)?;
+
+    let k = a.len();
+    let height = trace_height(k);
+    let width = batch_add_width(height, n);
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    let mut public_values: Vec<F> = Vec::with_capacity(k * n);
+
+    let zero = vec![0u32; n];
+    for j in 0..height {
+        let (a_j, b_j) = if j < k { (&a[j], &b[j]) } else { (&zero, &zero) };
+
+        // Assign the row index and its one-hot selector
+        values.push(F::from_canonical_usize(j));
+        values.extend((0..height).map(|l| F::from_bool(l == j)));
+
+        // Add the 2 polynomials with their carries
+        // u64 keeps a[i] + b[i] from overflowing for 32-bits moduli
+        let sums: Vec<u64> = (0..n).map(|i| a_j[i] as u64 + b_j[i] as u64).collect();
+        let out_j: Vec<u32> = sums.iter().map(|&s| (s % modulus as u64) as u32).collect();
+        let carry_j: Vec<bool> = sums.iter().map(|&s| s >= modulus as u64).collect();
+        let out: Vec<F> = out_j.iter().map(|&c| F::from_canonical_u32(c)).collect();
+        values.extend(a_j.iter().chain(b_j.iter()).map(|&c| F::from_canonical_u32(c)));
+        values.extend_from_slice(&out);
+        values.extend(carry_j.iter().map(|&c| F::from_bool(c)));
+
+        // Add the range checks of out and the carries of the low limb sums, on the padding rows too
+        let mask = (1 << LIMB_BITS) - 1;
+        let low: Vec<i64> = (0..n).map(|i| (a_j[i] & mask) as i64 + (b_j[i] & mask) as i64).collect();
+        values.extend(reduced_sums_witness::<F>(&low, &carry_j, &out_j, modulus));
+
+        if j < k {
+            public_values.extend(out);
+        }
+    }
+    Ok((RowMajorMatrix::new(values, width), public_values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField32;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::add::assign_reduced_sum;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    fn random_batch(k: usize, n: usize) -> (Vec<Vec<u32>>, Vec<Vec<u32>>) {
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        ((0..k).map(|_| random_poly()).collect(), (0..k).map(|_| random_poly()).collect())
+    }
+
+    #[test]
+    fn test_batch_add() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let (k, n) = (8, 16);
+        let (a, b) = random_batch(k, n);
+
+        let air = BatchAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let (trace, public_values) = generate_batch_add_trace::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+
+        // the verifier's expected outputs, computed on the host
+        let expected: Vec<Val> = (0..k).flat_map(|j| {
+            let (a, b) = (&a[j], &b[j]);
+            (0..n).map(move |i| Val::from_canonical_u32(((a[i] as u64 + b[i] as u64) % P1 as u64) as u32))
+        }).collect();
+        assert_eq!(public_values, expected);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &expected).expect("verification failed");
+    }
+
+    #[test]
+    fn test_batch_add_pads_to_power_of_two() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 5 additions need 5 rows, which are padded to 8 zero-selector rows
+        let (k, n) = (5, 16);
+        let (a, b) = random_batch(k, n);
+
+        let air = BatchAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let (trace, public_values) = generate_batch_add_trace::<Val>(a, b, P1, n).unwrap();
+        assert_eq!(trace_height(k), 8);
+        assert_eq!(trace.height(), trace_height(k));
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &public_values).expect("verification failed");
+    }
+
+    #[test]
+    fn test_batch_add_wrong_output() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let (k, n) = (8, 16);
+        let (a, b) = random_batch(k, n);
+
+        let air = BatchAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let (trace, public_values) = generate_batch_add_trace::<Val>(a, b, P1, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        // one coefficient of the 6th expected output is off by one
+        let mut wrong = public_values.clone();
+        wrong[5*n+3] += Val::one();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        assert!(verify(&config, &air, &mut challenger, &proof, &wrong).is_err(), "batch proof verified against a wrong output");
+
+        // the last expected coefficient is missing
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        assert!(verify(&config, &air, &mut challenger, &proof, &public_values[..k*n-1].to_vec()).is_err(), "batch proof verified against a short output");
+    }
+
+    #[test]
+    fn test_batch_add_forged_carry() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // (P1-1) + (P1-1) on row 1 is above n: a carry of 0 with out = 2 * P1 - 2 - n still holds mod n
+        // with out in [0, P1), and is exposed as the public output, but does not hold mod 2^8
+        let (k, n) = (2, 4);
+        let (mut a, mut b) = random_batch(k, n);
+        (a[1][0], b[1][0]) = (P1 - 1, P1 - 1);
+        let air = BatchAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let (mut trace, mut public_values) = generate_batch_add_trace::<Val>(a, b, P1, n).unwrap();
+
+        let forged = (2 * P1 as u64 - 2 - Val::ORDER_U32 as u64) as u32;
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap().start;
+        let width = trace.width();
+        let row = &mut trace.values[width..2*width];
+        row[col("out")] = Val::from_canonical_u32(forged);
+        row[col("carry")] = Val::zero();
+        let (range, bits) = (col("out_range"), col("low_carry_bits"));
+        let mask = (1 << LIMB_BITS) - 1;
+        let (out_range, low_carry_bits) = row.split_at_mut(bits);
+        assign_reduced_sum(&mut out_range[range..range + RANGE_CHECK_WIDTH], &mut low_carry_bits[..MUL_CARRY_BITS], 2 * ((P1 - 1) & mask) as i64, false, forged, P1);
+        public_values[n] = Val::from_canonical_u32(forged);
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &public_values).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "batch proof with a forged carry was accepted");
+    }
+}
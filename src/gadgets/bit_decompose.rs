@@ -0,0 +1,189 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::pad_trace;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct BitDecomposeAir {
+    pub a: Vec<u32>,
+    // number of bits per coefficient, at most RANGE_CHECK_BITS
+    pub num_bits: usize,
+    pub n: usize
+}
+
+impl BitDecomposeAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, num_bits: usize) -> Self {
+        Self { a, num_bits, n: N }
+    }
+}
+
+/*
+Bit Decomposition Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- num_bits: number of bits per coefficient
+Output:
+- bits = bits[i][0], ..., bits[i][num_bits-1]: little-endian bit decomposition of a[i]
+
+Note:
+- Every bits[i][k] is constrained to be boolean with bits[i][k] * (bits[i][k] - 1) === 0,
+and a[i] === sum_k bits[i][k] * 2^k.
+- The sum is taken in the native field, so it proves a[i] < 2^num_bits only for num_bits < 31:
+with 31 bits, every element of the native field has a representative, and 2^31 - 1 wraps around to 0.
+RangeCheckAir compares the 31 bits with the modulus on top of this decomposition to fix the integer.
+- eval_bit_decompose() and eval_from_bits() are the constraints of this gadget, which every gadget proving
+the bits of one of its columns (range checks, comparisons, gadget decomposition, limbs) embeds.
+*/
+impl<F: Field> BaseAir<F> for BitDecomposeAir {
+    // Air Table looks like this
+    // row:[ a: N ][ bits: num_bits * N ]
+    //     ^input^^-calculated by generate_bit_decompose_trace-^
+    //     [0...............................0]
+    //     [0...............................0]
+    //     [0...............................0]
+    fn width(&self) -> usize {
+        (1+self.num_bits)*self.n
+    }
+}
+
+impl GadgetLayout for BitDecomposeAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("bits", self.num_bits*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for BitDecomposeAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+
+        // Enforce a[i] === sum_k bits[i][k] * 2^k, with every bits[i][k] boolean
+        for i in 0..n {
+            let bits = n + i*self.num_bits;
+            eval_bit_decompose(builder, row[i].into(), &row[bits..bits+self.num_bits]);
+        }
+    }
+}
+
+// Enforce every column of `bits` to be a bit, and return the little-endian integer they represent
+pub(crate) fn eval_from_bits<AB: AirBuilder>(builder: &mut AB, bits: &[AB::Var]) -> AB::Expr {
+    let mut sum = AB::Expr::zero();
+    for (b, &bit) in bits.iter().enumerate() {
+        builder.assert_bool(bit);
+        sum = sum + bit * AB::F::from_wrapped_u64(1 << b);
+    }
+    sum
+}
+
+// Enforce value === sum_k bits[k] * 2^k, with every column of `bits` a bit
+pub(crate) fn eval_bit_decompose<AB: AirBuilder>(builder: &mut AB, value: AB::Expr, bits: &[AB::Var]) {
+    let sum = eval_from_bits(builder, bits);
+    builder.assert_eq(value, sum);
+}
+
+// Little-endian bits of x
+pub(crate) fn bits(x: u64, count: usize) -> impl Iterator<Item = bool> {
+    (0..count).map(move |b| (x >> b) & 1 == 1)
+}
+
+// Define a function to generate execution trace
+pub fn generate_bit_decompose_trace<F: Field>(a: Vec<u32>, num_bits: usize, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if a.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: a.len() });
+    }
+    if let Some(index) = a.iter().position(|&c| (c as u64) >> num_bits != 0) {
+        return Err(GadgetError::ValueOutOfRange { index, bits: num_bits });
+    }
+    let width = (1+num_bits)*n;
+
+    let mut values: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial, then the bits of every coefficient
+    values.extend(a.iter().map(|&c| F::from_wrapped_u32(c)));
+    for &c in a.iter() {
+        values.extend(bits(c as u64, num_bits).map(F::from_bool));
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows: 0 is the decomposition of 0
+    Ok(pad_trace(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::range_check::RANGE_CHECK_BITS;
+    use crate::params::P1;
+
+    #[test]
+    fn test_bit_decompose() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // decompose mod - 1, 0 and 1 into 31 bits
+        let n = 4;
+        let poly = vec![P1 - 1, 0, 1, P1 / 2];
+        let trace = generate_bit_decompose_trace::<Val>(poly.clone(), RANGE_CHECK_BITS, n).unwrap();
+
+        // reconstruct mod - 1 from its bits
+        let row = trace.row_slice(0);
+        let reconstructed = (0..RANGE_CHECK_BITS).fold(0u64, |sum, k| {
+            sum + ((row[n+k] == Val::one()) as u64) * (1 << k)
+        });
+        assert_eq!(reconstructed, (P1 - 1) as u64);
+        drop(row);
+
+        let air = BitDecomposeAir { a:poly, num_bits:RANGE_CHECK_BITS, n };
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_bit_decompose_non_boolean() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 2 = 2 * 2^0 has the right sum, but a bit of 2
+        let n = 1;
+        let air = BitDecomposeAir { a:vec![2], num_bits:4, n };
+        let mut trace = generate_bit_decompose_trace::<Val>(vec![2], 4, n).unwrap();
+        trace.values[1] = Val::two();
+        trace.values[2] = Val::zero();
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof with a non-boolean bit was accepted");
+
+        // a value with more than num_bits bits has no decomposition
+        assert!(matches!(
+            generate_bit_decompose_trace::<Val>(vec![16], 4, n),
+            Err(GadgetError::ValueOutOfRange { index: 0, bits: 4 })
+        ));
+    }
+}
@@ -0,0 +1,148 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+
+// Define AIR constraint inputs
+pub struct BitReverseAir {
+    pub input: Vec<u32>,
+    // the input has 2^log_n elements
+    pub log_n: usize
+}
+
+impl BitReverseAir {
+    // Number of permuted elements
+    fn n(&self) -> usize {
+        1 << self.log_n
+    }
+}
+
+/*
+Bit-Reversal Permutation Air
+Input:
+- input = input[0], ..., input[2^log_n - 1]
+Output:
+- out: out[i] = input[bitrev(i, log_n)], where bitrev reverses the log_n low bits of i
+
+Note:
+- This is the reordering of the radix-2 NTTs, which take their input or return their output in bit-reversed order.
+- bitrev(_, log_n) is an involution, so the permutation is its own inverse: applying it twice gives the input back.
+- The permutation only depends on log_n, so it is baked into the constraints as in GaloisAutomorphismAir,
+with one equality per element and no extra column.
+- The elements are read modulo n, so the input is not bounded by a modulus: any u32 is permuted as its residue.
+*/
+impl<F: Field> BaseAir<F> for BitReverseAir {
+    // Air Table looks like this
+    // row:[ input: 2^log_n ][ out: 2^log_n ]
+    //     ^-----input------^^-calculated by generate_bit_reverse_trace
+    //     [0...............................0]
+    //     [0...............................0]
+    //     [0...............................0]
+    fn width(&self) -> usize {
+        2*self.n()
+    }
+}
+
+impl GadgetLayout for BitReverseAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("input", self.n())
+            .push("out", self.n())
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for BitReverseAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n();
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.input as the input
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_wrapped_u32(self.input[i]));
+        }
+
+        // Enforce out[i] === input[bitrev(i, log_n)]
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[n+i], row[bit_reverse(i, self.log_n)]);
+        }
+    }
+}
+
+// Reverse the log_n low bits of i
+pub fn bit_reverse(i: usize, log_n: usize) -> usize {
+    if log_n == 0 { 0 } else { i.reverse_bits() >> (usize::BITS as usize - log_n) }
+}
+
+// Define a function to generate execution trace
+pub fn generate_bit_reverse_trace<F: Field>(input: Vec<u32>, log_n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let n = 1 << log_n;
+    if input.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: input.len() });
+    }
+
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * 2*n);
+
+    // Add the input, then the input in bit-reversed order to values vector
+    values.extend(input.iter().map(|&x| F::from_wrapped_u32(x)));
+    values.extend((0..n).map(|i| F::from_wrapped_u32(input[bit_reverse(i, log_n)])));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 2*n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejects_corrupted;
+    use crate::params::P1;
+
+    #[test]
+    fn test_bit_reverse() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 0b000, 0b001, ..., 0b111 reversed on 3 bits
+        let log_n = 3;
+        let table = [0, 4, 2, 6, 1, 5, 3, 7];
+        for (i, &j) in table.iter().enumerate() {
+            assert_eq!(bit_reverse(i, log_n), j);
+            assert_eq!(bit_reverse(j, log_n), i);
+        }
+        assert_eq!(bit_reverse(0, 0), 0);
+
+        let mut rng = thread_rng();
+        let input: Vec<u32> = (0..8).map(|_| rng.gen_range(0..P1)).collect();
+        let air = BitReverseAir { input:input.clone(), log_n };
+        let trace = generate_bit_reverse_trace::<Val>(input.clone(), log_n).unwrap();
+        let out = air.layout().get("out").unwrap();
+        let row = trace.row_slice(0);
+        for i in 0..8 {
+            assert_eq!(row[out.start+i], Val::from_canonical_u32(input[table[i]]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace.clone(), &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        assert_rejects_corrupted(&air, &trace, "out");
+    }
+
+    #[test]
+    fn test_bit_reverse_length_mismatch() {
+        assert_eq!(
+            generate_bit_reverse_trace::<Val>(vec![0; 6], 3).unwrap_err(),
+            GadgetError::LengthMismatch { expected: 8, actual: 6 }
+        );
+    }
+}
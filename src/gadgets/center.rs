@@ -0,0 +1,245 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::noise_bound::center;
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct CenterAir {
+    pub poly: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl CenterAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, modulus: u32) -> Self {
+        Self { poly, modulus, n: N }
+    }
+}
+
+/*
+Centered Representation Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1} with coefficients in [0, q)
+- mod: q
+Output:
+- out[i] = a[i] if a[i] <= q/2, else a[i] - q, i.e. the representative of a[i] in (-q/2, q/2]
+
+Note:
+- As in NoiseBoundAir, the centered value is written as a sign bit neg[i] and a magnitude mag[i]:
+    a[i] === mag[i] + neg[i] * (q - 2 * mag[i])
+    out[i] === mag[i] - 2 * neg[i] * mag[i]
+so out[i] is the centered value as a native field element, negative values being n - mag[i].
+- (-q/2, q/2] contains q/2 itself only when q is even, so the magnitude bound depends on the parity of q:
+    q odd:  0 <= mag[i] <= (q-1)/2 for either sign
+    q even: 0 <= mag[i] <= q/2 when neg[i] = 0, and mag[i] <= q/2 - 1 when neg[i] = 1
+Both are proven with one comparison, mag[i] + neg[i] * even < floor(q/2) + 1 where even = 1 - q mod 2,
+which rejects the second decomposition q/2 = q - q/2 of the boundary coefficient when q is even.
+- neg[i] = 1 with mag[i] = 0 would need a[i] = q, which the input constraint excludes.
+*/
+impl<F: Field> BaseAir<F> for CenterAir {
+    // Air Table looks like this
+    // row:[ a: N ][ neg: N ][ mag: N ][ out: N ][ bits: 31N ][ eq: 31N ]
+    //     ^input^^-------------calculated by generate_center_trace-------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        (4 + 2*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for CenterAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("neg", self.n)
+            .push("mag", self.n)
+            .push("out", self.n)
+            .push("bits", RANGE_CHECK_BITS*self.n)
+            .push("eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for CenterAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (a, neg, mag, out) = (0, n, 2*n, 3*n);
+        let bits = 4*n;
+        let eq = bits + RANGE_CHECK_BITS*n;
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+        let even = AB::F::from_bool(self.modulus % 2 == 0);
+
+        // Enforce self.poly as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[a+i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+
+        for i in 0..n {
+            // Enforce a[i] === mag[i] + neg[i] * (q - 2 * mag[i])
+            builder.assert_bool(row[neg+i]);
+            builder.assert_eq(row[a+i], row[mag+i] + row[neg+i] * (AB::Expr::from(modulus) - row[mag+i] * AB::F::two()));
+
+            // Enforce out[i] === mag[i] - 2 * neg[i] * mag[i]
+            builder.assert_eq(row[out+i], row[mag+i] - row[neg+i] * row[mag+i] * AB::F::two());
+
+            // Enforce 0 <= mag[i] + neg[i] * even < floor(q/2) + 1
+            let bits = bits + i*RANGE_CHECK_BITS;
+            let eq = eq + i*RANGE_CHECK_BITS;
+            let value = row[mag+i] + row[neg+i] * even;
+            eval_range_check(builder, value, &row[bits..bits+RANGE_CHECK_BITS], &row[eq..eq+RANGE_CHECK_BITS], self.modulus / 2 + 1);
+        }
+    }
+}
+
+// Centered representative of c mod modulus in (-modulus/2, modulus/2]
+pub fn centered(c: u32, modulus: u32) -> i64 {
+    match center(c, modulus) {
+        (false, mag) => mag as i64,
+        (true, mag) => -(mag as i64),
+    }
+}
+
+// Representative in [0, modulus) of every signed coefficient of `poly`, the inverse of centered()
+// Coefficients outside of (-modulus/2, modulus/2] are rejected rather than wrapped, since they are not centered
+pub fn uncentered(poly: &[i64], modulus: u32) -> Result<Vec<u32>, GadgetError> {
+    poly.iter().enumerate().map(|(index, &value)| {
+        let m = modulus as i64;
+        if 2*value <= -m || 2*value > m {
+            return Err(GadgetError::SignedCoefficientOutOfRange { index, value, modulus });
+        }
+        Ok(if value < 0 { (m + value) as u32 } else { value as u32 })
+    }).collect()
+}
+
+// Define a function to generate execution trace
+pub fn generate_center_trace<F: Field>(poly: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+
+    let width = (4 + 2*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial, signs, magnitudes and centered values to the row
+    let signed: Vec<(bool, u32)> = poly.iter().map(|&c| center(c, modulus)).collect();
+    row.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    row.extend(signed.iter().map(|&(neg, _)| F::from_bool(neg)));
+    row.extend(signed.iter().map(|&(_, mag)| F::from_canonical_u32(mag)));
+    row.extend(signed.iter().map(|&(neg, mag)| if neg { -F::from_canonical_u32(mag) } else { F::from_canonical_u32(mag) }));
+
+    // Assign bits and prefix equality flags of every mag[i] + neg[i] * even against floor(q/2) + 1
+    let even = (modulus % 2 == 0) as u32;
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = signed.iter()
+        .map(|&(neg, mag)| range_check_columns(mag + neg as u32 * even, modulus / 2 + 1))
+        .collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    // random coefficients, with the ones around q/2 and both ends of [0, q)
+    fn boundary_poly(n: usize, modulus: u32) -> Vec<u32> {
+        let mut rng = thread_rng();
+        let mut poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+        let half = modulus / 2;
+        poly[..6].copy_from_slice(&[half - 1, half, half + 1, half + 2, 0, modulus - 1]);
+        poly
+    }
+
+    #[test]
+    fn test_center() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 8;
+        // P1 is odd, and 2^20 is even so that q/2 itself is in (-q/2, q/2]
+        for modulus in [P1, 1 << 20] {
+            let poly = boundary_poly(n, modulus);
+            let half = (modulus / 2) as i64;
+            let q = modulus as i64;
+            // floor(q/2) stays positive for either parity, and the next coefficient is the most negative one
+            let expected = [half - 1, half, half + 1 - q, half + 2 - q, 0, -1];
+            for k in 0..6 {
+                assert_eq!(centered(poly[k], modulus), expected[k]);
+            }
+
+            let air = CenterAir { poly:poly.clone(), modulus, n };
+            let trace = generate_center_trace::<Val>(poly.clone(), modulus, n).unwrap();
+
+            let row = trace.row_slice(0);
+            for i in 0..n {
+                let c = centered(poly[i], modulus);
+                let value = if c < 0 { -Val::from_canonical_u32((-c) as u32) } else { Val::from_canonical_u32(c as u32) };
+                assert_eq!(row[3*n+i], value);
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_center_even_boundary_sign() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // With q even, q/2 = q - q/2 also satisfies the sign and magnitude identity with neg = 1,
+        // which would center q/2 to -q/2 outside (-q/2, q/2]
+        let n = 8;
+        let modulus = 1 << 20;
+        let half = modulus / 2;
+        let poly = boundary_poly(n, modulus);
+
+        let air = CenterAir { poly:poly.clone(), modulus, n };
+        let mut trace = generate_center_trace::<Val>(poly, modulus, n).unwrap();
+
+        // poly[1] = q/2: flip its sign to -q/2, with consistent bits for mag + neg = q/2 + 1
+        let (bits, eq) = range_check_columns(half + 1, half + 1);
+        let width = trace.width();
+        for r in 0..4 {
+            let row = &mut trace.values[r*width..(r+1)*width];
+            row[n+1] = Val::one();
+            row[3*n+1] = -Val::from_canonical_u32(half);
+            for k in 0..RANGE_CHECK_BITS {
+                row[4*n + RANGE_CHECK_BITS + k] = Val::from_bool(bits[k]);
+                row[4*n + (n+1)*RANGE_CHECK_BITS + k] = Val::from_bool(eq[k]);
+            }
+        }
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "q/2 centered to -q/2 was accepted");
+    }
+}
@@ -0,0 +1,149 @@
+use std::ops::Range;
+use p3_field::{AbstractField, PrimeField32};
+use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+use crate::gadgets::config::{Val, ZkConfig};
+use crate::gadgets::error::{check_air_inputs, check_nonempty, check_poly, GadgetError};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::io::{prove_air_with_public_values, verify_air_outputs, ProofIoError, ZkProof};
+use crate::params::N;
+
+// Define the inputs of the chunked addition
+pub struct ChunkedAddAir {
+    // polynomials of any number of coefficients, the same for both
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    // number of coefficients per chunk
+    pub chunk_n: usize
+}
+
+impl ChunkedAddAir {
+    // Construct the chunked addition with chunks of the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, modulus, chunk_n: N }
+    }
+
+    // Number of chunks, the last of which holds the remaining a.len() % chunk_n coefficients if any
+    pub fn num_chunks(&self) -> usize {
+        self.a.len().div_ceil(self.chunk_n)
+    }
+
+    // Coefficients of the chunk j
+    fn range(&self, j: usize) -> Range<usize> {
+        j*self.chunk_n..((j+1)*self.chunk_n).min(self.a.len())
+    }
+
+    // PolyAddAir proving the chunk j of a + b
+    pub fn chunk(&self, j: usize) -> PolyAddAir {
+        let range = self.range(j);
+        PolyAddAir { a: self.a[range.clone()].to_vec(), b: self.b[range.clone()].to_vec(), modulus: self.modulus, n: range.len() }
+    }
+}
+
+/*
+Chunked Polynomial Addition
+Input:
+- a, b: polynomials with any number of coefficients L, e.g. more than a single PolyAddAir handles efficiently
+- mod: FHE ciphertext modulus
+- chunk_n: number of coefficients per chunk
+Output:
+- out = (a + b) % mod, and one PolyAddAir proof per chunk of chunk_n coefficients
+
+Note:
+- out[i] only depends on a[i] and b[i]: the addition has no carry from one coefficient to the next,
+so the chunks are independent and each is proven on its own, without linking constraints between the proofs.
+- Every proof binds its chunk of out through the public values, as in PolynomialOpAir::eval_outputs(),
+and its chunk of a and b through the input constraints of the PolyAddAir of the chunk, so a proof only verifies
+at the position of the chunk it was proven for, and the proofs together prove the concatenation out.
+- Multiplication does not chunk this way: out[i] of a product depends on every a[j] * b[i-j], so the chunks
+would need to overlap by the 2N-1 coefficients of the partial products and their sums be linked across proofs.
+*/
+
+// Prove every chunk of a + b, returning the sum out and the proofs of its chunks in order
+pub fn prove_chunked_add(zk_config: &ZkConfig, air: &ChunkedAddAir) -> Result<(Vec<u32>, Vec<ZkProof>), GadgetError> {
+    check_nonempty(air.chunk_n)?;
+    check_poly(&air.a, air.a.len(), air.modulus)?;
+    check_poly(&air.b, air.a.len(), air.modulus)?;
+
+    let mut out = Vec::with_capacity(air.a.len());
+    let mut proofs = Vec::with_capacity(air.num_chunks());
+    for j in 0..air.num_chunks() {
+        let chunk = air.chunk(j);
+        let trace = generate_polyadd_trace::<Val>(chunk.a.clone(), chunk.b.clone(), chunk.modulus, chunk.n)?;
+        let public_values = chunk.public_outputs(&trace);
+        out.extend(public_values.iter().map(|x| x.as_canonical_u32()));
+        proofs.push(prove_air_with_public_values(zk_config, &chunk, trace, &public_values));
+    }
+    Ok((out, proofs))
+}
+
+// Verify the proofs produced by prove_chunked_add() against the claimed sum `out` of the whole polynomials
+pub fn verify_chunked_add(zk_config: &ZkConfig, air: &ChunkedAddAir, out: &[u32], proofs: &[ZkProof]) -> Result<(), ProofIoError> {
+    check_nonempty(air.chunk_n).map_err(ProofIoError::InvalidPolynomial)?;
+    check_air_inputs(&[&air.b, out], air.a.len(), air.modulus).map_err(ProofIoError::InvalidPolynomial)?;
+    if proofs.len() != air.num_chunks() {
+        return Err(ProofIoError::ChunkCount { expected: air.num_chunks(), actual: proofs.len() });
+    }
+
+    for (j, proof) in proofs.iter().enumerate() {
+        let expected: Vec<Val> = out[air.range(j)].iter().map(|&c| Val::from_wrapped_u32(c)).collect();
+        verify_air_outputs(zk_config, &air.chunk(j), proof, &expected).map_err(|error| ProofIoError::Bundle { index: j, error })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use crate::gadgets::config::initialize_config;
+    use crate::gadgets::reference;
+    use crate::params::P1;
+
+    #[test]
+    fn test_chunked_add() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        // 2 polynomials of 2N coefficients, added in 2 chunks of N
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..2*N).map(|_| rng.gen_range(0..P1)).collect();
+        let b: Vec<u32> = (0..2*N).map(|_| rng.gen_range(0..P1)).collect();
+        let air = ChunkedAddAir::new(a.clone(), b.clone(), P1);
+        assert_eq!(air.num_chunks(), 2);
+
+        let (out, proofs) = prove_chunked_add(&zk_config, &air).unwrap();
+        let widen = |poly: &[u32]| poly.iter().map(|&c| c as u128).collect::<Vec<_>>();
+        assert_eq!(widen(&out), reference::add(&widen(&a), &widen(&b), P1 as u128));
+        verify_chunked_add(&zk_config, &air, &out, &proofs)?;
+
+        // a wrong coefficient in the second chunk, swapped or missing chunk proofs are rejected
+        let mut wrong = out.clone();
+        wrong[N+1] = (wrong[N+1] + 1) % P1;
+        assert!(matches!(verify_chunked_add(&zk_config, &air, &wrong, &proofs), Err(ProofIoError::Bundle { index: 1, .. })));
+        let swapped: Vec<ZkProof> = proofs.into_iter().rev().collect();
+        assert!(matches!(verify_chunked_add(&zk_config, &air, &out, &swapped), Err(ProofIoError::Bundle { index: 0, .. })));
+        assert!(matches!(
+            verify_chunked_add(&zk_config, &air, &out, &swapped[..1]),
+            Err(ProofIoError::ChunkCount { expected: 2, actual: 1 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunked_add_partial_chunk() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        // 10 coefficients in chunks of 4: the last chunk holds the remaining 2
+        let a: Vec<u32> = (0..10).map(|i| P1 - 1 - i).collect();
+        let b: Vec<u32> = (0..10).map(|i| 2*i).collect();
+        let air = ChunkedAddAir { a:a.clone(), b:b.clone(), modulus:P1, chunk_n: 4 };
+        assert_eq!(air.num_chunks(), 3);
+        assert_eq!(air.chunk(2).n, 2);
+
+        let (out, proofs) = prove_chunked_add(&zk_config, &air).unwrap();
+        assert_eq!(out, (0..10).map(|i| (i + P1 - 1) % P1).collect::<Vec<_>>());
+        verify_chunked_add(&zk_config, &air, &out, &proofs)
+    }
+}
@@ -0,0 +1,249 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// A BFV/BGV ciphertext (c0, c1): 2 polynomials with N coefficients mod the ciphertext modulus
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ciphertext {
+    pub c0: Vec<u32>,
+    pub c1: Vec<u32>
+}
+
+impl Ciphertext {
+    pub fn new(c0: Vec<u32>, c1: Vec<u32>) -> Self {
+        Self { c0, c1 }
+    }
+
+    // Check that both components have `n` coefficients in [0, modulus)
+    pub fn check(&self, n: usize, modulus: u32) -> Result<(), GadgetError> {
+        check_poly(&self.c0, n, modulus)?;
+        check_poly(&self.c1, n, modulus)
+    }
+}
+
+// Define AIR constraint inputs
+pub struct CiphertextAddAir {
+    pub a: Ciphertext,
+    pub b: Ciphertext,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl CiphertextAddAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Ciphertext, b: Ciphertext, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+}
+
+/*
+Ciphertext Addition Air
+Input:
+- a = (a0, a1), b = (b0, b1): 2 ciphertexts, each a pair of polynomials with N coefficients
+- mod: FHE ciphertext modulus
+Output:
+- out = (out0, out1) where out0[i] = (a0[i] + b0[i]) % mod and out1[i] = (a1[i] + b1[i]) % mod
+- carry = carry0, carry1: 1 if the component sum wrapped around mod, otherwise 0
+
+Note:
+- CiphertextAddAir does not have a state transition. Both components are added in one row, so a single proof covers the ciphertext.
+- a[i] + b[i] lies in [0, 2p-1), so at most one multiple of p (the carry) is needed to bring it into [0, p).
+As in PolySubAir, we enforce a[i] + b[i] === carry[i] * mod + out[i] with carry[i] constrained to be a bit.
+- out0 and out1 are range checked into [0, mod) and the sums are also enforced mod 2^8 over the lowest 8-bits limbs,
+through eval_reduced_sum() as in PolyAddAir: a sum is above n for large moduli, so mod n alone would take the other carry.
+*/
+impl<F: Field> BaseAir<F> for CiphertextAddAir {
+    // Air Table looks like this
+    // row:[a0: N][a1: N][b0: N][b1: N][mod:1][out0: N][out1: N][carry0: N][carry1: N][out_range: 124N][low_carry_bits: 46N]
+    //     ^---------------inputs-------------^^-------------------calculated by generate_ciphertext_add_trace----------------^
+    //     [0.................................................................................................................0]
+    //     [0.................................................................................................................0]
+    //     [0.................................................................................................................0]
+    fn width(&self) -> usize {
+        ciphertext_add_width(self.n)
+    }
+}
+
+fn ciphertext_add_width(n: usize) -> usize {
+    8*n+1 + reduced_sums_width(2*n)
+}
+
+impl GadgetLayout for CiphertextAddAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a0", self.n)
+            .push("a1", self.n)
+            .push("b0", self.n)
+            .push("b1", self.n)
+            .push("mod", 1)
+            .push("out0", self.n)
+            .push("out1", self.n)
+            .push("carry0", self.n)
+            .push("carry1", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*2*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*2*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for CiphertextAddAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a and self.b as 2 input ciphertexts
+        let inputs = [&self.a.c0, &self.a.c1, &self.b.c0, &self.b.c1];
+        for (k, poly) in inputs.iter().enumerate() {
+            for i in 0..n {
+                builder.when_first_row().assert_eq(row[k*n+i], AB::Expr::from_canonical_u32(poly[i]));
+            }
+        }
+
+        // Enforce self.modulus as mod
+        let mod_col = 4*n;
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce a_c[i] + b_c[i] === carry_c[i] * mod + out_c[i] for both components c = 0, 1, as 2N sums
+        // over out = (out0, out1) and carry = (carry0, carry1), from the constant limbs of the inputs
+        let mask = (1 << LIMB_BITS) - 1;
+        let (out, carry) = (4*n+1, 6*n+1);
+        let mut sums = Vec::with_capacity(2*n);
+        let mut sums_low = Vec::with_capacity(2*n);
+        let mut carries = Vec::with_capacity(2*n);
+        for c in 0..2 {
+            let (a, b) = (c*n, (2+c)*n);
+            let (x, y) = (inputs[c], inputs[2+c]);
+            for i in 0..n {
+                sums.push(row[a+i] + row[b+i]);
+                sums_low.push(AB::Expr::from_canonical_u32((x[i] & mask) + (y[i] & mask)));
+                carries.push(row[carry + c*n + i].into());
+            }
+        }
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &row[out..out + 2*n], &row[8*n+1..ciphertext_add_width(n)], self.modulus);
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_ciphertext_add_trace<F: Field>(a: Ciphertext, b: Ciphertext, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    a.check(n, modulus)?;
+    b.check(n, modulus)?;
+
+    check_range_modulus(modulus)?;
+
+    // Add the 2 ciphertexts componentwise
+    // u64 keeps a[i] + b[i] from overflowing for 32-bits moduli
+    let sums: Vec<u64> = [(&a.c0, &b.c0), (&a.c1, &b.c1)].iter()
+        .flat_map(|(x, y)| (0..n).map(|i| x[i] as u64 + y[i] as u64))
+        .collect();
+    let out: Vec<u32> = sums.iter().map(|&s| (s % modulus as u64) as u32).collect();
+    let carry: Vec<bool> = sums.iter().map(|&s| s >= modulus as u64).collect();
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(ciphertext_add_row(&a, &b, &out, &carry, modulus), ciphertext_add_width(n)))
+}
+
+// Row of the sums out = (out0, out1) of a and b with their carries, the range checks of out and the carries of the low limb sums
+fn ciphertext_add_row<F: Field>(a: &Ciphertext, b: &Ciphertext, out: &[u32], carry: &[bool], modulus: u32) -> Vec<F> {
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * ciphertext_add_width(a.c0.len()));
+
+    // Add input ciphertexts, modulus, the sums and the carries to values vector
+    for poly in [&a.c0, &a.c1, &b.c0, &b.c1] {
+        values.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    }
+    values.push(F::from_canonical_u32(modulus));
+    values.extend(out.iter().map(|&c| F::from_canonical_u32(c)));
+    values.extend(carry.iter().map(|&c| F::from_bool(c)));
+
+    // Add the range checks of out and the carries of the low limb sums
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = a.c0.iter().chain(&a.c1).zip(b.c0.iter().chain(&b.c1)).map(|(&x, &y)| (x & mask) as i64 + (y & mask) as i64).collect();
+    values.extend(reduced_sums_witness::<F>(&low, carry, out, modulus));
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use p3_field::PrimeField32;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::params::P1;
+
+    fn random_ciphertext(n: usize) -> Ciphertext {
+        let mut rng = thread_rng();
+        Ciphertext::new(
+            (0..n).map(|_| rng.gen_range(0..P1)).collect(),
+            (0..n).map(|_| rng.gen_range(0..P1)).collect(),
+        )
+    }
+
+    #[test]
+    fn test_ciphertext_add() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // force a wrap-around in both components
+        let n = 16;
+        let mut a = random_ciphertext(n);
+        let mut b = random_ciphertext(n);
+        a.c0[0] = P1 - 1;
+        b.c0[0] = P1 - 1;
+        a.c1[n-1] = P1 - 1;
+        b.c1[n-1] = 1;
+
+        let air = CiphertextAddAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+
+        let trace = generate_ciphertext_add_trace::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+
+        // both output components match the host-side sums
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let out0 = ((a.c0[i] as u64 + b.c0[i] as u64) % P1 as u64) as u32;
+            let out1 = ((a.c1[i] as u64 + b.c1[i] as u64) % P1 as u64) as u32;
+            assert_eq!(row[4*n+1+i], Val::from_canonical_u32(out0));
+            assert_eq!(row[5*n+1+i], Val::from_canonical_u32(out1));
+        }
+        assert_eq!(row[4*n+1], Val::from_canonical_u32(P1 - 2));
+        assert_eq!(row[5*n+1+n-1], Val::zero());
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_ciphertext_add_forged_carry() {
+        // (P1-1) + (P1-1) is above n: a carry of 0 with out = 2 * P1 - 2 - n still holds mod n with out in [0, P1),
+        // in either component, but not mod 2^8
+        let n = 4;
+        let a = Ciphertext::new(vec![P1 - 1; n], vec![P1 - 1; n]);
+        let air = CiphertextAddAir { a: a.clone(), b: a.clone(), modulus: P1, n };
+        let forged = (2 * P1 as u64 - 2 - Val::ORDER_U32 as u64) as u32;
+        let honest = P1 - 2;
+
+        for c in 0..2 {
+            let mut out = vec![honest; 2*n];
+            let mut carry = vec![true; 2*n];
+            out[c*n] = forged;
+            carry[c*n] = false;
+            let trace = pad_trace(ciphertext_add_row::<Val>(&a, &a, &out, &carry, P1), ciphertext_add_width(n));
+            assert_rejected(&air, trace, &format!("a forged carry{}", c));
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_low, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_nonempty, check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct CoeffSumAir {
+    pub poly: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl CoeffSumAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, modulus: u32) -> Self {
+        Self { poly, modulus, n: N }
+    }
+}
+
+/*
+Coefficient Sum Air
+Input:
+- poly = poly[0] + poly[1] * X + ... + poly[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = (poly[0] + ... + poly[N-1]) % mod = poly(1) % mod
+- acc = acc[0], ..., acc[N-1]: running sums, with out = acc[N-1]
+- carry = carry[1], ..., carry[N-1]: carry[i] = 1 when acc[i-1] + poly[i] >= mod
+
+Note:
+- The sum is accumulated from the lowest coefficient up, one addition per coefficient as in CiphertextAddAir:
+    acc[0] === poly[0]
+    acc[i-1] + poly[i] === carry[i] * mod + acc[i]    for i = 1, ..., N-1
+so every constraint is linear, except carry[i] being a bit, which is of degree 2.
+- This is PolyEvalAir at x = 1 without the multiplications: the Horner steps have a quotient of up to mod
+instead of a carry bit, and read x from a column.
+- acc[i-1] + poly[i] is at most 2 * mod - 2 < 2^32, which overflows n: with acc[i] range checked into [0, mod),
+the difference of both sides of a step is in (-2 * mod, 2 * mod), so it is 0 once it is 0 mod n and mod 2^8.
+Every step goes through eval_reduced_sum() as in PolyAddAir, enforcing it mod 2^8 over the lowest 8-bits limbs
+with a single offset carry:
+    acc[i-1]_0 + poly[i]_0 - carry[i] * mod_0 - acc[i]_0 === 2^8 * c[i]
+where acc[i-1]_0 is read from the range check of acc[i-1], and acc[0]_0 = poly[0]_0 is a constant.
+- The range checks and the low limb steps are enforced on the first row, as in PolyMulAir.
+*/
+impl<F: Field> BaseAir<F> for CoeffSumAir {
+    // Air Table looks like this
+    // row:[  poly: N  ][  acc: N  ][ carry: N-1 ][ acc_range: 62(N-1) ][ low_carry_bits: 23(N-1) ]
+    //     ^--inputs--^^-----------------calculated by generate_coeff_sum_trace-----------------^
+    //     [0.............................................................................0]
+    //     [0.............................................................................0]
+    //     [0.............................................................................0]
+    fn width(&self) -> usize {
+        coeff_sum_width(self.n)
+    }
+}
+
+fn coeff_sum_width(n: usize) -> usize {
+    3*n-1 + reduced_sums_width(n-1)
+}
+
+impl GadgetLayout for CoeffSumAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("poly", self.n)
+            .push("acc", self.n)
+            .push("carry", self.n-1)
+            .push("acc_range", RANGE_CHECK_WIDTH*(self.n-1))
+            .push("low_carry_bits", MUL_CARRY_BITS*(self.n-1))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for CoeffSumAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.poly as input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+
+        // Enforce acc[0] === poly[0] and every step acc[i-1] + poly[i] === carry[i] * mod + acc[i] for i = [1..N),
+        // over the lowest limbs of acc[i-1] and of the constant poly[i]
+        let (acc, carry, acc_range) = (n, 2*n, 3*n-1);
+        builder.assert_eq(row[acc], row[0]);
+
+        let mask = (1 << LIMB_BITS) - 1;
+        let low_limb = |i: usize| -> AB::Expr {
+            if i == 0 {
+                AB::Expr::from_canonical_u32(self.poly[0] & mask)
+            } else {
+                let block = acc_range + (i-1)*RANGE_CHECK_WIDTH;
+                reduced_low::<AB>(&row[block..block + RANGE_CHECK_WIDTH])
+            }
+        };
+        let sums = (1..n).map(|i| row[acc+i-1] + row[i]).collect();
+        let sums_low = (1..n).map(|i| low_limb(i-1) + AB::F::from_canonical_u32(self.poly[i] & mask)).collect();
+        let carries = (1..n).map(|i| row[carry+i-1].into()).collect();
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &row[acc+1..acc+n], &row[acc_range..coeff_sum_width(n)], self.modulus);
+    }
+}
+
+// Column of out = acc[N-1] in the coefficient sum trace
+pub fn coeff_sum_output(n: usize) -> usize {
+    2*n-1
+}
+
+// (poly[0] + ... + poly[N-1]) % mod computed on the host
+pub fn coeff_sum(poly: &[u32], modulus: u32) -> u32 {
+    (poly.iter().map(|&c| c as u64).sum::<u64>() % modulus as u64) as u32
+}
+
+// Define a function to generate execution trace
+pub fn generate_coeff_sum_trace<F: Field>(poly: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_nonempty(n)?;
+    check_poly(&poly, n, modulus)?;
+    check_range_modulus(modulus)?;
+
+    // Accumulate the running sums and their carries on the host
+    let mut acc = vec![poly[0]];
+    let mut carry = Vec::with_capacity(n-1);
+    for &c in poly[1..].iter() {
+        let sum = acc[acc.len()-1] as u64 + c as u64;
+        carry.push(sum >= modulus as u64);
+        acc.push((sum % modulus as u64) as u32);
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(coeff_sum_row(&poly, &acc, &carry, modulus), coeff_sum_width(poly.len())))
+}
+
+// Row of the running sums acc of poly and their carries, with the range checks and the carries of the low limb steps
+fn coeff_sum_row<F: Field>(poly: &[u32], acc: &[u32], carry: &[bool], modulus: u32) -> Vec<F> {
+    let n = poly.len();
+    let width = coeff_sum_width(n);
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * width);
+
+    // Add input polynomial, the running sums and the carries to values vector
+    values.extend(poly.iter().chain(acc.iter()).map(|&c| F::from_canonical_u32(c)));
+    values.extend(carry.iter().map(|&c| F::from_bool(c)));
+
+    // Add the range checks of the running sums acc[1..N), then the carries of the low limb steps
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = (1..n).map(|i| (acc[i-1] & mask) as i64 + (poly[i] & mask) as i64).collect();
+    values.extend(reduced_sums_witness::<F>(&low, carry, &acc[1..], modulus));
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::poly_eval::poly_eval;
+    use p3_field::PrimeField32;
+    use crate::gadgets::soundness::{assert_rejected, assert_rejects_corrupted};
+    use crate::params::P1;
+
+    #[test]
+    fn test_coeff_sum() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // a random polynomial, and one of mod - 1 coefficients which wraps around at every step
+        let n = 16;
+        let mut rng = thread_rng();
+        let random: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        for poly in [random, vec![P1 - 1; n]] {
+            let expected = coeff_sum(&poly, P1);
+            assert_eq!(expected as u64, poly.iter().map(|&c| c as u64).sum::<u64>() % P1 as u64);
+            assert_eq!(expected, poly_eval(&poly, 1, P1));
+
+            let air = CoeffSumAir { poly:poly.clone(), modulus:P1, n };
+            let trace = generate_coeff_sum_trace::<Val>(poly, P1, n).unwrap();
+            assert_eq!(trace.row_slice(0)[coeff_sum_output(n)], Val::from_canonical_u32(expected));
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace.clone(), &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+            assert_rejects_corrupted(&air, &trace, "acc");
+        }
+
+        assert_eq!(coeff_sum(&vec![P1 - 1; n], P1), P1 - n as u32);
+    }
+
+    #[test]
+    fn test_coeff_sum_forged_carry() {
+        // the last step of mod - 1 coefficients wraps around, with a sum 2 * mod - n above n
+        let n = 16;
+        let poly = vec![P1 - 1; n];
+        let air = CoeffSumAir { poly: poly.clone(), modulus: P1, n };
+        let mut acc: Vec<u32> = (1..=n as u32).map(|i| P1 - i).collect();
+        let mut carry = vec![true; n-1];
+        let sum = acc[n-2] as u64 + poly[n-1] as u64;
+        assert!(sum >= Val::ORDER_U32 as u64 && acc[n-1] as u64 == sum - P1 as u64);
+
+        // a carry of 0 with acc[n-1] = sum - n still holds mod n, and acc[n-1] < mod
+        carry[n-2] = false;
+        acc[n-1] = (sum - Val::ORDER_U32 as u64) as u32;
+        let trace = pad_trace(coeff_sum_row::<Val>(&poly, &acc, &carry, P1), coeff_sum_width(n));
+
+        assert_rejected(&air, trace, "a forged carry[n-1]");
+    }
+}
@@ -0,0 +1,267 @@
+use p3_air::{Air, AirBuilderWithPublicValues, BaseAir};
+use p3_challenger::CanObserve;
+use p3_commit::Mmcs;
+use p3_field::{AbstractField, Field, PrimeField32};
+use p3_matrix::{Dimensions, Matrix};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{prove, verify};
+use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+use crate::gadgets::config::{Challenger, Val, ValMmcs, ZkConfig};
+use crate::gadgets::error::{check_air_inputs, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::range_checked_limbs;
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{eval_range_checks, range_check_witness, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::io::{observe_public_values, VerificationError, ZkProof};
+
+// Merkle root of the input polynomials, and the authentication path of one of its rows
+pub type InputCommitment = <ValMmcs as Mmcs<Val>>::Commitment;
+pub type InputOpeningProof = <ValMmcs as Mmcs<Val>>::Proof;
+
+// Opened row i = [a[i], b[i]] of the committed inputs, with its authentication path
+pub struct InputOpening {
+    pub values: Vec<Val>,
+    pub proof: InputOpeningProof,
+}
+
+// Proof of an addition over committed inputs: the openings of every input row, then the STARK proof
+pub struct CommittedProof {
+    pub openings: Vec<InputOpening>,
+    pub proof: ZkProof,
+}
+
+// Define AIR constraint inputs
+pub struct CommittedAddAir {
+    // commitment to the inputs a and b, from commit_inputs()
+    pub commitment: InputCommitment,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl CommittedAddAir {
+    // PolyAddAir of the same modulus and size, whose operation constraint is reused over the committed inputs
+    fn op(&self) -> PolyAddAir {
+        PolyAddAir { a: Vec::new(), b: Vec::new(), modulus: self.modulus, n: self.n }
+    }
+}
+
+/*
+Polynomial Addition Air over Committed Inputs
+Input:
+- commitment: Merkle root, under the ValMmcs of the ZkConfig, of the n x 2 matrix with rows [a[i], b[i]]
+- mod: FHE ciphertext modulus
+Output:
+- out = (a + b) % mod, bound to the public values
+
+Note:
+- The AIR does not know a and b: its row is the one of PolyAddAir, with the inputs pinned to the public values
+    public_values = [a: N][b: N][out: N]
+instead of constants, so the same AIR proves an addition of any committed inputs.
+- The public a and b are not taken from the caller but from the openings of the commitment: verify_committed_add()
+checks the authentication path of every row of the matrix against the commitment, then verifies the STARK proof
+with the opened values as public values, so the proof only verifies for the committed inputs.
+- The opened a and b are range checked into [0, mod) by the AIR, whose sum constraint also reads their lowest limbs.
+- The commitment is observed into the challenger before the public values, so the proof is also bound to it.
+- out is public and can be committed in turn, with commit_inputs(), as an input of the next operation, which links
+the operations into a chain of proofs over commitments.
+- Opening every row reveals the inputs and is linear in N: the commitment binds the inputs, but does not hide them,
+and a succinct link would need the commitment to be part of the trace commitment of a multi-trace STARK.
+*/
+impl<F: Field> BaseAir<F> for CommittedAddAir {
+    // Air Table looks like this
+    // row:[  a: N  ][  b: N  ][mod:1][ out(x): N ][ ...: 86N ][ a_range: 62N ][ b_range: 62N ]
+    //     ^--opened from the commitment--^^--------calculated by generate_committed_add_trace---------^
+    //     [0...................................................................................0]
+    //     [0...................................................................................0]
+    //     [0...................................................................................0]
+    fn width(&self) -> usize {
+        BaseAir::<F>::width(&self.op()) + 2*RANGE_CHECK_WIDTH*self.n
+    }
+}
+
+impl GadgetLayout for CommittedAddAir {
+    fn layout(&self) -> TraceLayout {
+        self.op().layout()
+            .push("a_range", RANGE_CHECK_WIDTH*self.n)
+            .push("b_range", RANGE_CHECK_WIDTH*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for CommittedAddAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+        let public_values: Vec<AB::Expr> = builder.public_values().iter().map(|&v| v.into()).collect();
+
+        // Public values other than [a: N][b: N][out: N] are rejected instead of indexed past their end
+        if public_values.len() != 3*n {
+            builder.when_first_row().assert_one(AB::Expr::zero());
+            return;
+        }
+
+        // Enforce the opened a, b as the input polynomials and the claimed out as the output
+        let op = self.op();
+        for (i, col) in (0..2*n).chain(op.output_columns()).enumerate() {
+            builder.when_first_row().assert_eq(row[col], public_values[i].clone());
+        }
+
+        // Enforce 0 <= a[i], b[i] < mod: the opened values are any field elements, and the sum needs their lowest limbs
+        let a_range = BaseAir::<AB::F>::width(&op);
+        let inputs: Vec<AB::Expr> = (0..2*n).map(|i| row[i].into()).collect();
+        eval_range_checks(builder, &inputs, &row[a_range..], self.modulus);
+
+        // Enforce out = (a + b) % mod as in PolyAddAir, over the lowest limbs of the range checked inputs
+        let low_limb = |i: usize| {
+            let block = a_range + i*RANGE_CHECK_WIDTH;
+            range_checked_limbs::<AB>(&row[block..block + RANGE_CHECK_BITS])[0].clone()
+        };
+        let inputs_low = (0..n).map(|i| low_limb(i) + low_limb(n+i)).collect();
+        op.eval_sum(builder, &row, inputs_low);
+    }
+}
+
+// Trace of PolyAddAir for a + b, followed by the range checks of a and b
+fn generate_committed_add_trace(a: &[u32], b: &[u32], modulus: u32, n: usize) -> Result<RowMajorMatrix<Val>, GadgetError> {
+    let trace = generate_polyadd_trace::<Val>(a.to_vec(), b.to_vec(), modulus, n)?;
+    let mut values = trace.row_slice(0).to_vec();
+    values.extend(range_check_witness::<Val>(a, modulus));
+    values.extend(range_check_witness::<Val>(b, modulus));
+    let width = values.len();
+    Ok(pad_trace(values, width))
+}
+
+// n x 2 matrix with rows [a[i], b[i]]; the opening of a row gives a coefficient of both inputs
+fn input_matrix(a: &[u32], b: &[u32]) -> RowMajorMatrix<Val> {
+    let values = a.iter().zip(b).flat_map(|(&x, &y)| [x, y]).map(Val::from_canonical_u32).collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+// Commit to the input polynomials a and b with the ValMmcs of `zk_config`
+pub fn commit_inputs(zk_config: &ZkConfig, a: &[u32], b: &[u32], modulus: u32, n: usize) -> Result<InputCommitment, GadgetError> {
+    check_air_inputs(&[a, b], n, modulus)?;
+    let (commitment, _) = zk_config.config.pcs().mmcs.commit_matrix(input_matrix(a, b));
+    Ok(commitment)
+}
+
+// Challenger of the proof of `air`, with the commitment and then the public values observed
+fn challenger(zk_config: &ZkConfig, air: &CommittedAddAir, public_values: &[Val]) -> Challenger {
+    let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+    challenger.observe(air.commitment.clone());
+    observe_public_values(&mut challenger, public_values);
+    challenger
+}
+
+// Prove a + b over the inputs committed to by air.commitment, returning the sum out and the proof with the openings
+pub fn prove_committed_add(zk_config: &ZkConfig, air: &CommittedAddAir, a: Vec<u32>, b: Vec<u32>) -> Result<(Vec<u32>, CommittedProof), GadgetError> {
+    check_air_inputs(&[&a, &b], air.n, air.modulus)?;
+    let mmcs = &zk_config.config.pcs().mmcs;
+    let (commitment, prover_data) = mmcs.commit_matrix(input_matrix(&a, &b));
+    if commitment != air.commitment {
+        return Err(GadgetError::CommitmentMismatch);
+    }
+
+    // Open every row of the committed inputs
+    let openings = (0..air.n).map(|i| {
+        let (mut values, proof) = mmcs.open_batch(i, &prover_data);
+        InputOpening { values: values.remove(0), proof }
+    }).collect();
+
+    let trace = generate_committed_add_trace(&a, &b, air.modulus, air.n)?;
+    let out = air.op().public_outputs(&trace);
+    let public_values: Vec<Val> = a.iter().chain(b.iter()).map(|&x| Val::from_canonical_u32(x)).chain(out.iter().copied()).collect();
+
+    let mut challenger = challenger(zk_config, air, &public_values);
+    let proof = prove(&zk_config.config, air, &mut challenger, trace, &public_values);
+    Ok((out.iter().map(|x| x.as_canonical_u32()).collect(), CommittedProof { openings, proof }))
+}
+
+// Verify a proof produced by prove_committed_add() against air.commitment and the claimed sum `out`
+pub fn verify_committed_add(zk_config: &ZkConfig, air: &CommittedAddAir, out: &[u32], proof: &CommittedProof) -> Result<(), VerificationError> {
+    let n = air.n;
+    if out.len() != n {
+        return Err(VerificationError::PublicValueMismatch { expected: n, actual: out.len() });
+    }
+    if proof.openings.len() != n || proof.openings.iter().any(|opening| opening.values.len() != 2) {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    // Check every opened row against the commitment, then read a and b from the opened rows
+    let mmcs = &zk_config.config.pcs().mmcs;
+    let dimensions = [Dimensions { width: 2, height: n }];
+    for (index, opening) in proof.openings.iter().enumerate() {
+        mmcs.verify_batch(&air.commitment, &dimensions, index, &[opening.values.clone()], &opening.proof)
+            .map_err(|e| VerificationError::InvalidOpening { index, reason: format!("{:?}", e) })?;
+    }
+    let a = proof.openings.iter().map(|opening| opening.values[0]);
+    let b = proof.openings.iter().map(|opening| opening.values[1]);
+    let public_values: Vec<Val> = a.chain(b).chain(out.iter().map(|&c| Val::from_wrapped_u32(c))).collect();
+
+    let mut challenger = challenger(zk_config, air, &public_values);
+    verify(&zk_config.config, air, &mut challenger, &proof.proof, &public_values).map_err(VerificationError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use crate::gadgets::config::initialize_config;
+    use crate::gadgets::reference;
+    use crate::params::P1;
+
+    #[test]
+    fn test_committed_add() -> Result<(), VerificationError> {
+
+        let zk_config = initialize_config();
+
+        // the verifier only holds the commitment to a and b
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly = |rng: &mut rand::rngs::ThreadRng| -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let (a, b, c) = (random_poly(&mut rng), random_poly(&mut rng), random_poly(&mut rng));
+        let commitment = commit_inputs(&zk_config, &a, &b, P1, n).unwrap();
+        let air = CommittedAddAir { commitment, modulus: P1, n };
+
+        let (out, proof) = prove_committed_add(&zk_config, &air, a.clone(), b.clone()).unwrap();
+        let widen = |poly: &[u32]| poly.iter().map(|&x| x as u128).collect::<Vec<_>>();
+        assert_eq!(widen(&out), reference::add(&widen(&a), &widen(&b), P1 as u128));
+        verify_committed_add(&zk_config, &air, &out, &proof)?;
+
+        // chain: out is committed with c as the inputs of the next addition
+        let next = CommittedAddAir { commitment: commit_inputs(&zk_config, &out, &c, P1, n).unwrap(), modulus: P1, n };
+        let (sum, next_proof) = prove_committed_add(&zk_config, &next, out.clone(), c.clone()).unwrap();
+        verify_committed_add(&zk_config, &next, &sum, &next_proof)?;
+        assert_eq!(widen(&sum), reference::add(&reference::add(&widen(&a), &widen(&b), P1 as u128), &widen(&c), P1 as u128));
+
+        // the proof does not verify against the commitment to other inputs, nor for another sum
+        let other = CommittedAddAir { commitment: commit_inputs(&zk_config, &a, &c, P1, n).unwrap(), modulus: P1, n };
+        assert!(matches!(verify_committed_add(&zk_config, &other, &out, &proof), Err(VerificationError::InvalidOpening { index: 0, .. })));
+        let mut wrong = out.clone();
+        wrong[1] = (wrong[1] + 1) % P1;
+        assert!(verify_committed_add(&zk_config, &air, &wrong, &proof).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_committed_add_tampered_opening() {
+
+        let zk_config = initialize_config();
+
+        let n = 4;
+        let (a, b) = (vec![1, 2, 3, 4], vec![P1 - 1, 5, 6, 7]);
+        let air = CommittedAddAir { commitment: commit_inputs(&zk_config, &a, &b, P1, n).unwrap(), modulus: P1, n };
+
+        // the prover cannot prove over inputs other than the committed ones
+        assert_eq!(prove_committed_add(&zk_config, &air, a.clone(), vec![0; n]).unwrap_err(), GadgetError::CommitmentMismatch);
+
+        // an opened value changed after the fact no longer matches its authentication path
+        let (out, mut proof) = prove_committed_add(&zk_config, &air, a, b).unwrap();
+        proof.openings[2].values[1] += Val::one();
+        assert!(matches!(verify_committed_add(&zk_config, &air, &out, &proof), Err(VerificationError::InvalidOpening { index: 2, .. })));
+
+        proof.openings.pop();
+        assert_eq!(verify_committed_add(&zk_config, &air, &out, &proof), Err(VerificationError::InvalidProofShape));
+    }
+}
@@ -1,39 +1,504 @@
+use std::fmt;
 use std::marker::PhantomData;
 use p3_mersenne_31::Mersenne31;
 use p3_challenger::{HashChallenger, SerializingChallenger32};
 use p3_circle::CirclePcs;
 use p3_commit::ExtensionMmcs;
+use p3_field::PrimeField32;
 use p3_field::extension::BinomialExtensionField;
 use p3_fri::FriConfig;
 use p3_keccak::Keccak256Hash;
 use p3_merkle_tree::FieldMerkleTreeMmcs;
 use p3_symmetric::{CompressionFunctionFromHasher, SerializingHasher32};
-use p3_uni_stark::StarkConfig;
-use tracing_forest::util::LevelFilter;
-use tracing_forest::ForestLayer;
-use tracing_subscriber::layer::SubscriberExt;
-use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{EnvFilter, Registry};
+use p3_uni_stark::{StarkConfig, SymbolicAirBuilder, ProverConstraintFolder, VerifierConstraintFolder};
+use p3_air::Air;
+#[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
+use tracing_forest::{util::LevelFilter, ForestLayer};
+#[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Registry};
 
 // Define a struct to hold all configuration types
-pub struct ZkConfig {
-    pub config: StarkConfig<Pcs, BinomialExtensionField<Mersenne31, 3>, SerializingChallenger32<Mersenne31, HashChallenger<u8, Keccak256Hash, 32>>>,
+// SC defaults to the Mersenne31 / CirclePcs configuration used throughout the gadgets
+pub struct ZkConfig<SC = MyConfig> {
+    pub config: SC,
     pub byte_hash: Keccak256Hash,
 }
 
 // Type aliases for the ZK system configuration
 pub type Val = Mersenne31;
-pub type Challenge = BinomialExtensionField<Val, 3>;
+pub type Challenge = ExtChallenge<CHALLENGE_DEGREE>;
 pub type ByteHash = Keccak256Hash;
 pub type FieldHash = SerializingHasher32<ByteHash>;
 pub type MyCompress = CompressionFunctionFromHasher<u8, ByteHash, 2, 32>;
 pub type ValMmcs = FieldMerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
-pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+pub type ChallengeMmcs = ExtChallengeMmcs<CHALLENGE_DEGREE>;
 pub type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
-pub type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+pub type Pcs = ExtPcs<CHALLENGE_DEGREE>;
+pub type MyConfig = ExtConfig<CHALLENGE_DEGREE>;
 
-pub fn initialize_config() -> ZkConfig {
+/*
+Degree of the challenge field over Val
+Note:
+- The random challenges of the STARK and FRI are drawn from a degree-D extension, so a cheating prover passes a check
+with probability about (degree of the constraints) / |F|^D: the default D = 3 gives about 3 * 31 = 93 bits.
+- The Ext* aliases below build the same configuration over any D for which Val is binomially extendable,
+through ZkConfigBuilder::build_with_degree(). x^D - W is irreducible, so that BinomialExtensionField<Val, D> is a field,
+when W is not a d-th power residue for every prime d dividing D, and p = 1 mod 4 when 4 divides D.
+Plonky3 implements BinomiallyExtendable<D> with such a W for Mersenne31 at D = 3 only (W = 5): with p - 1 = 2 * 3^2 * 7 * 11 * 31 * 151 * 331,
+every element is a 5-th power, and p = 3 mod 4 rules out D = 4.
+BabyBear, with p - 1 = 2^27 * 3 * 5, has binomial extensions of degree 4 and 5, see babybear::ExtConfig
+and the BinomiallyExtendable impls of p3-mersenne-31 and p3-baby-bear for their W.
+*/
+pub const CHALLENGE_DEGREE: usize = 3;
+pub type ExtChallenge<const D: usize> = BinomialExtensionField<Val, D>;
+pub type ExtChallengeMmcs<const D: usize> = ExtensionMmcs<Val, ExtChallenge<D>, ValMmcs>;
+pub type ExtPcs<const D: usize> = CirclePcs<Val, ValMmcs, ExtChallengeMmcs<D>>;
+pub type ExtConfig<const D: usize> = StarkConfig<ExtPcs<D>, ExtChallenge<D>, Challenger>;
+
+// Type aliases for the BabyBear configuration, committed with a two-adic FRI PCS
+pub mod babybear {
+    use super::{ByteHash, MyCompress};
+    use p3_baby_bear::BabyBear;
+    use p3_challenger::{HashChallenger, SerializingChallenger32};
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::Radix2DitParallel;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_fri::TwoAdicFriPcs;
+    use p3_merkle_tree::FieldMerkleTreeMmcs;
+    use p3_symmetric::SerializingHasher32;
+    use p3_uni_stark::StarkConfig;
+
+    pub type Val = BabyBear;
+    pub type Challenge = ExtChallenge<CHALLENGE_DEGREE>;
+    pub type FieldHash = SerializingHasher32<ByteHash>;
+    pub type ValMmcs = FieldMerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    pub type ChallengeMmcs = ExtChallengeMmcs<CHALLENGE_DEGREE>;
+    pub type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    pub type Dft = Radix2DitParallel;
+    pub type Pcs = ExtPcs<CHALLENGE_DEGREE>;
+    pub type MyConfig = ExtConfig<CHALLENGE_DEGREE>;
+
+    // Degree of the challenge field over BabyBear: D = 4 gives about 4 * 31 = 124 bits, and D = 5 is also available
+    pub const CHALLENGE_DEGREE: usize = 4;
+    pub type ExtChallenge<const D: usize> = BinomialExtensionField<Val, D>;
+    pub type ExtChallengeMmcs<const D: usize> = ExtensionMmcs<Val, ExtChallenge<D>, ValMmcs>;
+    pub type ExtPcs<const D: usize> = TwoAdicFriPcs<Val, Dft, ValMmcs, ExtChallengeMmcs<D>>;
+    pub type ExtConfig<const D: usize> = StarkConfig<ExtPcs<D>, ExtChallenge<D>, Challenger>;
+}
+
+// Type aliases for the Goldilocks configuration, committed with a two-adic FRI PCS
+pub mod goldilocks {
+    use super::{ByteHash, MyCompress};
+    use p3_goldilocks::Goldilocks;
+    use p3_challenger::{HashChallenger, SerializingChallenger64};
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::Radix2DitParallel;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_fri::TwoAdicFriPcs;
+    use p3_merkle_tree::FieldMerkleTreeMmcs;
+    use p3_symmetric::SerializingHasher64;
+    use p3_uni_stark::StarkConfig;
+
+    pub type Val = Goldilocks;
+    pub type Challenge = BinomialExtensionField<Val, 2>;
+    pub type FieldHash = SerializingHasher64<ByteHash>;
+    pub type ValMmcs = FieldMerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    pub type Challenger = SerializingChallenger64<Val, HashChallenger<u8, ByteHash, 32>>;
+    pub type Dft = Radix2DitParallel;
+    pub type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    pub type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+}
+
+/*
+Type aliases for the Mersenne31 / CirclePcs configuration hashed with Poseidon2 instead of Keccak256
+Note:
+- The Merkle trees hash field elements natively with a Poseidon2 sponge of width 16 and rate 8, and compress
+2 digests of 8 elements with the truncated permutation, instead of serializing them to bytes for Keccak256.
+- The challenger is a duplex sponge over the same permutation, so the whole transcript is made of field operations,
+which is what a recursive verifier needs to check the proof in a circuit. Natively, Keccak256 is faster.
+- The round constants are drawn from a fixed seed, POSEIDON2_SEED: the prover and the verifier must build
+the same permutation, and a proof only verifies under a configuration with the same constants.
+- x^5 is the S-box, the smallest d with gcd(d, p - 1) = 1 for p - 1 = 2 * 3^2 * 7 * 11 * 31 * 151 * 331.
+*/
+pub mod poseidon2 {
+    use super::CHALLENGE_DEGREE;
+    use p3_challenger::DuplexChallenger;
+    use p3_circle::CirclePcs;
+    use p3_commit::ExtensionMmcs;
+    use p3_field::Field;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_merkle_tree::FieldMerkleTreeMmcs;
+    use p3_mersenne_31::{DiffusionMatrixMersenne31, Mersenne31};
+    use p3_poseidon2::{Poseidon2, Poseidon2ExternalMatrixGeneral};
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+    use p3_uni_stark::StarkConfig;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    pub type Val = Mersenne31;
+    pub type Challenge = BinomialExtensionField<Val, CHALLENGE_DEGREE>;
+    pub type Perm = Poseidon2<Val, Poseidon2ExternalMatrixGeneral, DiffusionMatrixMersenne31, 16, 5>;
+    pub type FieldHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    pub type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    pub type ValMmcs = FieldMerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, FieldHash, MyCompress, 8>;
+    pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    pub type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    pub type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    pub type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    // Seed of the round constants of the permutation
+    pub const POSEIDON2_SEED: u64 = 0x7666_6865;
+
+    // The configuration and the permutation its challengers are created from, as ZkConfig holds the byte hash
+    pub struct ZkConfig {
+        pub config: MyConfig,
+        pub perm: Perm,
+    }
+
+    impl ZkConfig {
+        // A fresh challenger, in the same initial state for the prover and the verifier
+        pub fn challenger(&self) -> Challenger {
+            Challenger::new(self.perm.clone())
+        }
+    }
+
+    // The Poseidon2 permutation with the round constants of POSEIDON2_SEED
+    pub fn perm() -> Perm {
+        let mut rng = StdRng::seed_from_u64(POSEIDON2_SEED);
+        Perm::new_from_rng_128(Poseidon2ExternalMatrixGeneral, DiffusionMatrixMersenne31::default(), &mut rng)
+    }
+}
+
+// Hash function of the Merkle trees and the challenger, selected at initialize_hash_config() time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashConfig {
+    // Keccak256 over the serialized field elements (the default)
+    Keccak256,
+    // Poseidon2 over Mersenne31, see the poseidon2 module
+    Poseidon2,
+}
+
+// A Mersenne31 / CirclePcs ZkConfig for one of the supported hash functions
+pub enum HashZkConfig {
+    Keccak256(ZkConfig),
+    Poseidon2(poseidon2::ZkConfig),
+}
+
+// Base field of the proof system, selected at initialize_field_config() time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldConfig {
+    // Mersenne31 with a CirclePcs (the default)
+    Mersenne31,
+    // BabyBear with a TwoAdicFriPcs
+    BabyBear,
+    // Goldilocks with a TwoAdicFriPcs
+    Goldilocks,
+}
+
+// A ZkConfig for one of the supported base fields
+pub enum FieldZkConfig {
+    Mersenne31(ZkConfig),
+    BabyBear(ZkConfig<babybear::MyConfig>),
+    Goldilocks(ZkConfig<goldilocks::MyConfig>),
+}
+
+// Every AIR that can be proven and verified under MyConfig
+// (debug builds additionally run the prover's constraint checker on the trace)
+#[cfg(debug_assertions)]
+pub trait ZkAir: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<p3_uni_stark::DebugConstraintBuilder<'a, Val>> {}
+
+#[cfg(debug_assertions)]
+impl<A> ZkAir for A where A: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<p3_uni_stark::DebugConstraintBuilder<'a, Val>> {}
+
+#[cfg(not(debug_assertions))]
+pub trait ZkAir: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>> {}
+
+#[cfg(not(debug_assertions))]
+impl<A> ZkAir for A where A: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>> {}
+
+// The verifier's half of ZkAir, which is object safe, so AIRs of different gadgets can be verified together
+// through io::verify_many()
+pub trait VerifierAir: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>> {}
+
+impl<A> VerifierAir for A where A: Air<SymbolicAirBuilder<Val>>
+    + for<'a> Air<VerifierConstraintFolder<'a, MyConfig>> {}
+
+// Default FRI parameters used by initialize_config()
+pub const DEFAULT_LOG_BLOWUP: usize = 1;
+pub const DEFAULT_NUM_QUERIES: usize = 100;
+pub const DEFAULT_PROOF_OF_WORK_BITS: usize = 16;
+
+// The challenger grinds for a field element with `proof_of_work_bits` leading zero bits,
+// which must be fewer than the 31 bits of the Mersenne31 / BabyBear elements
+pub const MAX_PROOF_OF_WORK_BITS: usize = 30;
+
+// Errors raised while building a ZkConfig from invalid FRI parameters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    // log_blowup must be at least 1, so the quotient polynomial fits in the blown-up domain
+    InvalidLogBlowup(usize),
+    // FRI needs at least one query to be sound at all
+    ZeroQueries,
+    // proof_of_work_bits is above MAX_PROOF_OF_WORK_BITS, which the challenger cannot grind for
+    ProofOfWorkBitsTooLarge(usize),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidLogBlowup(log_blowup) => write!(f, "log_blowup must be at least 1, got {}", log_blowup),
+            ConfigError::ZeroQueries => write!(f, "num_queries must be at least 1"),
+            ConfigError::ProofOfWorkBitsTooLarge(bits) => {
+                write!(f, "proof_of_work_bits must be at most {}, got {}", MAX_PROOF_OF_WORK_BITS, bits)
+            }
+        }
+    }
+}
 
+impl std::error::Error for ConfigError {}
+
+// Builder for ZkConfig with tunable FRI parameters
+pub struct ZkConfigBuilder {
+    log_blowup: usize,
+    num_queries: usize,
+    proof_of_work_bits: usize,
+}
+
+impl Default for ZkConfigBuilder {
+    fn default() -> Self {
+        Self {
+            log_blowup: DEFAULT_LOG_BLOWUP,
+            num_queries: DEFAULT_NUM_QUERIES,
+            proof_of_work_bits: DEFAULT_PROOF_OF_WORK_BITS,
+        }
+    }
+}
+
+impl ZkConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // log2 of the FRI blowup factor: larger values mean fewer queries are needed for the same soundness, but slower proving
+    pub fn log_blowup(mut self, log_blowup: usize) -> Self {
+        self.log_blowup = log_blowup;
+        self
+    }
+
+    // Number of FRI queries: fewer queries give smaller proofs and faster verification at the cost of soundness
+    pub fn num_queries(mut self, num_queries: usize) -> Self {
+        self.num_queries = num_queries;
+        self
+    }
+
+    // Grinding bits required before sampling the FRI queries, at most MAX_PROOF_OF_WORK_BITS.
+    // A cheating prover has to redo a 2^bits grinding for every attempt at the queries, so the bits add directly
+    // to the security level of the queries, while the honest prover pays about 2^bits hashes once.
+    // 0 disables grinding: soundness then rests on num_queries and log_blowup alone.
+    pub fn proof_of_work_bits(mut self, proof_of_work_bits: usize) -> Self {
+        self.proof_of_work_bits = proof_of_work_bits;
+        self
+    }
+
+    // Check the FRI parameters without building anything
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.log_blowup == 0 {
+            return Err(ConfigError::InvalidLogBlowup(self.log_blowup));
+        }
+        if self.num_queries == 0 {
+            return Err(ConfigError::ZeroQueries);
+        }
+        if self.proof_of_work_bits > MAX_PROOF_OF_WORK_BITS {
+            return Err(ConfigError::ProofOfWorkBitsTooLarge(self.proof_of_work_bits));
+        }
+        Ok(())
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration, panicking on invalid parameters
+    pub fn build(self) -> ZkConfig {
+        self.try_build().unwrap_or_else(|e| panic!("invalid configuration: {}", e))
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration
+    pub fn try_build(self) -> Result<ZkConfig, ConfigError> {
+        self.validate()?;
+        init_tracing();
+        Ok(self.mersenne31_config())
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration over a degree-D challenge field, panicking on invalid parameters.
+    // Proving needs Mersenne31 to be binomially extendable to degree D, see CHALLENGE_DEGREE.
+    pub fn build_with_degree<const D: usize>(self) -> ZkConfig<ExtConfig<D>> {
+        self.expect_valid();
+        init_tracing();
+        self.mersenne31_config()
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration for verification only, panicking on invalid parameters
+    pub fn build_verifier(self) -> ZkConfig {
+        self.try_build_verifier().unwrap_or_else(|e| panic!("invalid configuration: {}", e))
+    }
+
+    /*
+    Build the Mersenne31 / CirclePcs configuration for verification only
+    Note:
+    - The FRI parameters must be the ones the proofs were generated with: they are not part of the proof,
+    and a verifier with other parameters rejects them.
+    - The PCS and MMCS are the same as the prover's, since the verifier recomputes the Merkle paths with them,
+    but the prover-side setup is skipped: no tracing subscriber is installed for the prover spans.
+    CirclePcs holds no prover-only state such as the DFT of TwoAdicFriPcs, so this is all the prover needs beyond it.
+    */
+    pub fn try_build_verifier(self) -> Result<ZkConfig, ConfigError> {
+        self.validate()?;
+        Ok(self.mersenne31_config())
+    }
+
+    // PCS and MMCS of the Mersenne31 / CirclePcs configuration, shared by the prover and the verifier
+    fn mersenne31_config<const D: usize>(&self) -> ZkConfig<ExtConfig<D>> {
+        // Initialize zk system configuration
+        let byte_hash = ByteHash {};
+        let field_hash = FieldHash::new(Keccak256Hash {});
+        let compress = MyCompress::new(byte_hash);
+
+        let val_mmcs = ValMmcs::new(field_hash, compress);
+        let challenge_mmcs = ExtChallengeMmcs::<D>::new(val_mmcs.clone());
+
+        let pcs = ExtPcs::<D> {
+            mmcs: val_mmcs,
+            fri_config: self.fri_config(challenge_mmcs),
+            _phantom: PhantomData,
+        };
+
+        let config = StarkConfig::new(pcs);
+
+        ZkConfig {
+            config,
+            byte_hash,
+        }
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration with Poseidon2 Merkle trees and challenger, panicking on invalid parameters
+    pub fn build_poseidon2(self) -> poseidon2::ZkConfig {
+        self.expect_valid();
+        init_tracing();
+
+        let perm = poseidon2::perm();
+        let field_hash = poseidon2::FieldHash::new(perm.clone());
+        let compress = poseidon2::MyCompress::new(perm.clone());
+
+        let val_mmcs = poseidon2::ValMmcs::new(field_hash, compress);
+        let challenge_mmcs = poseidon2::ChallengeMmcs::new(val_mmcs.clone());
+
+        let pcs = poseidon2::Pcs {
+            mmcs: val_mmcs,
+            fri_config: self.fri_config(challenge_mmcs),
+            _phantom: PhantomData,
+        };
+
+        poseidon2::ZkConfig {
+            config: StarkConfig::new(pcs),
+            perm,
+        }
+    }
+
+    // Build the Mersenne31 / CirclePcs configuration hashed with the selected hash function
+    pub fn build_hash(self, hash: HashConfig) -> HashZkConfig {
+        match hash {
+            HashConfig::Keccak256 => HashZkConfig::Keccak256(self.build()),
+            HashConfig::Poseidon2 => HashZkConfig::Poseidon2(self.build_poseidon2()),
+        }
+    }
+
+    // Build the BabyBear / TwoAdicFriPcs configuration
+    pub fn build_babybear(self) -> ZkConfig<babybear::MyConfig> {
+        self.build_babybear_with_degree()
+    }
+
+    // Build the BabyBear / TwoAdicFriPcs configuration over a degree-D challenge field, D = 4 or 5
+    pub fn build_babybear_with_degree<const D: usize>(self) -> ZkConfig<babybear::ExtConfig<D>> {
+        self.expect_valid();
+        init_tracing();
+
+        let byte_hash = ByteHash {};
+        let field_hash = babybear::FieldHash::new(Keccak256Hash {});
+        let compress = MyCompress::new(byte_hash);
+
+        let val_mmcs = babybear::ValMmcs::new(field_hash, compress);
+        let challenge_mmcs = babybear::ExtChallengeMmcs::<D>::new(val_mmcs.clone());
+
+        let pcs = babybear::ExtPcs::<D>::new(babybear::Dft::default(), val_mmcs, self.fri_config(challenge_mmcs));
+
+        ZkConfig {
+            config: StarkConfig::new(pcs),
+            byte_hash,
+        }
+    }
+
+    // Build the Goldilocks / TwoAdicFriPcs configuration
+    pub fn build_goldilocks(self) -> ZkConfig<goldilocks::MyConfig> {
+        self.expect_valid();
+        init_tracing();
+
+        let byte_hash = ByteHash {};
+        let field_hash = goldilocks::FieldHash::new(Keccak256Hash {});
+        let compress = MyCompress::new(byte_hash);
+
+        let val_mmcs = goldilocks::ValMmcs::new(field_hash, compress);
+        let challenge_mmcs = goldilocks::ChallengeMmcs::new(val_mmcs.clone());
+
+        let pcs = goldilocks::Pcs::new(goldilocks::Dft::default(), val_mmcs, self.fri_config(challenge_mmcs));
+
+        ZkConfig {
+            config: StarkConfig::new(pcs),
+            byte_hash,
+        }
+    }
+
+    // Build the configuration of the selected base field, reporting invalid parameters as an error
+    pub fn try_build_field(self, field: FieldConfig) -> Result<FieldZkConfig, ConfigError> {
+        self.validate()?;
+        Ok(self.build_field(field))
+    }
+
+    // Build the configuration of the selected base field
+    pub fn build_field(self, field: FieldConfig) -> FieldZkConfig {
+        match field {
+            FieldConfig::Mersenne31 => FieldZkConfig::Mersenne31(self.build()),
+            FieldConfig::BabyBear => FieldZkConfig::BabyBear(self.build_babybear()),
+            FieldConfig::Goldilocks => FieldZkConfig::Goldilocks(self.build_goldilocks()),
+        }
+    }
+
+    fn expect_valid(&self) {
+        if let Err(e) = self.validate() {
+            panic!("invalid configuration: {}", e);
+        }
+    }
+
+    fn fri_config<M>(&self, mmcs: M) -> FriConfig<M> {
+        FriConfig {
+            log_blowup: self.log_blowup,
+            num_queries: self.num_queries,
+            proof_of_work_bits: self.proof_of_work_bits,
+            mmcs,
+        }
+    }
+}
+
+#[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
+fn init_tracing() {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
@@ -43,32 +508,224 @@ pub fn initialize_config() -> ZkConfig {
         .with(ForestLayer::default())
         .try_init() // Use try_init() to prevent conflicts
         .ok(); // Ignore errors if already initialized
+}
 
-    // Initialize zk system configuration
-    let byte_hash = ByteHash {};
-    let field_hash = FieldHash::new(Keccak256Hash {});
-    let compress = MyCompress::new(byte_hash);
+// There is no terminal to print the tracing forest to in the browser,
+// and without the tracing feature the embedder installs its own subscriber, if any
+#[cfg(any(not(feature = "tracing"), target_arch = "wasm32"))]
+fn init_tracing() {}
 
-    let val_mmcs = ValMmcs::new(field_hash, compress);
-    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+// Build a ZkConfig with the default FRI parameters
+pub fn initialize_config() -> ZkConfig {
+    try_initialize_config().unwrap_or_else(|e| panic!("invalid configuration: {}", e))
+}
 
-    let fri_config = FriConfig {
-        log_blowup: 1,
-        num_queries: 100,
-        proof_of_work_bits: 16,
-        mmcs: challenge_mmcs,
-    };
+// Build a ZkConfig with the default FRI parameters for a verifier that does not prove,
+// e.g. a verification node checking proofs produced elsewhere with initialize_config()
+pub fn verifier_config() -> ZkConfig {
+    ZkConfigBuilder::default().build_verifier()
+}
+
+// Build a ZkConfig with the default FRI parameters, reporting failures as a ConfigError
+// A tracing subscriber that is already installed is not an error: every config after the first one hits it.
+// Without the tracing feature, no subscriber is installed at all
+pub fn try_initialize_config() -> Result<ZkConfig, ConfigError> {
+    ZkConfigBuilder::default().try_build()
+}
+
+// Build a Mersenne31 ZkConfig hashed with `hash` with the default FRI parameters
+pub fn initialize_hash_config(hash: HashConfig) -> HashZkConfig {
+    ZkConfigBuilder::default().build_hash(hash)
+}
+
+// Build a ZkConfig for `field` with the default FRI parameters
+pub fn initialize_field_config(field: FieldConfig) -> FieldZkConfig {
+    ZkConfigBuilder::default().build_field(field)
+}
+
+/*
+Conjectured soundness of a ZkConfig, in bits
+Note:
+- Each FRI query catches a prover committed to a function far from the code with probability 1 - 2^{-log_blowup},
+under the conjecture that Reed-Solomon codes are list-decodable up to capacity (the "conjectured soundness" of
+the ethSTARK paper and Plonky3), and grinding multiplies the cost of every attempt by 2^{proof_of_work_bits}:
+    fri_bits = log_blowup * num_queries + proof_of_work_bits
+- The random challenges are drawn from the degree-D challenge field, so no estimate goes above its size,
+    challenge_bits = D * log2(p)
+and the estimate is min(fri_bits, challenge_bits). With the defaults, fri_bits = 1 * 100 + 16 = 116 and
+challenge_bits = 3 * 31 = 93.
+- This is an estimate, not a proven bound: the proven (Johnson bound) soundness is about half the FRI bits,
+and the terms in the degree of the constraints and the trace length, a few bits each, are ignored.
+*/
+pub fn estimate_soundness_bits(zk_config: &ZkConfig) -> f64 {
+    let fri_config = &zk_config.config.pcs().fri_config;
+    let fri_bits = (fri_config.log_blowup * fri_config.num_queries + fri_config.proof_of_work_bits) as f64;
+    let challenge_bits = CHALLENGE_DEGREE as f64 * (Val::ORDER_U32 as f64).log2();
+    fri_bits.min(challenge_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::io::{serialize_proof, ZkProof};
+    use crate::params::P1;
+
+    fn prove_add(zk_config: &ZkConfig, air: &PolyAddAir) -> ZkProof {
+        let trace = generate_polyadd_trace::<Val>(air.a.clone(), air.b.clone(), air.modulus, air.n).unwrap();
+        let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+        prove(&zk_config.config, air, &mut challenger, trace, &vec![])
+    }
+
+    fn random_add_air(n: usize) -> PolyAddAir {
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        PolyAddAir { a:random_poly1, b:random_poly2, modulus:P1, n }
+    }
+
+    #[test]
+    fn test_builder_num_queries() {
+        let zk_config = ZkConfigBuilder::new().num_queries(50).build();
+        let air = random_add_air(16);
+
+        let proof = prove_add(&zk_config, &air);
+
+        let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+        verify(&zk_config.config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_estimate_soundness_bits() {
+        // the defaults are capped by the 93 bits of the challenge field
+        let bits = estimate_soundness_bits(&initialize_config());
+        assert!(bits > 80.0 && bits < 94.0, "implausible soundness estimate: {}", bits);
+
+        // below the cap, every query adds log_blowup bits and grinding adds proof_of_work_bits
+        let bits = estimate_soundness_bits(&ZkConfigBuilder::new().log_blowup(2).num_queries(20).proof_of_work_bits(8).build());
+        assert_eq!(bits, 48.0);
+        let fewer = estimate_soundness_bits(&ZkConfigBuilder::new().log_blowup(2).num_queries(10).proof_of_work_bits(8).build());
+        assert_eq!(fewer, 28.0);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(try_initialize_config().is_ok());
+
+        let result = ZkConfigBuilder::new().num_queries(0).try_build();
+        assert!(matches!(result, Err(ConfigError::ZeroQueries)));
+
+        let result = ZkConfigBuilder::new().log_blowup(0).try_build();
+        assert!(matches!(result, Err(ConfigError::InvalidLogBlowup(0))));
+
+        let result = ZkConfigBuilder::new().num_queries(0).try_build_field(FieldConfig::Goldilocks);
+        assert!(matches!(result, Err(ConfigError::ZeroQueries)));
+
+        let result = ZkConfigBuilder::new().proof_of_work_bits(MAX_PROOF_OF_WORK_BITS + 1).try_build();
+        assert!(matches!(result, Err(ConfigError::ProofOfWorkBitsTooLarge(31))));
+    }
+
+    #[test]
+    fn test_builder_proof_of_work_bits() {
+        let air = random_add_air(16);
+
+        for bits in [0, 20] {
+            let zk_config = ZkConfigBuilder::new().proof_of_work_bits(bits).try_build().unwrap();
+            let proof = prove_add(&zk_config, &air);
+
+            let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+            verify(&zk_config.config, &air, &mut challenger, &proof, &vec![])
+                .unwrap_or_else(|e| panic!("verification failed with {} proof of work bits: {:?}", bits, e));
+        }
+    }
+
+    #[test]
+    fn test_poly_add_babybear() {
+        let ZkConfig { config, byte_hash } = match initialize_field_config(FieldConfig::BabyBear) {
+            FieldZkConfig::BabyBear(zk_config) => zk_config,
+            _ => panic!("expected a BabyBear configuration"),
+        };
+
+        // P1 < BabyBear's modulus 2^31 - 2^27 + 1, so the coefficients are canonical in both fields
+        let air = random_add_air(16);
+        let trace = generate_polyadd_trace::<babybear::Val>(air.a.clone(), air.b.clone(), air.modulus, air.n).unwrap();
+
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_challenge_extension_degree() {
+        // P1 < BabyBear's modulus, so the same trace is canonical over BabyBear
+        let air = random_add_air(16);
+        let trace = generate_polyadd_trace::<babybear::Val>(air.a.clone(), air.b.clone(), air.modulus, air.n).unwrap();
+
+        // degree 4, the BabyBear default, and degree 5 for about 5 * 31 bits of challenge field
+        let ZkConfig { config, byte_hash } = ZkConfigBuilder::new().build_babybear_with_degree::<4>();
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace.clone(), &vec![]);
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        let ZkConfig { config, byte_hash } = ZkConfigBuilder::new().build_babybear_with_degree::<5>();
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+        let mut challenger = babybear::Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        // the Mersenne31 configuration at CHALLENGE_DEGREE is the default one, so its proofs verify under initialize_config()
+        let zk_config: ZkConfig = ZkConfigBuilder::new().build_with_degree::<CHALLENGE_DEGREE>();
+        let proof = prove_add(&zk_config, &air);
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_poly_add_poseidon2() {
+        let zk_config = match initialize_hash_config(HashConfig::Poseidon2) {
+            HashZkConfig::Poseidon2(zk_config) => zk_config,
+            _ => panic!("expected a Poseidon2 configuration"),
+        };
+
+        let air = random_add_air(16);
+        let trace = generate_polyadd_trace::<Val>(air.a.clone(), air.b.clone(), air.modulus, air.n).unwrap();
+        let proof = prove(&zk_config.config, &air, &mut zk_config.challenger(), trace, &vec![]);
+        verify(&zk_config.config, &air, &mut zk_config.challenger(), &proof, &vec![]).expect("verification failed");
+
+        // the round constants are fixed by POSEIDON2_SEED, so a verifier building its own configuration accepts the proof
+        let verifier = ZkConfigBuilder::new().build_poseidon2();
+        verify(&verifier.config, &air, &mut verifier.challenger(), &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_poly_add_goldilocks() {
+        let ZkConfig { config, byte_hash } = ZkConfigBuilder::new().build_goldilocks();
+
+        let air = random_add_air(16);
+        let trace = generate_polyadd_trace::<goldilocks::Val>(air.a.clone(), air.b.clone(), air.modulus, air.n).unwrap();
+
+        let mut challenger = goldilocks::Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = goldilocks::Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
 
-    let pcs = Pcs {
-        mmcs: val_mmcs,
-        fri_config,
-        _phantom: PhantomData,
-    };
+    #[test]
+    fn test_fewer_queries_smaller_proof() {
+        let air = random_add_air(16);
 
-    let config = StarkConfig::new(pcs);
+        let default_proof = prove_add(&initialize_config(), &air);
+        let small_proof = prove_add(&ZkConfigBuilder::new().num_queries(50).build(), &air);
 
-    ZkConfig {
-        config,
-        byte_hash,
+        let default_size = serialize_proof(&default_proof).unwrap().len();
+        let small_size = serialize_proof(&small_proof).unwrap().len();
+        assert!(small_size < default_size, "{} queries: {} bytes, 50 queries: {} bytes", DEFAULT_NUM_QUERIES, default_size, small_size);
     }
 }
@@ -0,0 +1,75 @@
+use p3_air::Air;
+use p3_uni_stark::{get_symbolic_constraints, SymbolicAirBuilder, SymbolicExpression};
+use crate::gadgets::config::Val;
+
+// Shape of the constraint system of an AIR, for comparing gadgets such as the Vandermonde and NTT multiplications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConstraintStats {
+    // Number of assert_eq / assert_zero / assert_bool / ... constraints
+    pub constraints: usize,
+    // Largest degree of a constraint, counting the row selectors of when_first_row() and friends as degree 1
+    pub max_degree: usize,
+    // Total number of nodes of the constraint expressions: the work of evaluating them once
+    pub nodes: usize,
+}
+
+/*
+Count the constraints of `air` by running its eval() against the SymbolicAirBuilder of p3_uni_stark,
+which records every constraint as an expression instead of checking it.
+
+Note:
+- With no public values, as every gadget of this crate is proven with prove(.., &vec![]).
+- The number of constraints alone can hide the cost of a gadget: PolyMulAir has O(N) constraints,
+but each of them evaluates polynomials of N terms, so its number of nodes is O(N^2).
+- Expressions are trees of Rc nodes, and shared subexpressions are counted every time they are used.
+*/
+pub fn constraint_stats<A: Air<SymbolicAirBuilder<Val>>>(air: &A) -> ConstraintStats {
+    let constraints = get_symbolic_constraints::<Val, A>(air, 0, 0);
+    ConstraintStats {
+        constraints: constraints.len(),
+        max_degree: constraints.iter().map(|c| c.degree_multiple()).max().unwrap_or(0),
+        nodes: constraints.iter().map(count_nodes).sum(),
+    }
+}
+
+// Number of nodes of the expression tree of `expr`
+fn count_nodes(expr: &SymbolicExpression<Val>) -> usize {
+    match expr {
+        SymbolicExpression::Add { x, y, .. } | SymbolicExpression::Sub { x, y, .. } | SymbolicExpression::Mul { x, y, .. } => {
+            1 + count_nodes(x) + count_nodes(y)
+        }
+        SymbolicExpression::Neg { x, .. } => 1 + count_nodes(x),
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::add::PolyAddAir;
+    use crate::gadgets::mul::{MulShape, PolyMulAir};
+    use crate::params::P1;
+
+    #[test]
+    fn test_constraint_stats() {
+        let stats = |n: usize| {
+            let poly = vec![0u32; n];
+            let add = constraint_stats(&PolyAddAir { a: poly.clone(), b: poly.clone(), modulus: P1, n });
+            let mul = constraint_stats(&PolyMulAir { a: poly.clone(), b: poly, modulus: P1, n, shape: MulShape::Row });
+            (add, mul)
+        };
+
+        let (add, mul) = stats(8);
+        // the range checks of out are enforced on the first row, and the reduction witness with degree-2 limb products
+        assert_eq!(add.max_degree, 3);
+        assert_eq!(mul.max_degree, 3);
+        assert!(mul.nodes > add.nodes);
+
+        // doubling N doubles the number of constraints of both, but quadruples the work of PolyMulAir
+        let (add2, mul2) = stats(16);
+        assert!(add2.constraints <= 2*add.constraints + 2);
+        assert!(mul2.constraints <= 2*mul.constraints + 2);
+        assert!(add2.nodes <= 3*add.nodes);
+        assert!(mul2.nodes >= 3*mul.nodes);
+    }
+}
@@ -0,0 +1,338 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs, LIMB_BITS};
+use crate::gadgets::bit_decompose::eval_bit_decompose;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_wide_reduction, eval_wide_reduction, limb_value, COEFF_LIMBS, CRT_LIMBS as SUM_LIMBS, MUL_CARRY_BITS, MUL_CRT_BITS};
+use crate::gadgets::range_check::{eval_less_than, less_than_columns};
+use crate::gadgets::rns::{crt_recombine, RNS_MODULI};
+use crate::gadgets::trace::repeat_row;
+use crate::params::{N, P};
+
+// x in [0, P) is held in CRT_LIMBS little-endian limbs of CRT_LIMB_BITS bits each (96 bits >= the 91 bits of P)
+pub const CRT_LIMBS: usize = 6;
+pub const CRT_LIMB_BITS: usize = 16;
+const CRT_BITS: usize = CRT_LIMBS * CRT_LIMB_BITS;
+
+// Define AIR constraint inputs
+pub struct CrtRecombineAir {
+    // residue polynomials r_1 mod P1, r_2 mod P2, r_3 mod P3
+    pub residues: [Vec<u32>; 3],
+    pub n: usize
+}
+
+impl CrtRecombineAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(residues: [Vec<u32>; 3]) -> Self {
+        Self { residues, n: N }
+    }
+}
+
+/*
+CRT Recombination Air
+Input:
+- r_k = r_k[0] + r_k[1] * X + ... + r_k[N-1] * X^{N-1} mod P_k, for k = 1, 2, 3 (RNS_MODULI)
+Output:
+- x = x[0] + x[1] * X + ... + x[N-1] * X^{N-1} mod P = P1 * P2 * P3, where x[i] is the unique value in [0, P)
+with x[i] = r_k[i] mod P_k for every k. Each x[i] is stored as 6 limbs of 16 bits: x[i] = sum_l limb[i][l] * 2^{16l}
+
+Note:
+- P is 91 bits and does not fit in the native field, so x[i] only exists as limbs. Every constraint below only
+multiplies limbs or bits by constants, and is evaluated in the native field.
+- limbs: each limb is the sum of 16 bits, so 0 <= limb[i][l] < 2^16 and x[i] is a well-defined 96-bits integer.
+- x[i] < P: the 96 bits of x[i] are compared with the constant bits of P from the MSB down, as in range_check.rs.
+- x[i] = r_k[i] mod P_k: with the precomputed CRT coefficients c[k][l] = 2^{16l} mod P_k,
+    x[i] mod P_k = sum_l limb[i][l] * c[k][l] mod P_k,
+and we enforce sum_l limb[i][l] * c[k][l] === q[i][k] * P_k + r_k[i] over the integers as in PolyMulAir:
+mod n directly, and mod 2^MUL_CRT_BITS by eval_wide_reduction(), with q[i][k] decomposed into MUL_CRT_BITS bits and the sum
+written over the 8-bits halves of the limbs, read from their bits. Both sides are below 6 * 2^16 * 2^31 + 2^43 * P_k < 2^43 * n.
+- By the CRT there is exactly one x in [0, P) with the 3 residues, so together these pin x[i] down.
+*/
+impl<F: Field> BaseAir<F> for CrtRecombineAir {
+    // Air Table looks like this
+    // row:[ r_1: N ][ r_2: N ][ r_3: N ][ limbs: 6N ][ q: 3N ][ bits: 96N ][ eq: 96N ][ q_bits: 43*3N ][ carry_bits: 138*3N ]
+    //     ^-----------inputs-----------^^----------------------calculated by generate_crt_recombine_trace----------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        crt_width(self.n)
+    }
+}
+
+impl GadgetLayout for CrtRecombineAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("r_1", self.n)
+            .push("r_2", self.n)
+            .push("r_3", self.n)
+            .push("limbs", CRT_LIMBS*self.n)
+            .push("q", 3*self.n)
+            .push("bits", CRT_BITS*self.n)
+            .push("eq", CRT_BITS*self.n)
+            .push("q_bits", MUL_CRT_BITS*3*self.n)
+            .push("carry_bits", REDUCTION_CARRIES*3*self.n)
+    }
+}
+
+// Carry columns of the reduction of one channel
+const REDUCTION_CARRIES: usize = SUM_LIMBS*MUL_CARRY_BITS;
+
+// Column offsets of the CrtRecombineAir row
+struct CrtLayout {
+    limbs: usize,
+    q: usize,
+    bits: usize,
+    eq: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    width: usize
+}
+
+impl CrtLayout {
+    fn new(n: usize) -> Self {
+        let limbs = 3*n;
+        let q = limbs + CRT_LIMBS*n;
+        let bits = q + 3*n;
+        let eq = bits + CRT_BITS*n;
+        let q_bits = eq + CRT_BITS*n;
+        let carry_bits = q_bits + MUL_CRT_BITS*3*n;
+        let width = carry_bits + REDUCTION_CARRIES*3*n;
+        Self { limbs, q, bits, eq, q_bits, carry_bits, width }
+    }
+}
+
+fn crt_width(n: usize) -> usize {
+    CrtLayout::new(n).width
+}
+
+// c[k][l] = 2^{16l} mod P_k
+fn crt_coefficients() -> [[u32; CRT_LIMBS]; 3] {
+    RNS_MODULI.map(|modulus| {
+        let mut coeffs = [0; CRT_LIMBS];
+        let mut power = 1u64;
+        for coeff in coeffs.iter_mut() {
+            *coeff = power as u32;
+            power = (power << CRT_LIMB_BITS) % modulus as u64;
+        }
+        coeffs
+    })
+}
+
+// Little-endian bits of P, padded to CRT_BITS
+fn modulus_bits() -> Vec<bool> {
+    (0..CRT_BITS).map(|k| (P >> k) & 1 == 1).collect()
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for CrtRecombineAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let layout = CrtLayout::new(n);
+
+        // Enforce self.residues as the 3 input polynomials
+        for k in 0..3 {
+            for i in 0..n {
+                builder.when_first_row().assert_eq(row[k*n+i], AB::Expr::from_canonical_u32(self.residues[k][i]));
+            }
+        }
+
+        let coeffs = crt_coefficients();
+        let bound = modulus_bits();
+        for i in 0..n {
+            let x_limbs = layout.limbs + i*CRT_LIMBS;
+            let bits = layout.bits + i*CRT_BITS;
+            let eq = layout.eq + i*CRT_BITS;
+
+            // Enforce limb[i][l] === sum_j bits[i][16l+j] * 2^j
+            for l in 0..CRT_LIMBS {
+                let limb_bits = bits + l*CRT_LIMB_BITS;
+                eval_bit_decompose(builder, row[x_limbs+l].into(), &row[limb_bits..limb_bits+CRT_LIMB_BITS]);
+            }
+
+            // Enforce x[i] < P
+            eval_less_than(builder, &row[bits..bits+CRT_BITS], &row[eq..eq+CRT_BITS], &bound);
+
+            // 8-bits halves of the limbs, from their bits
+            let halves: Vec<AB::Expr> = (0..2*CRT_LIMBS).map(|h| limb_value::<AB>(&row[bits + h*LIMB_BITS..bits + (h+1)*LIMB_BITS])).collect();
+
+            // Enforce sum_l limb[i][l] * c[k][l] === q[i][k] * P_k + r_k[i] for every channel k, mod n and mod 2^MUL_CRT_BITS
+            for (k, &modulus) in RNS_MODULI.iter().enumerate() {
+                let mut sum = AB::Expr::zero();
+                for l in 0..CRT_LIMBS {
+                    sum = sum + row[x_limbs+l] * AB::F::from_canonical_u32(coeffs[k][l]);
+                }
+                let q = layout.q + i*3 + k;
+                builder.assert_eq(sum, row[q] * AB::F::from_canonical_u32(modulus) + row[k*n+i]);
+
+                // half h of limb h/2 weighs c[k][h/2] * 2^{8(h mod 2)}, so its product with the limb l of c[k][h/2] lands at position l + h mod 2
+                let mut sum = vec![AB::Expr::zero(); SUM_LIMBS];
+                for (h, half) in halves.iter().enumerate() {
+                    for (l, c) in limbs(coeffs[k][h/2] as u128, COEFF_LIMBS).into_iter().enumerate() {
+                        sum[l + h%2] = sum[l + h%2].clone() + half.clone() * AB::F::from_canonical_u64(c);
+                    }
+                }
+                let residue = limbs(self.residues[k][i] as u128, COEFF_LIMBS).into_iter().map(AB::Expr::from_canonical_u64).collect();
+                let (q_bits, carry_bits) = (layout.q_bits + (i*3 + k)*MUL_CRT_BITS, layout.carry_bits + (i*3 + k)*REDUCTION_CARRIES);
+                eval_wide_reduction(
+                    builder, sum, row[q].into(), residue,
+                    &row[q_bits..q_bits + MUL_CRT_BITS],
+                    &row[carry_bits..carry_bits + REDUCTION_CARRIES],
+                    modulus
+                );
+            }
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_crt_recombine_trace<F: Field>(residues: [Vec<u32>; 3], n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    for (k, &modulus) in RNS_MODULI.iter().enumerate() {
+        check_poly(&residues[k], n, modulus)?;
+    }
+
+    // Recombine every coefficient on the host
+    let xs: Vec<u128> = (0..n).map(|i| crt_recombine([residues[0][i], residues[1][i], residues[2][i]])).collect();
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&crt_row(&residues, &xs)))
+}
+
+// Row of the recombined coefficients xs of residues, with the quotients q[i][k] = sum / P_k of their reductions
+fn crt_row<F: Field>(residues: &[Vec<u32>; 3], xs: &[u128]) -> Vec<F> {
+    let n = xs.len();
+    let layout = CrtLayout::new(n);
+    let mut row = vec![F::zero(); layout.width];
+
+    // Assign residue polynomials to the row
+    for k in 0..3 {
+        for i in 0..n {
+            row[k*n+i] = F::from_canonical_u32(residues[k][i]);
+        }
+    }
+
+    let bound = modulus_bits();
+    for (i, &x) in xs.iter().enumerate() {
+        // Assign limbs
+        for l in 0..CRT_LIMBS {
+            row[layout.limbs + i*CRT_LIMBS + l] = F::from_canonical_u32(((x >> (l*CRT_LIMB_BITS)) & 0xffff) as u32);
+        }
+
+        // Assign bits and prefix equality flags against P
+        let bits: Vec<bool> = (0..CRT_BITS).map(|j| (x >> j) & 1 == 1).collect();
+        let eq = less_than_columns(&bits, &bound);
+        for j in 0..CRT_BITS {
+            row[layout.bits + i*CRT_BITS + j] = F::from_bool(bits[j]);
+            row[layout.eq + i*CRT_BITS + j] = F::from_bool(eq[j]);
+        }
+
+        // Assign quotients: sum_l limb[l] * c[k][l] < 6 * 2^16 * 2^31 < 2^50, so it fits in u64
+        for (k, &modulus) in RNS_MODULI.iter().enumerate() {
+            let q = channel_sum(x, k) as u64 / modulus as u64;
+            assign_crt_reduction(&mut row, &layout, (i, k), x, q, residues[k][i]);
+        }
+    }
+    row
+}
+
+// sum_l limb[l] * c[k][l] for the limbs of x
+fn channel_sum(x: u128, k: usize) -> u128 {
+    let coeffs = crt_coefficients();
+    (0..CRT_LIMBS).map(|l| ((x >> (l*CRT_LIMB_BITS)) & 0xffff) * coeffs[k][l] as u128).sum()
+}
+
+// Assign the quotient q of the reduction of x in channel k at coefficient i, with its bits and carries
+fn assign_crt_reduction<F: Field>(row: &mut [F], layout: &CrtLayout, (i, k): (usize, usize), x: u128, q: u64, residue: u32) {
+    let coeffs = crt_coefficients();
+    row[layout.q + i*3 + k] = F::from_wrapped_u64(q);
+
+    // limb positions of the sum over the 8-bits halves of the limbs, as in eval()
+    let mut sum = vec![0u64; SUM_LIMBS];
+    for h in 0..2*CRT_LIMBS {
+        let half = ((x >> (h*LIMB_BITS)) & 0xff) as u64;
+        let conv = convolve(&[half], &limbs(coeffs[k][h/2] as u128, COEFF_LIMBS), SUM_LIMBS - h%2);
+        for (m, c) in conv.into_iter().enumerate() {
+            sum[m + h%2] += c;
+        }
+    }
+
+    let (q_bits, carry_bits) = (layout.q_bits + (i*3 + k)*MUL_CRT_BITS, (i*3 + k)*REDUCTION_CARRIES);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + REDUCTION_CARRIES],
+        &sum, q, residue, RNS_MODULI[k]
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_field::PrimeField32;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::gadgets::utils::mod_inv;
+
+    #[test]
+    fn test_crt_recombine_air() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // pick the expected coefficients in [0, P) first, including both ends, and reduce them into the 3 channels
+        let n = 4;
+        let mut rng = thread_rng();
+        let mut expected: Vec<u128> = (0..n).map(|_| rng.gen_range(0..P)).collect();
+        expected[0] = 0;
+        expected[n-1] = P - 1;
+        let residues = RNS_MODULI.map(|modulus| expected.iter().map(|&x| (x % modulus as u128) as u32).collect::<Vec<u32>>());
+
+        let air = CrtRecombineAir { residues: residues.clone(), n };
+
+        let trace = generate_crt_recombine_trace::<Val>(residues, n).unwrap();
+
+        // the limbs put back together match the host-side u128 values
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let x = (0..CRT_LIMBS).fold(0u128, |x, l| {
+                x | (row[3*n + i*CRT_LIMBS + l].as_canonical_u32() as u128) << (l*CRT_LIMB_BITS)
+            });
+            assert_eq!(x, expected[i]);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_crt_recombine_forged_coefficient() {
+        let n = 2;
+        let mut rng = thread_rng();
+        let xs: Vec<u128> = (0..n).map(|_| rng.gen_range(0..P - 1)).collect();
+        let residues = RNS_MODULI.map(|modulus| xs.iter().map(|&x| (x % modulus as u128) as u32).collect::<Vec<u32>>());
+        let air = CrtRecombineAir { residues: residues.clone(), n };
+
+        // x[0] + 1 < P, with the quotients re-solved mod n so that the 3 reductions still hold mod n
+        let mut forged = xs.clone();
+        forged[0] += 1;
+        let mut row: Vec<Val> = crt_row(&residues, &forged);
+        let order = Val::ORDER_U32 as u128;
+        for (k, &modulus) in RNS_MODULI.iter().enumerate() {
+            let q = (channel_sum(forged[0], k) % order + order - residues[k][0] as u128) % order
+                * mod_inv(modulus as u64, order as u64) as u128 % order;
+            assign_crt_reduction(&mut row, &CrtLayout::new(n), (0, k), forged[0], q as u64, residues[k][0]);
+        }
+
+        assert_rejected(&air, repeat_row(&row), "a forged x[0] + 1");
+    }
+}
@@ -0,0 +1,282 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_low, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::ciphertext::Ciphertext;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace, negacyclic_coeffs, negacyclic_layout, negacyclic_reduced, negacyclic_reduced_range, negacyclic_width};
+use crate::gadgets::range_check::RANGE_CHECK_WIDTH;
+use crate::gadgets::relinearize::{RelinearizeAir, generate_relinearize_trace, relinearize, relinearize_inputs, relinearize_layout, relinearize_output, relinearize_width};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct CtMulAir {
+    // a = (a0, a1), b = (b0, b1): 2 ciphertexts decrypting as c0 + c1 * s
+    pub a: Ciphertext,
+    pub b: Ciphertext,
+    // relinearization key: rlk0[l], rlk1[l] encrypt base^l * s^2 for l = [0..levels)
+    pub rlk0: Vec<Vec<u32>>,
+    pub rlk1: Vec<Vec<u32>>,
+    pub base: u32,
+    pub levels: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl CtMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Ciphertext, b: Ciphertext, rlk0: Vec<Vec<u32>>, rlk1: Vec<Vec<u32>>, base: u32, levels: usize, modulus: u32) -> Self {
+        Self { a, b, rlk0, rlk1, base, levels, modulus, n: N }
+    }
+
+    // The 4 products a0 * b0, a0 * b1, a1 * b0, a1 * b1, in the order their sub-traces are laid out
+    fn products(&self) -> [NegacyclicMulAir; 4] {
+        tensor_operands(&self.a, &self.b).map(|(x, y)| NegacyclicMulAir { a: x.clone(), b: y.clone(), modulus: self.modulus, n: self.n })
+    }
+
+    // Relinearization of the tensor product (d0, d1, d2)
+    fn relinearize_air(&self) -> RelinearizeAir {
+        let (d, d2) = tensor(&self.a, &self.b, self.modulus);
+        RelinearizeAir { ct: d, c2: d2, rlk0: self.rlk0.clone(), rlk1: self.rlk1.clone(), base: self.base, levels: self.levels, modulus: self.modulus, n: self.n }
+    }
+}
+
+/*
+Ciphertext Multiplication Air
+Input:
+- a = (a0, a1), b = (b0, b1): 2 ciphertexts with N coefficients per polynomial
+- rlk = (rlk0[l], rlk1[l]) for l = [0..L): relinearization key
+- base: B, levels: L of the gadget decomposition of d2
+- mod: FHE ciphertext modulus
+Output:
+- (c0', c1'): relinearization of the tensor product (d0, d1, d2) where, in Z_mod[X]/(X^N+1),
+    d0 = a0 * b0
+    d1 = a0 * b1 + a1 * b0
+    d2 = a1 * b1
+so that (a0 + a1 * s) * (b0 + b1 * s) = d0 + d1 * s + d2 * s^2
+
+Note:
+- The row is the composition of sub-AIRs, each embedded through its eval_row():
+    1) 4 NegacyclicMulAir for the products, side by side as in InnerProductAir
+    2) d1 = a0 * b1 + a1 * b0 with a carry bit per coefficient as in CiphertextAddAir, through eval_reduced_sum():
+       d1 is range checked and the sum is also enforced mod 2^8 over the lowest limbs of the range checked products
+    3) RelinearizeAir of (d0, d1, d2)
+and the inputs c0, c1, c2 of 3) are constrained equal to d0, d1 and d2 on the first row, so the relinearization uses the proven products.
+- The BFV rescaling of the tensor product by t/mod is not part of this gadget: it can be proven separately with ModSwitchAir.
+*/
+impl<F: Field> BaseAir<F> for CtMulAir {
+    // Air Table looks like this
+    // row:[ NegacyclicMulAir a0*b0 ][ a0*b1 ][ a1*b0 ][ a1*b1 ][d1: N][carry: N][d1_range: 62N][low_carry_bits: 23N][ RelinearizeAir (d0, d1, d2) ]
+    //     [0..........................................................................................................][ GadgetDecomposeAir repeated ]
+    //     [0..........................................................................................................][ GadgetDecomposeAir repeated ]
+    //     [0..........................................................................................................][ GadgetDecomposeAir repeated ]
+    fn width(&self) -> usize {
+        ct_mul_width(self.base, self.levels, self.n)
+    }
+}
+
+impl GadgetLayout for CtMulAir {
+    fn layout(&self) -> TraceLayout {
+        let products = ["a0_b0", "a0_b1", "a1_b0", "a1_b1"]
+            .into_iter()
+            .fold(TraceLayout::new(), |layout, name| layout.nest(name, negacyclic_layout(self.n)));
+        products
+            .push("d1", self.n)
+            .push("carry", self.n)
+            .push("d1_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+            .nest("relin", relinearize_layout(self.base, self.levels, self.n))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for CtMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // 1) products, with the reduced a_j * b_k at negacyclic_reduced(N) within each channel
+        let channel_width = negacyclic_width(n);
+        let reduced = |p: usize| p*channel_width + negacyclic_reduced(n);
+        for (p, product) in self.products().iter().enumerate() {
+            product.eval_row(builder, &row[p*channel_width..(p+1)*channel_width]);
+        }
+
+        // 2) Enforce a0b1[i] + a1b0[i] === carry[i] * mod + d1[i], with the lowest limbs of both products read from their range checks
+        let d1 = 4*channel_width;
+        let carry = d1 + n;
+        let relin = ct_mul_relin(n);
+        let low = |p: usize, i: usize| {
+            let block = p*channel_width + negacyclic_reduced_range(n) + i*RANGE_CHECK_WIDTH;
+            reduced_low::<AB>(&row[block..block + RANGE_CHECK_WIDTH])
+        };
+        let sums = (0..n).map(|i| row[reduced(1)+i] + row[reduced(2)+i]).collect();
+        let sums_low = (0..n).map(|i| low(1, i) + low(2, i)).collect();
+        let carries = (0..n).map(|i| row[carry+i].into()).collect();
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &row[d1..d1+n], &row[carry+n..relin], self.modulus);
+
+        // 3) relinearization, with c0 === d0, c1 === d1 and c2 === d2
+        let relin_width = relinearize_width(self.base, self.levels, n);
+        self.relinearize_air().eval_row(builder, &row[relin..relin+relin_width]);
+        // c2 is the first polynomial of the GadgetDecomposeAir columns, and (c0, c1) follow the inner products
+        let c = relin + relinearize_inputs(self.base, self.levels, n);
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[c+i], row[reduced(0)+i]);
+            builder.when_first_row().assert_eq(row[c+n+i], row[d1+i]);
+            builder.when_first_row().assert_eq(row[relin+i], row[reduced(3)+i]);
+        }
+    }
+}
+
+fn ct_mul_width(base: u32, levels: usize, n: usize) -> usize {
+    ct_mul_relin(n) + relinearize_width(base, levels, n)
+}
+
+// Column of the RelinearizeAir, after the products, d1, its carries and their witness
+fn ct_mul_relin(n: usize) -> usize {
+    4*negacyclic_width(n) + 2*n + reduced_sums_width(n)
+}
+
+// Column of c0'[0] in the ciphertext multiplication trace; c1' starts 2N columns later
+pub fn ct_mul_output(base: u32, levels: usize, n: usize) -> usize {
+    ct_mul_relin(n) + relinearize_output(base, levels, n)
+}
+
+// (a0, b0), (a0, b1), (a1, b0), (a1, b1)
+fn tensor_operands<'a>(a: &'a Ciphertext, b: &'a Ciphertext) -> [(&'a Vec<u32>, &'a Vec<u32>); 4] {
+    [(&a.c0, &b.c0), (&a.c0, &b.c1), (&a.c1, &b.c0), (&a.c1, &b.c1)]
+}
+
+// Tensor product ((d0, d1), d2) of a and b computed on the host
+pub fn tensor(a: &Ciphertext, b: &Ciphertext, modulus: u32) -> (Ciphertext, Vec<u32>) {
+    let [d0, a0b1, a1b0, d2] = tensor_operands(a, b).map(|(x, y)| negacyclic_coeffs(x, y, modulus));
+    let d1: Vec<u32> = a0b1.iter().zip(a1b0.iter()).map(|(&x, &y)| ((x as u64 + y as u64) % modulus as u64) as u32).collect();
+    (Ciphertext::new(d0, d1), d2)
+}
+
+// Relinearized product of a and b computed on the host
+pub fn ct_mul(a: &Ciphertext, b: &Ciphertext, rlk0: &[Vec<u32>], rlk1: &[Vec<u32>], base: u32, levels: usize, modulus: u32) -> Ciphertext {
+    let (d, d2) = tensor(a, b, modulus);
+    relinearize(&d, &d2, rlk0, rlk1, base, levels, modulus)
+}
+
+// Define a function to generate execution trace
+pub fn generate_ctmul_trace<F: Field>(a: Ciphertext, b: Ciphertext, rlk0: Vec<Vec<u32>>, rlk1: Vec<Vec<u32>>, base: u32, levels: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    a.check(n, modulus)?;
+    b.check(n, modulus)?;
+
+    let product_traces = tensor_operands(&a, &b).into_iter()
+        .map(|(x, y)| generate_negacyclic_mul_trace::<F>(x.clone(), y.clone(), modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+    // generate_relinearize_trace checks the key
+    let (d, d2) = tensor(&a, &b, modulus);
+    let relin_trace = generate_relinearize_trace::<F>(d.clone(), d2, rlk0, rlk1, base, levels, modulus, n)?;
+
+    // a0 * b1 + a1 * b0 wrapped around mod exactly when the reduced sum is below a0 * b1
+    let (a0b1, a1b0) = (negacyclic_coeffs(&a.c0, &b.c1, modulus), negacyclic_coeffs(&a.c1, &b.c0, modulus));
+    let carries: Vec<bool> = a0b1.iter().zip(d.c1.iter()).map(|(&x, &y)| y < x).collect();
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = (0..n).map(|i| (a0b1[i] & mask) as i64 + (a1b0[i] & mask) as i64).collect();
+
+    // Concatenate the sub-traces row by row, with d1 and the carries on the first row
+    let width = ct_mul_width(base, levels, n);
+    let height = relin_trace.height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        for trace in product_traces.iter() {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+        if r == 0 {
+            values.extend(d.c1.iter().map(|&c| F::from_canonical_u32(c)));
+            values.extend(carries.iter().map(|&c| F::from_bool(c)));
+            values.extend(reduced_sums_witness::<F>(&low, &carries, &d.c1, modulus));
+        } else {
+            values.extend((0..2*n + reduced_sums_width(n)).map(|_| F::zero()));
+        }
+        values.extend_from_slice(&relin_trace.row_slice(r));
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    // c0 + c1 * s in Z_mod[X]/(X^N+1)
+    fn phase(ct: &Ciphertext, s: &[u32]) -> Vec<u32> {
+        let c1s = negacyclic_mul_ref(&ct.c1, s, P1, s.len());
+        ct.c0.iter().zip(c1s.iter()).map(|(&x, &y)| ((x as u64 + y as u64) % P1 as u64) as u32).collect()
+    }
+
+    #[test]
+    fn test_ct_mul_toy_key() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 4;
+        let base = 1 << 8;
+        let levels = 4;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let a = Ciphertext::new(random_poly(), random_poly());
+        let b = Ciphertext::new(random_poly(), random_poly());
+
+        // noiseless toy key under s: rlk1[l] = r_l and rlk0[l] = base^l * s^2 - r_l * s
+        let s = random_poly();
+        let s2 = negacyclic_mul_ref(&s, &s, P1, n);
+        let rlk1: Vec<Vec<u32>> = (0..levels).map(|_| random_poly()).collect();
+        let rlk0: Vec<Vec<u32>> = rlk1.iter().enumerate().map(|(l, r)| {
+            let scale = (base as u64).pow(l as u32) % P1 as u64;
+            let rs = negacyclic_mul_ref(r, &s, P1, n);
+            (0..n).map(|i| ((s2[i] as u64 * scale + P1 as u64 - rs[i] as u64) % P1 as u64) as u32).collect()
+        }).collect();
+
+        // the relinearized product decrypts to the product of the phases, as in a host BFV multiply before rescaling
+        let out = ct_mul(&a, &b, &rlk0, &rlk1, base, levels, P1);
+        assert_eq!(phase(&out, &s), negacyclic_mul_ref(&phase(&a, &s), &phase(&b, &s), P1, n));
+
+        let air = CtMulAir { a: a.clone(), b: b.clone(), rlk0: rlk0.clone(), rlk1: rlk1.clone(), base, levels, modulus: P1, n };
+        let trace = generate_ctmul_trace::<Val>(a, b, rlk0, rlk1, base, levels, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        let start = ct_mul_output(base, levels, n);
+        for i in 0..n {
+            assert_eq!(row[start+i], Val::from_canonical_u32(out.c0[i]));
+            assert_eq!(row[start+2*n+i], Val::from_canonical_u32(out.c1[i]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_ct_mul_forged_product() {
+        let n = 4;
+        let (base, levels) = (1 << 16, 2);
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let a = Ciphertext::new(random_poly(), random_poly());
+        let b = Ciphertext::new(random_poly(), random_poly());
+        let rlk0: Vec<Vec<u32>> = (0..levels).map(|_| random_poly()).collect();
+        let rlk1: Vec<Vec<u32>> = (0..levels).map(|_| random_poly()).collect();
+        let air = CtMulAir { a: a.clone(), b: b.clone(), rlk0: rlk0.clone(), rlk1: rlk1.clone(), base, levels, modulus: P1, n };
+
+        // the tensor products and the relinearization products with a wrong raw product and re-solved quotients
+        assert_rejects_forged_product(&air, &generate_ctmul_trace(a, b, rlk0, rlk1, base, levels, P1, n).unwrap(), |_| P1);
+    }
+}
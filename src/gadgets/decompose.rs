@@ -0,0 +1,298 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::bit_decompose::{bits as to_bits, eval_bit_decompose};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_less_than, less_than_columns};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct GadgetDecomposeAir {
+    pub poly: Vec<u32>,
+    pub base: u32,
+    // number of digits per coefficient
+    pub levels: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl GadgetDecomposeAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, base: u32, levels: usize, modulus: u32) -> Self {
+        Self { poly, base, levels, modulus, n: N }
+    }
+
+    // Number of bits of every digit: the bit length of base, so that base itself is representable as the comparison bound
+    fn digit_bits(&self) -> usize {
+        digit_bits(self.base)
+    }
+}
+
+/*
+Gadget Decomposition Air
+Input:
+- poly = poly[0] + poly[1] * X + ... + poly[N-1] * X^{N-1} mod q
+- base: B, levels: L with B^{L-1} < q <= B^L
+Output:
+- digit[i][0], ..., digit[i][L-1]: base-B digits of poly[i], least significant first
+
+Note:
+- The reconstruction poly[i] === sum_l digit[i][l] * B^l holds over the integers only if the right hand side is below n.
+Range checking every digit against B alone is not enough for that (B^L can exceed n, e.g. B = 2^8, L = 4),
+so the top digit is checked against top = ceil(q / B^{L-1}) instead, which every honest top digit is below:
+    0 <= digit[i][l] < B  for l < L-1
+    0 <= digit[i][L-1] < top
+and the reconstructed integer is then below q + B^{L-1} < n for the moduli of this crate.
+- Each digit is decomposed into as many bits as B has, and compared with the bound with eval_less_than() of range_check.rs.
+*/
+impl<F: Field> BaseAir<F> for GadgetDecomposeAir {
+    // Air Table looks like this, with D the bit length of base
+    // row:[ poly: N ][ digit: LN ][ digit bits: DLN ][ digit eq: DLN ]
+    //     ^-input--^^---------calculated by generate_decompose_trace--------^
+    //     ... the same row repeated 3 times, since every row must pass the comparisons
+    fn width(&self) -> usize {
+        decompose_width(self.base, self.levels, self.n)
+    }
+}
+
+impl GadgetLayout for GadgetDecomposeAir {
+    fn layout(&self) -> TraceLayout {
+        decompose_layout(self.base, self.levels, self.n)
+    }
+}
+
+// Layout of GadgetDecomposeAir, shared with the gadgets embedding it
+pub(crate) fn decompose_layout(base: u32, levels: usize, n: usize) -> TraceLayout {
+    TraceLayout::new()
+        .push("poly", n)
+        .push("digit", levels*n)
+        .push("digit_bits", digit_bits(base)*levels*n)
+        .push("digit_eq", digit_bits(base)*levels*n)
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for GadgetDecomposeAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+        self.eval_row(builder, &row);
+    }
+}
+
+impl GadgetDecomposeAir {
+    // Enforce the decomposition constraints over `row`, which starts at the poly[0] column,
+    // so other gadgets can embed the GadgetDecomposeAir layout at any column offset
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+        let levels = self.levels;
+        let d = self.digit_bits();
+
+        let digit = n;
+        let bits = digit + levels*n;
+        let eq = bits + d*levels*n;
+
+        let bounds = digit_bounds(self.base, levels, self.modulus);
+        let bound_bits: Vec<Vec<bool>> = bounds.iter().map(|&bound| to_bits(bound as u64, d).collect()).collect();
+
+        for i in 0..n {
+            // Enforce self.poly as the input polynomial
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.poly[i]));
+
+            // Enforce poly[i] === sum_l digit[i][l] * base^l
+            let mut sum = AB::Expr::zero();
+            let mut power: u64 = 1;
+            for l in 0..levels {
+                sum = sum + row[digit + i*levels + l] * AB::F::from_wrapped_u64(power);
+                power *= self.base as u64;
+            }
+            builder.assert_eq(row[i], sum);
+
+            // Enforce 0 <= digit[i][l] < bound[l]
+            for l in 0..levels {
+                let bits = bits + (i*levels + l)*d;
+                let eq = eq + (i*levels + l)*d;
+                eval_bit_decompose(builder, row[digit + i*levels + l].into(), &row[bits..bits+d]);
+                eval_less_than(builder, &row[bits..bits+d], &row[eq..eq+d], &bound_bits[l]);
+            }
+        }
+    }
+}
+
+// Width of the GadgetDecomposeAir row, for gadgets embedding it
+pub(crate) fn decompose_width(base: u32, levels: usize, n: usize) -> usize {
+    (1 + levels*(1 + 2*digit_bits(base)))*n
+}
+
+// Column of digit[i][l] within the row
+pub(crate) fn digit_column(i: usize, l: usize, levels: usize, n: usize) -> usize {
+    n + i*levels + l
+}
+
+pub(crate) fn digit_bits(base: u32) -> usize {
+    (32 - base.leading_zeros()) as usize
+}
+
+// Exclusive bound of every digit: base, except ceil(modulus / base^{levels-1}) for the top one
+fn digit_bounds(base: u32, levels: usize, modulus: u32) -> Vec<u32> {
+    let top_weight = (base as u64).pow(levels as u32 - 1);
+    let top = (modulus as u64).div_ceil(top_weight) as u32;
+    let mut bounds = vec![base; levels];
+    bounds[levels-1] = top.min(base);
+    bounds
+}
+
+// Base-`base` digits of x, least significant first
+pub fn decompose(x: u32, base: u32, levels: usize) -> Vec<u32> {
+    let mut rest = x;
+    (0..levels).map(|_| {
+        let digit = rest % base;
+        rest /= base;
+        digit
+    }).collect()
+}
+
+// Check base >= 2 and base^{levels-1} < modulus <= base^levels
+fn check_decomposition(base: u32, levels: usize, modulus: u32) -> Result<(), GadgetError> {
+    let error = GadgetError::InvalidDecomposition { base, levels, modulus };
+    if base < 2 || levels == 0 {
+        return Err(error);
+    }
+    let top_weight = (base as u64).checked_pow(levels as u32 - 1).ok_or_else(|| error.clone())?;
+    let covered = top_weight.checked_mul(base as u64).unwrap_or(u64::MAX);
+    if top_weight >= modulus as u64 || covered < modulus as u64 {
+        return Err(error);
+    }
+    Ok(())
+}
+
+// d_l = sum_i digit[i][l] * X^i for l = [0..levels)
+pub(crate) fn digit_polynomials(poly: &[u32], base: u32, levels: usize) -> Vec<Vec<u32>> {
+    let digits: Vec<Vec<u32>> = poly.iter().map(|&c| decompose(c, base, levels)).collect();
+    (0..levels).map(|l| digits.iter().map(|digit| digit[l]).collect()).collect()
+}
+
+// Define a function to generate execution trace
+pub fn generate_decompose_trace<F: Field>(poly: Vec<u32>, base: u32, levels: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+    check_decomposition(base, levels, modulus)?;
+
+    let d = digit_bits(base);
+    let width = (1 + levels*(1 + 2*d))*n;
+    let bounds = digit_bounds(base, levels, modulus);
+    let bound_bits: Vec<Vec<bool>> = bounds.iter().map(|&bound| to_bits(bound as u64, d).collect()).collect();
+
+    // Assign input polynomial and its digits to the row
+    let digits: Vec<Vec<u32>> = poly.iter().map(|&c| decompose(c, base, levels)).collect();
+    let mut row: Vec<F> = Vec::with_capacity(width);
+    row.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    for digit in digits.iter() {
+        row.extend(digit.iter().map(|&x| F::from_canonical_u32(x)));
+    }
+
+    // Assign bits and prefix equality flags of every digit against its bound
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = digits.iter().flat_map(|digit| {
+        digit.iter().enumerate().map(|(l, &x)| {
+            let bits: Vec<bool> = to_bits(x as u64, d).collect();
+            let eq = less_than_columns(&bits, &bound_bits[l]);
+            (bits, eq)
+        }).collect::<Vec<_>>()
+    }).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_gadget_decompose() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // P1 < 2^31 needs 4 digits in base 2^8, and its top digit is below ceil(P1 / 2^24) = 65
+        let base = 1 << 8;
+        let levels = 4;
+        let poly = vec![0, 1, 255, 256, 0x01020304, 0x3f00ff00, P1 - 1, 12345];
+        let n = poly.len();
+
+        assert_eq!(decompose(0x01020304, base, levels), vec![4, 3, 2, 1]);
+        assert_eq!(digit_bounds(base, levels, P1), vec![256, 256, 256, 65]);
+
+        let air = GadgetDecomposeAir { poly:poly.clone(), base, levels, modulus:P1, n };
+        let trace = generate_decompose_trace::<Val>(poly.clone(), base, levels, P1, n).unwrap();
+
+        // digits reconstruct every coefficient
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let mut sum: u64 = 0;
+            for l in (0..levels).rev() {
+                let digit = decompose(poly[i], base, levels)[l];
+                assert_eq!(row[n + i*levels + l], Val::from_canonical_u32(digit));
+                sum = sum * base as u64 + digit as u64;
+            }
+            assert_eq!(sum, poly[i] as u64);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_gadget_decompose_digit_out_of_range() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let base = 1 << 8;
+        let levels = 4;
+        let n = 4;
+        let air = GadgetDecomposeAir { poly:vec![256, 1, 2, 3], base, levels, modulus:P1, n };
+
+        // claim 256 = 256 * 2^0 + 0 * 2^8, which reconstructs but has a digit equal to base
+        let mut trace = generate_decompose_trace::<Val>(air.poly.clone(), base, levels, P1, n).unwrap();
+        let width = trace.width();
+        for r in 0..4 {
+            trace.values[r*width + n] = Val::from_canonical_u32(256);
+            trace.values[r*width + n + 1] = Val::zero();
+        }
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof with digit 256 in base 256 was accepted");
+    }
+
+    #[test]
+    fn test_invalid_decomposition() {
+        // 3 digits in base 2^8 cover only [0, 2^24)
+        assert_eq!(
+            generate_decompose_trace::<Val>(vec![0; 4], 1 << 8, 3, P1, 4).unwrap_err(),
+            GadgetError::InvalidDecomposition { base: 1 << 8, levels: 3, modulus: P1 }
+        );
+        // 5 digits leave the top one always 0
+        assert!(generate_decompose_trace::<Val>(vec![0; 4], 1 << 8, 5, P1, 4).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_low, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::mod_switch::{ModSwitchAir, COLUMNS_PER_COEFF, generate_modswitch_trace, mod_switch, mod_switch_layout};
+use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace, negacyclic_coeffs, negacyclic_layout, negacyclic_reduced, negacyclic_reduced_range, negacyclic_width};
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+// The secret key s is a witness of the trace only, and is not part of the AIR
+pub struct DecryptAir {
+    pub c0: Vec<u32>,
+    pub c1: Vec<u32>,
+    // ciphertext modulus q
+    pub modulus: u32,
+    // plaintext modulus t
+    pub plain_modulus: u32,
+    pub n: usize
+}
+
+impl DecryptAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(c0: Vec<u32>, c1: Vec<u32>, modulus: u32, plain_modulus: u32) -> Self {
+        Self { c0, c1, modulus, plain_modulus, n: N }
+    }
+
+    // Negacyclic product c1 * s, with s read from the trace
+    fn product(&self) -> NegacyclicMulAir {
+        NegacyclicMulAir { a: vec![], b: vec![], modulus: self.modulus, n: self.n }
+    }
+
+    // Rounding of the phase c0 + c1 * s from q to t, with the phase read from the trace
+    fn rescale(&self) -> ModSwitchAir {
+        ModSwitchAir { input: vec![], q: self.modulus, q_prime: self.plain_modulus, n: self.n }
+    }
+}
+
+/*
+BFV Decryption Air
+Input:
+- (c0, c1): ciphertext mod q
+- s: secret key polynomial, a private witness
+- t: plaintext modulus
+Output:
+- m = round((c0 + c1 * s) * t / q) mod t: the recovered message
+
+Note:
+- The row chains 3 gadgets, every intermediate being a constrained column of the same trace:
+    1) a NegacyclicMulAir of (c1, s), embedded through eval_product(): its a input is pinned to self.c1,
+       and its b input is s, which no constraint pins to a public value
+    2) the phase, with a carry bit per coefficient as in CiphertextAddAir:
+       c0[i] + (c1 * s)[i] === carry[i] * q + phase[i]
+       through eval_reduced_sum() as in PolyAddAir: phase is range checked into [0, q) and the sum is also enforced mod 2^8
+       over the lowest limbs of c0 and of the range checked (c1 * s)[i], since it is above n for large moduli
+    3) a ModSwitchAir from q to t of the phase, embedded through eval_row(), whose x column is phase[i]
+- Every row is the same, as the rounding needs every row to pass its comparisons, so the links between the gadgets
+hold on every row.
+- s is bound to nothing but the ciphertext: the proof shows that some s decrypts (c0, c1) to m.
+Binding it to a public key is left to the calling protocol.
+*/
+impl<F: Field> BaseAir<F> for DecryptAir {
+    // Air Table looks like this
+    // row:[ c0: N ][ NegacyclicMulAir c1*s ][ phase: N ][ carry: N ][ phase_range: 62N ][ low_carry_bits: 23N ][ ModSwitchAir phase: q -> t ]
+    //     ^input^^(c1 input, s witness)-------------------------calculated by generate_decrypt_trace-------------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the rounding comparisons
+    fn width(&self) -> usize {
+        decrypt_width(self.n)
+    }
+}
+
+impl GadgetLayout for DecryptAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("c0", self.n)
+            .nest("mul", negacyclic_layout(self.n))
+            .push("phase", self.n)
+            .push("carry", self.n)
+            .push("phase_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+            .nest("rescale", mod_switch_layout(self.n))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for DecryptAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let mul = n;
+        let (c1, reduced) = (mul, mul + negacyclic_reduced(n));
+        let phase = mul + negacyclic_width(n);
+        let (carry, rescale) = (phase + n, decrypt_rescale(n));
+
+        // Enforce self.c0 and self.c1 as the ciphertext, and leave s free
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.c0[i]));
+            builder.when_first_row().assert_eq(row[c1+i], AB::Expr::from_canonical_u32(self.c1[i]));
+        }
+
+        // Enforce the negacyclic product c1 * s
+        self.product().eval_product(builder, &row[mul..phase]);
+
+        // Enforce c0[i] + (c1 * s)[i] === carry[i] * q + phase[i] on every row, as the rest of the row,
+        // with the lowest limbs of c0 and of the range checked product
+        let mask = (1 << LIMB_BITS) - 1;
+        let reduced_range = mul + negacyclic_reduced_range(n);
+        let sums = (0..n).map(|i| row[i] + row[reduced+i]).collect();
+        let sums_low = (0..n).map(|i| {
+            let block = reduced_range + i*RANGE_CHECK_WIDTH;
+            reduced_low::<AB>(&row[block..block + RANGE_CHECK_WIDTH]) + AB::F::from_canonical_u32(self.c0[i] & mask)
+        }).collect();
+        let carries = (0..n).map(|i| row[carry+i].into()).collect();
+        eval_reduced_sums(builder, sums, sums_low, carries, &row[phase..phase+n], &row[carry+n..rescale], self.modulus);
+
+        // Enforce the rounding of the phase from q to t, with its x columns === phase
+        self.rescale().eval_row(builder, &row[rescale..]);
+        for i in 0..n {
+            builder.assert_eq(row[rescale + i*COLUMNS_PER_COEFF], row[phase+i]);
+        }
+    }
+}
+
+fn decrypt_width(n: usize) -> usize {
+    decrypt_rescale(n) + COLUMNS_PER_COEFF*n
+}
+
+// Column of the ModSwitchAir of the phase, after the phase, its carries and their witness
+fn decrypt_rescale(n: usize) -> usize {
+    n + negacyclic_width(n) + 2*n + reduced_sums_width(n)
+}
+
+// Column of the message coefficient m[i] in the decryption trace
+pub fn decrypt_output(i: usize, n: usize) -> usize {
+    decrypt_rescale(n) + i*COLUMNS_PER_COEFF + 4
+}
+
+// round((c0 + c1 * s) * t / q) mod t computed on the host
+pub fn decrypt(c0: &[u32], c1: &[u32], s: &[u32], modulus: u32, plain_modulus: u32) -> Vec<u32> {
+    let product = negacyclic_coeffs(c1, s, modulus);
+    c0.iter().zip(product).map(|(&x, y)| {
+        let phase = ((x as u64 + y as u64) % modulus as u64) as u32;
+        mod_switch(phase, modulus, plain_modulus)
+    }).collect()
+}
+
+// Define a function to generate execution trace
+pub fn generate_decrypt_trace<F: Field>(c0: Vec<u32>, c1: Vec<u32>, s: Vec<u32>, modulus: u32, plain_modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&c0, n, modulus)?;
+    if plain_modulus == 0 {
+        return Err(GadgetError::ZeroModulus);
+    }
+    check_range_modulus(modulus)?;
+
+    let width = decrypt_width(n);
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Add c0, then the first row of the product c1 * s
+    row.extend(c0.iter().map(|&x| F::from_canonical_u32(x)));
+    let product = generate_negacyclic_mul_trace::<F>(c1.clone(), s.clone(), modulus, n)?;
+    row.extend_from_slice(&product.row_slice(0));
+    let product = negacyclic_coeffs(&c1, &s, modulus);
+
+    // Add the phase and the carries
+    let phase: Vec<u32> = (0..n).map(|i| ((c0[i] as u64 + product[i] as u64) % modulus as u64) as u32).collect();
+    let carry: Vec<bool> = (0..n).map(|i| c0[i] as u64 + product[i] as u64 >= modulus as u64).collect();
+    row.extend(phase.iter().map(|&x| F::from_canonical_u32(x)));
+    row.extend(carry.iter().map(|&c| F::from_bool(c)));
+
+    // Add the range checks of the phase and the carries of the low limb sums
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = (0..n).map(|i| (c0[i] & mask) as i64 + (product[i] & mask) as i64).collect();
+    row.extend(reduced_sums_witness::<F>(&low, &carry, &phase, modulus));
+
+    // Add the first row of the rounding of the phase
+    let rescale = generate_modswitch_trace::<F>(phase, modulus, plain_modulus, n)?;
+    row.extend_from_slice(&rescale.row_slice(0));
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_modswitch_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_decrypt() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // BFV encryption of m under a binary secret s: c1 = a, c0 = -a * s + e + floor(q / t) * m
+        let (n, q, t) = (8, P1 as u128, 16);
+        let mut rng = thread_rng();
+        let m: Vec<u128> = (0..n).map(|_| rng.gen_range(0..t)).collect();
+        let s: Vec<u128> = (0..n).map(|_| rng.gen_range(0..2)).collect();
+        let a: Vec<u128> = (0..n).map(|_| rng.gen_range(0..q)).collect();
+        let e: Vec<u128> = (0..n).map(|_| (rng.gen_range(-8i64..=8) as i128).rem_euclid(q as i128) as u128).collect();
+        let delta_m = reference::scalar_mul(&m, q / t, q);
+        let c0 = reference::add(&reference::add(&reference::neg(&reference::negacyclic_mul(&a, &s, q), q), &e, q), &delta_m, q);
+
+        let to_u32 = |poly: &[u128]| poly.iter().map(|&x| x as u32).collect::<Vec<_>>();
+        let (c0, c1, s) = (to_u32(&c0), to_u32(&a), to_u32(&s));
+        assert_eq!(decrypt(&c0, &c1, &s, P1, t as u32), to_u32(&m));
+
+        let air = DecryptAir { c0:c0.clone(), c1:c1.clone(), modulus:P1, plain_modulus:t as u32, n };
+        let trace = generate_decrypt_trace::<Val>(c0, c1, s, P1, t as u32, n).unwrap();
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            assert_eq!(row[decrypt_output(i, n)], Val::from_canonical_u32(m[i] as u32));
+            assert_eq!(air.layout().get(&format!("rescale.coeff[{}].out", i)), Some(decrypt_output(i, n)..decrypt_output(i, n)+1));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_decrypt_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let c0: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let c1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let s: Vec<u32> = (0..n).map(|_| rng.gen_range(0..2)).collect();
+        let air = DecryptAir { c0:c0.clone(), c1:c1.clone(), modulus:P1, plain_modulus:16, n };
+
+        // c1 * s with a wrong raw product and re-solved quotients, folded into the honest reduced product, so that the phase and the message are unchanged
+        assert_rejects_forged_product(&air, &generate_decrypt_trace(c0, c1, s, P1, 16, n).unwrap(), |_| P1);
+    }
+}
@@ -0,0 +1,153 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_nonempty, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+
+// Define AIR constraint inputs
+pub struct DegreeBoundAir {
+    // coefficients of any length, e.g. the 2N-1 coefficients of a product before the negacyclic reduction
+    pub poly: Vec<u32>,
+    // bound on the number of coefficients: poly[j] = 0 for j >= n
+    pub n: usize
+}
+
+/*
+Degree Bound Air
+Input:
+- poly = poly[0] + poly[1] * X + ... + poly[L-1] * X^{L-1}, with L >= n coefficients
+- n: degree bound, e.g. N for a polynomial of Z_mod[X]/(X^N+1)
+Output:
+- none: the proof shows that deg(poly) < n, i.e. poly[j] == 0 for every j >= n
+
+Note:
+- The high coefficients are asserted to be 0 column by column, with eval_degree_bound(), so the check composes
+with other gadgets over the row of a polynomial they produce, e.g. the unreduced product of PolyMulAir.
+- The coefficients are read modulo the native field, as in BitReverseAir: a coefficient below 2^31 - 1,
+e.g. any coefficient reduced by a modulus of this crate, is 0 exactly when its residue is.
+- generate_degree_bound_trace() rejects a polynomial with a nonzero high coefficient on the host, and a trace
+holding one anyway fails the constraints.
+*/
+impl<F: Field> BaseAir<F> for DegreeBoundAir {
+    // Air Table looks like this
+    // row:[ poly[0..n): n ][ poly[n..L): L-n ]
+    //     ^-------------input-------------^
+    //     [0.................................0]
+    //     [0.................................0]
+    //     [0.................................0]
+    fn width(&self) -> usize {
+        self.poly.len()
+    }
+}
+
+impl GadgetLayout for DegreeBoundAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("low", self.n)
+            .push("high", self.poly.len() - self.n)
+    }
+}
+
+// Enforce every coefficient of `poly` at index >= n to be 0
+pub(crate) fn eval_degree_bound<AB: AirBuilder>(builder: &mut AB, poly: &[AB::Var], n: usize) {
+    for &coeff in poly.iter().skip(n) {
+        builder.assert_zero(coeff);
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for DegreeBoundAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.poly as the input polynomial
+        for (j, &coeff) in self.poly.iter().enumerate() {
+            builder.when_first_row().assert_eq(row[j], AB::Expr::from_wrapped_u32(coeff));
+        }
+
+        // Enforce poly[j] === 0 for j >= n
+        eval_degree_bound(builder, &row[..self.poly.len()], self.n);
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_degree_bound_trace<F: Field>(poly: Vec<u32>, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_nonempty(n)?;
+    if poly.len() < n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: poly.len() });
+    }
+    if let Some(index) = poly.iter().skip(n).position(|&c| c != 0) {
+        return Err(GadgetError::IndexOutOfRange { index: n + index, n });
+    }
+
+    let width = poly.len();
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * width);
+
+    // Add input polynomial to values vector
+    values.extend(poly.iter().map(|&c| F::from_wrapped_u32(c)));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_degree_bound() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 2n-1 coefficients, as the unreduced product of 2 polynomials of n coefficients, with zero high terms
+        let n = 8;
+        let mut rng = thread_rng();
+        let mut poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        poly.resize(2*n-1, 0);
+
+        let air = DegreeBoundAir { poly:poly.clone(), n };
+        let trace = generate_degree_bound_trace::<Val>(poly, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_degree_bound_high_term() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // a nonzero coefficient of X^{n+2}
+        let n = 8;
+        let mut poly = vec![1u32; n];
+        poly.resize(2*n-1, 0);
+        poly[n+2] = 5;
+        assert_eq!(generate_degree_bound_trace::<Val>(poly.clone(), n).unwrap_err(), GadgetError::IndexOutOfRange { index: n+2, n });
+        assert_eq!(generate_degree_bound_trace::<Val>(vec![1; n-1], n).unwrap_err(), GadgetError::LengthMismatch { expected: n, actual: n-1 });
+
+        // the trace of the polynomial as is fails the constraints
+        let air = DegreeBoundAir { poly:poly.clone(), n };
+        let trace = pad_trace(poly.iter().map(|&c| Val::from_canonical_u32(c)).collect(), poly.len());
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof of a polynomial with a high-degree term was accepted");
+    }
+}
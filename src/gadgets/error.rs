@@ -0,0 +1,160 @@
+use std::fmt;
+use crate::params::ParamError;
+
+// Errors raised while building gadget inputs and traces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GadgetError {
+    // An input polynomial does not have the expected number of coefficients
+    LengthMismatch { expected: usize, actual: usize },
+    // A coefficient (or scalar) is not a canonical representative in [0, modulus)
+    CoefficientOutOfRange { index: usize, value: u32, modulus: u32 },
+    // Two operands are reduced modulo different moduli
+    ModulusMismatch { expected: u32, actual: u32 },
+    // base^levels does not cover [0, modulus) with exactly `levels` digits, or base < 2
+    InvalidDecomposition { base: u32, levels: usize, modulus: u32 },
+    // X -> X^k is not an automorphism of Z[X]/(X^N+1) for an even k
+    InvalidAutomorphism { k: usize, n: usize },
+    // The modulus has no known generator, or no root of unity of the requested order
+    UnsupportedNttSize { size: usize, modulus: u32 },
+    // A coefficient index is not in [0, n)
+    IndexOutOfRange { index: usize, n: usize },
+    // An unreduced value does not fit in the number of bits the gadget supports
+    ValueOutOfRange { index: usize, bits: usize },
+    // Montgomery reduction needs an odd modulus, coprime to R = 2^32
+    EvenModulus { modulus: u32 },
+    // An exact division by 0
+    ZeroDivisor,
+    // A requested trace height is not a power of two, or is below the `min` rows the trace needs
+    InvalidTraceHeight { height: usize, min: usize },
+    // A polynomial with N = 0 coefficients
+    EmptyPolynomial,
+    // mod + 2 * divisor exceeds 2^31, so a rounded division could wrap around the native field
+    DivisorTooLarge { divisor: u32, modulus: u32 },
+    // A signed coefficient is not a centered representative in (-modulus/2, modulus/2]
+    SignedCoefficientOutOfRange { index: usize, value: i64, modulus: u32 },
+    // A modulus of 0, which has no residues to reduce into
+    ZeroModulus,
+    // No gadget is registered under this operation name
+    UnknownOperation { name: String },
+    // The modulus is above `max`, the largest the gadget supports: e.g. 2 packed coefficients per native element,
+    // or a comparison over RANGE_CHECK_BITS bits
+    ModulusTooLarge { modulus: u32, max: u32 },
+    // A nonzero coefficient shares a factor with a composite modulus, so it has no inverse
+    NotInvertible { index: usize, value: u32, modulus: u32 },
+    // The input polynomials do not hash to the commitment the AIR is proven against
+    CommitmentMismatch,
+    // More coefficients than the `max` the bounds of the gadget's argument hold for
+    TooManyCoefficients { n: usize, max: usize },
+    // The FheParams an AIR is constructed with do not define it, e.g. a channel out of range
+    Params(ParamError),
+}
+
+impl fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GadgetError::LengthMismatch { expected, actual } => {
+                write!(f, "expected a polynomial with {} coefficients, got {}", expected, actual)
+            }
+            GadgetError::CoefficientOutOfRange { index, value, modulus } => {
+                write!(f, "coefficient {} at index {} is not in [0, {})", value, index, modulus)
+            }
+            GadgetError::ModulusMismatch { expected, actual } => {
+                write!(f, "expected an operand modulo {}, got one modulo {}", expected, actual)
+            }
+            GadgetError::InvalidDecomposition { base, levels, modulus } => {
+                write!(f, "{} digits in base {} do not decompose [0, {})", levels, base, modulus)
+            }
+            GadgetError::InvalidAutomorphism { k, n } => {
+                write!(f, "X -> X^{} is not an automorphism of Z[X]/(X^{}+1): k must be odd", k, n)
+            }
+            GadgetError::UnsupportedNttSize { size, modulus } => {
+                write!(f, "no root of unity of order {} modulo {}", size, modulus)
+            }
+            GadgetError::IndexOutOfRange { index, n } => {
+                write!(f, "coefficient index {} is not in [0, {})", index, n)
+            }
+            GadgetError::ValueOutOfRange { index, bits } => {
+                write!(f, "value at index {} does not fit in {} bits", index, bits)
+            }
+            GadgetError::EvenModulus { modulus } => {
+                write!(f, "modulus {} is even and has no inverse modulo a power of two", modulus)
+            }
+            GadgetError::ZeroDivisor => {
+                write!(f, "the divisor of an exact division must be nonzero")
+            }
+            GadgetError::InvalidTraceHeight { height, min } => {
+                write!(f, "trace height {} is not a power of two of at least {}", height, min)
+            }
+            GadgetError::EmptyPolynomial => {
+                write!(f, "a polynomial needs at least 1 coefficient")
+            }
+            GadgetError::DivisorTooLarge { divisor, modulus } => {
+                write!(f, "divisor {} is too large for modulus {}: mod + 2 * divisor must be at most 2^31", divisor, modulus)
+            }
+            GadgetError::SignedCoefficientOutOfRange { index, value, modulus } => {
+                write!(f, "signed coefficient {} at index {} is not in (-{}/2, {}/2]", value, index, modulus, modulus)
+            }
+            GadgetError::ZeroModulus => {
+                write!(f, "the modulus must be nonzero")
+            }
+            GadgetError::UnknownOperation { name } => {
+                write!(f, "unknown operation {}", name)
+            }
+            GadgetError::ModulusTooLarge { modulus, max } => {
+                write!(f, "modulus {} is too large: the gadget supports moduli up to {}", modulus, max)
+            }
+            GadgetError::NotInvertible { index, value, modulus } => {
+                write!(f, "coefficient {} at index {} has no inverse modulo {}", value, index, modulus)
+            }
+            GadgetError::CommitmentMismatch => write!(f, "the inputs do not match the commitment"),
+            GadgetError::TooManyCoefficients { n, max } => {
+                write!(f, "{} coefficients are too many: the gadget supports at most {}", n, max)
+            }
+            GadgetError::Params(e) => write!(f, "invalid parameters: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GadgetError {}
+
+impl From<ParamError> for GadgetError {
+    fn from(e: ParamError) -> Self {
+        GadgetError::Params(e)
+    }
+}
+
+// Check that the gadget has at least 1 coefficient, which its 2N-1 output degree needs
+pub fn check_nonempty(n: usize) -> Result<(), GadgetError> {
+    if n == 0 {
+        return Err(GadgetError::EmptyPolynomial);
+    }
+    Ok(())
+}
+
+// Check that `poly` has exactly `n` coefficients, all in [0, modulus), for a nonzero modulus
+pub fn check_poly(poly: &[u32], n: usize, modulus: u32) -> Result<(), GadgetError> {
+    if modulus == 0 {
+        return Err(GadgetError::ZeroModulus);
+    }
+    if poly.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: poly.len() });
+    }
+    match poly.iter().position(|&c| c >= modulus) {
+        Some(index) => Err(GadgetError::CoefficientOutOfRange { index, value: poly[index], modulus }),
+        None => Ok(()),
+    }
+}
+
+// Check the shape of the inputs of an AIR at construction: at least 1 coefficient, exactly `n` coefficients
+// per polynomial, and a nonzero modulus. Unlike check_poly() the coefficients are not compared with the modulus,
+// since an AIR may be built for a claimed input that the trace will not match.
+pub fn check_air_inputs(polys: &[&[u32]], n: usize, modulus: u32) -> Result<(), GadgetError> {
+    check_nonempty(n)?;
+    if modulus == 0 {
+        return Err(GadgetError::ZeroModulus);
+    }
+    match polys.iter().find(|poly| poly.len() != n) {
+        Some(poly) => Err(GadgetError::LengthMismatch { expected: n, actual: poly.len() }),
+        None => Ok(()),
+    }
+}
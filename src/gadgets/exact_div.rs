@@ -0,0 +1,195 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct ExactDivAir {
+    pub value: Vec<u32>,
+    // known factor dividing every coefficient, e.g. the prime dropped by RNS rescaling
+    pub divisor: u32,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl ExactDivAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(value: Vec<u32>, divisor: u32, modulus: u32) -> Self {
+        Self { value, divisor, modulus, n: N }
+    }
+}
+
+// Largest quotient of a coefficient in [0, modulus) by divisor, plus 1: the bound out[i] is range checked against
+fn quotient_bound(divisor: u32, modulus: u32) -> u32 {
+    (modulus - 1) / divisor + 1
+}
+
+/*
+Exact Division Air
+Input:
+- value = value[0] + value[1] * X + ... + value[N-1] * X^{N-1} with coefficients in [0, mod)
+- divisor: d > 0
+- mod: FHE ciphertext modulus
+Output:
+- out[i] = value[i] / d, where d divides every value[i] as an integer
+
+Note:
+- The division is written as the integer lift value[i] === out[i] * d + rem[i], with the remainder rem[i] === 0.
+- In the native field, out[i] * d can wrap around n, so any out[i] = value[i] * d^{-1} mod n would pass.
+Range checking out[i] < floor((mod-1)/d) + 1 as in RangeCheckAir gives out[i] * d <= mod - 1 < n,
+so the identity holds over the integers, out[i] is the exact quotient, and value[i] === out[i] * d mod mod as well.
+- A coefficient that d does not divide leaves rem[i] != 0 in the trace, and the proof fails.
+*/
+impl<F: Field> BaseAir<F> for ExactDivAir {
+    // Air Table looks like this
+    // row:[ value: N ][ out: N ][ rem: N ][ bits: 31N ][ eq: 31N ]
+    //     ^--input--^^-------calculated by generate_exact_div_trace------^
+    //     ... the same row repeated 3 times, since every row must pass the range checks
+    fn width(&self) -> usize {
+        (3 + 2*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for ExactDivAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("value", self.n)
+            .push("out", self.n)
+            .push("rem", self.n)
+            .push("bits", RANGE_CHECK_BITS*self.n)
+            .push("eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for ExactDivAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (value, out, rem) = (0, n, 2*n);
+        let bits = 3*n;
+        let eq = bits + RANGE_CHECK_BITS*n;
+        let divisor = AB::F::from_canonical_u32(self.divisor);
+        let bound = quotient_bound(self.divisor, self.modulus);
+
+        // Enforce self.value as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[value+i], AB::Expr::from_canonical_u32(self.value[i]));
+        }
+
+        for i in 0..n {
+            // Enforce value[i] === out[i] * d + rem[i] with rem[i] === 0
+            builder.assert_eq(row[value+i], row[out+i] * divisor + row[rem+i]);
+            builder.assert_zero(row[rem+i]);
+
+            // Enforce 0 <= out[i] < floor((mod-1)/d) + 1
+            let bits = bits + i*RANGE_CHECK_BITS;
+            let eq = eq + i*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[out+i].into(), &row[bits..bits+RANGE_CHECK_BITS], &row[eq..eq+RANGE_CHECK_BITS], bound);
+        }
+    }
+}
+
+// Define a function to generate execution trace
+// A coefficient that divisor does not divide is not an error here: its remainder is written to the trace for the proof to reject
+pub fn generate_exact_div_trace<F: Field>(value: Vec<u32>, divisor: u32, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&value, n, modulus)?;
+    if divisor == 0 {
+        return Err(GadgetError::ZeroDivisor);
+    }
+
+    let width = (3 + 2*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial, quotients and remainders to the row
+    row.extend(value.iter().map(|&c| F::from_canonical_u32(c)));
+    row.extend(value.iter().map(|&c| F::from_canonical_u32(c / divisor)));
+    row.extend(value.iter().map(|&c| F::from_canonical_u32(c % divisor)));
+
+    // Assign bits and prefix equality flags of every out[i] against floor((mod-1)/d) + 1
+    let bound = quotient_bound(divisor, modulus);
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = value.iter().map(|&c| range_check_columns(c / divisor, bound)).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::{P1, P2};
+
+    #[test]
+    fn test_exact_div() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // multiples of a small divisor and of a 31-bit RNS prime, including the largest one below mod
+        let n = 8;
+        let mut rng = thread_rng();
+        for divisor in [3, P2 >> 4] {
+            let max = (P1 - 1) / divisor;
+            let mut quotients: Vec<u32> = (0..n).map(|_| rng.gen_range(0..=max)).collect();
+            quotients[0] = max;
+            quotients[1] = 0;
+            let value: Vec<u32> = quotients.iter().map(|&q| q * divisor).collect();
+
+            let air = ExactDivAir { value:value.clone(), divisor, modulus:P1, n };
+            let trace = generate_exact_div_trace::<Val>(value, divisor, P1, n).unwrap();
+
+            let row = trace.row_slice(0);
+            for i in 0..n {
+                assert_eq!(row[n+i], Val::from_canonical_u32(quotients[i]));
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_exact_div_remainder() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 3 does not divide value[2] = 3 * 5 + 1
+        let n = 8;
+        let divisor = 3;
+        let mut value: Vec<u32> = (0..n as u32).map(|i| i * divisor).collect();
+        value[2] = 16;
+
+        let air = ExactDivAir { value:value.clone(), divisor, modulus:P1, n };
+        let trace = generate_exact_div_trace::<Val>(value, divisor, P1, n).unwrap();
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "a division with a remainder was accepted");
+    }
+}
@@ -0,0 +1,252 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::ciphertext::Ciphertext;
+use crate::gadgets::decompose::{GadgetDecomposeAir, decompose_layout, decompose_width, digit_column, digit_polynomials, generate_decompose_trace};
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::inner_product::{InnerProductAir, generate_inner_product_trace, inner_product_layout, inner_product_output, inner_product_width};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::negacyclic::{negacyclic_coeffs, negacyclic_width};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct ExternalProductAir {
+    // GLWE ciphertext (c0, c1) with k = 1, i.e. an RLWE ciphertext
+    pub glwe: Ciphertext,
+    // GGSW ciphertext: 2L GLWE rows, the first L for the digits of c0 and the last L for the digits of c1
+    pub ggsw: Vec<Ciphertext>,
+    pub base: u32,
+    pub levels: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl ExternalProductAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(glwe: Ciphertext, ggsw: Vec<Ciphertext>, base: u32, levels: usize, modulus: u32) -> Self {
+        Self { glwe, ggsw, base, levels, modulus, n: N }
+    }
+
+    // The decompositions of c0 and c1, in the order their sub-traces are laid out
+    fn decompose_airs(&self) -> [GadgetDecomposeAir; 2] {
+        [&self.glwe.c0, &self.glwe.c1].map(|poly| GadgetDecomposeAir { poly: poly.clone(), base: self.base, levels: self.levels, modulus: self.modulus, n: self.n })
+    }
+
+    // The inner products of the 2L digit polynomials with the 2 components of the GGSW rows
+    fn inner_products(&self) -> [InnerProductAir; 2] {
+        let digits = glwe_digits(&self.glwe, self.base, self.levels);
+        let components = ggsw_components(&self.ggsw);
+        components.map(|column| InnerProductAir { a: digits.clone(), b: column, modulus: self.modulus, n: self.n })
+    }
+}
+
+/*
+External Product Air (GGSW x GLWE)
+Input:
+- (c0, c1): GLWE ciphertext with N coefficients per polynomial
+- G[0..2L): GGSW ciphertext, each row G[l] = (G[l].c0, G[l].c1) a GLWE ciphertext
+- base: B, levels: L of the gadget decomposition
+- mod: FHE ciphertext modulus
+Output:
+- out = sum_l d_l * G[l] in (Z_mod[X]/(X^N+1))^2, where d_0..d_{L-1} are the base-B digit polynomials of c0
+and d_L..d_{2L-1} those of c1, i.e. out.c_k = <d, (G[0].c_k, ..., G[2L-1].c_k)> for k = 0, 1
+
+Note:
+- As in RelinearizeAir, the row is the composition of sub-AIRs embedded through their eval_row():
+    1) GadgetDecomposeAir of c0 and GadgetDecomposeAir of c1, proving the 2L digit polynomials
+    2) one InnerProductAir of length 2L per output component
+and the digit columns of 1) are constrained equal to the a_l inputs of both inner products on the first row.
+- When G encrypts a bit m, out encrypts m times the message of (c0, c1), which is the selection step of a CMux.
+*/
+impl<F: Field> BaseAir<F> for ExternalProductAir {
+    // Air Table looks like this
+    // row:[ GadgetDecomposeAir c0 ][ GadgetDecomposeAir c1 ][ InnerProductAir out.c0 ][ InnerProductAir out.c1 ]
+    //     [ repeated              ][ repeated              ][0.............................................0]
+    //     [ repeated              ][ repeated              ][0.............................................0]
+    //     [ repeated              ][ repeated              ][0.............................................0]
+    fn width(&self) -> usize {
+        external_product_width(self.base, self.levels, self.n)
+    }
+}
+
+impl GadgetLayout for ExternalProductAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .nest("c0", decompose_layout(self.base, self.levels, self.n))
+            .nest("c1", decompose_layout(self.base, self.levels, self.n))
+            .nest("out.c0", inner_product_layout(2*self.levels, self.n))
+            .nest("out.c1", inner_product_layout(2*self.levels, self.n))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for ExternalProductAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let levels = self.levels;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // 1) decompositions of c0 and c1
+        let decompose_cols = decompose_width(self.base, levels, n);
+        for (p, decompose) in self.decompose_airs().iter().enumerate() {
+            decompose.eval_row(builder, &row[p*decompose_cols..(p+1)*decompose_cols]);
+        }
+
+        // 2) inner products, with a_l[i] === digit[i][l mod L] of c0 for l < L, and of c1 for l >= L
+        let ip_width = inner_product_width(2*levels, n);
+        let channel_width = negacyclic_width(n);
+        for (k, ip) in self.inner_products().iter().enumerate() {
+            let offset = 2*decompose_cols + k*ip_width;
+            ip.eval_row(builder, &row[offset..offset+ip_width]);
+            for l in 0..2*levels {
+                let digits = (l / levels)*decompose_cols;
+                for i in 0..n {
+                    builder.when_first_row().assert_eq(row[offset + l*channel_width + i], row[digits + digit_column(i, l % levels, levels, n)]);
+                }
+            }
+        }
+    }
+}
+
+fn external_product_width(base: u32, levels: usize, n: usize) -> usize {
+    2*decompose_width(base, levels, n) + 2*inner_product_width(2*levels, n)
+}
+
+// Column of out.c0[0] in the external product trace, and the offset to out.c1[0]
+pub fn external_product_output(base: u32, levels: usize, n: usize) -> (usize, usize) {
+    let ip_width = inner_product_width(2*levels, n);
+    (2*decompose_width(base, levels, n) + inner_product_output(2*levels, n), ip_width)
+}
+
+// The 2L digit polynomials of c0, then c1
+fn glwe_digits(glwe: &Ciphertext, base: u32, levels: usize) -> Vec<Vec<u32>> {
+    let mut digits = digit_polynomials(&glwe.c0, base, levels);
+    digits.extend(digit_polynomials(&glwe.c1, base, levels));
+    digits
+}
+
+// (G[0].c0, ..., G[2L-1].c0) and (G[0].c1, ..., G[2L-1].c1)
+fn ggsw_components(ggsw: &[Ciphertext]) -> [Vec<Vec<u32>>; 2] {
+    [ggsw.iter().map(|row| row.c0.clone()).collect(), ggsw.iter().map(|row| row.c1.clone()).collect()]
+}
+
+// sum_l d_l * G[l] computed on the host
+pub fn external_product(glwe: &Ciphertext, ggsw: &[Ciphertext], base: u32, levels: usize, modulus: u32) -> Ciphertext {
+    let digits = glwe_digits(glwe, base, levels);
+    let [out0, out1] = ggsw_components(ggsw).map(|column| {
+        let mut acc = vec![0u64; glwe.c0.len()];
+        for (d, key) in digits.iter().zip(column.iter()) {
+            for (a, p) in acc.iter_mut().zip(negacyclic_coeffs(d, key, modulus)) {
+                *a = (*a + p as u64) % modulus as u64;
+            }
+        }
+        acc.iter().map(|&a| a as u32).collect::<Vec<u32>>()
+    });
+    Ciphertext::new(out0, out1)
+}
+
+// Define a function to generate execution trace
+pub fn generate_external_product_trace<F: Field>(glwe: Ciphertext, ggsw: Vec<Ciphertext>, base: u32, levels: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    glwe.check(n, modulus)?;
+    if ggsw.len() != 2*levels {
+        return Err(GadgetError::LengthMismatch { expected: 2*levels, actual: ggsw.len() });
+    }
+    for row in ggsw.iter() {
+        row.check(n, modulus)?;
+    }
+
+    let decompose_traces = [&glwe.c0, &glwe.c1].into_iter()
+        .map(|poly| generate_decompose_trace::<F>(poly.clone(), base, levels, modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+    let digits = glwe_digits(&glwe, base, levels);
+    let ip_traces = ggsw_components(&ggsw).into_iter()
+        .map(|column| generate_inner_product_trace::<F>(digits.clone(), column, modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Concatenate the sub-traces row by row
+    let width = external_product_width(base, levels, n);
+    let height = decompose_traces[0].height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        for trace in decompose_traces.iter().chain(ip_traces.iter()) {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    // Noiseless GGSW of the bit m: G[l] = (m * B^l, 0) and G[L+l] = (0, m * B^l), so that out = m * (c0, c1)
+    fn trivial_ggsw(m: u32, base: u32, levels: usize, n: usize) -> Vec<Ciphertext> {
+        let constant = |l: usize| -> Vec<u32> {
+            let mut poly = vec![0u32; n];
+            poly[0] = (m as u64 * (base as u64).pow(l as u32) % P1 as u64) as u32;
+            poly
+        };
+        let mut ggsw: Vec<Ciphertext> = (0..levels).map(|l| Ciphertext::new(constant(l), vec![0; n])).collect();
+        ggsw.extend((0..levels).map(|l| Ciphertext::new(vec![0; n], constant(l))));
+        ggsw
+    }
+
+    #[test]
+    fn test_external_product_selects() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 4;
+        let base = 1 << 8;
+        let levels = 4;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let glwe = Ciphertext::new(random_poly(), random_poly());
+
+        // m = 1 keeps the ciphertext, m = 0 zeroes it
+        for m in [0, 1] {
+            let ggsw = trivial_ggsw(m, base, levels, n);
+            let out = external_product(&glwe, &ggsw, base, levels, P1);
+            let selected = if m == 1 { glwe.clone() } else { Ciphertext::new(vec![0; n], vec![0; n]) };
+            assert_eq!(out, selected);
+
+            let air = ExternalProductAir { glwe: glwe.clone(), ggsw: ggsw.clone(), base, levels, modulus: P1, n };
+            let trace = generate_external_product_trace::<Val>(glwe.clone(), ggsw, base, levels, P1, n).unwrap();
+
+            let (start, next) = external_product_output(base, levels, n);
+            let row = trace.row_slice(0);
+            for i in 0..n {
+                assert_eq!(row[start+i], Val::from_canonical_u32(out.c0[i]));
+                assert_eq!(row[start+next+i], Val::from_canonical_u32(out.c1[i]));
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_external_product_forged_product() {
+        let n = 4;
+        let (base, levels) = (1 << 16, 2);
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let glwe = Ciphertext::new(random_poly(), random_poly());
+        let ggsw: Vec<Ciphertext> = (0..2*levels).map(|_| Ciphertext::new(random_poly(), random_poly())).collect();
+        let air = ExternalProductAir { glwe: glwe.clone(), ggsw: ggsw.clone(), base, levels, modulus: P1, n };
+
+        // every digit-GGSW product with a wrong raw product and re-solved quotients
+        assert_rejects_forged_product(&air, &generate_external_product_trace(glwe, ggsw, base, levels, P1, n).unwrap(), |_| P1);
+    }
+}
@@ -0,0 +1,259 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_low, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace, negacyclic_coeffs, negacyclic_layout, negacyclic_reduced, negacyclic_reduced_range, negacyclic_width};
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct InnerProductAir {
+    // a_0, ..., a_{L-1}, e.g. the decomposed digit polynomials
+    pub a: Vec<Vec<u32>>,
+    // b_0, ..., b_{L-1}, e.g. the key-switching key polynomials
+    pub b: Vec<Vec<u32>>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl InnerProductAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<Vec<u32>>, b: Vec<Vec<u32>>, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+
+    // The L negacyclic multiplications a_l * b_l, in the order their sub-traces are laid out
+    fn channels(&self) -> Vec<NegacyclicMulAir> {
+        self.a.iter().zip(self.b.iter())
+            .map(|(a, b)| NegacyclicMulAir { a: a.clone(), b: b.clone(), modulus: self.modulus, n: self.n })
+            .collect()
+    }
+}
+
+/*
+Inner Product Air
+Input:
+- a_0, ..., a_{L-1} and b_0, ..., b_{L-1}: 2 vectors of L polynomials with N coefficients
+- mod: FHE ciphertext modulus
+Output:
+- out = sum_l a_l * b_l in Z_mod[X]/(X^N+1)
+
+Note:
+- Each product a_l * b_l is an independent NegacyclicMulAir, placed side by side in one row as in PtCtMulAir.
+- The products are accumulated left to right, with a carry bit per coefficient as in CiphertextAddAir:
+    acc_0 = reduced_0
+    acc_{l-1}[i] + reduced_l[i] === carry_l[i] * mod + acc_l[i]  for l = [1..L)
+so every partial sum is constrained, and out = acc_{L-1}.
+- acc_{l-1}[i] + reduced_l[i] is above n for large moduli, so every partial sum goes through eval_reduced_sum() as in PolyAddAir:
+acc_l is range checked into [0, mod) and the step is also enforced mod 2^8 over the lowest limbs of acc_{l-1} and reduced_l,
+read from their range checks, on the first row.
+*/
+impl<F: Field> BaseAir<F> for InnerProductAir {
+    // Air Table looks like this
+    // row:[ NegacyclicMulAir a_0 * b_0 ]...[ NegacyclicMulAir a_{L-1} * b_{L-1} ][ sum_1 ]...[ sum_{L-1} ]
+    // sum_l:[acc_l: N][carry_l: N][acc_range_l: 62N][low_carry_bits_l: 23N]
+    //     [0..........................................................................................0]
+    //     [0..........................................................................................0]
+    //     [0..........................................................................................0]
+    fn width(&self) -> usize {
+        inner_product_width(self.a.len(), self.n)
+    }
+}
+
+impl GadgetLayout for InnerProductAir {
+    fn layout(&self) -> TraceLayout {
+        inner_product_layout(self.a.len(), self.n)
+    }
+}
+
+// Layout of InnerProductAir with `levels` products, shared with the gadgets embedding it
+pub(crate) fn inner_product_layout(levels: usize, n: usize) -> TraceLayout {
+    let products = (0..levels).fold(TraceLayout::new(), |layout, l| layout.nest(&format!("product[{}]", l), negacyclic_layout(n)));
+    (1..levels).fold(products, |layout, l| {
+        layout.push(format!("acc[{}]", l), n)
+            .push(format!("carry[{}]", l), n)
+            .push(format!("acc_range[{}]", l), RANGE_CHECK_WIDTH*n)
+            .push(format!("low_carry_bits[{}]", l), MUL_CARRY_BITS*n)
+    })
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for InnerProductAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+        self.eval_row(builder, &row);
+    }
+}
+
+impl InnerProductAir {
+    // Enforce the inner product constraints over `row`, which starts at the a_0[0] column,
+    // so other gadgets can embed the InnerProductAir layout at any column offset
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+
+        let channel_width = negacyclic_width(n);
+        let channels = self.channels();
+        for (l, channel) in channels.iter().enumerate() {
+            channel.eval_row(builder, &row[l*channel_width..(l+1)*channel_width]);
+        }
+
+        // reduced starts at negacyclic_reduced() within each channel, and its range checks at negacyclic_reduced_range()
+        let reduced = |l: usize| l*channel_width + negacyclic_reduced(n);
+        let reduced_range = |l: usize| l*channel_width + negacyclic_reduced_range(n);
+        let acc = |l: usize| channels.len()*channel_width + (l-1)*partial_sum_width(n);
+
+        // Enforce acc_{l-1}[i] + reduced_l[i] === carry_l[i] * mod + acc_l[i], where acc_0 is reduced_0,
+        // with the lowest limbs of both terms read from their range checks
+        let low = |range: usize, i: usize| reduced_low::<AB>(&row[range + i*RANGE_CHECK_WIDTH..range + (i+1)*RANGE_CHECK_WIDTH]);
+        for l in 1..channels.len() {
+            let (prev, prev_range) = if l == 1 { (reduced(0), reduced_range(0)) } else { (acc(l-1), acc(l-1) + 2*n) };
+            let carry = acc(l) + n;
+            let sums = (0..n).map(|i| row[prev+i] + row[reduced(l)+i]).collect();
+            let sums_low = (0..n).map(|i| low(prev_range, i) + low(reduced_range(l), i)).collect();
+            let carries = (0..n).map(|i| row[carry+i].into()).collect();
+            eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &row[acc(l)..acc(l)+n], &row[carry+n..acc(l) + partial_sum_width(n)], self.modulus);
+        }
+    }
+}
+
+// Columns of one partial sum acc_l of the inner product: acc_l, carry_l and the witness of their eval_reduced_sums()
+fn partial_sum_width(n: usize) -> usize {
+    2*n + reduced_sums_width(n)
+}
+
+pub(crate) fn inner_product_width(levels: usize, n: usize) -> usize {
+    levels*negacyclic_width(n) + (levels-1)*partial_sum_width(n)
+}
+
+// Column of out = acc_{L-1}[0] in the inner product trace
+pub fn inner_product_output(levels: usize, n: usize) -> usize {
+    if levels == 1 { negacyclic_reduced(n) } else { inner_product_width(levels, n) - partial_sum_width(n) }
+}
+
+// Column of the range check of out[0] in the inner product trace, a block of RANGE_CHECK_WIDTH columns per coefficient
+pub(crate) fn inner_product_output_range(levels: usize, n: usize) -> usize {
+    if levels == 1 { negacyclic_reduced_range(n) } else { inner_product_output(levels, n) + 2*n }
+}
+
+// Define a function to generate execution trace
+pub fn generate_inner_product_trace<F: Field>(a: Vec<Vec<u32>>, b: Vec<Vec<u32>>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if a.len() != b.len() || a.is_empty() {
+        return Err(GadgetError::LengthMismatch { expected: a.len().max(1), actual: b.len() });
+    }
+    let channel_traces = a.iter().zip(b.iter())
+        .map(|(a, b)| generate_negacyclic_mul_trace::<F>(a.clone(), b.clone(), modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    check_range_modulus(modulus)?;
+
+    // Accumulate the products on the host, keeping the carries and the witness of their reductions
+    let mask = (1 << LIMB_BITS) - 1;
+    let products: Vec<Vec<u32>> = a.iter().zip(b.iter()).map(|(a, b)| negacyclic_coeffs(a, b, modulus)).collect();
+    let mut acc = products[0].clone();
+    let mut partial_sums: Vec<Vec<F>> = Vec::with_capacity(products.len() - 1);
+    for product in products.iter().skip(1) {
+        let sums: Vec<u64> = (0..n).map(|i| acc[i] as u64 + product[i] as u64).collect();
+        let carry: Vec<bool> = sums.iter().map(|&s| s >= modulus as u64).collect();
+        let low: Vec<i64> = (0..n).map(|i| (acc[i] & mask) as i64 + (product[i] & mask) as i64).collect();
+        acc = sums.iter().map(|&s| (s % modulus as u64) as u32).collect();
+
+        let mut values: Vec<F> = acc.iter().map(|&c| F::from_canonical_u32(c)).collect();
+        values.extend(carry.iter().map(|&c| F::from_bool(c)));
+        values.extend(reduced_sums_witness::<F>(&low, &carry, &acc, modulus));
+        partial_sums.push(values);
+    }
+
+    // Concatenate the channel traces row by row, then the accumulator on the first row
+    let width = inner_product_width(a.len(), n);
+    let height = channel_traces[0].height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        for trace in channel_traces.iter() {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+        for partial_sum in partial_sums.iter() {
+            if r == 0 {
+                values.extend_from_slice(partial_sum);
+            } else {
+                values.extend((0..partial_sum_width(n)).map(|_| F::zero()));
+            }
+        }
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_inner_product() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // a_0 * X^3 and a_1 * X^5, which are independently checkable rotations
+        let n = 8;
+        let mut rng = thread_rng();
+        let a: Vec<Vec<u32>> = (0..2).map(|_| (0..n).map(|_| rng.gen_range(0..P1)).collect()).collect();
+        let mut b = vec![vec![0u32; n]; 2];
+        b[0][3] = 1;
+        b[1][5] = 1;
+
+        let rotate = |c: &[u32], k: usize| -> Vec<u32> {
+            let mut rotated = vec![0u32; n];
+            for i in 0..n {
+                if i + k < n {
+                    rotated[i+k] = c[i];
+                } else {
+                    rotated[i+k-n] = (P1 - c[i]) % P1;
+                }
+            }
+            rotated
+        };
+        let (p0, p1) = (rotate(&a[0], 3), rotate(&a[1], 5));
+        assert_eq!(negacyclic_mul_ref(&a[0], &b[0], P1, n), p0);
+        assert_eq!(negacyclic_mul_ref(&a[1], &b[1], P1, n), p1);
+
+        let air = InnerProductAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let trace = generate_inner_product_trace::<Val>(a, b, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        let out = inner_product_output(2, n);
+        for i in 0..n {
+            let expected = ((p0[i] as u64 + p1[i] as u64) % P1 as u64) as u32;
+            assert_eq!(row[out+i], Val::from_canonical_u32(expected));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_inner_product_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<Vec<u32>> = (0..2).map(|_| (0..n).map(|_| rng.gen_range(0..P1)).collect()).collect();
+        let b: Vec<Vec<u32>> = (0..2).map(|_| (0..n).map(|_| rng.gen_range(0..P1)).collect()).collect();
+        let air = InnerProductAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+
+        // every a_j * b_j with a wrong raw product and re-solved quotients, accumulated into a consistent sum
+        assert_rejects_forged_product(&air, &generate_inner_product_trace(a, b, P1, n).unwrap(), |_| P1);
+    }
+}
@@ -0,0 +1,260 @@
+use std::ops::Range;
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::GadgetError;
+
+/*
+Column layout of a gadget trace
+
+Note:
+- The layout is the ABI of a gadget: the named column blocks of one row, in order, as described by the "Air Table" comment of its BaseAir,
+e.g. PolyAddAir starts with [a: 0..N][b: N..2N][mod: 2N..2N+1][out: 2N+1..3N+1].
+- Blocks are appended one after another, so they never overlap and always cover the row: the width is the end of the last block.
+- Composite gadgets nest the layouts of their sub-AIRs, with the names prefixed by the name of the sub-AIR,
+e.g. "rlk0.product[1].reduced" for the reduced product a_1 * b_1 of the rlk0 inner product of RelinearizeAir.
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceLayout {
+    columns: Vec<(String, Range<usize>)>,
+    width: usize
+}
+
+impl TraceLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Append a block of `len` columns named `name`
+    pub fn push(mut self, name: impl Into<String>, len: usize) -> Self {
+        self.columns.push((name.into(), self.width..self.width + len));
+        self.width += len;
+        self
+    }
+
+    // Append the blocks of `inner`, named "`name`.<block>"
+    pub fn nest(mut self, name: &str, inner: TraceLayout) -> Self {
+        for (block, range) in inner.columns {
+            self.columns.push((format!("{}.{}", name, block), self.width + range.start..self.width + range.end));
+        }
+        self.width += inner.width;
+        self
+    }
+
+    // Number of columns of the trace
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // The named blocks of the row, in column order
+    pub fn columns(&self) -> &[(String, Range<usize>)] {
+        &self.columns
+    }
+
+    // Column range of the block `name`
+    pub fn get(&self, name: &str) -> Option<Range<usize>> {
+        self.columns.iter().find(|(block, _)| block == name).map(|(_, range)| range.clone())
+    }
+
+    // Check that an externally-produced trace has the width of this layout
+    pub fn check_trace<F: Field>(&self, trace: &RowMajorMatrix<F>) -> Result<(), GadgetError> {
+        if trace.width() != self.width {
+            return Err(GadgetError::LengthMismatch { expected: self.width, actual: trace.width() });
+        }
+        Ok(())
+    }
+}
+
+// Gadgets that document their trace layout
+pub trait GadgetLayout {
+    fn layout(&self) -> TraceLayout;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_air::BaseAir;
+    use p3_field::AbstractField;
+    use crate::gadgets::config::Val;
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::gadgets::add_then_mul::{AddThenMulAir, add_then_mul_output};
+    use crate::gadgets::approx_equal::ApproxEqualAir;
+    use crate::gadgets::automorphism::GaloisAutomorphismAir;
+    use crate::gadgets::barrett::BarrettReduceAir;
+    use crate::gadgets::base_extend::BaseExtendAir;
+    use crate::gadgets::batch_add::BatchAddAir;
+    use crate::gadgets::bit_decompose::BitDecomposeAir;
+    use crate::gadgets::bit_reverse::BitReverseAir;
+    use crate::gadgets::center::CenterAir;
+    use crate::gadgets::ciphertext::{Ciphertext, CiphertextAddAir};
+    use crate::gadgets::coeff_sum::CoeffSumAir;
+    use crate::gadgets::crt::CrtRecombineAir;
+    use crate::gadgets::ct_mul::{CtMulAir, ct_mul_output};
+    use crate::gadgets::decompose::GadgetDecomposeAir;
+    use crate::gadgets::decrypt::DecryptAir;
+    use crate::gadgets::degree_bound::DegreeBoundAir;
+    use crate::gadgets::exact_div::ExactDivAir;
+    use crate::gadgets::external_product::{ExternalProductAir, external_product_output};
+    use crate::gadgets::inner_product::{InnerProductAir, inner_product_output};
+    use crate::gadgets::less_than::LessThanAir;
+    use crate::gadgets::mat_vec::{PolyMatVecMulAir, mat_vec_output};
+    use crate::gadgets::mod_exp::ModExpAir;
+    use crate::gadgets::mod_inverse::ModInverseAir;
+    use crate::gadgets::mod_switch::ModSwitchAir;
+    use crate::gadgets::monomial_mul::MonomialMulAir;
+    use crate::gadgets::montgomery::MontgomeryReduceAir;
+    use crate::gadgets::mul::{MulShape, PolyMulAir};
+    use crate::gadgets::neg::PolyNegAir;
+    use crate::gadgets::negacyclic::NegacyclicMulAir;
+    use crate::gadgets::noise_bound::NoiseBoundAir;
+    use crate::gadgets::ntt::{NttAir, InttAir};
+    use crate::gadgets::ntt_mul::NttMulAir;
+    use crate::gadgets::packed_add::PackedPolyAddAir;
+    use crate::gadgets::pointwise_mul::PointwiseMulAir;
+    use crate::gadgets::poly_eval::{PolyEvalAir, poly_eval_output};
+    use crate::gadgets::poly_op::PolynomialOpAir;
+    use crate::gadgets::ptct_mul::PtCtMulAir;
+    use crate::gadgets::range_check::RangeCheckAir;
+    use crate::gadgets::relinearize::{RelinearizeAir, relinearize_output};
+    use crate::gadgets::rns::RnsPolyMulAir;
+    use crate::gadgets::round_div::{RoundDivAir, RoundMode};
+    use crate::gadgets::sample_extract::SampleExtractAir;
+    use crate::gadgets::scalar_mul::PolyScalarMulAir;
+    use crate::gadgets::square::PolySquareAir;
+    use crate::gadgets::sub::PolySubAir;
+    use crate::gadgets::wide_reduce::WideReduceAir;
+    use crate::params::{P1, P2, P3};
+
+    fn assert_width<A: BaseAir<Val> + GadgetLayout>(air: &A) {
+        let layout = air.layout();
+        assert_eq!(layout.width(), air.width());
+        // blocks are contiguous from column 0
+        let mut start = 0;
+        for (name, range) in layout.columns() {
+            assert_eq!(range.start, start, "block {} does not follow the previous one", name);
+            start = range.end;
+        }
+        assert_eq!(start, layout.width());
+    }
+
+    #[test]
+    fn test_layout_matches_width() {
+        let n = 8;
+        let poly = vec![0u32; n];
+        let ct = Ciphertext::new(poly.clone(), poly.clone());
+        let (base, levels) = (1 << 10, 3);
+        let keys = vec![poly.clone(); levels];
+
+        assert_width(&PolyAddAir { a: poly.clone(), b: poly.clone(), modulus: P1, n });
+        assert_width(&PackedPolyAddAir { a: poly.clone(), b: poly.clone(), modulus: 257, n });
+        assert_width(&AddThenMulAir { a: poly.clone(), b: poly.clone(), c: poly.clone(), modulus: P1, n });
+        assert_width(&PolySubAir { a: poly.clone(), b: poly.clone(), modulus: P1, n });
+        assert_width(&PolyMulAir { a: poly.clone(), b: poly.clone(), modulus: P1, n, shape: MulShape::Row });
+        assert_width(&PolyMulAir { a: poly.clone(), b: poly.clone(), modulus: P1, n, shape: MulShape::Rows });
+        assert_width(&PolySquareAir { a: poly.clone(), modulus: P1, n });
+        assert_width(&PolyNegAir { a: poly.clone(), modulus: P1, n });
+        assert_width(&PolyScalarMulAir { a: poly.clone(), scalar: 3, modulus: P1, n });
+        assert_width(&PolyEvalAir { poly: poly.clone(), x: 3, modulus: P1, n });
+        assert_width(&CoeffSumAir { poly: poly.clone(), modulus: P1, n });
+        assert_width(&DegreeBoundAir { poly: vec![0; 2*n-1], n });
+        assert_width(&NegacyclicMulAir { a: poly.clone(), b: poly.clone(), modulus: P1, n });
+        assert_width(&PointwiseMulAir { a_ntt: poly.clone(), b_ntt: poly.clone(), modulus: P1, n });
+        assert_width(&NttAir { input: poly.clone(), modulus: P1, n, root: 1 });
+        assert_width(&InttAir { input: poly.clone(), modulus: P1, n, root: 1, n_inv: 1 });
+        assert_width(&NttMulAir { a: poly.clone(), b: poly.clone(), modulus: P1, n, root: 1 });
+        assert_width(&RnsPolyMulAir { channels: [P1, P2, P3].map(|p| PolyMulAir { a: poly.clone(), b: poly.clone(), modulus: p, n, shape: MulShape::Row }).into(), n });
+        assert_width(&CrtRecombineAir { residues: [poly.clone(), poly.clone(), poly.clone()], n });
+        assert_width(&GaloisAutomorphismAir { poly: poly.clone(), k: 3, modulus: P1, n });
+        assert_width(&MonomialMulAir { poly: poly.clone(), k: 3, modulus: P1, n });
+        assert_width(&SampleExtractAir { rlwe_a: poly.clone(), rlwe_b: poly.clone(), index: 0, modulus: P1, n });
+        assert_width(&RangeCheckAir { a: poly.clone(), modulus: P1, n });
+        assert_width(&LessThanAir { a: poly.clone(), b: poly.clone(), modulus: P1, n });
+        assert_width(&CenterAir { poly: poly.clone(), modulus: P1, n });
+        assert_width(&NoiseBoundAir { poly: poly.clone(), bound: 3, modulus: P1, n });
+        assert_width(&ApproxEqualAir { a: poly.clone(), b: poly.clone(), bound: 3, modulus: P1, n });
+        assert_width(&DecryptAir { c0: poly.clone(), c1: poly.clone(), modulus: P1, plain_modulus: 16, n });
+        assert_width(&ExactDivAir { value: poly.clone(), divisor: 3, modulus: P1, n });
+        assert_width(&RoundDivAir { value: poly.clone(), divisor: 3, modulus: P1, mode: RoundMode::Nearest, n });
+        assert_width(&BitDecomposeAir { a: poly.clone(), num_bits: 12, n });
+        assert_width(&BitReverseAir { input: poly.clone(), log_n: 3 });
+        assert_width(&ModSwitchAir { input: poly.clone(), q: P1, q_prime: P2, n });
+        assert_width(&ModExpAir::new(3, 5, P1).unwrap());
+        assert_width(&ModInverseAir { a: poly.clone(), modulus: P1, n });
+        assert_width(&BarrettReduceAir { value: vec![0; n], modulus: P1, n });
+        assert_width(&BaseExtendAir { r1: poly.clone(), r2: poly.clone(), n });
+        assert_width(&WideReduceAir { value: vec![0; n], n });
+        assert_width(&MontgomeryReduceAir { value: vec![0; n], modulus: P1, r: 1 << 32, q_inv: 1, n });
+        assert_width(&BatchAddAir { a: vec![poly.clone(); 5], b: vec![poly.clone(); 5], modulus: P1, n });
+        assert_width(&CiphertextAddAir { a: ct.clone(), b: ct.clone(), modulus: P1, n });
+        assert_width(&PtCtMulAir { ct: ct.clone(), m: poly.clone(), modulus: P1, n });
+        assert_width(&InnerProductAir { a: keys.clone(), b: keys.clone(), modulus: P1, n });
+        assert_width(&PolyMatVecMulAir { matrix: vec![keys.clone(); 2], vector: keys.clone(), modulus: P1, n });
+        assert_width(&GadgetDecomposeAir { poly: poly.clone(), base, levels, modulus: P1, n });
+        assert_width(&RelinearizeAir { ct: ct.clone(), c2: poly.clone(), rlk0: keys.clone(), rlk1: keys.clone(), base, levels, modulus: P1, n });
+        assert_width(&ExternalProductAir { glwe: ct.clone(), ggsw: vec![ct.clone(); 2*levels], base, levels, modulus: P1, n });
+        assert_width(&CtMulAir { a: ct.clone(), b: ct.clone(), rlk0: keys.clone(), rlk1: keys.clone(), base, levels, modulus: P1, n });
+    }
+
+    #[test]
+    fn test_layout_matches_eval() {
+        let n = 8;
+        let poly = vec![0u32; n];
+        let ct = Ciphertext::new(poly.clone(), poly.clone());
+        let (base, levels) = (1 << 10, 3);
+        let keys = vec![poly.clone(); levels];
+
+        // the output columns of the layouts are the ones read back by the composite gadgets
+        let add = PolyAddAir { a: poly.clone(), b: poly.clone(), modulus: P1, n };
+        assert_eq!(add.layout().get("out"), Some(add.output_columns()));
+        let sub = PolySubAir { a: poly.clone(), b: poly.clone(), modulus: P1, n };
+        assert_eq!(sub.layout().get("out"), Some(sub.output_columns()));
+        let mul = PolyMulAir { a: poly.clone(), b: poly.clone(), modulus: P1, n, shape: MulShape::Row };
+        assert_eq!(mul.layout().get("out"), Some(mul.output_columns()));
+        let mul_rows = PolyMulAir { shape: MulShape::Rows, ..mul };
+        assert_eq!(mul_rows.layout().get("out"), Some(mul_rows.output_columns()));
+        let less_than = LessThanAir { a: poly.clone(), b: poly.clone(), modulus: P1, n };
+        assert_eq!(less_than.layout().get("lt"), Some(less_than.output_columns()));
+
+        let eval = PolyEvalAir { poly: poly.clone(), x: 3, modulus: P1, n };
+        assert_eq!(eval.layout().get("acc").unwrap().start, poly_eval_output(n));
+
+        let inner_product = InnerProductAir { a: keys.clone(), b: keys.clone(), modulus: P1, n };
+        assert_eq!(inner_product.layout().get(&format!("acc[{}]", levels-1)).unwrap().start, inner_product_output(levels, n));
+        let single = InnerProductAir { a: vec![poly.clone()], b: vec![poly.clone()], modulus: P1, n };
+        assert_eq!(single.layout().get("product[0].reduced").unwrap().start, inner_product_output(1, n));
+
+        let mat_vec = PolyMatVecMulAir { matrix: vec![keys.clone(); 2], vector: keys.clone(), modulus: P1, n };
+        assert_eq!(mat_vec.layout().get(&format!("row[1].acc[{}]", levels-1)).unwrap().start, mat_vec_output(1, levels, n));
+
+        let relin = RelinearizeAir { ct: ct.clone(), c2: poly.clone(), rlk0: keys.clone(), rlk1: keys.clone(), base, levels, modulus: P1, n };
+        let layout = relin.layout();
+        assert_eq!(layout.get("out0").unwrap().start, relinearize_output(base, levels, n));
+        assert_eq!(layout.get("out1").unwrap().start, relinearize_output(base, levels, n) + 2*n);
+
+        let external = ExternalProductAir { glwe: ct.clone(), ggsw: vec![ct.clone(); 2*levels], base, levels, modulus: P1, n };
+        let (start, offset) = external_product_output(base, levels, n);
+        let layout = external.layout();
+        assert_eq!(layout.get(&format!("out.c0.acc[{}]", 2*levels-1)).unwrap().start, start);
+        assert_eq!(layout.get(&format!("out.c1.acc[{}]", 2*levels-1)).unwrap().start, start + offset);
+
+        let ct_mul = CtMulAir { a: ct.clone(), b: ct.clone(), rlk0: keys.clone(), rlk1: keys.clone(), base, levels, modulus: P1, n };
+        assert_eq!(ct_mul.layout().get("relin.out0").unwrap().start, ct_mul_output(base, levels, n));
+
+        let add_then_mul = AddThenMulAir { a: poly.clone(), b: poly.clone(), c: poly.clone(), modulus: P1, n };
+        assert_eq!(add_then_mul.layout().get("mul.reduced").unwrap().start, add_then_mul_output(n));
+
+        // an externally-produced trace is read through the layout, and its width is checked against it
+        let a: Vec<u32> = (0..n as u32).collect();
+        let b = vec![P1 - 1; n];
+        let trace = generate_polyadd_trace::<Val>(a.clone(), b.clone(), P1, n).unwrap();
+        add.layout().check_trace(&trace).unwrap();
+        let row = trace.row_slice(0);
+        for (i, column) in add.layout().get("out").unwrap().enumerate() {
+            assert_eq!(row[column], Val::from_canonical_u32((a[i] + b[i]) % P1));
+        }
+        drop(row);
+
+        let narrow = RowMajorMatrix::new(vec![Val::zero(); 4 * (3*n)], 3*n);
+        assert_eq!(add.layout().check_trace(&narrow), Err(GadgetError::LengthMismatch { expected: BaseAir::<Val>::width(&add), actual: 3*n }));
+    }
+}
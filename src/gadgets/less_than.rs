@@ -0,0 +1,217 @@
+use std::ops::Range;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct LessThanAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl LessThanAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+}
+
+/*
+Coefficient Comparison Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- lt[i] = 1 if a[i] < b[i] as integers in [0, mod), otherwise 0
+
+Note:
+- Comparing a[i] - b[i] in the native field does not work: the difference wraps around n,
+and a[i] - b[i] and b[i] - a[i] - 1 can both have representatives below mod when 2 * mod > n (e.g. P1 and Mersenne31).
+- Instead a[i] and b[i] are decomposed into 31 bits, each range checked against mod as in RangeCheckAir,
+which fixes both as integers, and a[i] - b[i] is computed bit by bit with a borrow chain, from the LSB up:
+    a_k - b_k - borrow_k === d_k - 2 * borrow_{k+1},  borrow_0 = 0
+where the difference bit d_k = a_k - b_k - borrow_k + 2 * borrow_{k+1} is only constrained to be boolean.
+Every term is in {-2, ..., 2}, so the chain holds over the integers, and the final borrow_31 is 1 exactly when a[i] < b[i]:
+    lt[i] === borrow_31
+- The output columns lt can be bound to public values through PolynomialOpAir, so a verifier can check the comparisons.
+*/
+impl<F: Field> BaseAir<F> for LessThanAir {
+    // Air Table looks like this
+    // row:[ a: N ][ b: N ][ lt: N ][ a bits: 31N ][ a eq: 31N ][ b bits: 31N ][ b eq: 31N ][ borrow: 31N ]
+    //     ^-inputs------^^--------------------calculated by generate_less_than_trace---------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparisons
+    fn width(&self) -> usize {
+        (3 + 5*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for LessThanAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("lt", self.n)
+            .push("a_bits", RANGE_CHECK_BITS*self.n)
+            .push("a_eq", RANGE_CHECK_BITS*self.n)
+            .push("b_bits", RANGE_CHECK_BITS*self.n)
+            .push("b_eq", RANGE_CHECK_BITS*self.n)
+            .push("borrow", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for LessThanAir {
+    fn eval(&self, builder: &mut AB) {
+        self.eval_poly_op(builder);
+    }
+}
+
+impl PolynomialOpAir for LessThanAir {
+    fn a(&self) -> &[u32] {
+        &self.a
+    }
+
+    fn b(&self) -> &[u32] {
+        &self.b
+    }
+
+    fn modulus(&self) -> u32 {
+        self.modulus
+    }
+
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn output_columns(&self) -> Range<usize> {
+        2*self.n..3*self.n
+    }
+
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+        let lt = 2*n;
+        let block = |k: usize| 3*n + k*RANGE_CHECK_BITS*n;
+        let (a_bits, a_eq, b_bits, b_eq, borrow) = (block(0), block(1), block(2), block(3), block(4));
+
+        for i in 0..n {
+            let offset = i*RANGE_CHECK_BITS;
+            let (a_bits, a_eq) = (a_bits + offset, a_eq + offset);
+            let (b_bits, b_eq) = (b_bits + offset, b_eq + offset);
+            let borrow = borrow + offset;
+
+            // Enforce 0 <= a[i] < mod and 0 <= b[i] < mod over their bits
+            eval_range_check(builder, row[i].into(), &row[a_bits..a_bits+RANGE_CHECK_BITS], &row[a_eq..a_eq+RANGE_CHECK_BITS], self.modulus);
+            eval_range_check(builder, row[n+i].into(), &row[b_bits..b_bits+RANGE_CHECK_BITS], &row[b_eq..b_eq+RANGE_CHECK_BITS], self.modulus);
+
+            // Enforce d_k = a_k - b_k - borrow_k + 2 * borrow_{k+1} to be a bit, where borrow[k] stores borrow_{k+1}
+            for k in 0..RANGE_CHECK_BITS {
+                builder.assert_bool(row[borrow+k]);
+                let borrow_in: AB::Expr = if k == 0 { AB::Expr::zero() } else { row[borrow+k-1].into() };
+                let d = row[a_bits+k] - row[b_bits+k] - borrow_in + row[borrow+k] * AB::F::two();
+                builder.assert_bool(d);
+            }
+
+            // Enforce lt[i] === borrow_31
+            builder.assert_eq(row[lt+i], row[borrow+RANGE_CHECK_BITS-1]);
+        }
+    }
+
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError> {
+        generate_less_than_trace(self.a.clone(), self.b.clone(), self.modulus, self.n)
+    }
+}
+
+// borrow_1, ..., borrow_31 of the bitwise subtraction a - b, as laid out in the borrow columns
+fn borrow_columns(a: u32, b: u32) -> Vec<bool> {
+    let mut borrows = Vec::with_capacity(RANGE_CHECK_BITS);
+    let mut borrow = 0i64;
+    for k in 0..RANGE_CHECK_BITS {
+        let diff = ((a >> k) & 1) as i64 - ((b >> k) & 1) as i64 - borrow;
+        borrow = (diff < 0) as i64;
+        borrows.push(borrow == 1);
+    }
+    borrows
+}
+
+// Define a function to generate execution trace
+pub fn generate_less_than_trace<F: Field>(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+
+    let width = (3 + 5*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomials and the comparison results to the row
+    row.extend(a.iter().chain(b.iter()).map(|&c| F::from_canonical_u32(c)));
+    row.extend(a.iter().zip(b.iter()).map(|(&x, &y)| F::from_bool(x < y)));
+
+    // Assign bits and prefix equality flags of a and b against mod, then the borrows of a - b
+    for poly in [&a, &b] {
+        let columns: Vec<(Vec<bool>, Vec<bool>)> = poly.iter().map(|&c| range_check_columns(c, modulus)).collect();
+        for (bits, _) in columns.iter() {
+            row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+        }
+        for (_, eq) in columns.iter() {
+            row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+        }
+    }
+    for (&x, &y) in a.iter().zip(b.iter()) {
+        row.extend(borrow_columns(x, y).iter().map(|&borrow| F::from_bool(borrow)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_less_than() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // random pairs, with equal coefficients and differences wider than n - P1
+        let n = 16;
+        let mut rng = thread_rng();
+        let mut a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let mut b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        a[0] = b[0];
+        (a[1], b[1]) = (0, P1 - 1);
+        (a[2], b[2]) = (P1 - 1, 0);
+
+        let air = LessThanAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+        let trace = air.generate_trace::<Val>().unwrap();
+        let public_values = air.public_outputs(&trace);
+
+        // cross-check the proven outputs against the host comparisons
+        let expected: Vec<Val> = (0..n).map(|i| Val::from_bool(a[i] < b[i])).collect();
+        assert_eq!(public_values, expected);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &expected).expect("verification failed");
+
+        // a flipped comparison result is rejected
+        let mut wrong = expected.clone();
+        wrong[3] = Val::one() - wrong[3];
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        assert!(verify(&config, &air, &mut challenger, &proof, &wrong).is_err());
+    }
+}
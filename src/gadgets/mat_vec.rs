@@ -0,0 +1,181 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::inner_product::{InnerProductAir, generate_inner_product_trace, inner_product_layout, inner_product_output, inner_product_width};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::negacyclic::negacyclic_coeffs;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct PolyMatVecMulAir {
+    // M[j][i]: rows x cols matrix of polynomials, e.g. a key-switching key
+    pub matrix: Vec<Vec<Vec<u32>>>,
+    // v[i]: cols polynomials, e.g. the decomposed digit polynomials
+    pub vector: Vec<Vec<u32>>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PolyMatVecMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(matrix: Vec<Vec<Vec<u32>>>, vector: Vec<Vec<u32>>, modulus: u32) -> Self {
+        Self { matrix, vector, modulus, n: N }
+    }
+
+    // One inner product <M[j], v> per output row, in the order their sub-traces are laid out
+    fn rows(&self) -> Vec<InnerProductAir> {
+        self.matrix.iter()
+            .map(|row| InnerProductAir { a: row.clone(), b: self.vector.clone(), modulus: self.modulus, n: self.n })
+            .collect()
+    }
+}
+
+/*
+Polynomial Matrix-Vector Multiplication Air
+Input:
+- M: rows x cols matrix of polynomials with N coefficients
+- v: vector of cols polynomials with N coefficients
+- mod: FHE ciphertext modulus
+Output:
+- out_j = sum_i M[j][i] * v[i] in Z_mod[X]/(X^N+1) for j = [0..rows)
+
+Note:
+- Each output row is an independent InnerProductAir of length cols, placed side by side in one row as in PtCtMulAir,
+so out_j is the accumulator output of the j-th sub-trace at inner_product_output(cols, N).
+*/
+impl<F: Field> BaseAir<F> for PolyMatVecMulAir {
+    // Air Table looks like this
+    // row:[ InnerProductAir <M[0], v> ]...[ InnerProductAir <M[rows-1], v> ]
+    //     [0..................................................................0]
+    //     [0..................................................................0]
+    //     [0..................................................................0]
+    fn width(&self) -> usize {
+        self.matrix.len() * inner_product_width(self.vector.len(), self.n)
+    }
+}
+
+impl GadgetLayout for PolyMatVecMulAir {
+    fn layout(&self) -> TraceLayout {
+        (0..self.matrix.len()).fold(TraceLayout::new(), |layout, j| {
+            layout.nest(&format!("row[{}]", j), inner_product_layout(self.vector.len(), self.n))
+        })
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PolyMatVecMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let ip_width = inner_product_width(self.vector.len(), self.n);
+        for (j, ip) in self.rows().iter().enumerate() {
+            ip.eval_row(builder, &row[j*ip_width..(j+1)*ip_width]);
+        }
+    }
+}
+
+// Column of out_j[0] in the matrix-vector trace
+pub fn mat_vec_output(j: usize, cols: usize, n: usize) -> usize {
+    j*inner_product_width(cols, n) + inner_product_output(cols, n)
+}
+
+// out_j = sum_i M[j][i] * v[i] computed on the host
+pub fn mat_vec_mul(matrix: &[Vec<Vec<u32>>], vector: &[Vec<u32>], modulus: u32) -> Vec<Vec<u32>> {
+    matrix.iter().map(|row| {
+        let mut acc = vec![0u64; vector[0].len()];
+        for (m, v) in row.iter().zip(vector.iter()) {
+            for (a, p) in acc.iter_mut().zip(negacyclic_coeffs(m, v, modulus)) {
+                *a = (*a + p as u64) % modulus as u64;
+            }
+        }
+        acc.iter().map(|&a| a as u32).collect()
+    }).collect()
+}
+
+// Define a function to generate execution trace
+pub fn generate_mat_vec_mul_trace<F: Field>(matrix: Vec<Vec<Vec<u32>>>, vector: Vec<Vec<u32>>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if matrix.is_empty() {
+        return Err(GadgetError::LengthMismatch { expected: 1, actual: 0 });
+    }
+    // generate_inner_product_trace checks every row has as many entries as the vector
+    let ip_traces = matrix.iter()
+        .map(|row| generate_inner_product_trace::<F>(row.clone(), vector.clone(), modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Concatenate the sub-traces row by row
+    let width = matrix.len() * inner_product_width(vector.len(), n);
+    let height = ip_traces[0].height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        for trace in ip_traces.iter() {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_field::AbstractField;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_mat_vec_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let matrix: Vec<Vec<Vec<u32>>> = (0..2).map(|_| (0..2).map(|_| random_poly()).collect()).collect();
+        let vector: Vec<Vec<u32>> = (0..2).map(|_| random_poly()).collect();
+
+        // out_j from the products of each entry, independently of mat_vec_mul
+        let expected: Vec<Vec<u32>> = (0..2).map(|j| {
+            let p0 = negacyclic_mul_ref(&matrix[j][0], &vector[0], P1, n);
+            let p1 = negacyclic_mul_ref(&matrix[j][1], &vector[1], P1, n);
+            (0..n).map(|i| ((p0[i] as u64 + p1[i] as u64) % P1 as u64) as u32).collect()
+        }).collect();
+        assert_eq!(mat_vec_mul(&matrix, &vector, P1), expected);
+
+        let air = PolyMatVecMulAir { matrix:matrix.clone(), vector:vector.clone(), modulus:P1, n };
+        let trace = generate_mat_vec_mul_trace::<Val>(matrix, vector, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        for (j, out) in expected.iter().enumerate() {
+            let start = mat_vec_output(j, 2, n);
+            for i in 0..n {
+                assert_eq!(row[start+i], Val::from_canonical_u32(out[i]));
+            }
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_mat_vec_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let matrix: Vec<Vec<Vec<u32>>> = (0..2).map(|_| (0..2).map(|_| random_poly()).collect()).collect();
+        let vector: Vec<Vec<u32>> = (0..2).map(|_| random_poly()).collect();
+        let air = PolyMatVecMulAir { matrix:matrix.clone(), vector:vector.clone(), modulus:P1, n };
+
+        // every entry product with a wrong raw product and re-solved quotients, accumulated into consistent outputs
+        assert_rejects_forged_product(&air, &generate_mat_vec_mul_trace(matrix, vector, P1, n).unwrap(), |_| P1);
+    }
+}
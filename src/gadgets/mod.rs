@@ -1,3 +1,62 @@
 pub mod add;
+pub mod sub;
 pub mod mul;
-pub mod config;
\ No newline at end of file
+pub mod negacyclic;
+pub mod scalar_mul;
+pub mod rns;
+pub mod config;
+pub mod error;
+pub mod utils;
+pub mod ntt_mul;
+pub mod range_check;
+pub mod poly_op;
+pub mod crt;
+pub mod mod_exp;
+pub mod ciphertext;
+pub mod ptct_mul;
+pub mod mod_switch;
+pub mod neg;
+pub mod noise_bound;
+pub mod polynomial;
+pub mod decompose;
+pub mod automorphism;
+pub mod ntt;
+pub mod pointwise_mul;
+pub mod inner_product;
+pub mod relinearize;
+pub mod batch_add;
+pub mod center;
+pub mod less_than;
+pub mod sample_extract;
+pub mod barrett;
+pub mod montgomery;
+pub mod external_product;
+pub mod mat_vec;
+pub mod monomial_mul;
+pub mod exact_div;
+pub mod square;
+pub mod trace;
+pub mod ct_mul;
+pub mod poly_eval;
+pub mod layout;
+pub mod round_div;
+pub mod bit_decompose;
+#[cfg(test)]
+pub mod reference;
+#[cfg(test)]
+pub mod soundness;
+pub mod roots;
+pub mod constraints;
+pub mod add_then_mul;
+pub mod base_extend;
+pub mod approx_equal;
+pub mod decrypt;
+pub mod registry;
+pub mod packed_add;
+pub mod mod_inverse;
+pub mod chunked_add;
+pub mod bit_reverse;
+pub mod committed_add;
+pub mod wide_reduce;
+pub mod coeff_sum;
+pub mod degree_bound;
\ No newline at end of file
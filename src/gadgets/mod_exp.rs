@@ -0,0 +1,298 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, eval_carry_chain, range_checked_limbs, COEFF_LIMBS, MUL_CARRY_BITS};
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_check, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+
+// One row per exponent bit: exponents are below 2^31, and the last row is kept as a 0 bit
+pub const MOD_EXP_ROWS: usize = 32;
+
+// Define AIR constraint inputs
+// The fields are only set through the checked constructor outside of the crate
+pub struct ModExpAir {
+    pub(crate) base: u32,
+    pub(crate) exp: u32,
+    pub(crate) modulus: u32
+}
+
+impl ModExpAir {
+    // Construct the AIR, checking that exp is below 2^31 and that the modulus is nonzero and of at most 31 bits
+    pub fn new(base: u32, exp: u32, modulus: u32) -> Result<Self, GadgetError> {
+        check_mod_exp_inputs(exp, modulus)?;
+        Ok(Self { base, exp, modulus })
+    }
+
+    // Read the result base^exp mod p from the last row of `trace`, to be passed as the public value
+    pub fn public_outputs<F: Field>(&self, trace: &RowMajorMatrix<F>) -> Vec<F> {
+        vec![trace.row_slice(MOD_EXP_ROWS - 1)[NEW_RESULT]]
+    }
+}
+
+// exp needs fewer than MOD_EXP_ROWS bits, since the last row is a 0 bit, and the modulus is the bound of the range checks
+fn check_mod_exp_inputs(exp: u32, modulus: u32) -> Result<(), GadgetError> {
+    if modulus == 0 {
+        return Err(GadgetError::ZeroModulus);
+    }
+    check_range_modulus(modulus)?;
+    check_poly(&[exp], 1, 1 << (MOD_EXP_ROWS - 1))
+}
+
+/*
+Modular Exponentiation Air
+Input:
+- base, exp, mod
+Output (public value, optional):
+- result = base^exp mod mod
+
+Note:
+- This mirrors the host mod_exp() in utils.rs: the exponent is scanned from the LSB to the MSB, one bit per row,
+and row r holds the state (result, base) before processing bit r:
+    if bit[r] == 1: new_result = result * base mod p, otherwise new_result = result
+    new_base = base * base mod p
+The next row starts from (new_result, new_base), which is enforced with when_transition().
+- Both products are reduced with a quotient column over the integers, as in PolyScalarMulAir:
+    result * (bit * base + 1 - bit) === q_result * p + new_result
+    base * base === q_base * p + new_base
+result, base, new_result, q_result, new_base and q_base are range checked into [0, mod) on every row, so both sides are below mod^2 < 2^62,
+and each identity is proven 1) mod 2^32 with the carry chain of PolyMulAir over the limbs of the range checks and 2) mod n.
+- The bits are tied to exp by accumulating acc = sum_{j <= r} bit[j] * 2^j with pow = 2^r, and checking acc === exp on the last row.
+The last bit is forced to 0, so the 31 remaining bits sum below 2^31; the only other decomposition congruent mod n is
+all ones for exp = 0.
+- The new_result of the last row is then base^exp mod p. It is bound to the public value when one is given,
+which the verifier compares with its own expected result; any other number of public values is unsatisfiable.
+*/
+impl<F: Field> BaseAir<F> for ModExpAir {
+    // Air Table looks like this
+    // row r:[bit][pow][acc][result][base][new_result][q_result][new_base][q_base][ range checks: 6 * 62 ][ carry bits: 2 * 4 * 23 ]
+    //       ... MOD_EXP_ROWS rows, one per exponent bit from the LSB
+    fn width(&self) -> usize {
+        MOD_EXP_WIDTH
+    }
+}
+
+impl GadgetLayout for ModExpAir {
+    fn layout(&self) -> TraceLayout {
+        let layout = ["bit", "pow", "acc", "result", "base", "new_result", "q_result", "new_base", "q_base"]
+            .into_iter()
+            .fold(TraceLayout::new(), |layout, name| layout.push(name, 1));
+        ["result_range", "base_range", "new_result_range", "q_result_range", "new_base_range", "q_base_range"]
+            .into_iter()
+            .fold(layout, |layout, name| layout.push(name, RANGE_CHECK_WIDTH))
+            .push("product_carry_bits", CARRY_COLUMNS)
+            .push("square_carry_bits", CARRY_COLUMNS)
+    }
+}
+
+// Columns of the state and of the quotients, range checked in this order from RANGE_CHECKS
+const RESULT: usize = 3;
+const NEW_RESULT: usize = 5;
+const RANGE_CHECKED: usize = 6;
+const RANGE_CHECKS: usize = 9;
+
+// Bits of the carries of one product, at the COEFF_LIMBS limb positions of the identity 1) mod 2^32
+const CARRY_COLUMNS: usize = COEFF_LIMBS*MUL_CARRY_BITS;
+const CARRY_BITS: usize = RANGE_CHECKS + RANGE_CHECKED*RANGE_CHECK_WIDTH;
+
+const MOD_EXP_WIDTH: usize = CARRY_BITS + 2*CARRY_COLUMNS;
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for ModExpAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        let (bit, pow, acc, result, base) = (0, 1, 2, RESULT, 4);
+        let (new_result, q_result, new_base, q_base) = (NEW_RESULT, 6, 7, 8);
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+
+        // Initial state: result = 1 mod p, base = base mod p, and acc holds bit[0]
+        builder.when_first_row().assert_one(local[pow]);
+        builder.when_first_row().assert_eq(local[acc], local[bit]);
+        builder.when_first_row().assert_eq(local[result], AB::Expr::from_canonical_u32(1 % self.modulus));
+        builder.when_first_row().assert_eq(local[base], AB::Expr::from_canonical_u32(self.base % self.modulus));
+
+        // Square-and-multiply step, 2) mod n
+        builder.assert_bool(local[bit]);
+        let factor = local[bit] * local[base] + AB::Expr::one() - local[bit];
+        builder.assert_eq(local[result] * factor, local[q_result] * modulus + local[new_result]);
+        builder.assert_eq(local[base] * local[base], local[q_base] * modulus + local[new_base]);
+
+        // Enforce 0 <= value < mod for the state and the quotients of every row, which are all steps
+        let block = |k: usize| RANGE_CHECKS + k*RANGE_CHECK_WIDTH;
+        for k in 0..RANGE_CHECKED {
+            let bits = &local[block(k)..block(k) + RANGE_CHECK_BITS];
+            let eq = &local[block(k) + RANGE_CHECK_BITS..block(k) + RANGE_CHECK_WIDTH];
+            eval_range_check(builder, local[RESULT + k].into(), bits, eq, self.modulus);
+        }
+
+        /*
+        1) Enforce x_m * y_m - sum_{l+l'=m} q_l * p_l' - out_m + carry_m === 2^8 * carry_{m+1} for both products,
+        where x * y is the limb convolution of the range checked factors. The limbs of bit * base + 1 - bit are
+        bit * base_l + (1 - bit) * [l == 0], so the product term is bit * (result * base)_m + (1 - bit) * result_m, of degree 3.
+        */
+        let limbs_of = |col: usize| range_checked_limbs::<AB>(&local[block(col - RESULT)..block(col - RESULT) + RANGE_CHECK_BITS]);
+        let (result_limbs, base_limbs) = (limbs_of(result), limbs_of(base));
+        let mod_limbs = limbs(self.modulus as u128, COEFF_LIMBS);
+        let convolution = |x: &[AB::Expr], y: &[AB::Expr], m: usize| -> AB::Expr {
+            (0..=m).fold(AB::Expr::zero(), |sum, l| sum + x[l].clone() * y[m-l].clone())
+        };
+        let reduction = |q: usize, out: usize, m: usize| -> AB::Expr {
+            let (q_limbs, out_limbs) = (limbs_of(q), limbs_of(out));
+            (0..=m).fold(out_limbs[m].clone(), |sum, l| sum + q_limbs[m-l].clone() * AB::F::from_canonical_u64(mod_limbs[l]))
+        };
+
+        let product_terms: Vec<AB::Expr> = (0..COEFF_LIMBS).map(|m| {
+            local[bit] * convolution(&result_limbs, &base_limbs, m) + (AB::Expr::one() - local[bit]) * result_limbs[m].clone()
+                - reduction(q_result, new_result, m)
+        }).collect();
+        eval_carry_chain(builder, product_terms, &local[CARRY_BITS..CARRY_BITS + CARRY_COLUMNS]);
+
+        let square_terms: Vec<AB::Expr> = (0..COEFF_LIMBS).map(|m| {
+            convolution(&base_limbs, &base_limbs, m) - reduction(q_base, new_base, m)
+        }).collect();
+        eval_carry_chain(builder, square_terms, &local[CARRY_BITS + CARRY_COLUMNS..MOD_EXP_WIDTH]);
+
+        // The next row continues from the new state, with the next bit of the exponent
+        builder.when_transition().assert_eq(next[pow], local[pow] * AB::F::two());
+        builder.when_transition().assert_eq(next[acc], local[acc] + next[bit] * next[pow]);
+        builder.when_transition().assert_eq(next[result], local[new_result]);
+        builder.when_transition().assert_eq(next[base], local[new_base]);
+
+        // Tie the bits to self.exp
+        builder.when_last_row().assert_zero(local[bit]);
+        builder.when_last_row().assert_eq(local[acc], AB::Expr::from_wrapped_u32(self.exp));
+
+        // Bind the final result to the public value, when it is given
+        let public_values: Vec<AB::Expr> = builder.public_values().iter().map(|&v| v.into()).collect();
+        match public_values.len() {
+            0 => {}
+            1 => builder.when_last_row().assert_eq(local[new_result], public_values[0].clone()),
+            _ => builder.when_last_row().assert_one(AB::Expr::zero()),
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_mod_exp_trace<F: Field>(base: u32, exp: u32, modulus: u32) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_mod_exp_inputs(exp, modulus)?;
+    let p = modulus as u64;
+
+    let mut values: Vec<F> = vec![F::zero(); MOD_EXP_ROWS * MOD_EXP_WIDTH];
+    let mut result = 1 % p;
+    let mut base = base as u64 % p;
+    for (r, row) in values.chunks_mut(MOD_EXP_WIDTH).enumerate() {
+        let bit = (exp >> r) & 1;
+        let acc = exp as u64 & ((1 << (r + 1)) - 1);
+
+        // if the bit is 1: result * base, as in mod_exp()
+        let factor = if bit == 1 { base } else { 1 };
+        let (product, square) = (result * factor, base * base);
+
+        row[0] = F::from_canonical_u32(bit);
+        row[1] = F::from_wrapped_u64(1 << r);
+        row[2] = F::from_wrapped_u64(acc);
+
+        // Add result, base, new_result, q_result, new_base, q_base with their range checks
+        let checked = [result, base, product % p, product / p, square % p, square / p];
+        for (k, &value) in checked.iter().enumerate() {
+            row[RESULT + k] = F::from_canonical_u64(value);
+            let block = RANGE_CHECKS + k*RANGE_CHECK_WIDTH;
+            assign_range_check(&mut row[block..block + RANGE_CHECK_WIDTH], value as u32, modulus);
+        }
+
+        // Add the carries of result * factor - q_result * p - new_result and base * base - q_base * p - new_base
+        let (product_carries, square_carries) = row[CARRY_BITS..].split_at_mut(CARRY_COLUMNS);
+        assign_step_carries(product_carries, result, factor, product, modulus);
+        assign_step_carries(square_carries, base, base, square, modulus);
+
+        result = product % p;
+        base = square % p;
+    }
+    Ok(RowMajorMatrix::new(values, MOD_EXP_WIDTH))
+}
+
+// The carry bits of the identity 1) for x * y = q * p + out, as assigned by assign_carry_chain()
+fn assign_step_carries<F: Field>(block: &mut [F], x: u64, y: u64, product: u64, modulus: u32) {
+    let p = modulus as u64;
+    let xy = convolve(&limbs(x as u128, COEFF_LIMBS), &limbs(y as u128, COEFF_LIMBS), COEFF_LIMBS);
+    let qp = convolve(&limbs((product / p) as u128, COEFF_LIMBS), &limbs(p as u128, COEFF_LIMBS), COEFF_LIMBS);
+    let out = limbs((product % p) as u128, COEFF_LIMBS);
+    let diff: Vec<i64> = (0..COEFF_LIMBS).map(|m| xy[m] as i64 - qp[m] as i64 - out[m] as i64).collect();
+    assign_carry_chain(block, &diff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_field::PrimeField32;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected_with;
+    use crate::gadgets::utils::mod_exp;
+    use crate::params::{P1, P2, P3};
+
+    #[test]
+    fn test_mod_exp() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        for (base, exp, modulus) in [(3, 200, P1), (2, 0, P2), (P3 - 1, (1 << 31) - 2, P3), (5, 17, 1), (7, 1, 13), (11, P1 - 1, P1)] {
+            let air = ModExpAir::new(base, exp, modulus).unwrap();
+
+            let trace = generate_mod_exp_trace::<Val>(base, exp, modulus).unwrap();
+
+            // new_result of the last row matches the host mod_exp()
+            let expected = mod_exp(base as u64, exp as u64, modulus as u64);
+            let public_values = air.public_outputs(&trace);
+            assert_eq!(public_values, vec![Val::from_canonical_u64(expected)]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &public_values).expect("verification failed");
+
+            // the proof does not verify against another result
+            let wrong = vec![Val::from_canonical_u64((expected + 1) % modulus.max(2) as u64)];
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            assert!(verify(&config, &air, &mut challenger, &proof, &wrong).is_err(), "mod_exp proof verified against a wrong result");
+        }
+    }
+
+    #[test]
+    fn test_mod_exp_invalid_modulus() {
+        assert_eq!(ModExpAir::new(3, 5, 0).err(), Some(GadgetError::ZeroModulus));
+        assert_eq!(generate_mod_exp_trace::<Val>(3, 5, 0).unwrap_err(), GadgetError::ZeroModulus);
+        assert!(matches!(ModExpAir::new(3, 5, 1 << 31).err(), Some(GadgetError::ModulusTooLarge { .. })));
+        assert!(matches!(generate_mod_exp_trace::<Val>(3, 1 << 31, P1).unwrap_err(), GadgetError::CoefficientOutOfRange { .. }));
+    }
+
+    #[test]
+    fn test_mod_exp_forged_square() {
+        // On the last row, new_base is not read by any other row: replace base^2 = q_base * p + new_base by
+        // base^2 - n = q' * p + v, which holds mod n with q' and v in [0, p), but not mod 2^32
+        let air = ModExpAir::new(3, 200, P1).unwrap();
+        let mut trace = generate_mod_exp_trace::<Val>(3, 200, P1).unwrap();
+        let public_values = air.public_outputs(&trace);
+
+        let row = &mut trace.values[(MOD_EXP_ROWS - 1) * MOD_EXP_WIDTH..];
+        let base = row[4].as_canonical_u32() as u64;
+        let forged = (base * base).checked_sub(Val::ORDER_U32 as u64).expect("base^2 >= n");
+        let (new_base, q_base) = ((forged % P1 as u64) as u32, (forged / P1 as u64) as u32);
+        row[7] = Val::from_canonical_u32(new_base);
+        row[8] = Val::from_canonical_u32(q_base);
+        for (k, value) in [(4, new_base), (5, q_base)] {
+            let block = RANGE_CHECKS + k*RANGE_CHECK_WIDTH;
+            assign_range_check(&mut row[block..block + RANGE_CHECK_WIDTH], value, P1);
+        }
+        assign_step_carries(&mut row[CARRY_BITS + CARRY_COLUMNS..MOD_EXP_WIDTH], base, base, forged, P1);
+        assert_eq!(row[4] * row[4], row[8] * Val::from_canonical_u32(P1) + row[7]);
+
+        assert_rejected_with(&air, trace, &public_values, "a forged square");
+    }
+}
@@ -0,0 +1,320 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_wide_reduction, eval_wide_reduction, range_checked_limbs, COEFF_LIMBS, CRT_LIMBS, MUL_CARRY_BITS, MUL_CRT_BITS};
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_checks, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct ModInverseAir {
+    pub a: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl ModInverseAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, modulus: u32) -> Self {
+        Self { a, modulus, n: N }
+    }
+}
+
+/*
+Coefficient-wise Modular Inverse Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- inv = inv[0], ..., inv[N-1] where inv[i] = a[i]^{-1} % mod, and inv[i] = 0 for a[i] = 0
+- zero: zero[i] = 1 when a[i] = 0
+
+Note:
+- 0 has no inverse, and is mapped to 0 by convention instead of being rejected, so that a polynomial with
+zero coefficients still has a trace. The zero flag selects between the 2 cases with a zero-check:
+    a[i] * zero[i] === 0,   inv[i] * zero[i] === 0
+and a single multiplication check, with one quotient column per coefficient as in PointwiseMulAir:
+    a[i] * inv[i] === q[i] * mod + 1 - zero[i]
+so a[i] != 0 forces zero[i] = 0 and a[i] * inv[i] = 1 mod mod, and a[i] = 0 forces zero[i] = 1 and inv[i] = 0.
+- The product is below mod^2 < 2^62, so as in PointwiseMulAir the check is enforced mod n, and mod 2^MUL_CRT_BITS
+by eval_wide_reduction() over the limbs of the constant a[i] and of inv[i], with inv[i] range checked into [0, mod)
+and q[i] decomposed into MUL_CRT_BITS bits: it then holds over the integers.
+- A nonzero a[i] with gcd(a[i], mod) != 1 has no inverse either: this only happens for a composite modulus,
+and generate_mod_inverse_trace rejects it.
+*/
+impl<F: Field> BaseAir<F> for ModInverseAir {
+    // Air Table looks like this
+    // row:[ a: N ][mod:1][ inv: N ][ q: N ][ zero: N ][ inv_range: 62N ][ q_bits: 43N ][ carry_bits: 138N ]
+    //     ^----inputs---^^-------------------calculated by generate_mod_inverse_trace--------------------^
+    //     [0.................................................................................0]
+    //     [0.................................................................................0]
+    //     [0.................................................................................0]
+    fn width(&self) -> usize {
+        InverseLayout::new(self.n).width
+    }
+}
+
+// Column offsets of the ModInverseAir row
+struct InverseLayout {
+    inv: usize,
+    q: usize,
+    zero: usize,
+    inv_range: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    width: usize
+}
+
+impl InverseLayout {
+    fn new(n: usize) -> Self {
+        let (inv, q, zero) = (n+1, 2*n+1, 3*n+1);
+        let inv_range = 4*n+1;
+        let q_bits = inv_range + RANGE_CHECK_WIDTH*n;
+        let carry_bits = q_bits + MUL_CRT_BITS*n;
+        let width = carry_bits + CRT_LIMBS*MUL_CARRY_BITS*n;
+        Self { inv, q, zero, inv_range, q_bits, carry_bits, width }
+    }
+}
+
+impl GadgetLayout for ModInverseAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("mod", 1)
+            .push("inv", self.n)
+            .push("q", self.n)
+            .push("zero", self.n)
+            .push("inv_range", RANGE_CHECK_WIDTH*self.n)
+            .push("q_bits", MUL_CRT_BITS*self.n)
+            .push("carry_bits", CRT_LIMBS*MUL_CARRY_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for ModInverseAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let layout = InverseLayout::new(n);
+        let (a, mod_col, inv, q, zero) = (0, n, layout.inv, layout.q, layout.zero);
+
+        // Enforce self.a as the input polynomial and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[a+i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
+
+        for i in 0..n {
+            // Enforce a[i] * zero[i] === 0 and inv[i] * zero[i] === 0, with zero[i] a bit
+            builder.assert_bool(row[zero+i]);
+            builder.assert_zero(row[a+i] * row[zero+i]);
+            builder.assert_zero(row[inv+i] * row[zero+i]);
+
+            // Enforce a[i] * inv[i] === q[i] * mod + 1 - zero[i]
+            builder.assert_eq(row[a+i] * row[inv+i], row[q+i] * row[mod_col] + AB::Expr::one() - row[zero+i]);
+        }
+
+        // Enforce 0 <= inv[i] < mod
+        let inverses: Vec<AB::Expr> = (0..n).map(|i| row[inv+i].into()).collect();
+        eval_range_checks(builder, &inverses, &row[layout.inv_range..layout.q_bits], self.modulus);
+
+        // Enforce a[i] * inv[i] === q[i] * mod + 1 - zero[i] (mod 2^MUL_CRT_BITS), from the limbs of a[i] and inv[i]
+        for i in 0..n {
+            let block = layout.inv_range + i*RANGE_CHECK_WIDTH;
+            let inv_limbs = range_checked_limbs::<AB>(&row[block..block + RANGE_CHECK_BITS]);
+            let a_limbs = limbs(self.a[i] as u128, COEFF_LIMBS);
+            let sum = (0..CRT_LIMBS).map(|m| {
+                let mut sum = AB::Expr::zero();
+                for l in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+                    sum = sum + inv_limbs[l].clone() * AB::F::from_canonical_u64(a_limbs[m-l]);
+                }
+                sum
+            }).collect();
+            let mut one_limbs = vec![AB::Expr::zero(); COEFF_LIMBS];
+            one_limbs[0] = AB::Expr::one() - row[zero+i];
+            let (q_bits, carry_bits) = (layout.q_bits + i*MUL_CRT_BITS, layout.carry_bits + i*CRT_LIMBS*MUL_CARRY_BITS);
+            eval_wide_reduction(
+                builder, sum, row[q+i].into(), one_limbs,
+                &row[q_bits..q_bits + MUL_CRT_BITS],
+                &row[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+                self.modulus
+            );
+        }
+    }
+}
+
+// Inverse of x modulo `modulus` by the extended Euclidean algorithm, or None when gcd(x, modulus) != 1 or modulus = 0
+pub fn mod_inverse(x: u32, modulus: u32) -> Option<u32> {
+    // Invariant: r0 = s0 * x and r1 = s1 * x modulo `modulus`
+    let (mut r0, mut r1) = (modulus as i64, x.checked_rem(modulus)? as i64);
+    let (mut s0, mut s1) = (0i64, 1i64);
+    while r1 != 0 {
+        let quotient = r0 / r1;
+        (r0, r1) = (r1, r0 - quotient * r1);
+        (s0, s1) = (s1, s0 - quotient * s1);
+    }
+    (r0 == 1).then(|| s0.rem_euclid(modulus as i64) as u32)
+}
+
+// Define a function to generate execution trace
+pub fn generate_mod_inverse_trace<F: Field>(a: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_range_modulus(modulus)?;
+
+    // Invert every coefficient, mapping 0 to 0
+    let mut inv = Vec::with_capacity(n);
+    for (index, &x) in a.iter().enumerate() {
+        match (x, mod_inverse(x, modulus)) {
+            (0, _) => inv.push(0),
+            (_, Some(y)) => inv.push(y),
+            (_, None) => return Err(GadgetError::NotInvertible { index, value: x, modulus }),
+        }
+    }
+
+    let layout = InverseLayout::new(n);
+    let mut row = vec![F::zero(); layout.width];
+
+    // Add input polynomial and modulus, then the inverses, the quotients and the zero flags with their witness
+    for (i, &x) in a.iter().enumerate() {
+        row[i] = F::from_canonical_u32(x);
+    }
+    row[n] = F::from_canonical_u32(modulus);
+    for i in 0..n {
+        assign_inverse(&mut row, &layout, i, a[i], inv[i], a[i] as u64 * inv[i] as u64 / modulus as u64, modulus);
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(row, layout.width))
+}
+
+// Assign the inverse y of the coefficient x at i, with the quotient q of x * y and their witness
+fn assign_inverse<F: Field>(row: &mut [F], layout: &InverseLayout, i: usize, x: u32, y: u32, q: u64, modulus: u32) {
+    row[layout.inv+i] = F::from_canonical_u32(y);
+    row[layout.q+i] = F::from_wrapped_u64(q);
+    row[layout.zero+i] = F::from_bool(x == 0);
+
+    let block = layout.inv_range + i*RANGE_CHECK_WIDTH;
+    assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], y, modulus);
+    let sum = convolve(&limbs(x as u128, COEFF_LIMBS), &limbs(y as u128, COEFF_LIMBS), CRT_LIMBS);
+    let (q_bits, carry_bits) = (layout.q_bits + i*MUL_CRT_BITS, i*CRT_LIMBS*MUL_CARRY_BITS);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+        &sum, q, (x != 0) as u32, modulus
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use p3_field::PrimeField32;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::gadgets::utils::mod_inv;
+    use crate::params::{P1, P2};
+
+    #[test]
+    fn test_mod_inverse() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // random coefficients with 0, 1 and mod - 1, which is its own inverse
+        let n = 8;
+        let mut rng = thread_rng();
+        let mut a: Vec<u32> = (0..n).map(|_| rng.gen_range(1..P1)).collect();
+        (a[0], a[1], a[2]) = (0, 1, P1 - 1);
+
+        // the extended Euclid inverses agree with Fermat's x^{p-2} for a prime modulus
+        for &x in a[1..].iter() {
+            let y = mod_inverse(x, P1).unwrap();
+            assert_eq!(y as u64, mod_inv(x as u64, P1 as u64));
+            assert_eq!(x as u64 * y as u64 % P1 as u64, 1);
+        }
+        assert_eq!(mod_inverse(P1 - 1, P1), Some(P1 - 1));
+        assert_eq!(mod_inverse(0, P1), None);
+
+        let air = ModInverseAir { a:a.clone(), modulus:P1, n };
+        let trace = generate_mod_inverse_trace::<Val>(a.clone(), P1, n).unwrap();
+        let (inv, zero) = (air.layout().get("inv").unwrap(), air.layout().get("zero").unwrap());
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let expected = if a[i] == 0 { 0 } else { mod_inv(a[i] as u64, P1 as u64) as u32 };
+            assert_eq!(row[inv.start+i], Val::from_canonical_u32(expected));
+            assert_eq!(row[zero.start+i], Val::from_bool(a[i] == 0));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_mod_inverse_composite_modulus() {
+        // the units of Z_12 are their own inverses, and 2, 3, 4, 6 have none
+        for x in [1, 5, 7, 11] {
+            assert_eq!(mod_inverse(x, 12), Some(x));
+        }
+        for x in [2, 3, 4, 6] {
+            assert_eq!(mod_inverse(x, 12), None);
+        }
+        assert_eq!(
+            generate_mod_inverse_trace::<Val>(vec![5, 0, 4, 1], 12, 4).unwrap_err(),
+            GadgetError::NotInvertible { index: 2, value: 4, modulus: 12 }
+        );
+    }
+
+    #[test]
+    fn test_mod_inverse_wrong_zero_flag() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // claiming 0 is invertible with inverse 0 leaves a[i] * inv[i] = 0 = q[i] * mod + 1 unsatisfied for q[i] = 0
+        let n = 4;
+        let a = vec![0, 3, 5, 7];
+        let air = ModInverseAir { a:a.clone(), modulus:P2, n };
+        let mut trace = generate_mod_inverse_trace::<Val>(a, P2, n).unwrap();
+        let zero = air.layout().get("zero").unwrap();
+        trace.values[zero.start] = Val::zero();
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof of an inverse of 0 was accepted");
+    }
+
+    #[test]
+    fn test_mod_inverse_forged_inverse() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(1..P1)).collect();
+        let air = ModInverseAir { a: a.clone(), modulus: P1, n };
+        let mut trace = generate_mod_inverse_trace::<Val>(a.clone(), P1, n).unwrap();
+
+        // inv[0] + 1 with q[0] re-solved mod n, so that a[0] * inv[0] === q[0] * mod + 1 still holds mod n
+        let order = Val::ORDER_U32 as u64;
+        let forged = (mod_inverse(a[0], P1).unwrap() + 1) % P1;
+        let q = (a[0] as u64 * forged as u64 % order + order - 1) % order * mod_inv(P1 as u64, order) % order;
+        let layout = InverseLayout::new(n);
+        assign_inverse(&mut trace.values[..layout.width], &layout, 0, a[0], forged, q, P1);
+
+        assert_rejected(&air, trace, "a forged inv[0] + 1");
+    }
+}
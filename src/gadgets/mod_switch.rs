@@ -0,0 +1,318 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs, LIMB_BITS};
+use crate::gadgets::bit_decompose::{bits, eval_bit_decompose};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, eval_carry_chain, limb_value, range_checked_limbs, COEFF_LIMBS, CRT_LIMBS, MUL_CARRY_BITS};
+use crate::gadgets::range_check::{eval_less_than, eval_range_check, less_than_columns, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Bits of the rounding remainder r < 2q, which needs one more bit than a 31-bits modulus
+const REMAINDER_BITS: usize = RANGE_CHECK_BITS + 1;
+
+// Carry columns of the rounding identity mod 2^48
+const CARRY_COLUMNS: usize = CRT_LIMBS*MUL_CARRY_BITS;
+
+// Columns per coefficient: [x][t][r][s][out][r bits][r eq][out bits][out eq][x bits][x eq][carry bits]
+pub(crate) const COLUMNS_PER_COEFF: usize = 5 + 2*REMAINDER_BITS + 4*RANGE_CHECK_BITS + CARRY_COLUMNS;
+
+// Define AIR constraint inputs
+pub struct ModSwitchAir {
+    pub input: Vec<u32>,
+    // source modulus: input coefficients are in [0, q)
+    pub q: u32,
+    // target modulus: output coefficients are in [0, q_prime)
+    pub q_prime: u32,
+    pub n: usize
+}
+
+impl ModSwitchAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(input: Vec<u32>, q: u32, q_prime: u32) -> Self {
+        Self { input, q, q_prime, n: N }
+    }
+}
+
+/*
+Modulus Switching Air
+Input:
+- x = x[0] + x[1] * X + ... + x[N-1] * X^{N-1} mod q
+- q: source modulus, q_prime: target modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[N-1] * X^{N-1} where out[i] = round(x[i] * q_prime / q) mod q_prime
+
+Note:
+- Rounding convention: round half up, i.e. round(y) = floor(y + 1/2), so a tie x[i] * q_prime / q = k + 1/2 goes to k + 1.
+- Clearing the denominators, t[i] = round(x[i] * q_prime / q) is the unique integer with
+    2 * x[i] * q_prime + q === 2q * t[i] + r[i],  0 <= r[i] < 2q
+and r[i] makes the rounding direction explicit: r[i] < q rounds down, r[i] >= q rounds up, and a tie gives r[i] = 0.
+- Since x[i] < q, t[i] <= q_prime, so a single bit s[i] brings it into [0, q_prime):
+    t[i] === s[i] * q_prime + out[i],  0 <= out[i] < q_prime
+- x[i] < q, r[i] < 2q and out[i] < q_prime are proven with the bit comparison of range_check.rs.
+- Both sides of the first identity are below 2^64, so it is enforced over the integers as in PolyMulAir:
+mod n directly, and mod 2^48 with a carry chain over the 8-bits limbs read from the bits of x[i], r[i] and out[i],
+substituting t[i] = s[i] * q_prime + out[i]:
+    2 * x[i] * q_prime + q - s[i] * 2q * q_prime - 2q * out[i] - r[i] === 0 (mod 2^48)
+Every limb position of the left side is below 2^20 in absolute value, so the carries fit in MUL_CARRY_BITS bits.
+*/
+impl<F: Field> BaseAir<F> for ModSwitchAir {
+    // Air Table looks like this, for every coefficient i
+    // row:[x][t][r][s][out][r bits: 32][r eq: 32][out bits: 31][out eq: 31][x bits: 31][x eq: 31][carry bits: 138] ... x N
+    //     ^x^^-----------------------------------calculated by generate_modswitch_trace------------------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparisons
+    fn width(&self) -> usize {
+        COLUMNS_PER_COEFF*self.n
+    }
+}
+
+impl GadgetLayout for ModSwitchAir {
+    fn layout(&self) -> TraceLayout {
+        mod_switch_layout(self.n)
+    }
+}
+
+// Layout of ModSwitchAir, shared with the gadgets embedding it
+pub(crate) fn mod_switch_layout(n: usize) -> TraceLayout {
+    // the columns of each coefficient are side by side, so every coefficient is a nested block
+    let coeff = TraceLayout::new()
+        .push("x", 1)
+        .push("t", 1)
+        .push("r", 1)
+        .push("s", 1)
+        .push("out", 1)
+        .push("r_bits", REMAINDER_BITS)
+        .push("r_eq", REMAINDER_BITS)
+        .push("out_bits", RANGE_CHECK_BITS)
+        .push("out_eq", RANGE_CHECK_BITS)
+        .push("x_bits", RANGE_CHECK_BITS)
+        .push("x_eq", RANGE_CHECK_BITS)
+        .push("carry_bits", CARRY_COLUMNS);
+    (0..n).fold(TraceLayout::new(), |layout, i| layout.nest(&format!("coeff[{}]", i), coeff.clone()))
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for ModSwitchAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.input as the input polynomial
+        for i in 0..self.n {
+            builder.when_first_row().assert_eq(row[i*COLUMNS_PER_COEFF], AB::Expr::from_canonical_u32(self.input[i]));
+        }
+
+        self.eval_row(builder, &row);
+    }
+}
+
+impl ModSwitchAir {
+    // Enforce the rounding constraints of every coefficient over `row`, which starts at the x column of coefficient 0.
+    // x is not pinned to self.input, so other gadgets can switch a polynomial computed in their own trace.
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let q = AB::F::from_canonical_u32(self.q);
+        let q_prime = AB::F::from_canonical_u32(self.q_prime);
+        let two_q: Vec<bool> = bits(2 * self.q as u64, REMAINDER_BITS).collect();
+
+        for i in 0..self.n {
+            let base = i*COLUMNS_PER_COEFF;
+            let (x, t, r, s, out) = (base, base+1, base+2, base+3, base+4);
+            let r_bits = base + 5;
+            let r_eq = r_bits + REMAINDER_BITS;
+            let out_bits = r_eq + REMAINDER_BITS;
+            let out_eq = out_bits + RANGE_CHECK_BITS;
+            let x_bits = out_eq + RANGE_CHECK_BITS;
+            let x_eq = x_bits + RANGE_CHECK_BITS;
+            let carry_bits = x_eq + RANGE_CHECK_BITS;
+
+            // Enforce 2 * x * q_prime + q === 2q * t + r
+            builder.assert_eq(
+                row[x] * q_prime * AB::F::two() + q,
+                row[t] * q * AB::F::two() + row[r],
+            );
+
+            // Enforce 0 <= r < 2q
+            eval_bit_decompose(builder, row[r].into(), &row[r_bits..r_bits+REMAINDER_BITS]);
+            eval_less_than(builder, &row[r_bits..r_bits+REMAINDER_BITS], &row[r_eq..r_eq+REMAINDER_BITS], &two_q);
+
+            // Enforce t === s * q_prime + out with 0 <= out < q_prime
+            builder.assert_bool(row[s]);
+            builder.assert_eq(row[t], row[s] * q_prime + row[out]);
+            eval_range_check(builder, row[out].into(), &row[out_bits..out_bits+RANGE_CHECK_BITS], &row[out_eq..out_eq+RANGE_CHECK_BITS], self.q_prime);
+
+            // Enforce 0 <= x < q
+            eval_range_check(builder, row[x].into(), &row[x_bits..x_bits+RANGE_CHECK_BITS], &row[x_eq..x_eq+RANGE_CHECK_BITS], self.q);
+
+            // Enforce 2 * x * q_prime + q - s * 2q * q_prime - 2q * out - r === 0 (mod 2^48)
+            let x_limbs = range_checked_limbs::<AB>(&row[x_bits..x_bits+RANGE_CHECK_BITS]);
+            let out_limbs = range_checked_limbs::<AB>(&row[out_bits..out_bits+RANGE_CHECK_BITS]);
+            let r_limbs: Vec<AB::Expr> = (0..COEFF_LIMBS).map(|l| limb_value::<AB>(&row[r_bits + l*LIMB_BITS..r_bits + (l+1)*LIMB_BITS])).collect();
+            let constants = rounding_constants(self.q, self.q_prime);
+            let terms = (0..CRT_LIMBS).map(|m| {
+                let mut term = AB::Expr::from_canonical_u64(constants.q[m]) - row[s] * AB::F::from_canonical_u64(constants.wrap[m]);
+                for l in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+                    term = term + x_limbs[l].clone() * AB::F::from_canonical_u64(2 * constants.q_prime[m-l])
+                        - out_limbs[l].clone() * AB::F::from_canonical_u64(2 * constants.q[m-l]);
+                }
+                if m < COEFF_LIMBS {
+                    term = term - r_limbs[m].clone();
+                }
+                term
+            }).collect();
+            eval_carry_chain(builder, terms, &row[carry_bits..carry_bits+CARRY_COLUMNS]);
+        }
+    }
+}
+
+// Limbs of the constants of the rounding identity mod 2^48: q, q_prime and the wrap-around term 2q * q_prime
+struct RoundingConstants {
+    q: Vec<u64>,
+    q_prime: Vec<u64>,
+    wrap: Vec<u64>
+}
+
+fn rounding_constants(q: u32, q_prime: u32) -> RoundingConstants {
+    RoundingConstants {
+        q: limbs(q as u128, CRT_LIMBS),
+        q_prime: limbs(q_prime as u128, CRT_LIMBS),
+        wrap: limbs(2 * q as u128 * q_prime as u128, CRT_LIMBS)
+    }
+}
+
+// round(x * q_prime / q) mod q_prime, rounding half up
+pub fn mod_switch(x: u32, q: u32, q_prime: u32) -> u32 {
+    let t = (2 * x as u128 * q_prime as u128 + q as u128) / (2 * q as u128);
+    (t % q_prime as u128) as u32
+}
+
+// Define a function to generate execution trace
+pub fn generate_modswitch_trace<F: Field>(input: Vec<u32>, q: u32, q_prime: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&input, n, q)?;
+
+    let width = COLUMNS_PER_COEFF*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+    for &x in input.iter() {
+        // 2 * x * q_prime + q = 2q * t + r
+        let numerator = 2 * x as u128 * q_prime as u128 + q as u128;
+        let t = (numerator / (2 * q as u128)) as u64;
+        let r = (numerator % (2 * q as u128)) as u64;
+        row.extend(coeff_columns::<F>(x, t, r, q, q_prime));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+// Columns of the coefficient x rounded to t with the remainder r, for t < 2 * q_prime and r < 2q
+fn coeff_columns<F: Field>(x: u32, t: u64, r: u64, q: u32, q_prime: u32) -> Vec<F> {
+    let mut columns: Vec<F> = Vec::with_capacity(COLUMNS_PER_COEFF);
+
+    // t = s * q_prime + out
+    let s = t >= q_prime as u64;
+    let out = (t % q_prime as u64) as u32;
+
+    columns.push(F::from_canonical_u32(x));
+    columns.push(F::from_wrapped_u64(t));
+    columns.push(F::from_wrapped_u64(r));
+    columns.push(F::from_bool(s));
+    columns.push(F::from_canonical_u32(out));
+
+    let two_q: Vec<bool> = bits(2 * q as u64, REMAINDER_BITS).collect();
+    let r_bits: Vec<bool> = bits(r, REMAINDER_BITS).collect();
+    let r_eq = less_than_columns(&r_bits, &two_q);
+    columns.extend(r_bits.iter().map(|&bit| F::from_bool(bit)));
+    columns.extend(r_eq.iter().map(|&flag| F::from_bool(flag)));
+
+    let (out_bits, out_eq) = range_check_columns(out, q_prime);
+    columns.extend(out_bits.iter().map(|&bit| F::from_bool(bit)));
+    columns.extend(out_eq.iter().map(|&flag| F::from_bool(flag)));
+
+    let (x_bits, x_eq) = range_check_columns(x, q);
+    columns.extend(x_bits.iter().map(|&bit| F::from_bool(bit)));
+    columns.extend(x_eq.iter().map(|&flag| F::from_bool(flag)));
+
+    // limbs of 2 * x * q_prime + q - s * 2q * q_prime - 2q * out - r at positions [0..CRT_LIMBS)
+    let constants = rounding_constants(q, q_prime);
+    let x_q_prime = convolve(&limbs(x as u128, COEFF_LIMBS), &constants.q_prime, CRT_LIMBS);
+    let out_q = convolve(&limbs(out as u128, COEFF_LIMBS), &constants.q, CRT_LIMBS);
+    let r_limbs = limbs(r as u128, CRT_LIMBS);
+    let diff: Vec<i64> = (0..CRT_LIMBS).map(|m| {
+        2 * x_q_prime[m] as i64 + constants.q[m] as i64 - s as i64 * constants.wrap[m] as i64 - 2 * out_q[m] as i64 - r_limbs[m] as i64
+    }).collect();
+    let mut carries = vec![F::zero(); CARRY_COLUMNS];
+    assign_carry_chain(&mut carries, &diff);
+    columns.extend(carries);
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_uni_stark::{prove, verify};
+    use p3_field::PrimeField32;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::params::{P1, P3};
+
+    fn prove_mod_switch(input: Vec<u32>, q: u32, q_prime: u32, expected: &[u32]) {
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let n = input.len();
+
+        let air = ModSwitchAir { input: input.clone(), q, q_prime, n };
+        let trace = generate_modswitch_trace::<Val>(input, q, q_prime, n).unwrap();
+
+        // out[i] sits at column 4 of every coefficient
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            assert_eq!(row[i*COLUMNS_PER_COEFF + 4], Val::from_canonical_u32(expected[i]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_mod_switch_rounding() {
+        // q = 12 -> q' = 8: x * 2/3 for x = 3 (exact 2), 2 (1.33 rounds down to 1), 1 (0.67 rounds up to 1), 0
+        prove_mod_switch(vec![3, 2, 1, 0], 12, 8, &[2, 1, 1, 0]);
+
+        // q = 16 -> q' = 8: x / 2 for x = 3 (tie 1.5 rounds up to 2), 5 (tie 2.5 rounds up to 3), 4 (exact 2),
+        // and 15 (tie 7.5 rounds up to 8, which wraps to 0 mod 8)
+        prove_mod_switch(vec![3, 5, 4, 15], 16, 8, &[2, 3, 2, 0]);
+    }
+
+    #[test]
+    fn test_mod_switch_forged_rounding() {
+        // from P3 to P1, t[0] + 1 with the remainder re-solved mod n: r - 2q + n < 2q, since 2q > n
+        let input = vec![P3 / 3, 1];
+        let (q, q_prime, n) = (P3, P1, input.len());
+        let air = ModSwitchAir { input: input.clone(), q, q_prime, n };
+        let honest = generate_modswitch_trace::<Val>(input.clone(), q, q_prime, n).unwrap();
+
+        let numerator = 2 * input[0] as u64 * q_prime as u64 + q as u64;
+        let (t, r) = (numerator / (2 * q as u64), numerator % (2 * q as u64));
+        let forged_r = (r + Val::ORDER_U32 as u64 - 2 * q as u64) % Val::ORDER_U32 as u64;
+        assert!(forged_r < 2 * q as u64);
+        let mut row: Vec<Val> = coeff_columns(input[0], t + 1, forged_r, q, q_prime);
+        row.extend_from_slice(&honest.row_slice(0)[COLUMNS_PER_COEFF..]);
+
+        assert_rejected(&air, repeat_row(&row), "a forged t[0] + 1");
+    }
+
+    #[test]
+    fn test_mod_switch_rns_moduli() {
+        // from P3 down to P1, including both ends of [0, P3)
+        let input = vec![0, 1, P3 / 2, P3 - 1];
+        let expected: Vec<u32> = input.iter().map(|&x| mod_switch(x, P3, P1)).collect();
+        assert_eq!(expected[0], 0);
+        assert_eq!(expected[3], P1 - 1);
+        prove_mod_switch(input, P3, P1, &expected);
+    }
+}
@@ -0,0 +1,170 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct MonomialMulAir {
+    pub poly: Vec<u32>,
+    // exponent of the monomial X^k, any k >= 0 since X^{2N} = 1
+    pub k: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl MonomialMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, k: usize, modulus: u32) -> Self {
+        Self { poly, k, modulus, n: N }
+    }
+}
+
+/*
+Monomial Multiplication Air
+Input:
+- poly: polynomial with N coefficients
+- k: exponent of the monomial X^k
+- mod: FHE ciphertext modulus
+Output:
+- out = poly * X^k in Z_mod[X]/(X^N+1)
+
+Note:
+- X^N = -1, so poly[i] * X^{i+k} lands on out[(i+k) mod N], negated when (i+k) mod 2N >= N:
+    out[(i+k) mod N] = poly[i]              if (i+k) mod 2N < N
+    out[(i+k) mod N] = -poly[i] % mod       otherwise
+- As in SampleExtractAir, the signed permutation only depends on k and N, so it is baked into the constraints,
+and the negations are proven as in PolyNegAir: poly[i] + out[j] === mod, or out[j] === 0 when poly[i] == 0
+*/
+impl<F: Field> BaseAir<F> for MonomialMulAir {
+    // Air Table looks like this
+    // row:[  poly: N  ][mod:1][  out: N  ]
+    //     ^-------inputs-----^^-calculated by generate_monomial_mul_trace
+    //     [0..............................0]
+    //     [0..............................0]
+    //     [0..............................0]
+    fn width(&self) -> usize {
+        2*self.n+1
+    }
+}
+
+impl GadgetLayout for MonomialMulAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("poly", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for MonomialMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.poly as input polynomial and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+        let mod_col = n;
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce out[j] === poly[i], or its negation past the wrap-around of X^N = -1
+        let out = n+1;
+        for i in 0..n {
+            let (j, negate) = monomial_index(i, self.k, n);
+            if !negate {
+                builder.when_first_row().assert_eq(row[out+j], row[i]);
+            } else if self.poly[i] == 0 {
+                builder.when_first_row().assert_zero(row[out+j]);
+            } else {
+                builder.when_first_row().assert_eq(row[i] + row[out+j], row[mod_col]);
+            }
+        }
+    }
+}
+
+// Index of the coefficient of out written by poly[i] when multiplying by X^k, and whether it is negated
+pub fn monomial_index(i: usize, k: usize, n: usize) -> (usize, bool) {
+    let shifted = i + k % (2*n);
+    (shifted % n, (shifted / n) % 2 == 1)
+}
+
+// poly * X^k computed on the host
+pub fn monomial_mul(poly: &[u32], k: usize, modulus: u32) -> Vec<u32> {
+    let n = poly.len();
+    let mut out = vec![0u32; n];
+    for (i, &c) in poly.iter().enumerate() {
+        let (j, negate) = monomial_index(i, k, n);
+        out[j] = if negate { (modulus - c) % modulus } else { c };
+    }
+    out
+}
+
+// Define a function to generate execution trace
+pub fn generate_monomial_mul_trace<F: Field>(poly: Vec<u32>, k: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+
+    let mut values: Vec<F>= Vec::with_capacity(MIN_TRACE_HEIGHT * (2*n+1));
+
+    // Add input polynomial and modulus to values vector
+    values.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(modulus));
+
+    // Rotate the coefficients and push them to values vector
+    values.extend(monomial_mul(&poly, k, modulus).iter().map(|&c| F::from_canonical_u32(c)));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 2*n+1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::params::P1;
+
+    #[test]
+    fn test_monomial_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let mut poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        // a 0 coefficient that is negated for k = N
+        poly[2] = 0;
+
+        let negated: Vec<u32> = poly.iter().map(|&c| (P1 - c) % P1).collect();
+        let mut x3 = vec![0u32; n];
+        x3[3] = 1;
+
+        // X^N = -1, X^{2N} = 1, and X^3 agrees with the negacyclic product by the monomial
+        for (k, expected) in [(n, negated), (2*n, poly.clone()), (3, negacyclic_mul_ref(&poly, &x3, P1, n))] {
+            assert_eq!(monomial_mul(&poly, k, P1), expected);
+
+            let air = MonomialMulAir { poly:poly.clone(), k, modulus:P1, n };
+            let trace = generate_monomial_mul_trace::<Val>(poly.clone(), k, P1, n).unwrap();
+
+            let row = trace.row_slice(0);
+            for j in 0..n {
+                assert_eq!(row[n+1+j], Val::from_canonical_u32(expected[j]));
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+}
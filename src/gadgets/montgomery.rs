@@ -0,0 +1,383 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs, propagate, CARRY_BITS, LIMB_BITS};
+use crate::gadgets::bit_decompose::{bits, eval_from_bits};
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// R = 2^32, above every modulus of this crate
+pub const MONTGOMERY_R_BITS: usize = 32;
+
+// 8-bits limbs of R-sized and R^2-sized integers
+const HALF_LIMBS: usize = MONTGOMERY_R_BITS / LIMB_BITS;
+const FULL_LIMBS: usize = 2 * HALF_LIMBS;
+
+// Define AIR constraint inputs
+pub struct MontgomeryReduceAir {
+    // values below mod * R, e.g. products of 2 values in Montgomery form
+    pub value: Vec<u64>,
+    // odd modulus
+    pub modulus: u32,
+    // R = 2^32
+    pub r: u64,
+    // mod^{-1} mod R
+    pub q_inv: u32,
+    pub n: usize
+}
+
+impl MontgomeryReduceAir {
+    // Construct the AIR with the default number of coefficients params::N, precomputing R and mod^{-1} mod R
+    pub fn new(value: Vec<u64>, modulus: u32) -> Self {
+        Self { value, modulus, r: 1 << MONTGOMERY_R_BITS, q_inv: montgomery_q_inv(modulus), n: N }
+    }
+
+    // -mod^{-1} mod R, the factor of the REDC step
+    fn neg_q_inv(&self) -> u32 {
+        self.q_inv.wrapping_neg()
+    }
+}
+
+/*
+Montgomery Reduction Air
+Input:
+- value[0], ..., value[N-1]: integers T below mod * R, with R = 2^32
+- mod: odd FHE ciphertext modulus, with q_inv = mod^{-1} mod R precomputed
+Output:
+- out[i] = value[i] * R^{-1} % mod
+
+Note:
+- The REDC steps are
+    m = (T mod R) * (-q_inv) mod R
+    t = (T + m * mod) / R, which is exact since T + m * mod = 0 mod R, and below 2 * mod
+    out = t - c * mod with c in {0, 1}, 0 <= out < mod
+- T, m * mod and R are beyond n, so the steps are proven over 8-bits limbs with carry chains as in BarrettReduceAir:
+    1) sum_{j+l=k} T_j * (-q_inv)_l + carry_k === w_k + 2^8 * carry_{k+1}  for k = [0..8), where m = w_0..w_3
+    2) out_k + c * mod_k + carry''_k === u_k + 2^8 * carry''_{k+1}          for k = [0..4), where u = t
+    3) sum_{j+l=k} m_j * mod_l + T_k + carry'_k === [k >= 4] u_{k-4} + 2^8 * carry'_{k+1}  for k = [0..8)
+1) only keeps the low half of T * (-q_inv), which is m, and 3) proves T + m * mod = t * R with 4 zero limbs at the bottom.
+- t is up to 2 * mod > n, so t = out + c * mod is also proven over limbs in 2) rather than in the native field.
+- out is range checked against mod with the bit comparison of range_check.rs, whose bits also give the limbs out_k.
+*/
+impl<F: Field> BaseAir<F> for MontgomeryReduceAir {
+    // Air Table looks like this
+    // row:[ T limbs: 8N ][ out: N ][ c: N ][ w bits: 64N ][ carry bits: 84N ][ out bits: 31N ][ out eq: 31N ][ u bits: 32N ][ carry'': 3N ][ carry' bits: 84N ]
+    //     ^----input----^^----------------------------------calculated by generate_montgomery_trace----------------------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        montgomery_width(self.n)
+    }
+}
+
+impl GadgetLayout for MontgomeryReduceAir {
+    fn layout(&self) -> TraceLayout {
+        let offsets = MontgomeryLayout::new(self.n);
+        TraceLayout::new()
+            .push("value", offsets.out - offsets.value)
+            .push("out", offsets.c - offsets.out)
+            .push("c", offsets.w_bits - offsets.c)
+            .push("w_bits", offsets.w_carry - offsets.w_bits)
+            .push("w_carry", offsets.out_bits - offsets.w_carry)
+            .push("out_bits", offsets.out_eq - offsets.out_bits)
+            .push("out_eq", offsets.u_bits - offsets.out_eq)
+            .push("u_bits", offsets.u_carry - offsets.u_bits)
+            .push("u_carry", offsets.t_carry - offsets.u_carry)
+            .push("t_carry", offsets.width - offsets.t_carry)
+    }
+}
+
+// Column offsets of the blocks
+struct MontgomeryLayout {
+    value: usize,
+    out: usize,
+    c: usize,
+    w_bits: usize,
+    w_carry: usize,
+    out_bits: usize,
+    out_eq: usize,
+    u_bits: usize,
+    u_carry: usize,
+    t_carry: usize,
+    width: usize
+}
+
+impl MontgomeryLayout {
+    fn new(n: usize) -> Self {
+        let value = 0;
+        let out = value + FULL_LIMBS*n;
+        let c = out + n;
+        let w_bits = c + n;
+        let w_carry = w_bits + LIMB_BITS*FULL_LIMBS*n;
+        let out_bits = w_carry + CARRY_BITS*(FULL_LIMBS-1)*n;
+        let out_eq = out_bits + RANGE_CHECK_BITS*n;
+        let u_bits = out_eq + RANGE_CHECK_BITS*n;
+        let u_carry = u_bits + LIMB_BITS*HALF_LIMBS*n;
+        let t_carry = u_carry + (HALF_LIMBS-1)*n;
+        let width = t_carry + CARRY_BITS*(FULL_LIMBS-1)*n;
+        Self { value, out, c, w_bits, w_carry, out_bits, out_eq, u_bits, u_carry, t_carry, width }
+    }
+}
+
+fn montgomery_width(n: usize) -> usize {
+    MontgomeryLayout::new(n).width
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for MontgomeryReduceAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let layout = MontgomeryLayout::new(n);
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let q = limbs(self.modulus as u128, HALF_LIMBS);
+        let neg_q_inv = limbs(self.neg_q_inv() as u128, HALF_LIMBS);
+        let limb_base = AB::F::from_canonical_u32(1 << LIMB_BITS);
+
+        for i in 0..n {
+            // Enforce self.value as input limbs
+            let value = layout.value + i*FULL_LIMBS;
+            for (j, limb) in limbs(self.value[i] as u128, FULL_LIMBS).into_iter().enumerate() {
+                builder.when_first_row().assert_eq(row[value+j], AB::Expr::from_canonical_u64(limb));
+            }
+
+            // limbs and carries as expressions of their bits
+            let w_bits = layout.w_bits + i*LIMB_BITS*FULL_LIMBS;
+            let w: Vec<AB::Expr> = (0..FULL_LIMBS).map(|k| eval_from_bits(builder, &row[w_bits + k*LIMB_BITS..w_bits + (k+1)*LIMB_BITS])).collect();
+            let w_carry = layout.w_carry + i*CARRY_BITS*(FULL_LIMBS-1);
+            let carry: Vec<AB::Expr> = (0..FULL_LIMBS-1).map(|k| eval_from_bits(builder, &row[w_carry + k*CARRY_BITS..w_carry + (k+1)*CARRY_BITS])).collect();
+
+            // 1) Enforce sum_{j+l=k} T_j * (-q_inv)_l + carry_k === w_k + 2^8 * carry_{k+1}, over the low half of T
+            for k in 0..FULL_LIMBS {
+                let mut lhs = if k > 0 { carry[k-1].clone() } else { AB::Expr::zero() };
+                for (l, &limb) in neg_q_inv.iter().enumerate() {
+                    if k >= l && k - l < HALF_LIMBS {
+                        lhs = lhs + row[value + k - l] * AB::F::from_canonical_u64(limb);
+                    }
+                }
+                let rhs = if k < FULL_LIMBS-1 { w[k].clone() + carry[k].clone() * limb_base } else { w[k].clone() };
+                builder.assert_eq(lhs, rhs);
+            }
+            let m = &w[..HALF_LIMBS];
+
+            // Enforce 0 <= out < mod, and c to be a bit
+            let out_bits = layout.out_bits + i*RANGE_CHECK_BITS;
+            let out_eq = layout.out_eq + i*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[layout.out+i].into(), &row[out_bits..out_bits+RANGE_CHECK_BITS], &row[out_eq..out_eq+RANGE_CHECK_BITS], self.modulus);
+            builder.assert_bool(row[layout.c+i]);
+            let out_limb = |k: usize| -> AB::Expr {
+                let mut sum = AB::Expr::zero();
+                for b in 0..LIMB_BITS {
+                    if k*LIMB_BITS + b < RANGE_CHECK_BITS {
+                        sum = sum + row[out_bits + k*LIMB_BITS + b] * AB::F::from_canonical_u32(1 << b);
+                    }
+                }
+                sum
+            };
+
+            // 2) Enforce out_k + c * mod_k + carry''_k === u_k + 2^8 * carry''_{k+1}
+            let u_bits = layout.u_bits + i*LIMB_BITS*HALF_LIMBS;
+            let u: Vec<AB::Expr> = (0..HALF_LIMBS).map(|k| eval_from_bits(builder, &row[u_bits + k*LIMB_BITS..u_bits + (k+1)*LIMB_BITS])).collect();
+            let u_carry = layout.u_carry + i*(HALF_LIMBS-1);
+            for k in 0..HALF_LIMBS {
+                let mut lhs = out_limb(k) + row[layout.c+i] * AB::F::from_canonical_u64(q[k]);
+                if k > 0 {
+                    lhs = lhs + row[u_carry+k-1];
+                }
+                let mut rhs = u[k].clone();
+                if k < HALF_LIMBS-1 {
+                    builder.assert_bool(row[u_carry+k]);
+                    rhs = rhs + row[u_carry+k] * limb_base;
+                }
+                builder.assert_eq(lhs, rhs);
+            }
+
+            // 3) Enforce sum_{j+l=k} m_j * mod_l + T_k + carry'_k === [k >= 4] u_{k-4} + 2^8 * carry'_{k+1}
+            let t_carry = layout.t_carry + i*CARRY_BITS*(FULL_LIMBS-1);
+            let carry: Vec<AB::Expr> = (0..FULL_LIMBS-1).map(|k| eval_from_bits(builder, &row[t_carry + k*CARRY_BITS..t_carry + (k+1)*CARRY_BITS])).collect();
+            for k in 0..FULL_LIMBS {
+                let mut lhs: AB::Expr = row[value+k].into();
+                if k > 0 {
+                    lhs = lhs + carry[k-1].clone();
+                }
+                for (l, &limb) in q.iter().enumerate() {
+                    if k >= l && k - l < HALF_LIMBS {
+                        lhs = lhs + m[k-l].clone() * AB::F::from_canonical_u64(limb);
+                    }
+                }
+                let mut rhs = if k >= HALF_LIMBS { u[k-HALF_LIMBS].clone() } else { AB::Expr::zero() };
+                if k < FULL_LIMBS-1 {
+                    rhs = rhs + carry[k].clone() * limb_base;
+                }
+                builder.assert_eq(lhs, rhs);
+            }
+        }
+    }
+}
+
+// mod^{-1} mod 2^32 for an odd modulus, by Newton iteration: each step doubles the number of correct low bits
+pub fn montgomery_q_inv(modulus: u32) -> u32 {
+    // q * q = 1 mod 8 for odd q, so q is its own inverse mod 2^3
+    let mut inv = modulus;
+    for _ in 0..4 {
+        inv = inv.wrapping_mul(2u32.wrapping_sub(modulus.wrapping_mul(inv)));
+    }
+    inv
+}
+
+// REDC: (out, c) with out = value * R^{-1} % mod and t = out + c * mod
+pub fn montgomery_reduce(value: u64, modulus: u32) -> (u32, bool) {
+    let m = (value as u32).wrapping_mul(montgomery_q_inv(modulus).wrapping_neg());
+    let t = ((value as u128 + m as u128 * modulus as u128) >> MONTGOMERY_R_BITS) as u64;
+    if t >= modulus as u64 { ((t - modulus as u64) as u32, true) } else { (t as u32, false) }
+}
+
+// x * R % mod
+pub fn to_montgomery(x: u32, modulus: u32) -> u32 {
+    (((x as u64) << MONTGOMERY_R_BITS) % modulus as u64) as u32
+}
+
+// x * R^{-1} % mod
+pub fn from_montgomery(x: u32, modulus: u32) -> u32 {
+    montgomery_reduce(x as u64, modulus).0
+}
+
+// Define a function to generate execution trace
+pub fn generate_montgomery_trace<F: Field>(value: Vec<u64>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if value.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: value.len() });
+    }
+    // REDC needs an odd modulus, and t < 2 * mod needs T < mod * R
+    if modulus % 2 == 0 {
+        return Err(GadgetError::EvenModulus { modulus });
+    }
+    let bound = (modulus as u128) << MONTGOMERY_R_BITS;
+    if let Some(index) = value.iter().position(|&v| v as u128 >= bound) {
+        return Err(GadgetError::ValueOutOfRange { index, bits: MONTGOMERY_R_BITS + (32 - modulus.leading_zeros()) as usize });
+    }
+
+    let layout = MontgomeryLayout::new(n);
+    let q = limbs(modulus as u128, HALF_LIMBS);
+    let neg_q_inv = limbs(montgomery_q_inv(modulus).wrapping_neg() as u128, HALF_LIMBS);
+    let mut row: Vec<F> = vec![F::zero(); layout.width];
+    let assign_bits = |row: &mut Vec<F>, start: usize, x: u64, count: usize| {
+        for (b, bit) in bits(x, count).enumerate() {
+            row[start+b] = F::from_bool(bit);
+        }
+    };
+
+    for i in 0..n {
+        let t_limbs = limbs(value[i] as u128, FULL_LIMBS);
+        for (j, &limb) in t_limbs.iter().enumerate() {
+            row[layout.value + i*FULL_LIMBS + j] = F::from_canonical_u64(limb);
+        }
+
+        // 1) limbs of (T mod R) * (-q_inv), whose low half is m
+        let conv = convolve(&t_limbs[..HALF_LIMBS], &neg_q_inv, FULL_LIMBS);
+        let (w, carries) = propagate(&conv, &[]);
+        for (k, &limb) in w.iter().enumerate() {
+            assign_bits(&mut row, layout.w_bits + i*LIMB_BITS*FULL_LIMBS + k*LIMB_BITS, limb, LIMB_BITS);
+        }
+        for (k, &carry) in carries.iter().enumerate() {
+            assign_bits(&mut row, layout.w_carry + i*CARRY_BITS*(FULL_LIMBS-1) + k*CARRY_BITS, carry, CARRY_BITS);
+        }
+
+        // conditional subtraction, and the comparison of out with mod
+        let (out, c) = montgomery_reduce(value[i], modulus);
+        row[layout.out+i] = F::from_canonical_u32(out);
+        row[layout.c+i] = F::from_bool(c);
+        let (out_bits, out_eq) = range_check_columns(out, modulus);
+        for b in 0..RANGE_CHECK_BITS {
+            row[layout.out_bits + i*RANGE_CHECK_BITS + b] = F::from_bool(out_bits[b]);
+            row[layout.out_eq + i*RANGE_CHECK_BITS + b] = F::from_bool(out_eq[b]);
+        }
+
+        // 2) limbs of t = out + c * mod
+        let mut conv = limbs(out as u128, HALF_LIMBS);
+        for k in 0..HALF_LIMBS {
+            conv[k] += c as u64 * q[k];
+        }
+        let (u, carries) = propagate(&conv, &[]);
+        for (k, &limb) in u.iter().enumerate() {
+            assign_bits(&mut row, layout.u_bits + i*LIMB_BITS*HALF_LIMBS + k*LIMB_BITS, limb, LIMB_BITS);
+        }
+        for (k, &carry) in carries.iter().enumerate() {
+            row[layout.u_carry + i*(HALF_LIMBS-1) + k] = F::from_canonical_u64(carry);
+        }
+
+        // 3) carries of T + m * mod - t * R, which propagates to 0 limbs
+        let mut conv = convolve(&w[..HALF_LIMBS], &q, FULL_LIMBS);
+        for k in 0..FULL_LIMBS {
+            conv[k] += t_limbs[k];
+        }
+        let target: Vec<u64> = vec![0; HALF_LIMBS].into_iter().chain(u).collect();
+        let (_, carries) = propagate(&conv, &target);
+        for (k, &carry) in carries.iter().enumerate() {
+            assign_bits(&mut row, layout.t_carry + i*CARRY_BITS*(FULL_LIMBS-1) + k*CARRY_BITS, carry, CARRY_BITS);
+        }
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::{P1, P2, P3};
+
+    #[test]
+    fn test_montgomery_helpers() {
+        let mut rng = thread_rng();
+        for modulus in [P1, P2, P3] {
+            let q_inv = montgomery_q_inv(modulus);
+            assert_eq!(modulus.wrapping_mul(q_inv), 1);
+            for _ in 0..100 {
+                let x = rng.gen_range(0..modulus);
+                assert_eq!(from_montgomery(to_montgomery(x, modulus), modulus), x);
+            }
+        }
+    }
+
+    #[test]
+    fn test_montgomery_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        for modulus in [P1, P2, P3] {
+            // products of 2 values in Montgomery form, below mod^2 < mod * R
+            let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+            let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+            let value: Vec<u64> = (0..n).map(|i| to_montgomery(a[i], modulus) as u64 * to_montgomery(b[i], modulus) as u64).collect();
+
+            let air = MontgomeryReduceAir { n, ..MontgomeryReduceAir::new(value.clone(), modulus) };
+            assert_eq!(air.r, 1u64 << 32);
+            let trace = generate_montgomery_trace::<Val>(value.clone(), modulus, n).unwrap();
+
+            // REDC of the product is a * b in Montgomery form, which converts back to the plain modular product
+            let layout = MontgomeryLayout::new(n);
+            let row = trace.row_slice(0);
+            for i in 0..n {
+                let (out, _) = montgomery_reduce(value[i], modulus);
+                assert_eq!(row[layout.out+i], Val::from_canonical_u32(out));
+                assert_eq!(from_montgomery(out, modulus), (a[i] as u64 * b[i] as u64 % modulus as u64) as u32);
+            }
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+}
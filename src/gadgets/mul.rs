@@ -1,25 +1,67 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use std::ops::Range;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
 use p3_field::{AbstractField, Field};
-use p3_matrix:: Matrix;
+use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
-use std::ops::{Add, Mul};
-// use ark_ff::fields::models::fp::{Fp64, MontBackend, MontConfig};
-// use ark_poly::{polynomial::univariate::DensePolynomial, DenseUVPolynomial};
-// use ark_poly::Polynomial;
-// use ark_ff::PrimeField;
-use crate::params::N;
-
-// #[derive(MontConfig)]
-// #[modulus = "1085276161"]
-// #[generator = "11"]
-// pub struct FqConfig;
-// pub type Fq = Fp64<MontBackend<FqConfig, 1>>;
+use tracing::info_span;
+use crate::gadgets::barrett::{convolve, limbs, LIMB_BITS};
+use crate::gadgets::bit_decompose::{bits, eval_bit_decompose, eval_from_bits};
+use crate::gadgets::error::{check_air_inputs, check_nonempty, check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_check, eval_range_checks, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{pad_trace_to, trace_height};
+use crate::params::{FheParams, N};
+#[cfg(feature = "packed")]
+use p3_field::PackedValue;
+#[cfg(feature = "packed")]
+use p3_mersenne_31::Mersenne31;
 
 // Define AIR constraint
+// The fields are only set through the checked constructors outside of the crate
 pub struct PolyMulAir {
-	pub a: Vec<u32>,
-	pub b: Vec<u32>,
-    pub modulus: u32
+    pub(crate) a: Vec<u32>,
+    pub(crate) b: Vec<u32>,
+    pub(crate) modulus: u32,
+    pub(crate) n: usize,
+    pub(crate) shape: MulShape
+}
+
+// How the PolyMulAir trace is laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MulShape {
+    // Every coefficient on the first row, 616N-245 columns: the layout embedded by NegacyclicMulAir and RnsPolyMulAir
+    Row,
+    // ceil(sqrt(2N-1)) output coefficients per row, one row per group: O(sqrt(N)) columns, see MulRowsLayout
+    Rows
+}
+
+impl PolyMulAir {
+    // Construct the AIR with the default number of coefficients params::N, in the MulShape::Row layout.
+    // Its single row has 616N-245 columns, about 2.16M for N = 3500, which is not practical to commit to:
+    // the MulShape::Row layout is meant for N up to a few hundred (157451 columns for N = 256).
+    // Above that, use with_shape() with MulShape::Rows, e.g. 20709 columns over 128 rows for N = 3500.
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Result<Self, GadgetError> {
+        Self::with_n(a, b, modulus, N)
+    }
+
+    // Construct the AIR with n coefficients, checking that a and b have n coefficients, that the modulus is nonzero,
+    // and the bounds of check_polymul_bounds(). The trace is laid out as MulShape::Row, see new() for the practical N
+    pub fn with_n(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        Self::with_shape(a, b, modulus, n, MulShape::Row)
+    }
+
+    // with_n() with the trace laid out as `shape`
+    pub fn with_shape(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize, shape: MulShape) -> Result<Self, GadgetError> {
+        check_air_inputs(&[&a, &b], n, modulus)?;
+        check_polymul_bounds(modulus, n)?;
+        Ok(Self { a, b, modulus, n, shape })
+    }
+
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`, checked as in with_n()
+    pub fn with_params(a: Vec<u32>, b: Vec<u32>, params: &FheParams, channel: usize) -> Result<Self, GadgetError> {
+        Self::with_n(a, b, params.modulus(channel)?, params.n)
+    }
 }
 
 /*
@@ -27,62 +69,461 @@ Polynomial Multiplication Air
 Input:
 - a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
 - b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
 Output:
 - out = out[0] + out[1] * X + ... + out[2N-2] * X^{2N-2}
+- q = q[0] + q[1] * X + ... + q[2N-2] * X^{2N-2}: quotients of the non-native modular reduction
 
 Note:
-- PolyMulAir does not have a state transition. Values required for constraints are all stored in one row.
+- With MulShape::Row, PolyMulAir does not have a state transition. Values required for constraints are all stored in one row.
+MulShape::Rows spreads the output coefficients over several rows instead, see MulRowsLayout.
 - While output polynomial `out` is calculated manually by generate_polymul_trace(),
-we prove that this multiplication was done correctly, by enforcing a constraint such that a(x)*b(x) === out(x)  at x = [0..2N-1) based on Lagrange polynomial interpolation.
+we prove that this multiplication was done correctly, by enforcing a constraint such that a(x)*b(x) === out(x) + mod * q(x)  at x = [0..2N-1) based on Lagrange polynomial interpolation,
+and the range checks and the mod 2^MUL_CRT_BITS identity which make it hold over the integers (see MUL_CRT_BITS).
+- N = 1 is a scalar modular product: the only evaluation point is x = 0, where the power table holds 0^0 = 1,
+so the single constraint is a[0] * b[0] === out[0] + mod * q[0] over the row [a[0]][b[0]][out[0]][q[0]][witness].
+N = 0 has no coefficients to multiply and no 2N-1 output degree, so generate_polymul_trace() rejects it.
 */
 impl<F: Field> BaseAir<F> for PolyMulAir {
     // Air Table looks like this
-    // row:[     a: N     ][     b: N     ][               out(x): 2N-1               ]
-    //     ^----------inputs------------- ^^---calculated by generate_polymul_trace---^
-    //     [0........................................................................0]
-    //     [0........................................................................0]
-    //     [0........................................................................0]
+    // row:[     a: N     ][     b: N     ][      out(x): 2N-1      ][       q(x): 2N-1       ][ a and b range checks: 62 * 2N ][ out range checks: 62 * (2N-1) ][ q bits: 43 * (2N-1) ][ carry bits: 6 * 23 * (2N-1) ]
+    //     ^----------inputs------------- ^^---------------------------------------------calculated by generate_polymul_trace------------------------------------------------------------------------------------^
+    //     [0..........................................................................................................................................................................................................0]
+    //     [0..........................................................................................................................................................................................................0]
+    //     [0..........................................................................................................................................................................................................0]
     fn width(&self) -> usize {
-         4*N-1
+        match self.shape {
+            MulShape::Row => polymul_width(self.n),
+            MulShape::Rows => MulRowsLayout::new(self.n).width
+        }
     }
 }
 
-fn mod_exp(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
-    if modulus == 1 {
-        return 0;
+impl GadgetLayout for PolyMulAir {
+    fn layout(&self) -> TraceLayout {
+        if self.shape == MulShape::Rows {
+            let layout = MulRowsLayout::new(self.n);
+            return TraceLayout::new()
+                .push("idx", 1)
+                .push("sel", layout.height)
+                .push("out", layout.slots)
+                .push("q", layout.slots)
+                .push("out_range", layout.q_bits - layout.out_range)
+                .push("q_bits", layout.carry_bits - layout.q_bits)
+                .push("carry_bits", layout.width - layout.carry_bits);
+        }
+        let layout = MulLayout::new(self.n);
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("out", 2*self.n-1)
+            .push("q", 2*self.n-1)
+            .push("input_range", layout.out_range - layout.input_range)
+            .push("out_range", layout.q_bits - layout.out_range)
+            .push("q_bits", layout.carry_bits - layout.q_bits)
+            .push("carry_bits", layout.width - layout.carry_bits)
     }
-    let mut result = 1;
-    base %= modulus; // Initial reduction of base
+}
+
+/*
+t for the *virtual* 2^t * n field expansion of the multiplication constraint (see add.rs for the CRT argument).
+Before reduction, out[k] is the convolution sum of at most N <= MUL_MAX_N = 2^12 products, each bounded by (p-1)^2,
+so for any p <= MAX_RANGE_CHECK_MODULUS < 2^31:
+    N * (p-1)^2 < 2^12 * 2^62 - 2^43 < 2^43 * n  and  q[k] = floor(sum / p) < N * p < 2^43
+q[k] is range checked into t = 43 bits and out[k] into [0, p), so both sides of sum_{i+j=k} a[i] * b[j] === q[k] * p + out[k]
+are in [0, 2^43 * n), and the identity holds over the integers once it holds
+    1) mod 2^43, proven below, and 2) mod n, proven by the Lagrange evaluations.
+- 1) is proven over the 8-bits limbs x_l of the integers as in barrett.rs, at the CRT_LIMBS = 6 positions m covering 2^48,
+with a single carry chain for the difference of both sides, where sum_{l+l'=m} x_l * y_l' is the limb convolution at position m:
+    sum_{i+j=k} sum_{l+l'=m} a[i]_l * b[j]_l' - sum_{l+l'=m} q[k]_l * p_l' - out[k]_m + carry_m === 2^8 * carry_{m+1},  carry_0 = 0
+so that the chain telescopes to difference === 2^48 * carry_6 === 0 (mod 2^48).
+- The limbs of a[i], b[j] and out[k] are read from the bits of their range checks, and the limbs of q[k] from its 43 bits.
+- A position sums at most 3 limb products per pair (i, j), so the left side is at most 2^12 * 3 * 255^2 + 2^22 + 2^18 < 3 * 2^28
+in absolute value, and the carries are in [-2^22, 2^22): they are stored with an offset of 2^22 in MUL_CARRY_BITS bits.
+Both sides of each equation then differ by less than n, so it holds over the integers.
+- The range checks and the chain are enforced on the first row, where the product is: the offset carries and the comparisons
+do not hold on the zero padding rows.
+*/
+pub const MUL_CRT_BITS: usize = 43;
+
+// Bits of an offset carry of the identity 1), in [0, 2^23)
+pub const MUL_CARRY_BITS: usize = 23;
+
+// Largest number of coefficients the bounds above hold for
+pub const MUL_MAX_N: usize = 1 << 12;
+
+// Limb positions of the identity 1), and limbs of a range checked coefficient
+pub(crate) const CRT_LIMBS: usize = MUL_CRT_BITS.div_ceil(LIMB_BITS);
+pub(crate) const COEFF_LIMBS: usize = RANGE_CHECK_BITS.div_ceil(LIMB_BITS);
+pub(crate) const CARRY_OFFSET: i64 = 1 << (MUL_CARRY_BITS - 1);
+
+// Check the bounds the reduction argument relies on: a modulus of at most 31 bits and at most MUL_MAX_N coefficients
+pub(crate) fn check_polymul_bounds(modulus: u32, n: usize) -> Result<(), GadgetError> {
+    check_range_modulus(modulus)?;
+    if n > MUL_MAX_N {
+        return Err(GadgetError::TooManyCoefficients { n, max: MUL_MAX_N });
+    }
+    Ok(())
+}
 
-    // perform exponentiation by iterating exponents in binary representation from the LSB to MSB
-    while exp > 0 {
-        // when the bit is 1: base * result
-        if exp % 2 == 1 {
-            result = base * result % modulus;
+// Column offsets of the PolyMulAir row, shared with the gadgets embedding it
+pub(crate) struct MulLayout {
+    pub(crate) out: usize,
+    pub(crate) q: usize,
+    input_range: usize,
+    out_range: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    pub(crate) width: usize
+}
+
+impl MulLayout {
+    pub(crate) fn new(n: usize) -> Self {
+        let out = 2*n;
+        let q = out + 2*n-1;
+        let input_range = q + 2*n-1;
+        let out_range = input_range + RANGE_CHECK_WIDTH*2*n;
+        let q_bits = out_range + RANGE_CHECK_WIDTH*(2*n-1);
+        let carry_bits = q_bits + MUL_CRT_BITS*(2*n-1);
+        let width = carry_bits + CRT_LIMBS*MUL_CARRY_BITS*(2*n-1);
+        Self { out, q, input_range, out_range, q_bits, carry_bits, width }
+    }
+}
+
+// Width of the PolyMulAir row with n coefficients
+pub(crate) fn polymul_width(n: usize) -> usize {
+    MulLayout::new(n).width
+}
+
+/*
+MulShape::Rows layout of PolyMulAir
+- With MulShape::Row, the 2N-1 output coefficients and their witness take 616N-245 columns of a single row.
+MulShape::Rows puts S = ceil(sqrt(2N-1)) of them on each row instead: slot v of row u holds out[k] and q[k] for k = u * S + v,
+with the range check of out[k], the bits of q[k] and the carries of the identity 1) of MUL_CRT_BITS, as in the MulShape::Row row.
+- The sum_{i+j=k} a[i] * b[j] a row reduces depends on the row, so every row carries a one-hot selector sel[0..H) of its index
+as in BatchAddAir, and the convolution sums are constants selected by it, linearly in sel:
+    sum[k] = sum_j sel[j] * sum[j * S + v]
+Every slot then enforces, on every row:
+    sum[k] === q[k] * p + out[k] (mod n),  0 <= out[k] < p,  and the identity 1) over the MUL_CRT_BITS limbs of sum[k]
+which prove the reduction over the integers as with MulShape::Row.
+- The slots k = [2N-1..H * S) and the rows past ceil((2N-1) / S) pad the trace to a power of two of height H, with 0 = 0 * p + 0:
+the range checks and carries of out = q = 0 hold, so no constraint is gated on the first row.
+- The public values, when given, are the 2N-1 coefficients of out, bound by out[v] === sum_j sel[j] * public_values[j * S + v].
+- Width: 1 + H + 245 * S, e.g. 11335 columns over 64 rows for N = 1024, instead of 630539 columns.
+*/
+pub(crate) struct MulRowsLayout {
+    pub(crate) slots: usize,
+    pub(crate) height: usize,
+    sel: usize,
+    pub(crate) out: usize,
+    q: usize,
+    out_range: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    pub(crate) width: usize
+}
+
+impl MulRowsLayout {
+    pub(crate) fn new(n: usize) -> Self {
+        let coeffs = 2*n-1;
+        let mut slots = 1;
+        while slots * slots < coeffs {
+            slots += 1;
         }
-        // right shift exponent to the right by 1
-        exp >>= 1;
-        base = base * base % modulus;
+        let height = trace_height(coeffs.div_ceil(slots));
+
+        let sel = 1;
+        let out = sel + height;
+        let q = out + slots;
+        let out_range = q + slots;
+        let q_bits = out_range + RANGE_CHECK_WIDTH*slots;
+        let carry_bits = q_bits + MUL_CRT_BITS*slots;
+        let width = carry_bits + CRT_LIMBS*MUL_CARRY_BITS*slots;
+        Self { slots, height, sel, out, q, out_range, q_bits, carry_bits, width }
     }
-    result
+}
+
+// x reduced into the native field, for the convolution sums below N * (p-1)^2 < 2^75
+fn from_u128<F: Field>(x: u128) -> F {
+    F::from_wrapped_u64((x >> 64) as u64) * F::from_wrapped_u64(1 << 32).square() + F::from_wrapped_u64(x as u64)
 }
 
 // Define constraints
-impl<AB: AirBuilder> Air<AB> for PolyMulAir {
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PolyMulAir {
     fn eval(&self, builder: &mut AB) {
+        match self.shape {
+            MulShape::Row => self.eval_poly_op(builder),
+            MulShape::Rows => self.eval_rows(builder)
+        }
+    }
+}
+
+impl PolyMulAir {
+    // Enforce the inputs and the multiplication constraints over `row`, which starts at the a[0] column.
+    // This lets other gadgets embed the PolyMulAir layout at any column offset.
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        self.eval_inputs(builder, row);
+        self.eval_op(builder, row);
+    }
+
+    // Enforce the range checks of a, b and out, and the identity 1) mod 2^MUL_CRT_BITS of every coefficient
+    fn eval_reduction<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+        let layout = MulLayout::new(n);
+
+        // Enforce 0 <= a[i], b[i] < mod and 0 <= out[k] < mod
+        let inputs: Vec<AB::Expr> = (0..2*n).map(|i| row[i].into()).collect();
+        eval_range_checks(builder, &inputs, &row[layout.input_range..layout.out_range], self.modulus);
+        let outputs: Vec<AB::Expr> = (0..2*n-1).map(|k| row[layout.out+k].into()).collect();
+        eval_range_checks(builder, &outputs, &row[layout.out_range..layout.q_bits], self.modulus);
+
+        // Limbs of a range checked value, read from the bits at the start of its block
+        let coeff_limbs = |block: usize| range_checked_limbs::<AB>(&row[block..block + RANGE_CHECK_BITS]);
+        let a_limbs: Vec<Vec<AB::Expr>> = (0..n).map(|i| coeff_limbs(layout.input_range + i*RANGE_CHECK_WIDTH)).collect();
+        let b_limbs: Vec<Vec<AB::Expr>> = (0..n).map(|j| coeff_limbs(layout.input_range + (n+j)*RANGE_CHECK_WIDTH)).collect();
+
+        for k in 0..2*n-1 {
+            // 1) Enforce sum_{i+j=k} a[i] * b[j] === q[k] * p + out[k] (mod 2^MUL_CRT_BITS), from the limb convolutions
+            let sum: Vec<AB::Expr> = (0..CRT_LIMBS).map(|m| {
+                let mut sum = AB::Expr::zero();
+                for i in k.saturating_sub(n-1)..=k.min(n-1) {
+                    for l in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+                        sum = sum + a_limbs[i][l].clone() * b_limbs[k-i][m-l].clone();
+                    }
+                }
+                sum
+            }).collect();
+            let q_bits = layout.q_bits + k*MUL_CRT_BITS;
+            let carry_bits = layout.carry_bits + k*CRT_LIMBS*MUL_CARRY_BITS;
+            eval_wide_reduction(
+                builder, sum, row[layout.q+k].into(),
+                coeff_limbs(layout.out_range + k*RANGE_CHECK_WIDTH),
+                &row[q_bits..q_bits + MUL_CRT_BITS],
+                &row[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+                self.modulus
+            );
+        }
+    }
+
+    // Enforce the MulShape::Rows trace: the row selector, then the reduction of every slot k = u * S + v of row u
+    fn eval_rows<AB: AirBuilderWithPublicValues>(&self, builder: &mut AB) {
+        let n = self.n;
+        let layout = MulRowsLayout::new(n);
+
+        let public_values: Vec<AB::Expr> = builder.public_values().iter().map(|&v| v.into()).collect();
 
         let main = builder.main();
-        let row = main.row_slice(0);
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        // The row index starts at 0 and increases by 1 per row
+        let (idx, sel) = (0, layout.sel);
+        builder.when_first_row().assert_zero(local[idx]);
+        builder.when_transition().assert_eq(next[idx], local[idx] + AB::Expr::one());
+
+        // Enforce sel as the one-hot encoding of idx
+        let mut sel_sum = AB::Expr::zero();
+        let mut sel_index = AB::Expr::zero();
+        for j in 0..layout.height {
+            builder.assert_bool(local[sel+j]);
+            sel_sum = sel_sum + local[sel+j];
+            sel_index = sel_index + local[sel+j] * AB::F::from_canonical_usize(j);
+        }
+        builder.assert_one(sel_sum);
+        builder.assert_eq(sel_index, local[idx]);
+
+        // The convolution sums, computed on the host as the a and b constants of the other gadgets
+        let sums: Vec<u128> = (0..2*n-1).map(|k| convolution_sum(&self.a, &self.b, k)).collect();
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+
+        for v in 0..layout.slots {
+            // Select sum[k] mod n, its limbs mod 2^(8 * CRT_LIMBS) and the expected out[k] for k = idx * S + v, 0 past 2N-1
+            let mut sum = AB::Expr::zero();
+            let mut sum_limbs = vec![AB::Expr::zero(); CRT_LIMBS];
+            let mut expected = AB::Expr::zero();
+            for j in 0..layout.height {
+                let k = j*layout.slots + v;
+                if k >= 2*n-1 {
+                    break;
+                }
+                sum = sum + local[sel+j] * from_u128::<AB::F>(sums[k]);
+                for (m, limb) in limbs(sums[k], CRT_LIMBS).into_iter().enumerate() {
+                    sum_limbs[m] = sum_limbs[m].clone() + local[sel+j] * AB::F::from_canonical_u64(limb);
+                }
+                if let Some(out_k) = public_values.get(k) {
+                    expected = expected + out_k.clone() * local[sel+j];
+                }
+            }
+
+            // Enforce sum[k] === q[k] * p + out[k] (mod n), 0 <= out[k] < p, and the identity 1)
+            let (out, q) = (local[layout.out+v], local[layout.q+v]);
+            builder.assert_eq(sum, q * modulus + out);
+            let block = layout.out_range + v*RANGE_CHECK_WIDTH;
+            eval_range_check(builder, out.into(), &local[block..block+RANGE_CHECK_BITS], &local[block+RANGE_CHECK_BITS..block+RANGE_CHECK_WIDTH], self.modulus);
+            let q_bits = layout.q_bits + v*MUL_CRT_BITS;
+            let carry_bits = layout.carry_bits + v*CRT_LIMBS*MUL_CARRY_BITS;
+            eval_wide_identity(
+                builder, sum_limbs, q.into(),
+                range_checked_limbs::<AB>(&local[block..block+RANGE_CHECK_BITS]),
+                &local[q_bits..q_bits + MUL_CRT_BITS],
+                &local[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+                self.modulus
+            );
+
+            // Bind out[k] to the public values, when they are given
+            if !public_values.is_empty() {
+                builder.assert_eq(out, expected);
+            }
+        }
+    }
+}
+
+/*
+Enforce sum === q * p + out (mod 2^MUL_CRT_BITS) on the first row, for a sum given by its CRT_LIMBS limb positions sum[m]
+(e.g. limb convolutions), out by its COEFF_LIMBS limbs (e.g. from range_checked_limbs()) and q by its MUL_CRT_BITS bits q_bits:
+    sum[m] - sum_{l+l'=m} q_l * p_l' - out_m + carry_m === 2^8 * carry_{m+1}
+Together with q === sum_b q_bits[b] * 2^b enforced here, and sum === q * p + out (mod n) and 0 <= out < p enforced by the caller,
+this proves the reduction over the integers for any sum below 2^MUL_CRT_BITS * n whose positions obey the bounds of MUL_CRT_BITS.
+*/
+pub(crate) fn eval_wide_reduction<AB: AirBuilder>(builder: &mut AB, sum: Vec<AB::Expr>, q: AB::Expr, out_limbs: Vec<AB::Expr>, q_bits: &[AB::Var], carry_bits: &[AB::Var], modulus: u32) {
+    eval_wide_identity(&mut builder.when_first_row(), sum, q, out_limbs, q_bits, carry_bits, modulus);
+}
+
+// eval_wide_reduction() on every row, for the traces whose rows all hold a reduction
+fn eval_wide_identity<AB: AirBuilder>(builder: &mut AB, sum: Vec<AB::Expr>, q: AB::Expr, out_limbs: Vec<AB::Expr>, q_bits: &[AB::Var], carry_bits: &[AB::Var], modulus: u32) {
+    eval_bit_decompose(builder, q, q_bits);
+    let q_limbs: Vec<AB::Expr> = (0..CRT_LIMBS).map(|l| limb_value::<AB>(&q_bits[l*LIMB_BITS..MUL_CRT_BITS.min((l+1)*LIMB_BITS)])).collect();
+    let mod_limbs = limbs(modulus as u128, COEFF_LIMBS);
+
+    let terms: Vec<AB::Expr> = sum.into_iter().enumerate().map(|(m, mut term)| {
+        for (l, &mod_l) in mod_limbs.iter().enumerate().take(m+1) {
+            term = term - q_limbs[m-l].clone() * AB::F::from_canonical_u64(mod_l);
+        }
+        if m < COEFF_LIMBS {
+            term = term - out_limbs[m].clone();
+        }
+        term
+    }).collect();
+    eval_carry_chain(builder, terms, carry_bits);
+}
+
+// Assign the bits of q and the carries checked by eval_wide_reduction() for the limb positions sum[m]
+pub(crate) fn assign_wide_reduction<F: Field>(q_bits: &mut [F], carry_bits: &mut [F], sum: &[u64], q: u64, out: u32, modulus: u32) {
+    for (bit, col) in bits(q, MUL_CRT_BITS).zip(q_bits.iter_mut()) {
+        *col = F::from_bool(bit);
+    }
+
+    // limbs of sum - q * p - out at positions [0..CRT_LIMBS)
+    let mut diff: Vec<i64> = sum.iter().map(|&x| x as i64).collect();
+    for (m, x) in convolve(&limbs(q as u128, CRT_LIMBS), &limbs(modulus as u128, COEFF_LIMBS), CRT_LIMBS).into_iter().enumerate() {
+        diff[m] -= x as i64;
+    }
+    for (m, x) in limbs(out as u128, COEFF_LIMBS).into_iter().enumerate() {
+        diff[m] -= x as i64;
+    }
+
+    // carry_{m+1} = (diff_m + carry_m) / 2^8, stored with CARRY_OFFSET
+    assign_carry_chain(carry_bits, &diff);
+}
+
+// Enforce terms[m] + carry_m === 2^8 * carry_{m+1} for m = [0..terms.len()), with carry_0 = 0 and the carries read from
+// MUL_CARRY_BITS bits each, with CARRY_OFFSET: the chain telescopes to sum_m terms[m] * 2^{8m} === 0 (mod 2^{8 * terms.len()}).
+// The terms must stay small enough for both sides of every equation to differ by less than n, as shown for MUL_CRT_BITS.
+pub(crate) fn eval_carry_chain<AB: AirBuilder>(builder: &mut AB, terms: Vec<AB::Expr>, carry_bits: &[AB::Var]) {
+    let limb_base = AB::F::from_canonical_u32(1 << LIMB_BITS);
+    let offset = AB::F::from_canonical_u32(CARRY_OFFSET as u32);
+    let mut carry = AB::Expr::zero();
+    for (m, term) in terms.into_iter().enumerate() {
+        let next = eval_from_bits(builder, &carry_bits[m*MUL_CARRY_BITS..(m+1)*MUL_CARRY_BITS]) - offset;
+        builder.assert_eq(term + carry, next.clone() * limb_base);
+        carry = next;
+    }
+}
+
+// Assign the carries of eval_carry_chain() for the integer terms diff to `block`.
+// They are computed with signed arithmetic and wrap into their bits, so that terms which do not telescope to 0 still give a trace.
+pub(crate) fn assign_carry_chain<F: Field>(block: &mut [F], diff: &[i64]) {
+    let mut carry = 0i64;
+    for (m, &d) in diff.iter().enumerate() {
+        carry = (d + carry).div_euclid(1 << LIMB_BITS);
+        for (bit, col) in bits((carry + CARRY_OFFSET) as u64, MUL_CARRY_BITS).zip(&mut block[m*MUL_CARRY_BITS..(m+1)*MUL_CARRY_BITS]) {
+            *col = F::from_bool(bit);
+        }
+    }
+}
+
+// The COEFF_LIMBS 8-bits limbs of a value range checked by eval_range_checks(), from the RANGE_CHECK_BITS bits of its block
+pub(crate) fn range_checked_limbs<AB: AirBuilder>(bits: &[AB::Var]) -> Vec<AB::Expr> {
+    (0..COEFF_LIMBS).map(|l| limb_value::<AB>(&bits[l*LIMB_BITS..RANGE_CHECK_BITS.min((l+1)*LIMB_BITS)])).collect()
+}
+
+// sum_b bits[b] * 2^b, for bits already constrained to be boolean
+pub(crate) fn limb_value<AB: AirBuilder>(bits: &[AB::Var]) -> AB::Expr {
+    let mut sum = AB::Expr::zero();
+    for (b, &bit) in bits.iter().enumerate() {
+        sum = sum + bit * AB::F::from_canonical_u32(1 << b);
+    }
+    sum
+}
+
+impl PolynomialOpAir for PolyMulAir {
+    fn a(&self) -> &[u32] {
+        &self.a
+    }
+
+    fn b(&self) -> &[u32] {
+        &self.b
+    }
+
+    fn modulus(&self) -> u32 {
+        self.modulus
+    }
 
-        // Enforce self.a and self.b as 2 input polynomials
-		for i in 0..N {
-            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
-			builder.when_first_row().assert_eq(row[i+N], AB::Expr::from_canonical_u32(self.b[i]));
-		}
+    fn n(&self) -> usize {
+        self.n
+    }
 
-        let mut a_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*N-1);
-        let mut b_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*N-1);
-        let mut out_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*N-1);
+    // The out slots of a row with MulShape::Rows
+    fn output_columns(&self) -> Range<usize> {
+        match self.shape {
+            MulShape::Row => 2*self.n..4*self.n-1,
+            MulShape::Rows => {
+                let layout = MulRowsLayout::new(self.n);
+                layout.out..layout.out + layout.slots
+            }
+        }
+    }
+
+    fn num_outputs(&self) -> usize {
+        2*self.n-1
+    }
+
+    // The 2N-1 coefficients of out, read slot by slot with MulShape::Rows
+    fn public_outputs<F: Field>(&self, trace: &RowMajorMatrix<F>) -> Vec<F> {
+        match self.shape {
+            MulShape::Row => {
+                let row = trace.row_slice(0);
+                self.output_columns().map(|col| row[col]).collect()
+            }
+            MulShape::Rows => {
+                let slots = self.output_columns();
+                (0..self.num_outputs()).map(|k| trace.row_slice(k / slots.len())[slots.start + k % slots.len()]).collect()
+            }
+        }
+    }
+
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+
+        let mut a_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*n-1);
+        let mut b_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*n-1);
+        let mut out_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*n-1);
+        let mut q_eval: Vec<<AB as AirBuilder>::Expr> = Vec::with_capacity(2*n-1);
+
+        // Precompute the power table x^j for x = [0..2N-1) and j = [0..2N-1) once,
+        // and share it between the evaluations of a, b, out and q
+        let powers = power_table::<AB::F>(n);
+        let power = |i: usize, j: usize| powers[i * (2*n-1) + j];
 
         // Evaluate 2 input polynomial a(x) and b(x) at x = [0..2N-1)
         // a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
@@ -90,156 +531,433 @@ impl<AB: AirBuilder> Air<AB> for PolyMulAir {
         // when x = 1, a_eval[1] = a[0] + a[1]*1 + a[2]*1^2 + ... + a[N-1] * 1^{N-1}
         // ...
         // when x = 2N-1, a_eval[2N-1] = a[0] + a[1]*(2N-1) + ... + a[N-1] * (2N-1)^{N-1}
-        for i in 0..2*N-1 {
+        for i in 0..2*n-1 {
             a_eval.push(AB::Expr::zero());
             b_eval.push(AB::Expr::zero());
-            for j in 0..N {
-                let power = AB::Expr::from_canonical_u64(mod_exp(i as u64, j as u64, self.modulus as u64));
-
-                let _ = a_eval[i].clone().add(row[j].mul(power.clone()));
-                let _ = b_eval[i].clone().add(row[j+N].mul(power));
+            for j in 0..n {
+                a_eval[i] = a_eval[i].clone() + row[j] * power(i, j);
+                b_eval[i] = b_eval[i].clone() + row[j+n] * power(i, j);
             }
         }
 
-        // Evaluate output polynomial out(x) at x = [0..2N-1)
-        for i in 0..2*N-1 {
+        // Evaluate output polynomial out(x) and quotient polynomial q(x) at x = [0..2N-1)
+        for i in 0..2*n-1 {
             out_eval.push(AB::Expr::zero());
-            for j in 0..2*N-1 {
-                let power = AB::Expr::from_canonical_u64(mod_exp(i as u64, j as u64, self.modulus as u64));
-
-                let _ = out_eval[i].clone().add(row[j+2*N].mul(power));
+            q_eval.push(AB::Expr::zero());
+            for j in 0..2*n-1 {
+                out_eval[i] = out_eval[i].clone() + row[j+2*n] * power(i, j);
+                q_eval[i] = q_eval[i].clone() + row[j+4*n-1] * power(i, j);
             }
         }
 
-       // Enforce a[x] * b[x] === out[x] at x = [0..2N-1)
-       // TODO: add non-native modular reduction
-       // currently this is under-constrained
-        for i in 0..2*N-1 {
-            builder.assert_eq(a_eval[i].clone().mul(b_eval[i].clone()), out_eval[i].clone());
+        /*
+        Enforce a[x] * b[x] === out[x] + mod * q[x] at x = [0..2N-1)
+        The 2N-1 evaluations pin down every coefficient, so this is the same as
+        sum_{i+j=k} a[i] * b[j] === q[k] * p + out[k] (mod n) for k = [0..2N-1),
+        which is the constraint 2) of the CRT argument in add.rs, with q[k] stored in the trace modulo n.
+        */
+        let modulus = AB::F::from_wrapped_u32(self.modulus);
+        for i in 0..2*n-1 {
+            builder.assert_eq(a_eval[i].clone() * b_eval[i].clone(), out_eval[i].clone() + q_eval[i].clone() * modulus);
         }
+
+        // Enforce the constraint 1), which makes the identity hold over the integers
+        self.eval_reduction(builder, row);
+    }
+
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError> {
+        match self.shape {
+            MulShape::Row => generate_polymul_trace(self.a.clone(), self.b.clone(), self.modulus, self.n),
+            MulShape::Rows => generate_polymul_rows_trace(self.a.clone(), self.b.clone(), self.modulus, self.n)
+        }
+    }
+}
+
+// Row-major (2N-1) x (2N-1) table of x^j for x, j = [0..2N-1)
+// Powers are taken in the proving field, since that is where the identity is checked
+pub(crate) fn power_table<F: Field>(n: usize) -> Vec<F> {
+    let points = 2*n-1;
+    let mut powers = Vec::with_capacity(points * points);
+    for i in 0..points {
+        let x = F::from_canonical_usize(i);
+        let mut power = F::one();
+        for _ in 0..points {
+            powers.push(power);
+            power *= x;
+        }
+    }
+    powers
+}
+
+// Multiply the 2 polynomials manually, returning the reduced coefficients out and the quotients q of the reduction
+// such that the convolution sum at degree i equals q[i] * modulus + out[i]
+pub(crate) fn polymul_coeffs(a: &[u32], b: &[u32], modulus: u32) -> (Vec<u128>, Vec<u128>) {
+    let n = a.len();
+
+    // A zero or monomial operand c * X^i only shifts and scales the other one, in O(N) instead of O(N^2)
+    if let Some(sums) = monomial_sums(a, b) {
+        return reduce_sums(sums, modulus);
     }
 
+    // Each convolution sum is independent, so with the `rayon` feature every out[i] runs on its own task.
+    // collect() keeps the coefficients in order in both cases.
+    #[cfg(feature = "rayon")]
+    let sums: Vec<u128> = {
+        use rayon::prelude::*;
+        (0..2*n-1).into_par_iter().map(|i| convolution_sum(a, b, i)).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let sums: Vec<u128> = (0..2*n-1).map(|i| convolution_sum(a, b, i)).collect();
+
+    reduce_sums(sums, modulus)
+}
+
+// Serial version of polymul_coeffs(), regardless of the `rayon` feature
+#[cfg(test)]
+fn polymul_coeffs_serial(a: &[u32], b: &[u32], modulus: u32) -> (Vec<u128>, Vec<u128>) {
+    let n = a.len();
+    reduce_sums((0..2*n-1).map(|i| convolution_sum(a, b, i)).collect(), modulus)
+}
+
+// out[i] = q[i] * p + (out[i] mod p)
+fn reduce_sums(sums: Vec<u128>, modulus: u32) -> (Vec<u128>, Vec<u128>) {
+    let q = sums.iter().map(|&sum| sum / modulus as u128).collect();
+    let out = sums.iter().map(|&sum| sum % modulus as u128).collect();
+    (out, q)
+}
+
+// The only nonzero coefficient (i, c) of `poly`, (0, 0) if it is all zeros, or None if it has several
+fn single_term(poly: &[u32]) -> Option<(usize, u32)> {
+    let mut terms = poly.iter().enumerate().filter(|(_, &c)| c != 0);
+    match (terms.next(), terms.next()) {
+        (None, _) => Some((0, 0)),
+        (Some((i, &c)), None) => Some((i, c)),
+        _ => None,
+    }
+}
+
+// Convolution sums of a * b when one operand is c * X^i: sums[i+j] = c * other[j], and 0 elsewhere
+fn monomial_sums(a: &[u32], b: &[u32]) -> Option<Vec<u128>> {
+    let ((i, c), other) = match (single_term(a), single_term(b)) {
+        (Some(term), _) => (term, b),
+        (None, Some(term)) => (term, a),
+        (None, None) => return None,
+    };
+    let mut sums = vec![0u128; a.len() + b.len() - 1];
+    if c != 0 {
+        for (j, &x) in other.iter().enumerate() {
+            sums[i+j] = c as u128 * x as u128;
+        }
+    }
+    Some(sums)
+}
+
+// Coefficient of X^i in a * b before reduction
+// Using u128 for intermediate values to avoid overflow: the convolution sums stay below N * (p-1)^2 < 2^74
+fn convolution_sum(a: &[u32], b: &[u32], i: usize) -> u128 {
+    let n = a.len();
+    let mut sum: u128 = 0;
+    if i < n {
+        // a's index increases from 0 to i, b's index decreases from i to 0
+        // ex. N = 3 where N is the number of coefficients
+        // when i = 0, a[0] * b[0]
+        // when i = 1, a[0] * b[1] + a[1] * b[0]
+        // when i = 2, a[0] * b[2] + a[1] * b[1] + a[2] * b[0]
+        for a_idx in 0..i+1 {
+            let b_idx = i - a_idx;
+            sum += a[a_idx] as u128 * b[b_idx] as u128;
+        }
+    } else {
+        // a's index increases from i-N+1 to N-1, which is the highest degree of input polynomial, b's index decreases from N-1 to i-(N-1)
+        // ex. N = 3 where N is the number of coefficients
+        // when i = 3, a[1] * b[2] + a[2] * b[1]
+        // when i = 4, a[2] * b[2]
+        for a_idx in i-n+1..n {
+            let b_idx = i - a_idx;
+            sum += a[a_idx] as u128 * b[b_idx] as u128;
+        }
+    }
+    sum
+}
+
+// p(x) mod `modulus` by Horner's rule
+fn horner(coeffs: &[u32], x: u64, modulus: u32) -> u64 {
+    coeffs.iter().rev().fold(0u64, |acc, &c| (acc * x + c as u64) % modulus as u64)
+}
+
+// Host-side check of a(x) * b(x) == out(x) mod `modulus` at x = [0..2N-1), which pins down every coefficient of out.
+// Panics on the first mismatching point: it is meant for debugging trace generation, not as a substitute for the proof.
+pub fn debug_assert_polymul_identity(a: &[u32], b: &[u32], out: &[u32], modulus: u32) {
+    let points = a.len() + b.len() - 1;
+    for x in 0..points as u64 {
+        let lhs = (horner(a, x, modulus) as u128 * horner(b, x, modulus) as u128 % modulus as u128) as u64;
+        let rhs = horner(out, x, modulus);
+        assert_eq!(lhs, rhs, "a(x) * b(x) != out(x) mod {} at x = {}", modulus, x);
+    }
 }
 
 // Define a function to generate execution trace
-pub fn generate_polymul_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32) -> RowMajorMatrix<F> {
-    let mut values: Vec<F>= Vec::with_capacity(4 * (4*N-1)); // 4 is the minimum number of rows required
-
-	// Assign input polynomials to values vector
-	for i in 0..N {
-		values.push(F::from_canonical_u32(a[i]));
-	}
-	for i in 0..N {
-		values.push(F::from_canonical_u32(b[i]));
-	}
-
-    let mut out:Vec<u128> = Vec::with_capacity(2*N-1);
-
-	// Multiply the 2 polynomials manually and assign coefficients to values vector
-    // Temporarily using u128 for intermediate values to avoid overflow
-	for i in 0..2*N-1 {
-        if i < N {
-            // a's index increases from 0 to i, b's index decreases from i to 0
-            // ex. N = 3 where N is the number of coefficients
-            // when i = 0, a[0] * b[0]
-            // when i = 1, a[0] * b[1] + a[1] * b[0]
-            // when i = 2, a[0] * b[2] + a[1] * b[1] + a[2] * b[0]
-            out.push(0);
-            for a_idx in 0..i+1 {
-                let b_idx = i - a_idx;
-                out[i] += a[a_idx] as u128 * b[b_idx] as u128 % modulus as u128;
-            }
+pub fn generate_polymul_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    generate_polymul_trace_with_height(a, b, modulus, n, trace_height(1))
+}
 
-        } else {
-            // a's index increases from i-N+1 to N-1, which is the highest degree of input polynomial, b's index decreases from N-1 to i-(N-1)
-            // ex. N = 3 where N is the number of coefficients
-            // when i = 3, a[1] * b[2] + a[2] * b[1]
-            // when i = 4, a[2] * b[2]
-            out.push(0);
-            for a_idx in i-N+1..N {
-                let b_idx = i - a_idx;
-                out[i] += a[a_idx] as u128 * b[b_idx] as u128 % modulus as u128;
-            }
+// generate_polymul_trace() padded with zero rows up to `height`, a power of two of at least MIN_TRACE_HEIGHT
+pub fn generate_polymul_trace_with_height<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize, height: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let _span = info_span!("trace_generation", n).entered();
+    check_nonempty(n)?;
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_polymul_bounds(modulus, n)?;
+
+    let (values, out) = polymul_row::<F>(&a, &b, modulus);
+
+    // check a(x) * b(x) == out(x) outside the circuit, to catch trace generation bugs in debug builds
+    if cfg!(debug_assertions) {
+        let out: Vec<u32> = out.iter().map(|&c| c as u32).collect();
+        debug_assert_polymul_identity(&a, &b, &out, modulus);
+    }
+
+    // Pad with zero rows up to the requested height
+    pad_trace_to(values, polymul_width(n), height)
+}
+
+// Define a function to generate the MulShape::Rows execution trace, with one row per group of S slots
+pub fn generate_polymul_rows_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let _span = info_span!("trace_generation", n).entered();
+    check_nonempty(n)?;
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_polymul_bounds(modulus, n)?;
+
+    let layout = MulRowsLayout::new(n);
+    let (out, q) = polymul_coeffs(&a, &b, modulus);
+    let mut trace = RowMajorMatrix::new(vec![F::zero(); layout.height * layout.width], layout.width);
+
+    for u in 0..layout.height {
+        let row = trace.row_mut(u);
+
+        // Assign the row index and its one-hot selector
+        row[0] = F::from_canonical_usize(u);
+        row[layout.sel+u] = F::one();
+
+        // Assign the slots k = u * S + v with their witness, as 0 = 0 * p + 0 past the 2N-1 coefficients
+        for v in 0..layout.slots {
+            let k = u*layout.slots + v;
+            let (sum, out_k, q_k) = if k < 2*n-1 { (convolution_sum(&a, &b, k), out[k] as u32, q[k] as u64) } else { (0, 0, 0) };
+            assign_rows_slot(row, &layout, v, sum, out_k, q_k, modulus);
         }
+    }
+    Ok(trace)
+}
+
+// Assign out[k] and q[k] reducing the convolution sum `sum` to slot v of `row`, with the range check of out[k],
+// the bits of q[k] and the carries of the identity 1). As in assign_output_witness(), a pair that does not reduce the sum still gives a row.
+fn assign_rows_slot<F: Field>(row: &mut [F], layout: &MulRowsLayout, v: usize, sum: u128, out: u32, q: u64, modulus: u32) {
+    row[layout.out+v] = F::from_wrapped_u32(out);
+    row[layout.q+v] = F::from_wrapped_u64(q);
+
+    let block = layout.out_range + v*RANGE_CHECK_WIDTH;
+    assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], out, modulus);
+    let (q_bits, carry_bits) = (layout.q_bits + v*MUL_CRT_BITS, v*CRT_LIMBS*MUL_CARRY_BITS);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+        &limbs(sum, CRT_LIMBS), q, out, modulus
+    );
+}
+
+// First row of generate_polymul_trace() for checked inputs, with the reduced coefficients out,
+// so that the gadgets embedding the PolyMulAir layout assign it in the same way
+pub(crate) fn polymul_row<F: Field>(a: &[u32], b: &[u32], modulus: u32) -> (Vec<F>, Vec<u128>) {
+    let n = a.len();
+    let layout = MulLayout::new(n);
+    let mut row = vec![F::zero(); layout.width];
+
+    // Assign input polynomials and their range checks
+    for (i, &c) in a.iter().chain(b).enumerate() {
+        row[i] = F::from_wrapped_u32(c);
+    }
+    assign_input_range(&mut row, &layout, a, b, modulus);
 
-        out[i] %= modulus as u128;
-        values.push(F::from_canonical_u32(out[i] as u32));
+    // Assign output coefficients and quotients, reduced into the native field, with their witness
+    // q[i] < N * p < 2^43, so it fits in u64
+    let (out, q) = polymul_coeffs(a, b, modulus);
+    let (a_limbs, b_limbs) = (coeff_limbs(a), coeff_limbs(b));
+    for k in 0..2*n-1 {
+        row[layout.out+k] = F::from_wrapped_u32(out[k] as u32);
+        row[layout.q+k] = F::from_wrapped_u64(q[k] as u64);
+        assign_output_witness(&mut row, &layout, (&a_limbs, &b_limbs), k, out[k] as u32, q[k] as u64, modulus);
+    }
+    (row, out)
+}
 
-	}
+// 8-bits limbs of every coefficient
+pub(crate) fn coeff_limbs(poly: &[u32]) -> Vec<Vec<u64>> {
+    poly.iter().map(|&c| limbs(c as u128, COEFF_LIMBS)).collect()
+}
 
-    // check a(x) * b(x) == out(x)
-    // this check is done outside the circuit/constraints just for test purposes
+// Assign the range checks of a and b to `row`
+fn assign_input_range<F: Field>(row: &mut [F], layout: &MulLayout, a: &[u32], b: &[u32], modulus: u32) {
+    for (i, &c) in a.iter().chain(b).enumerate() {
+        let block = layout.input_range + i*RANGE_CHECK_WIDTH;
+        assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], c, modulus);
+    }
+}
+
+/*
+Assign the range check of out[k], the bits of q[k] and the carries of the identity 1) to `row`.
+An out[k] and q[k] that do not reduce the product (e.g. a forged out[k] with q[k] re-solved mod n) still give a trace,
+which the constraints then reject.
+*/
+pub(crate) fn assign_output_witness<F: Field>(row: &mut [F], layout: &MulLayout, (a, b): (&[Vec<u64>], &[Vec<u64>]), k: usize, out: u32, q: u64, modulus: u32) {
+    let n = a.len();
+    let block = layout.out_range + k*RANGE_CHECK_WIDTH;
+    assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], out, modulus);
+
+    // limb convolutions of sum_{i+j=k} a[i] * b[j] at positions [0..CRT_LIMBS)
+    let mut sum = vec![0u64; CRT_LIMBS];
+    for i in k.saturating_sub(n-1)..=k.min(n-1) {
+        for (m, x) in convolve(&a[i], &b[k-i], CRT_LIMBS).into_iter().enumerate() {
+            sum[m] += x;
+        }
+    }
 
-    // let mut a_eval: Vec<Fq> = Vec::new();
-    // let mut b_eval: Vec<Fq> = Vec::new();
-    // let mut in_eval: Vec<u64> = Vec::new();
-    // let mut out_eval: Vec<u64> = Vec::new();
+    let (q_bits, carry_bits) = (layout.q_bits + k*MUL_CRT_BITS, k*CRT_LIMBS*MUL_CARRY_BITS);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+        &sum, q, out, modulus
+    );
+}
 
-    // let a_fq_coeffs: Vec<Fq> = a.iter().map(|&x: &u32| Fq::from(x)).collect();
-    // let a_poly = DensePolynomial::from_coefficients_slice(&a_fq_coeffs);
+// Same trace as generate_polymul_trace, written in place into a preallocated matrix
+// Memory footprint (4-byte field elements, 16-byte u128):
+// - generate_polymul_trace: the matrix, plus the sums, out and q buffers of polymul_coeffs,
+//   3 * (2N-1) u128, alive at the same time.
+// - this function: only the matrix and the limbs of a and b. Every convolution sum is reduced and written as soon as it is computed.
+pub fn generate_polymul_trace_streaming<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_nonempty(n)?;
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_polymul_bounds(modulus, n)?;
 
-    // let b_fq_coeffs: Vec<Fq> = b.iter().map(|&x| Fq::from(x)).collect();
-    // let b_poly = DensePolynomial::from_coefficients_slice(&b_fq_coeffs);
+    // trace_height(1) = MIN_TRACE_HEIGHT rows, and the rows after the first one stay 0
+    let layout = MulLayout::new(n);
+    let mut trace = RowMajorMatrix::new(vec![F::zero(); trace_height(1) * layout.width], layout.width);
+    let row = trace.row_mut(0);
 
-    // let out_fq_coeffs: Vec<Fq> = out.iter().map(|&x| Fq::from(x)).collect();
-    // let out_poly = DensePolynomial::from_coefficients_slice(&out_fq_coeffs);
+    // Assign input polynomials
+    for i in 0..n {
+        row[i] = F::from_wrapped_u32(a[i]);
+        row[n+i] = F::from_wrapped_u32(b[i]);
+    }
+    assign_input_range(row, &layout, &a, &b, modulus);
 
-    // for i in 0..2*N-1 {
+    // Assign each output coefficient and its quotient, reduced into the native field
+    let (a_limbs, b_limbs) = (coeff_limbs(&a), coeff_limbs(&b));
+    for k in 0..2*n-1 {
+        let sum = convolution_sum(&a, &b, k);
+        let (out, q) = ((sum % modulus as u128) as u32, (sum / modulus as u128) as u64);
+        row[layout.out+k] = F::from_wrapped_u32(out);
+        row[layout.q+k] = F::from_wrapped_u64(q);
+        assign_output_witness(row, &layout, (&a_limbs, &b_limbs), k, out, q, modulus);
+    }
+    Ok(trace)
+}
 
-    //     let i_as_fq = Fq::from(i as u64);
+// Same trace as generate_polymul_trace over Mersenne31, with out and q reduced into the native field
+// Packing::WIDTH coefficients at a time (AVX2, AVX-512 or NEON lanes when the target enables them, 1 otherwise).
+// The reductions mod `modulus` and the witness after [q] stay scalar: packed Mersenne31 arithmetic only reduces mod 2^31 - 1.
+#[cfg(feature = "packed")]
+pub fn generate_polymul_trace_packed(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<Mersenne31>, GadgetError> {
+    check_nonempty(n)?;
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_polymul_bounds(modulus, n)?;
 
-    //     // Evaluate a(x) and b(x) at x = [0..2N-1)
-    //     a_eval.push(a_poly.evaluate(&i_as_fq));
-    //     b_eval.push(b_poly.evaluate(&i_as_fq));
+    let layout = MulLayout::new(n);
+    let mut values: Vec<Mersenne31> = Vec::with_capacity(trace_height(1) * layout.width);
 
-    //     // Calculate a(x) * b(x) at x = [0..2N-1)
-    //     in_eval.push((a_eval[i] * b_eval[i]).into_bigint().as_ref()[0]);
+    // Assign input polynomials, then the output coefficients and the quotients
+    let (out, q) = polymul_coeffs(&a, &b, modulus);
+    values.extend(a.iter().chain(b.iter()).map(|&c| Mersenne31::from_wrapped_u32(c)));
+    values.extend(reduce_packed(&out));
+    values.extend(reduce_packed(&q));
 
-    //     // Evaluate out(x) at x = [0..2N-1)
-    //     out_eval.push(out_poly.evaluate(&i_as_fq).into_bigint().as_ref()[0]);
+    // Assign the range checks, the bits and the carries
+    values.resize(layout.width, Mersenne31::zero());
+    assign_input_range(&mut values, &layout, &a, &b, modulus);
+    let (a_limbs, b_limbs) = (coeff_limbs(&a), coeff_limbs(&b));
+    for k in 0..2*n-1 {
+        assign_output_witness(&mut values, &layout, (&a_limbs, &b_limbs), k, out[k] as u32, q[k] as u64, modulus);
+    }
 
-    //     println!("in_eval[{}]: {}", i, in_eval[i]);
-    //     println!("out_eval[{}]: {}", i, out_eval[i]);
+    pad_trace_to(values, layout.width, trace_height(1))
+}
 
-    // }
+// x mod 2^31 - 1 for every x < 2^64, as sum_k limb_k * 2^{16k} with 16-bit limbs, evaluated on packed lanes
+// All the arithmetic is canonical field arithmetic, so the result is the same as Mersenne31::from_wrapped_u64(x)
+#[cfg(feature = "packed")]
+fn reduce_packed(values: &[u128]) -> Vec<Mersenne31> {
+    type Packed = <Mersenne31 as Field>::Packing;
+    let weights = [0, 1, 2, 3].map(|k| Mersenne31::from_wrapped_u64(1 << (16*k)));
 
-    // Fill in the last 3 rows with 0
-    for _i in 0..3*(4*N-1) {
-        values.push(F::zero());
+    let mut reduced = Vec::with_capacity(values.len());
+    for chunk in values.chunks(Packed::WIDTH) {
+        // the last chunk is padded with zero lanes, which are dropped again below
+        let mut sum = Packed::zero();
+        for (k, &weight) in weights.iter().enumerate() {
+            let limbs = Packed::from_fn(|j| {
+                let limb = chunk.get(j).map_or(0, |&x| (x >> (16*k)) as u32 & 0xffff);
+                Mersenne31::from_canonical_u32(limb)
+            });
+            sum += limbs * weight;
+        }
+        reduced.extend_from_slice(&sum.as_slice()[..chunk.len()]);
     }
-    RowMajorMatrix::new(values, 4*N-1)
+    reduced
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fmt::Debug;
+    use std::mem::size_of;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::time::Instant;
+    use p3_field::{PrimeField32, PrimeField64};
     use p3_mersenne_31::Mersenne31;
     use p3_keccak::Keccak256Hash;
     use rand::{thread_rng, Rng};
     use p3_challenger::{HashChallenger, SerializingChallenger32};
-    use p3_uni_stark::{prove, verify};
+    use p3_uni_stark::{get_symbolic_constraints, prove, verify};
     use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
-    use crate::params::P1;
+    use crate::gadgets::range_check::MAX_RANGE_CHECK_MODULUS;
+    use crate::gadgets::soundness::{assert_rejected, assert_rejects_forged_product, ROUNDS};
+    use crate::gadgets::trace::{pad_trace, MIN_TRACE_HEIGHT};
+    use crate::params::{ParamError, P1};
 
     #[test]
     fn test_poly_mul() -> Result<(), impl Debug> {
 
         let ZkConfig { config, byte_hash } = initialize_config();
 
-        // generate 2 random input polynomials with N coefficients in the range of [0, N]
+        // generate 2 random input polynomials with n coefficients in the range of [0, P1)
+        let n = 16;
         let mut rng = thread_rng();
-        let random_poly1: Vec<u32> = (0..N).map(|_| {
+        let random_poly1: Vec<u32> = (0..n).map(|_| {
             rng.gen_range(0..P1)
         }).collect();
 
-        let random_poly2: Vec<u32> = (0..N).map(|_| {
+        let random_poly2: Vec<u32> = (0..n).map(|_| {
             rng.gen_range(0..P1)
         }).collect();
 
-        let air = PolyMulAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1};
+        let air = PolyMulAir::with_n(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
 
-        let trace = generate_polymul_trace::<Val>(random_poly1, random_poly2, P1);
+        let trace = generate_polymul_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
 
         let mut challenger: SerializingChallenger32<Mersenne31, HashChallenger<u8, Keccak256Hash, 32>> = Challenger::from_hasher(vec![], byte_hash);
 
@@ -248,4 +966,468 @@ mod tests {
         let mut challenger = Challenger::from_hasher(vec![], byte_hash);
         verify(&config, &air, &mut challenger, &proof, &vec![])
     }
+
+    #[test]
+    fn test_poly_mul_wrong_output() {
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let random_poly2: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let air = PolyMulAir::with_n(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+
+        // corrupt out[0], which lives right after the 2 input polynomials in the first row
+        let mut trace = generate_polymul_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        trace.values[2*n] += Val::one();
+        assert_rejected(&air, trace, "a wrong output polynomial");
+
+        // a wrong out[k] with q[k] recomputed for it, so that a(x) * b(x) === out(x) + mod * q(x) still holds mod n
+        let trace = pad_trace(polymul_row(&random_poly1, &random_poly2, P1).0, polymul_width(n));
+        assert_rejects_forged_product(&air, &trace, |_| P1);
+    }
+
+    #[test]
+    #[should_panic(expected = "a(x) * b(x) != out(x)")]
+    fn test_polymul_identity_check_fires() {
+        let n = 16;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // the honest output passes
+        let (out, _) = polymul_coeffs(&a, &b, P1);
+        let mut out: Vec<u32> = out.iter().map(|&c| c as u32).collect();
+        debug_assert_polymul_identity(&a, &b, &out, P1);
+
+        // one corrupted coefficient is caught
+        out[n] = (out[n] + 1) % P1;
+        debug_assert_polymul_identity(&a, &b, &out, P1);
+    }
+
+    #[test]
+    fn test_poly_mul_reduces_wide_coefficients() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // maximal coefficients: every convolution sum is far beyond both P1 and the native field
+        let n = 16;
+        let poly1: Vec<u32> = vec![P1 - 1; n];
+        let poly2: Vec<u32> = vec![P1 - 1; n];
+
+        let air = PolyMulAir::with_n(poly1.clone(), poly2.clone(), P1, n).unwrap();
+
+        let trace = generate_polymul_trace::<Val>(poly1, poly2, P1, n).unwrap();
+
+        // out[k] = (k+1) * (P1-1)^2 mod P1 = k+1 for k < N, and 2N-1-k for k >= N
+        let row = trace.row_slice(0);
+        for k in 0..2*n-1 {
+            let terms = if k < n { k + 1 } else { 2*n - 1 - k };
+            assert!(terms as u128 * ((P1 - 1) as u128).pow(2) > Mersenne31::ORDER_U32 as u128);
+            assert_eq!(row[2*n + k], Val::from_canonical_usize(terms % P1 as usize));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_poly_mul_runtime_n() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let mut rng = thread_rng();
+        for n in [4, 16, 64] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| {
+                rng.gen_range(0..P1)
+            }).collect();
+
+            let random_poly2: Vec<u32> = (0..n).map(|_| {
+                rng.gen_range(0..P1)
+            }).collect();
+
+            let air = PolyMulAir::with_n(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+
+            let trace = generate_polymul_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_poly_mul_scalar() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // N = 1: a[0] * b[0] mod P1, with a product far beyond the native field
+        let (a, b) = (P1 - 1, P1 - 2);
+        let air = PolyMulAir::with_n(vec![a], vec![b], P1, 1).unwrap();
+
+        let trace = generate_polymul_trace::<Val>(vec![a], vec![b], P1, 1).unwrap();
+        assert_eq!((trace.width(), trace.height()), (polymul_width(1), 4));
+        let product = a as u64 * b as u64;
+        assert_eq!(trace.row_slice(0)[2], Val::from_canonical_u64(product % P1 as u64));
+        assert_eq!(trace.row_slice(0)[3], Val::from_wrapped_u64(product / P1 as u64));
+        assert_eq!(generate_polymul_trace_streaming::<Val>(vec![a], vec![b], P1, 1).unwrap().values, trace.values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        // N = 0 is rejected instead of underflowing 2N-1
+        assert_eq!(generate_polymul_trace::<Val>(vec![], vec![], P1, 0).unwrap_err(), GadgetError::EmptyPolynomial);
+    }
+
+    #[test]
+    fn test_poly_mul_zero_and_monomial_operands() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // the zero polynomial, 5 * X^3, and (P1-1) * X^{N-1} whose products reach the top coefficient
+        let mut monomial = vec![0u32; n];
+        monomial[3] = 5;
+        let mut top = vec![0u32; n];
+        top[n-1] = P1 - 1;
+
+        for operand in [vec![0u32; n], monomial, top] {
+            for (a, b) in [(operand.clone(), random_poly.clone()), (random_poly.clone(), operand.clone())] {
+                // the fast path agrees with the full convolution
+                assert_eq!(polymul_coeffs(&a, &b, P1), polymul_coeffs_serial(&a, &b, P1));
+
+                let air = PolyMulAir::with_n(a.clone(), b.clone(), P1, n).unwrap();
+                let trace = generate_polymul_trace::<Val>(a, b, P1, n).unwrap();
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+            }
+        }
+    }
+
+    #[test]
+    #[ignore] // microbenchmark: cargo test --release -- --ignored --nocapture bench_poly_mul_eval
+    fn bench_poly_mul_eval() {
+        let mut rng = thread_rng();
+        for n in [64, 128, 256] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let air = PolyMulAir::with_n(random_poly1, random_poly2, P1, n).unwrap();
+
+            let start = Instant::now();
+            let constraints = get_symbolic_constraints::<Val, _>(&air, 0, 0);
+            println!("n = {:>4}: {} constraints evaluated in {:?}", n, constraints.len(), start.elapsed());
+        }
+    }
+
+    #[test]
+    fn test_poly_mul_checked_constructor() {
+        let air = PolyMulAir::with_n(vec![1, 2], vec![3, 4], P1, 2).unwrap();
+        assert_eq!(BaseAir::<Val>::width(&air), polymul_width(2));
+        assert_eq!(air.layout().width(), polymul_width(2));
+
+        // a or b without n coefficients, a zero modulus, and the empty product that has no 2N-1 outputs
+        assert_eq!(PolyMulAir::with_n(vec![1], vec![3, 4], P1, 2).err(), Some(GadgetError::LengthMismatch { expected: 2, actual: 1 }));
+        assert_eq!(PolyMulAir::with_n(vec![1, 2], vec![], P1, 2).err(), Some(GadgetError::LengthMismatch { expected: 2, actual: 0 }));
+        assert_eq!(PolyMulAir::with_n(vec![1, 2], vec![3, 4], 0, 2).err(), Some(GadgetError::ZeroModulus));
+        assert_eq!(PolyMulAir::with_n(vec![], vec![], P1, 0).err(), Some(GadgetError::EmptyPolynomial));
+
+        // the bounds of the reduction argument: a 31-bits modulus and at most MUL_MAX_N coefficients
+        assert_eq!(PolyMulAir::with_n(vec![1, 2], vec![3, 4], 1 << 31, 2).err(), Some(GadgetError::ModulusTooLarge { modulus: 1 << 31, max: MAX_RANGE_CHECK_MODULUS }));
+        let n = MUL_MAX_N + 1;
+        assert_eq!(PolyMulAir::with_n(vec![0; n], vec![0; n], P1, n).err(), Some(GadgetError::TooManyCoefficients { n, max: MUL_MAX_N }));
+        assert_eq!(generate_polymul_trace::<Val>(vec![0; n], vec![0; n], P1, n).unwrap_err(), GadgetError::TooManyCoefficients { n, max: MUL_MAX_N });
+
+        // new() expects params::N coefficients, and with_params() the N of the set and a channel of it
+        assert_eq!(PolyMulAir::new(vec![0; 2], vec![0; 2], P1).err(), Some(GadgetError::LengthMismatch { expected: N, actual: 2 }));
+        let params = FheParams::new(8, vec![P1]).unwrap();
+        assert!(PolyMulAir::with_params(vec![0; 8], vec![0; 8], &params, 0).is_ok());
+        assert_eq!(PolyMulAir::with_params(vec![0; 2], vec![0; 8], &params, 0).err(), Some(GadgetError::LengthMismatch { expected: 8, actual: 2 }));
+        assert_eq!(PolyMulAir::with_params(vec![0; 8], vec![0; 8], &params, 1).err(), Some(GadgetError::Params(ParamError::ChannelOutOfRange { channel: 1, channels: 1 })));
+    }
+
+    #[test]
+    fn test_polymul_trace_fuzz() {
+        // arbitrary (a, b, modulus, n), biased towards the edge cases: empty and mismatched lengths,
+        // modulus = 0, 1 and above the native field, and coefficients at modulus - 1 and u32::MAX
+        let mut rng = thread_rng();
+        for _ in 0..2000 {
+            let n = rng.gen_range(0..6);
+            let moduli = [0, 1, 2, P1, (1 << 31) - 1, 1 << 31, u32::MAX, rng.gen()];
+            let modulus = moduli[rng.gen_range(0..moduli.len())];
+            let random_poly = |rng: &mut rand::rngs::ThreadRng| -> Vec<u32> {
+                let len = (n + rng.gen_range(0..3)).saturating_sub(1);
+                (0..len).map(|_| {
+                    let coeffs = [0, 1, modulus.wrapping_sub(1), u32::MAX, rng.gen(), rng.gen_range(0..modulus.max(1))];
+                    coeffs[rng.gen_range(0..coeffs.len())]
+                }).collect()
+            };
+            let (a, b) = (random_poly(&mut rng), random_poly(&mut rng));
+
+            for streaming in [false, true] {
+                let (a, b) = (a.clone(), b.clone());
+                let result = panic::catch_unwind(AssertUnwindSafe(|| if streaming {
+                    generate_polymul_trace_streaming::<Val>(a, b, modulus, n)
+                } else {
+                    generate_polymul_trace::<Val>(a, b, modulus, n)
+                }));
+                // never a panic: either an error, or a matrix of the AIR width and a power of two height
+                let result = result.unwrap_or_else(|_| panic!("trace generation panicked for modulus {} and n {}", modulus, n));
+                if let Ok(trace) = result {
+                    assert_eq!(trace.width(), polymul_width(n));
+                    assert!(trace.height().is_power_of_two() && trace.height() >= MIN_TRACE_HEIGHT);
+                    assert_eq!(trace.values.len(), trace.width() * trace.height());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_polymul_zero_modulus() {
+        // regression: reducing by modulus = 0 used to rely on every coefficient failing the range check first
+        assert_eq!(generate_polymul_trace::<Val>(vec![0; 4], vec![0; 4], 0, 4).unwrap_err(), GadgetError::ZeroModulus);
+        assert_eq!(generate_polymul_trace_streaming::<Val>(vec![0; 4], vec![0; 4], 0, 4).unwrap_err(), GadgetError::ZeroModulus);
+        assert_eq!(generate_polymul_trace::<Val>(vec![], vec![], 0, 0).unwrap_err(), GadgetError::EmptyPolynomial);
+    }
+
+    #[test]
+    fn test_polymul_coeffs_parallel_matches_serial() {
+        let mut rng = thread_rng();
+        for n in [1, 4, 255, 1024] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            assert_eq!(polymul_coeffs(&random_poly1, &random_poly2, P1), polymul_coeffs_serial(&random_poly1, &random_poly2, P1));
+        }
+    }
+
+    #[test]
+    #[ignore] // benchmark: cargo test --release --features rayon -- --ignored --nocapture bench_polymul_coeffs
+    fn bench_polymul_coeffs() {
+        let mut rng = thread_rng();
+        for n in [1024, 2048, N] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+            let start = Instant::now();
+            let serial = polymul_coeffs_serial(&random_poly1, &random_poly2, P1);
+            let serial_time = start.elapsed();
+
+            let start = Instant::now();
+            let parallel = polymul_coeffs(&random_poly1, &random_poly2, P1);
+            let parallel_time = start.elapsed();
+
+            assert_eq!(serial, parallel);
+            println!("n = {:>4}: serial {:?}, polymul_coeffs {:?} (rayon: {})", n, serial_time, parallel_time, cfg!(feature = "rayon"));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "packed")]
+    fn test_polymul_trace_packed_matches_scalar() {
+        let mut rng = thread_rng();
+        // lengths around multiples of every packing width, a monomial operand, and the largest coefficients
+        for n in [1, 3, 4, 7, 8, 15, 16, 17, 255] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let mut monomial = vec![0; n];
+            monomial[n-1] = P1 - 1;
+            for random_poly2 in [(0..n).map(|_| rng.gen_range(0..P1)).collect(), monomial, vec![P1 - 1; n]] {
+                let scalar = generate_polymul_trace::<Mersenne31>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+                let packed = generate_polymul_trace_packed(random_poly1.clone(), random_poly2, P1, n).unwrap();
+                assert_eq!(packed.width(), scalar.width());
+                assert_eq!(packed.values, scalar.values);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "packed")]
+    #[ignore] // benchmark: cargo test --release --features packed -- --ignored --nocapture bench_polymul_trace_packed
+    fn bench_polymul_trace_packed() {
+        let mut rng = thread_rng();
+        for n in [1024, 2048, N] {
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+            let start = Instant::now();
+            let scalar = generate_polymul_trace::<Mersenne31>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+            let scalar_time = start.elapsed();
+
+            let start = Instant::now();
+            let packed = generate_polymul_trace_packed(random_poly1, random_poly2, P1, n).unwrap();
+            let packed_time = start.elapsed();
+
+            assert_eq!(scalar.values, packed.values);
+            println!("n = {:>4}: scalar {:?}, packed {:?} ({} lanes)", n, scalar_time, packed_time,
+                <<Mersenne31 as Field>::Packing as PackedValue>::WIDTH);
+        }
+    }
+
+    #[test]
+    fn test_poly_mul_with_height() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = PolyMulAir::with_n(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+
+        // the same AIR proves the product at the default height and at 4 times as many rows
+        for height in [trace_height(1), 16] {
+            let trace = generate_polymul_trace_with_height::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n, height).unwrap();
+            assert_eq!(trace.height(), height);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_polymul_trace_streaming_matches() {
+        let n = 128;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let trace = generate_polymul_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        let streamed = generate_polymul_trace_streaming::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+        assert_eq!(streamed.width(), trace.width());
+        assert_eq!(streamed.values, trace.values);
+    }
+
+    #[test]
+    fn test_poly_mul_invalid_inputs() {
+        let poly: Vec<u32> = vec![1; 4];
+
+        // shorter and longer than n
+        assert_eq!(
+            generate_polymul_trace::<Val>(vec![1; 3], poly.clone(), P1, 4).unwrap_err(),
+            GadgetError::LengthMismatch { expected: 4, actual: 3 }
+        );
+        assert_eq!(
+            generate_polymul_trace::<Val>(poly.clone(), vec![1; 5], P1, 4).unwrap_err(),
+            GadgetError::LengthMismatch { expected: 4, actual: 5 }
+        );
+
+        // a coefficient equal to the modulus is out of range
+        assert_eq!(
+            generate_polymul_trace::<Val>(poly.clone(), vec![0, 0, P1, 0], P1, 4).unwrap_err(),
+            GadgetError::CoefficientOutOfRange { index: 2, value: P1, modulus: P1 }
+        );
+    }
+
+    #[test]
+    fn test_poly_mul_rejects_unreduced_output() {
+        // out[k] + p with q[k] - 1 satisfies the identity mod n, but out[k] + p is not in [0, p)
+        let n = 4;
+        let (a, b) = (vec![P1 - 1; n], vec![P1 - 1; n]);
+        let air = PolyMulAir::with_n(a.clone(), b.clone(), P1, n).unwrap();
+        let layout = MulLayout::new(n);
+        let (out, q) = polymul_coeffs(&a, &b, P1);
+
+        for k in [0, n, 2*n-2] {
+            let (mut row, _) = polymul_row::<Val>(&a, &b, P1);
+            let (out_k, q_k) = (out[k] as u32 + P1, q[k] as u64 - 1);
+            row[layout.out+k] = Val::from_canonical_u32(out_k);
+            row[layout.q+k] = Val::from_wrapped_u64(q_k);
+            assign_output_witness(&mut row, &layout, (&coeff_limbs(&a), &coeff_limbs(&b)), k, out_k, q_k, P1);
+            assert_rejected(&air, pad_trace(row, layout.width), &format!("an unreduced out[{}]", k));
+        }
+    }
+    #[test]
+    fn test_poly_mul_rows() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // a scalar product on a single slot, and 2N-1 = 31 coefficients over 6 slots of 8 rows
+        for n in [1, 16] {
+            let mut rng = thread_rng();
+            let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+            let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+            let air = PolyMulAir::with_shape(random_poly1.clone(), random_poly2.clone(), P1, n, MulShape::Rows).unwrap();
+            let trace = air.generate_trace::<Val>().unwrap();
+            assert_eq!(trace.width(), <PolyMulAir as BaseAir<Val>>::width(&air));
+
+            // the slots in row order are the coefficients of out
+            let (out, _) = polymul_coeffs(&random_poly1, &random_poly2, P1);
+            let public_values = air.public_outputs(&trace);
+            assert_eq!(public_values, out.iter().map(|&c| Val::from_canonical_u32(c as u32)).collect::<Vec<_>>());
+
+            // without and with the outputs bound to the public values
+            for public_values in [vec![], public_values.clone()] {
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace.clone(), &public_values);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &public_values).expect("verification failed");
+            }
+
+            // the proof does not verify against another out
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+            let mut wrong = public_values;
+            wrong[n-1] += Val::one();
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            assert!(verify(&config, &air, &mut challenger, &proof, &wrong).is_err());
+
+            // a wrong out[k] with q[k] recomputed for it, so that sum[k] === q[k] * p + out[k] still holds mod n
+            let honest = generate_polymul_rows_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+            let layout = MulRowsLayout::new(n);
+            for _ in 0..ROUNDS {
+                let (k, delta) = (rng.gen_range(0..2*n-1), rng.gen_range(1..P1));
+                let (u, v) = (k / layout.slots, k % layout.slots);
+                let mut forged = honest.clone();
+                let row = forged.row_mut(u);
+                let sum = convolution_sum(&random_poly1, &random_poly2, k);
+                let out = (row[layout.out+v].as_canonical_u32() + delta) % P1;
+                let q = (from_u128::<Val>(sum) - Val::from_canonical_u32(out)) * Val::from_canonical_u32(P1).inverse();
+                assign_rows_slot(row, &layout, v, sum, out, q.as_canonical_u64(), P1);
+                assert_rejected(&air, forged, &format!("a forged out[{}] + {}", k, delta));
+            }
+        }
+    }
+
+    #[test]
+    fn test_poly_mul_rows_memory() {
+        // the traces of both shapes for N = 1024
+        let n = 1024;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let row = generate_polymul_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        let rows = generate_polymul_rows_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+        // 616N-245 = 630539 columns over MIN_TRACE_HEIGHT rows, against 1 + 64 + 245 * 46 = 11335 columns over 64 rows
+        assert_eq!((row.width(), row.height()), (630539, MIN_TRACE_HEIGHT));
+        assert_eq!((rows.width(), rows.height()), (11335, 64));
+
+        // 2522156 field elements against 725440
+        let bytes = |trace: &RowMajorMatrix<Val>| trace.values.len() * size_of::<Val>();
+        assert!(3 * bytes(&rows) < bytes(&row));
+    }
 }
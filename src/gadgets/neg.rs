@@ -0,0 +1,177 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::{FheParams, ParamError, N};
+
+// Define AIR constraint inputs
+pub struct PolyNegAir {
+    pub a: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PolyNegAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, modulus: u32) -> Self {
+        Self { a, modulus, n: N }
+    }
+
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`
+    pub fn with_params(a: Vec<u32>, params: &FheParams, channel: usize) -> Result<Self, ParamError> {
+        Ok(Self { a, modulus: params.modulus(channel)?, n: params.n })
+    }
+}
+
+/*
+Polynomial Negation Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[N-1] * X^{N-1} where out[i] = (mod - a[i]) % mod
+
+Note:
+- PolyNegAir does not have a state transition. Values required for constraints are all stored in one row.
+- For a[i] in [0, mod), out[i] is either mod - a[i] (a[i] != 0) or 0 (a[i] == 0, rather than mod).
+Since a is fixed by the AIR, the case is known when the constraints are built:
+    a[i] != 0: a[i] + out[i] === mod
+    a[i] == 0: out[i] === 0
+Both sides are below 2 * mod < n, so the identities hold over the integers, and no quotient or borrow column is needed.
+- The output constraints are enforced on the first row only, where the inputs are pinned.
+*/
+impl<F: Field> BaseAir<F> for PolyNegAir {
+    // Air Table looks like this
+    // row:[      a: N      ][mod:1][      out: N      ]
+    //     ^--------inputs--------^^-calculated by generate_polyneg_trace
+    //     [0..............................................0]
+    //     [0..............................................0]
+    //     [0..............................................0]
+    fn width(&self) -> usize {
+        2*self.n+1
+    }
+}
+
+impl GadgetLayout for PolyNegAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PolyNegAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a as input polynomial and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+        builder.when_first_row().assert_eq(row[n], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce a[i] + out[i] === mod, or out[i] === 0 when a[i] == 0
+        for i in 0..n {
+            if self.a[i] == 0 {
+                builder.when_first_row().assert_zero(row[i+n+1]);
+            } else {
+                builder.when_first_row().assert_eq(row[i] + row[i+n+1], row[n]);
+            }
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_polyneg_trace<F: Field>(a:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+
+    let mut values: Vec<F>= Vec::with_capacity(MIN_TRACE_HEIGHT * (2*n+1));
+
+    // Add input polynomial and modulus to values vector
+    for i in 0..n {
+        values.push(F::from_canonical_u32(a[i]));
+    }
+    values.push(F::from_canonical_u32(modulus));
+
+    // Negate the polynomial and push it to values vector
+    for i in 0..n {
+        values.push(F::from_canonical_u32((modulus - a[i]) % modulus));
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 2*n+1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_poly_neg() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate a random input polynomial with n coefficients in the range of [0, P1), including 0 and P1-1
+        let n = 16;
+        let mut rng = thread_rng();
+        let mut random_poly: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+        random_poly[0] = 0;
+        random_poly[1] = P1 - 1;
+
+        let air = PolyNegAir { a:random_poly.clone(), modulus:P1, n };
+
+        let trace = generate_polyneg_trace::<Val>(random_poly.clone(), P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        assert_eq!(row[n+1], Val::zero());
+        assert_eq!(row[n+2], Val::one());
+        for i in 2..n {
+            assert_eq!(row[i+n+1], Val::from_canonical_u32(P1 - random_poly[i]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_poly_neg_zero_is_not_modulus() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 4;
+        let air = PolyNegAir { a:vec![0, 1, 2, 3], modulus:P1, n };
+
+        // claim -0 = P1, which is congruent but not reduced
+        let mut trace = generate_polyneg_trace::<Val>(air.a.clone(), P1, n).unwrap();
+        trace.values[n+1] = Val::from_canonical_u32(P1);
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof with -0 = P1 was accepted");
+    }
+}
@@ -0,0 +1,269 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use tracing::info_span;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{MulShape, PolyMulAir, check_polymul_bounds, polymul_coeffs, polymul_row, polymul_width};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{eval_range_checks, range_check_witness, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::params::{FheParams, ParamError, N};
+
+// Define AIR constraint inputs
+pub struct NegacyclicMulAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl NegacyclicMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`
+    pub fn with_params(a: Vec<u32>, b: Vec<u32>, params: &FheParams, channel: usize) -> Result<Self, ParamError> {
+        Ok(Self { a, b, modulus: params.modulus(channel)?, n: params.n })
+    }
+}
+
+/*
+Negacyclic Polynomial Multiplication Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[2N-2] * X^{2N-2}: raw product, proven by PolyMulAir
+- reduced = reduced[0] + reduced[1] * X + ... + reduced[N-1] * X^{N-1}: out reduced in Z_mod[X]/(X^N+1)
+
+Note:
+- Since X^N = -1 in the negacyclic ring, out[i+N] * X^{i+N} folds back into -out[i+N] * X^i:
+    reduced[i] = (out[i] - out[i+N]) % mod  for i = [0..N-1)
+    reduced[N-1] = out[N-1]                 (the raw product has no X^{2N-1} term)
+- The raw product columns are laid out exactly like PolyMulAir, so its constraints are reused as-is,
+and the folding is proven with a borrow bit per coefficient like PolySubAir.
+- reduced is range checked into [0, mod): out[i] and out[i+N] are, so the folding identity holds over the integers,
+and a wrong borrow cannot shift reduced[i] by mod.
+*/
+impl<F: Field> BaseAir<F> for NegacyclicMulAir {
+    // Air Table looks like this
+    // row:[ a: N ][ b: N ][ out(x): 2N-1 ][ q(x): 2N-1 ][ witness ][ reduced: N ][ borrow: N-1 ][ reduced range checks: 62 * N ]
+    //     ^----------------------PolyMulAir-----------------------^^-------------calculated by generate_negacyclic_mul_trace-------------^
+    //     [0.........................................................................................................................0]
+    //     [0.........................................................................................................................0]
+    //     [0.........................................................................................................................0]
+    fn width(&self) -> usize {
+        negacyclic_width(self.n)
+    }
+}
+
+impl GadgetLayout for NegacyclicMulAir {
+    fn layout(&self) -> TraceLayout {
+        negacyclic_layout(self.n)
+    }
+}
+
+// Layout of NegacyclicMulAir, shared with the gadgets embedding it
+pub(crate) fn negacyclic_layout(n: usize) -> TraceLayout {
+    TraceLayout::new()
+        .push("a", n)
+        .push("b", n)
+        .push("out", 2*n-1)
+        .push("q", 2*n-1)
+        .push("witness", polymul_width(n) - (6*n-2))
+        .push("reduced", n)
+        .push("borrow", n-1)
+        .push("reduced_range", RANGE_CHECK_WIDTH*n)
+}
+
+// Width of the NegacyclicMulAir row with n coefficients
+pub(crate) fn negacyclic_width(n: usize) -> usize {
+    polymul_width(n) + 2*n-1 + RANGE_CHECK_WIDTH*n
+}
+
+// Column of reduced[0] within the NegacyclicMulAir row, right after the PolyMulAir columns
+pub(crate) fn negacyclic_reduced(n: usize) -> usize {
+    polymul_width(n)
+}
+
+// Column of the range check of reduced[0] within the NegacyclicMulAir row, a block of RANGE_CHECK_WIDTH columns per coefficient
+pub(crate) fn negacyclic_reduced_range(n: usize) -> usize {
+    negacyclic_reduced(n) + 2*n-1
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for NegacyclicMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+        self.eval_row(builder, &row);
+    }
+}
+
+impl NegacyclicMulAir {
+    // Enforce the negacyclic multiplication constraints over `row`, which starts at the a[0] column,
+    // so other gadgets can embed the NegacyclicMulAir layout at any column offset
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        // Enforce self.a and self.b as the input polynomials
+        let mul = PolyMulAir { a: self.a.clone(), b: self.b.clone(), modulus: self.modulus, n: self.n, shape: MulShape::Row };
+        mul.eval_inputs(builder, row);
+
+        self.eval_product(builder, row);
+    }
+
+    // eval_row() without pinning a and b to self.a and self.b, for gadgets whose operands are witnesses,
+    // such as a secret key, or are computed in their own trace: self.a and self.b are not read
+    pub(crate) fn eval_product<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+
+        // Enforce the raw product a(x) * b(x) === out(x) + mod * q(x)
+        let mul = PolyMulAir { a: vec![], b: vec![], modulus: self.modulus, n, shape: MulShape::Row };
+        mul.eval_op(builder, row);
+
+        let out = 2*n;
+        let reduced = negacyclic_reduced(n);
+        let borrow = reduced + n;
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+
+        // Enforce out[i] + borrow[i] * mod === out[i+N] + reduced[i] for i = [0..N-1)
+        for i in 0..n-1 {
+            builder.assert_bool(row[borrow+i]);
+            builder.assert_eq(row[out+i] + row[borrow+i] * modulus, row[out+i+n] + row[reduced+i]);
+        }
+
+        // Enforce reduced[N-1] === out[N-1]
+        builder.assert_eq(row[reduced+n-1], row[out+n-1]);
+
+        // Enforce 0 <= reduced[i] < mod
+        let reduced_range = negacyclic_reduced_range(n);
+        let values: Vec<AB::Expr> = (0..n).map(|i| row[reduced+i].into()).collect();
+        eval_range_checks(builder, &values, &row[reduced_range..reduced_range + RANGE_CHECK_WIDTH*n], self.modulus);
+    }
+}
+
+// a * b in Z_mod[X]/(X^N+1), i.e. the reduced columns of generate_negacyclic_mul_trace
+pub(crate) fn negacyclic_coeffs(a: &[u32], b: &[u32], modulus: u32) -> Vec<u32> {
+    let n = a.len();
+    let (out, _) = polymul_coeffs(a, b, modulus);
+    (0..n).map(|i| {
+        // X^N = -1 folds out[i+N] back into out[i] with the negative sign
+        let high = if i + n < 2*n-1 { out[i+n] } else { 0 };
+        ((out[i] + modulus as u128 - high) % modulus as u128) as u32
+    }).collect()
+}
+
+// Define a function to generate execution trace
+pub fn generate_negacyclic_mul_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let _span = info_span!("trace_generation", n).entered();
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    check_polymul_bounds(modulus, n)?;
+
+    // Assign the input polynomials, the raw product and its quotients, as in generate_polymul_trace
+    let (mut values, out) = polymul_row::<F>(&a, &b, modulus);
+
+    // Fold out[N..2N-1) back into out[0..N-1) with the negative sign
+    let mut borrow: Vec<bool> = Vec::with_capacity(n-1);
+    let mut reduced: Vec<u32> = Vec::with_capacity(n);
+    for i in 0..n-1 {
+        borrow.push(out[i] < out[i+n]);
+        reduced.push(((out[i] + modulus as u128 - out[i+n]) % modulus as u128) as u32);
+    }
+    reduced.push(out[n-1] as u32);
+    values.extend(reduced.iter().map(|&c| F::from_canonical_u32(c)));
+
+    for i in 0..n-1 {
+        values.push(F::from_bool(borrow[i]));
+    }
+
+    // Assign the range checks of reduced
+    values.extend(range_check_witness::<F>(&reduced, modulus));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, negacyclic_width(n)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use rand::{thread_rng, Rng};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::gadgets::soundness::{assert_rejected, assert_rejects_forged_product};
+    use crate::params::P1;
+
+    #[test]
+    fn test_negacyclic_mul() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // a = 1 + 2 * X^{N-1}, b = 1 + 5 * X
+        // a * b = 1 + 5 * X + 2 * X^{N-1} + 10 * X^N
+        //       = (1 - 10) + 5 * X + 2 * X^{N-1}   in Z_P1[X]/(X^N+1)
+        let n = 16;
+        let mut a = vec![0u32; n];
+        let mut b = vec![0u32; n];
+        a[0] = 1;
+        a[n-1] = 2;
+        b[0] = 1;
+        b[1] = 5;
+
+        let mut expected = vec![0u32; n];
+        expected[0] = P1 - 9;
+        expected[1] = 5;
+        expected[n-1] = 2;
+        assert_eq!(negacyclic_mul_ref(&a, &b, P1, n), expected);
+
+        let air = NegacyclicMulAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+
+        let trace = generate_negacyclic_mul_trace::<Val>(a, b, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            assert_eq!(row[negacyclic_reduced(n)+i], Val::from_canonical_u32(expected[i]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_negacyclic_mul_forged_product() {
+        let n = 8;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = NegacyclicMulAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+
+        // a wrong raw product with re-solved quotients, folded into a consistent reduced
+        assert_rejects_forged_product(&air, &generate_negacyclic_mul_trace(a, b, P1, n).unwrap(), |_| P1);
+    }
+
+    #[test]
+    fn test_negacyclic_mul_rejects_unreduced_fold() {
+        let n = 4;
+        let (a, b) = (vec![1, 0, 0, 0], vec![3, 0, 0, 0]);
+        let air = NegacyclicMulAir { a:a.clone(), b:b.clone(), modulus:P1, n };
+
+        // reduced[0] + mod with borrow[0] = 1 satisfies the folding identity, but is not in [0, mod)
+        let mut trace = generate_negacyclic_mul_trace::<Val>(a, b, P1, n).unwrap();
+        let (reduced, borrow) = (negacyclic_reduced(n), negacyclic_reduced(n) + n);
+        trace.values[reduced] += Val::from_canonical_u32(P1);
+        trace.values[borrow] = Val::one();
+        let block = borrow + n-1;
+        let witness = range_check_witness::<Val>(&[3 + P1], P1);
+        trace.values[block..block + RANGE_CHECK_WIDTH].copy_from_slice(&witness[..RANGE_CHECK_WIDTH]);
+        assert_rejected(&air, trace, "an unreduced reduced[0]");
+    }
+}
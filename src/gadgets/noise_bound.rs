@@ -0,0 +1,198 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct NoiseBoundAir {
+    pub poly: Vec<u32>,
+    // inclusive bound on the centered coefficients, below mod / 2
+    pub bound: u32,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl NoiseBoundAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, bound: u32, modulus: u32) -> Self {
+        Self { poly, bound, modulus, n: N }
+    }
+}
+
+/*
+Infinity-Norm Bound Air
+Input:
+- e = e[0] + e[1] * X + ... + e[N-1] * X^{N-1} mod q (e.g. a noise polynomial)
+- bound: B < q/2
+Output:
+- none: proves -B <= centered(e[i]) <= B for every coefficient
+
+Note:
+- The centered representative maps [0, q/2] to itself and (q/2, q) to negative values e[i] - q.
+It is written in the balanced representation as a sign bit neg[i] and a magnitude mag[i]:
+    neg[i] = 0: e[i] = mag[i]
+    neg[i] = 1: e[i] = q - mag[i]
+i.e. e[i] === mag[i] + neg[i] * (q - 2 * mag[i]), and |centered(e[i])| = mag[i].
+- mag[i] <= B is proven as mag[i] < B + 1 with the bit comparison of range_check.rs.
+- Every value in the identity is below q < n, so it holds over the integers. Since B < q/2, the decomposition is unique.
+*/
+impl<F: Field> BaseAir<F> for NoiseBoundAir {
+    // Air Table looks like this
+    // row:[ e: N ][ neg: N ][ mag: N ][ mag bits: 31N ][ mag eq: 31N ]
+    //     ^input^^---------calculated by generate_noise_bound_trace---------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        (3 + 2*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for NoiseBoundAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("e", self.n)
+            .push("neg", self.n)
+            .push("mag", self.n)
+            .push("mag_bits", RANGE_CHECK_BITS*self.n)
+            .push("mag_eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for NoiseBoundAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (e, neg, mag) = (0, n, 2*n);
+        let bits = 3*n;
+        let eq = bits + RANGE_CHECK_BITS*n;
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+
+        // Enforce self.poly as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[e+i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+
+        for i in 0..n {
+            // Enforce e[i] === mag[i] + neg[i] * (q - 2 * mag[i])
+            builder.assert_bool(row[neg+i]);
+            builder.assert_eq(row[e+i], row[mag+i] + row[neg+i] * (AB::Expr::from(modulus) - row[mag+i] * AB::F::two()));
+
+            // Enforce 0 <= mag[i] < bound + 1
+            let bits = bits + i*RANGE_CHECK_BITS;
+            let eq = eq + i*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[mag+i].into(), &row[bits..bits+RANGE_CHECK_BITS], &row[eq..eq+RANGE_CHECK_BITS], self.bound + 1);
+        }
+    }
+}
+
+// Sign and magnitude of the centered representative of c mod modulus
+pub fn center(c: u32, modulus: u32) -> (bool, u32) {
+    if c <= modulus / 2 { (false, c) } else { (true, modulus - c) }
+}
+
+// Define a function to generate execution trace
+// Coefficients are not checked against the bound here: proving that is the job of this gadget
+pub fn generate_noise_bound_trace<F: Field>(poly: Vec<u32>, bound: u32, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+    // bound < q/2 keeps the balanced representation unique
+    check_poly(&[bound], 1, modulus / 2)?;
+
+    let width = (3 + 2*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial, signs and magnitudes to the row
+    let centered: Vec<(bool, u32)> = poly.iter().map(|&c| center(c, modulus)).collect();
+    row.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    row.extend(centered.iter().map(|&(neg, _)| F::from_bool(neg)));
+    row.extend(centered.iter().map(|&(_, mag)| F::from_canonical_u32(mag)));
+
+    // Assign bits and prefix equality flags of every magnitude against bound + 1
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = centered.iter().map(|&(_, mag)| range_check_columns(mag, bound + 1)).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    // small noise: coefficients in [-bound, bound], with both ends
+    fn random_noise(n: usize, bound: u32) -> Vec<u32> {
+        let mut rng = thread_rng();
+        let mut noise: Vec<u32> = (0..n).map(|_| {
+            let e: i64 = rng.gen_range(-(bound as i64)..=bound as i64);
+            e.rem_euclid(P1 as i64) as u32
+        }).collect();
+        noise[0] = bound;
+        noise[1] = P1 - bound;
+        noise[2] = 0;
+        noise
+    }
+
+    #[test]
+    fn test_noise_bound() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let bound = 1 << 10;
+        let noise = random_noise(n, bound);
+
+        let air = NoiseBoundAir { poly:noise.clone(), bound, modulus:P1, n };
+
+        let trace = generate_noise_bound_trace::<Val>(noise, bound, P1, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_noise_bound_exceeded() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let bound = 1 << 10;
+        // one coefficient just past the bound on each side
+        for bad in [bound + 1, P1 - bound - 1] {
+            let mut noise = random_noise(n, bound);
+            noise[n/2] = bad;
+
+            let air = NoiseBoundAir { poly:noise.clone(), bound, modulus:P1, n };
+            let trace = generate_noise_bound_trace::<Val>(noise, bound, P1, n).unwrap();
+
+            // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+            }));
+            assert!(!matches!(result, Ok(true)), "proof with coefficient {} outside [-{}, {}] was accepted", bad, bound, bound);
+        }
+    }
+}
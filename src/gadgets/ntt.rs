@@ -0,0 +1,231 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use tracing::info_span;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::ntt_mul::{ntt_powers, reduce_sum, root_of_unity};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::gadgets::utils::mod_inv;
+
+// Define AIR constraint inputs
+pub struct NttAir {
+    pub input: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize,
+    // primitive N-th root of unity mod `modulus`
+    pub root: u32
+}
+
+impl NttAir {
+    pub fn new(input: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        let root = root_of_unity(modulus, n)?;
+        Ok(Self { input, modulus, n, root })
+    }
+
+    // Matrix of the forward transform: X[k] = sum_j w^{jk} * x[j]
+    fn matrix(&self) -> Vec<Vec<u32>> {
+        let powers = ntt_powers(self.root, self.n, self.modulus);
+        (0..self.n).map(|k| (0..self.n).map(|j| powers[j * k % self.n]).collect()).collect()
+    }
+}
+
+// Define AIR constraint inputs
+pub struct InttAir {
+    pub input: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize,
+    // primitive N-th root of unity mod `modulus`, the same one as the forward NttAir
+    pub root: u32,
+    // N^{-1} mod `modulus`, only depending on N and the modulus
+    pub n_inv: u32
+}
+
+impl InttAir {
+    pub fn new(input: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        let root = root_of_unity(modulus, n)?;
+        let n_inv = mod_inv(n as u64, modulus as u64) as u32;
+        Ok(Self { input, modulus, n, root, n_inv })
+    }
+
+    // Matrix of the inverse transform, with the N^{-1} scaling folded in: x[j] = sum_k N^{-1} * w^{-jk} * X[k]
+    fn matrix(&self) -> Vec<Vec<u32>> {
+        let n = self.n;
+        let p = self.modulus as u64;
+        let powers = ntt_powers(self.root, n, self.modulus);
+        (0..n).map(|j| (0..n).map(|k| (powers[(n - j * k % n) % n] as u64 * self.n_inv as u64 % p) as u32).collect()).collect()
+    }
+}
+
+/*
+NTT / Inverse NTT Air
+Input:
+- x = x[0], ..., x[N-1] mod p
+- mod: NTT-friendly FHE ciphertext modulus (P1, P2 or P3) with N | p-1
+Output:
+- NttAir:  X[k] = sum_j x[j] * w^{jk} mod p
+- InttAir: x[j] = N^{-1} * sum_k X[k] * w^{-jk} mod p
+where w is a primitive N-th root of unity mod p, so InttAir inverts NttAir.
+
+Note:
+- Both transforms are dense matrix-vector products with constant entries,
+proven with one quotient column per output: sum === q * p + out (mod n).
+- The scaling by N^{-1} only depends on N and p, so it is computed once in InttAir::new and folded into the matrix entries.
+*/
+impl<F: Field> BaseAir<F> for NttAir {
+    // Air Table looks like this
+    // row:[ x: N ][ X: N ][ q: N ]
+    //     ^input^^-calculated by generate_ntt_trace-^
+    //     [0......................0]
+    //     [0......................0]
+    //     [0......................0]
+    fn width(&self) -> usize {
+        3*self.n
+    }
+}
+
+impl GadgetLayout for NttAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("x", self.n)
+            .push("X", self.n)
+            .push("q", self.n)
+    }
+}
+
+impl<F: Field> BaseAir<F> for InttAir {
+    // Air Table looks like this
+    // row:[ X: N ][ x: N ][ q: N ]
+    //     ^input^^-calculated by generate_intt_trace-^
+    //     [0......................0]
+    //     [0......................0]
+    //     [0......................0]
+    fn width(&self) -> usize {
+        3*self.n
+    }
+}
+
+impl GadgetLayout for InttAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("X", self.n)
+            .push("x", self.n)
+            .push("q", self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for NttAir {
+    fn eval(&self, builder: &mut AB) {
+        eval_transform(builder, &self.input, &self.matrix(), self.modulus);
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for InttAir {
+    fn eval(&self, builder: &mut AB) {
+        eval_transform(builder, &self.input, &self.matrix(), self.modulus);
+    }
+}
+
+// Enforce out[k] === sum_j matrix[k][j] * input[j] mod p over the [input][out][q] layout
+fn eval_transform<AB: AirBuilder>(builder: &mut AB, input: &[u32], matrix: &[Vec<u32>], modulus: u32) {
+    let n = input.len();
+    let main = builder.main();
+    let row = main.row_slice(0);
+    let (out, q) = (n, 2*n);
+    let modulus = AB::F::from_canonical_u32(modulus);
+
+    // Enforce the input
+    for j in 0..n {
+        builder.when_first_row().assert_eq(row[j], AB::Expr::from_canonical_u32(input[j]));
+    }
+
+    // Enforce sum_j matrix[k][j] * input[j] === q[k] * mod + out[k]
+    for k in 0..n {
+        let mut sum = AB::Expr::zero();
+        for j in 0..n {
+            sum = sum + row[j] * AB::F::from_canonical_u32(matrix[k][j]);
+        }
+        builder.assert_eq(sum, row[q+k] * modulus + row[out+k]);
+    }
+}
+
+// [input][out][q] followed by 3 zero rows
+fn transform_trace<F: Field>(input: &[u32], matrix: &[Vec<u32>], modulus: u32) -> RowMajorMatrix<F> {
+    let n = input.len();
+    let _span = info_span!("trace_generation", n).entered();
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * 3*n);
+
+    values.extend(input.iter().map(|&x| F::from_canonical_u32(x)));
+    let outputs: Vec<(u32, u64)> = (0..n).map(|k| reduce_sum((0..n).map(|j| matrix[k][j] as u128 * input[j] as u128), modulus)).collect();
+    values.extend(outputs.iter().map(|&(r, _)| F::from_canonical_u32(r)));
+    values.extend(outputs.iter().map(|&(_, q)| F::from_wrapped_u64(q)));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    pad_trace(values, 3*n)
+}
+
+// Define a function to generate execution trace
+pub fn generate_ntt_trace<F: Field>(input: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&input, n, modulus)?;
+    let air = NttAir::new(input, modulus, n)?;
+    Ok(transform_trace(&air.input, &air.matrix(), modulus))
+}
+
+// Define a function to generate execution trace
+pub fn generate_intt_trace<F: Field>(input: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&input, n, modulus)?;
+    let air = InttAir::new(input, modulus, n)?;
+    Ok(transform_trace(&air.input, &air.matrix(), modulus))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField32;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::{P1, P2, P3};
+
+    #[test]
+    fn test_ntt_then_intt_is_identity() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        for modulus in [P1, P2, P3] {
+            let input: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+
+            // forward transform
+            let ntt = NttAir::new(input.clone(), modulus, n).unwrap();
+            let trace = generate_ntt_trace::<Val>(input.clone(), modulus, n).unwrap();
+            let transformed: Vec<u32> = trace.row_slice(0)[n..2*n].iter().map(|x| x.as_canonical_u32()).collect();
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &ntt, &mut challenger, trace, &vec![]);
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &ntt, &mut challenger, &proof, &vec![]).expect("NTT verification failed");
+
+            // inverse transform of the forward output
+            let intt = InttAir::new(transformed.clone(), modulus, n).unwrap();
+            let trace = generate_intt_trace::<Val>(transformed, modulus, n).unwrap();
+            let restored: Vec<u32> = trace.row_slice(0)[n..2*n].iter().map(|x| x.as_canonical_u32()).collect();
+            assert_eq!(restored, input);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &intt, &mut challenger, trace, &vec![]);
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &intt, &mut challenger, &proof, &vec![]).expect("INTT verification failed");
+        }
+    }
+
+    #[test]
+    fn test_intt_scaling() {
+        // N * N^{-1} = 1 mod p
+        let intt = InttAir::new(vec![0; 16], P1, 16).unwrap();
+        assert_eq!(16 * intt.n_inv as u64 % P1 as u64, 1);
+    }
+}
@@ -0,0 +1,466 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_nonempty, check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_wide_reduction, eval_wide_reduction, range_checked_limbs, COEFF_LIMBS, CRT_LIMBS, MUL_CARRY_BITS, MUL_CRT_BITS, MUL_MAX_N};
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_checks, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::gadgets::roots::nth_root_of_unity;
+use crate::gadgets::utils::mod_inv;
+
+// Define AIR constraint inputs
+pub struct NttMulAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize,
+    // primitive L-th root of unity mod `modulus`, where L = ntt_size(n)
+    pub root: u32
+}
+
+impl NttMulAir {
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        check_nttmul_bounds(modulus, n)?;
+        check_poly(&a, n, modulus)?;
+        check_poly(&b, n, modulus)?;
+        let root = root_of_unity(modulus, ntt_size(n))?;
+        Ok(Self { a, b, modulus, n, root })
+    }
+}
+
+// NTT length L: the smallest power of two that holds the 2N-1 coefficients of the product without wrapping around
+pub fn ntt_size(n: usize) -> usize {
+    (2*n-1).next_power_of_two()
+}
+
+// Largest number of coefficients: ntt_size(n) <= MUL_MAX_N keeps every stage within the bounds of PolyMulAir
+pub const NTT_MUL_MAX_N: usize = MUL_MAX_N / 2;
+
+// Check the bounds the reduction argument relies on: a modulus of at most 31 bits and at most NTT_MUL_MAX_N coefficients
+fn check_nttmul_bounds(modulus: u32, n: usize) -> Result<(), GadgetError> {
+    check_nonempty(n)?;
+    check_range_modulus(modulus)?;
+    if n > NTT_MUL_MAX_N {
+        return Err(GadgetError::TooManyCoefficients { n, max: NTT_MUL_MAX_N });
+    }
+    Ok(())
+}
+
+// Primitive `size`-th root of unity g^((p-1)/size) mod p, from the documented generator of p
+pub fn root_of_unity(modulus: u32, size: usize) -> Result<u32, GadgetError> {
+    nth_root_of_unity(modulus, size).ok_or(GadgetError::UnsupportedNttSize { size, modulus })
+}
+
+/*
+NTT Polynomial Multiplication Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: NTT-friendly FHE ciphertext modulus (P1, P2 or P3)
+Output:
+- out = out[0] + out[1] * X + ... + out[L-1] * X^{L-1}, where out[2N-1..L) = 0
+
+Note:
+- With w a primitive L-th root of unity mod p (L >= 2N-1), the product is computed in 3 stages:
+    1) forward NTT   A[k] = sum_j a[j] * w^{jk},  B[k] = sum_j b[j] * w^{jk}
+    2) pointwise     C[k] = A[k] * B[k]
+    3) inverse NTT   out[j] = L^{-1} * sum_k C[k] * w^{-jk}
+all mod p. Since L >= 2N-1, the cyclic convolution computed by the NTT never wraps around and equals the plain product.
+- Every stage is a linear (or, for 2), quadratic) function of the previous stage with constant coefficients,
+and is proven with one quotient column per output as in PolyMulAir: lhs === q * p + rhs over the integers, from
+    mod n, enforced directly, and mod 2^MUL_CRT_BITS, enforced over the limbs by eval_wide_reduction(),
+with a, b and every stage output range checked into [0, p), and q decomposed into MUL_CRT_BITS bits.
+- A stage output sums at most L <= MUL_MAX_N products of 2 values below p, a limb of the range checked inputs
+times a limb of a constant for 1) and 3), so the bounds of PolyMulAir hold for every stage.
+- The transforms are written as dense matrix-vector products rather than butterflies, so the constraints cost
+O(N*L) terms, but no intermediate butterfly layers have to be committed.
+*/
+impl<F: Field> BaseAir<F> for NttMulAir {
+    // Air Table looks like this
+    // row:[a:N][b:N][A:L][qA:L][B:L][qB:L][C:L][qC:L][out:L][qout:L][input_range:62*2N][stage_range:62*4L][q_bits:43*4L][carry_bits:138*4L]
+    //     ^inputs^^-------------------------------------calculated by generate_nttmul_trace------------------------------------------^
+    //     [0..............................................................................................................................0]
+    //     [0..............................................................................................................................0]
+    //     [0..............................................................................................................................0]
+    fn width(&self) -> usize {
+        NttMulLayout::new(self.n).width
+    }
+}
+
+// Column offsets of the NttMulAir row. The stages s = 0, 1, 2, 3 are A, B, C and out, each followed by its quotients,
+// and the range checks, q bits and carries of the stage outputs are stored stage after stage in their blocks.
+struct NttMulLayout {
+    l: usize,
+    input_range: usize,
+    stage_range: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    width: usize
+}
+
+impl NttMulLayout {
+    fn new(n: usize) -> Self {
+        let l = ntt_size(n);
+        let input_range = 2*n + 8*l;
+        let stage_range = input_range + RANGE_CHECK_WIDTH*2*n;
+        let q_bits = stage_range + RANGE_CHECK_WIDTH*4*l;
+        let carry_bits = q_bits + MUL_CRT_BITS*4*l;
+        let width = carry_bits + CRT_LIMBS*MUL_CARRY_BITS*4*l;
+        Self { l, input_range, stage_range, q_bits, carry_bits, width }
+    }
+
+    // Column of the output k of stage s, followed by its quotient at + l
+    fn stage(&self, n: usize, s: usize, k: usize) -> usize {
+        2*n + 2*s*self.l + k
+    }
+}
+
+impl GadgetLayout for NttMulAir {
+    fn layout(&self) -> TraceLayout {
+        let size = ntt_size(self.n);
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("A", size)
+            .push("qA", size)
+            .push("B", size)
+            .push("qB", size)
+            .push("C", size)
+            .push("qC", size)
+            .push("out", size)
+            .push("qout", size)
+            .push("input_range", RANGE_CHECK_WIDTH*2*self.n)
+            .push("stage_range", RANGE_CHECK_WIDTH*4*size)
+            .push("q_bits", MUL_CRT_BITS*4*size)
+            .push("carry_bits", CRT_LIMBS*MUL_CARRY_BITS*4*size)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for NttMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let layout = NttMulLayout::new(n);
+        let l = layout.l;
+        let p = self.modulus as u64;
+
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a and self.b as 2 input polynomials
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+            builder.when_first_row().assert_eq(row[n+i], AB::Expr::from_canonical_u32(self.b[i]));
+        }
+
+        // Enforce 0 <= a[i], b[i] < mod and 0 <= A[k], B[k], C[k], out[k] < mod
+        let inputs: Vec<AB::Expr> = (0..2*n).map(|i| row[i].into()).collect();
+        eval_range_checks(builder, &inputs, &row[layout.input_range..layout.stage_range], self.modulus);
+        let outputs: Vec<AB::Expr> = (0..4).flat_map(|s| (0..l).map(move |k| (s, k))).map(|(s, k)| row[layout.stage(n, s, k)].into()).collect();
+        eval_range_checks(builder, &outputs, &row[layout.stage_range..layout.q_bits], self.modulus);
+
+        // Limbs of the range checked inputs and stage outputs
+        let coeff_limbs = |block: usize| range_checked_limbs::<AB>(&row[block..block + RANGE_CHECK_BITS]);
+        let input_limbs: Vec<Vec<AB::Expr>> = (0..2*n).map(|i| coeff_limbs(layout.input_range + i*RANGE_CHECK_WIDTH)).collect();
+        let stage_limbs = |s: usize| -> Vec<Vec<AB::Expr>> {
+            (0..l).map(|k| coeff_limbs(layout.stage_range + (s*l + k)*RANGE_CHECK_WIDTH)).collect()
+        };
+
+        // w^0, ..., w^{L-1} mod p
+        let powers = ntt_powers(self.root, l, self.modulus);
+
+        // 1) forward NTT of a and b
+        for (s, poly) in [(0, 0), (1, n)] {
+            for k in 0..l {
+                let w = |j: usize| powers[j * k % l];
+                let mut sum = AB::Expr::zero();
+                for j in 0..n {
+                    sum = sum + row[poly+j] * AB::F::from_canonical_u32(w(j));
+                }
+                let limb_sum = weighted_limb_sum::<AB>(&input_limbs[poly..poly+n], w);
+                self.eval_stage_output(builder, &row, &layout, (s, k), sum, limb_sum);
+            }
+        }
+
+        // 2) pointwise product
+        let (a_limbs, b_limbs) = (stage_limbs(0), stage_limbs(1));
+        for k in 0..l {
+            let product = row[layout.stage(n, 0, k)] * row[layout.stage(n, 1, k)];
+            let limb_sum = (0..CRT_LIMBS).map(|m| {
+                let mut sum = AB::Expr::zero();
+                for i in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+                    sum = sum + a_limbs[k][i].clone() * b_limbs[k][m-i].clone();
+                }
+                sum
+            }).collect();
+            self.eval_stage_output(builder, &row, &layout, (2, k), product, limb_sum);
+        }
+
+        // 3) inverse NTT, with the L^{-1} scaling folded into the constant coefficients
+        let l_inv = mod_inv(l as u64, p);
+        let c_limbs = stage_limbs(2);
+        for j in 0..l {
+            let w_inv = |k: usize| (powers[(l - j * k % l) % l] as u64 * l_inv % p) as u32;
+            let mut sum = AB::Expr::zero();
+            for k in 0..l {
+                sum = sum + row[layout.stage(n, 2, k)] * AB::F::from_canonical_u32(w_inv(k));
+            }
+            let limb_sum = weighted_limb_sum::<AB>(&c_limbs, w_inv);
+            self.eval_stage_output(builder, &row, &layout, (3, j), sum, limb_sum);
+        }
+    }
+}
+
+impl NttMulAir {
+    // Enforce sum === q * p + r over the integers for the output r at (stage, k), from sum as a field element (mod n)
+    // and from the limb positions limb_sum of its integer value (mod 2^MUL_CRT_BITS)
+    fn eval_stage_output<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var], layout: &NttMulLayout, (s, k): (usize, usize), sum: AB::Expr, limb_sum: Vec<AB::Expr>) {
+        let col = layout.stage(self.n, s, k);
+        let index = s*layout.l + k;
+        builder.assert_eq(sum, row[col + layout.l] * AB::F::from_canonical_u32(self.modulus) + row[col]);
+
+        let out_bits = layout.stage_range + index*RANGE_CHECK_WIDTH;
+        let q_bits = layout.q_bits + index*MUL_CRT_BITS;
+        let carry_bits = layout.carry_bits + index*CRT_LIMBS*MUL_CARRY_BITS;
+        eval_wide_reduction(
+            builder, limb_sum, row[col + layout.l].into(),
+            range_checked_limbs::<AB>(&row[out_bits..out_bits + RANGE_CHECK_BITS]),
+            &row[q_bits..q_bits + MUL_CRT_BITS],
+            &row[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+            self.modulus
+        );
+    }
+}
+
+// Limb positions [0..CRT_LIMBS) of sum_j x[j] * w(j), from the limbs of the range checked x[j] and the constants w(j)
+fn weighted_limb_sum<AB: AirBuilder>(x: &[Vec<AB::Expr>], w: impl Fn(usize) -> u32) -> Vec<AB::Expr> {
+    let mut sum = vec![AB::Expr::zero(); CRT_LIMBS];
+    for (j, x_limbs) in x.iter().enumerate() {
+        let w_limbs = limbs(w(j) as u128, COEFF_LIMBS);
+        for (l, x_l) in x_limbs.iter().enumerate() {
+            for (l2, &w_l) in w_limbs.iter().enumerate() {
+                if l + l2 < CRT_LIMBS && w_l != 0 {
+                    sum[l+l2] = sum[l+l2].clone() + x_l.clone() * AB::F::from_canonical_u64(w_l);
+                }
+            }
+        }
+    }
+    sum
+}
+
+// w^0, ..., w^{size-1} mod p
+pub(crate) fn ntt_powers(root: u32, size: usize, modulus: u32) -> Vec<u32> {
+    let mut powers = Vec::with_capacity(size);
+    let mut power = 1u64;
+    for _ in 0..size {
+        powers.push(power as u32);
+        power = power * root as u64 % modulus as u64;
+    }
+    powers
+}
+
+// Sum the integer terms of one output, returning (sum mod p, sum div p)
+pub(crate) fn reduce_sum(terms: impl Iterator<Item = u128>, modulus: u32) -> (u32, u64) {
+    let sum: u128 = terms.sum();
+    ((sum % modulus as u128) as u32, (sum / modulus as u128) as u64)
+}
+
+// Define a function to generate execution trace
+pub fn generate_nttmul_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_nttmul_bounds(modulus, n)?;
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+
+    let layout = NttMulLayout::new(n);
+    let l = layout.l;
+    let p = modulus as u64;
+    let root = root_of_unity(modulus, l)?;
+    let powers = ntt_powers(root, l, modulus);
+
+    let mut row = vec![F::zero(); layout.width];
+
+    // Assign input polynomials and their range checks
+    for (i, &c) in a.iter().chain(&b).enumerate() {
+        row[i] = F::from_canonical_u32(c);
+        let block = layout.input_range + i*RANGE_CHECK_WIDTH;
+        assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], c, modulus);
+    }
+
+    // 1) forward NTT
+    let forward = |poly: &[u32]| -> Vec<(u128, Vec<u64>)> {
+        (0..l).map(|k| weighted_sum((0..n).map(|j| (poly[j], powers[j * k % l])))).collect()
+    };
+    let a_ntt = assign_stage(&mut row, &layout, n, 0, forward(&a), modulus);
+    let b_ntt = assign_stage(&mut row, &layout, n, 1, forward(&b), modulus);
+
+    // 2) pointwise product
+    let c_sums = (0..l).map(|k| weighted_sum(std::iter::once((a_ntt[k], b_ntt[k])))).collect();
+    let c_ntt = assign_stage(&mut row, &layout, n, 2, c_sums, modulus);
+
+    // 3) inverse NTT
+    assign_stage(&mut row, &layout, n, 3, inverse_ntt_sums(&c_ntt, &powers, modulus), modulus);
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(row, layout.width))
+}
+
+// The integer sum_j x_j * w_j of one stage output and its limb positions [0..CRT_LIMBS)
+fn weighted_sum(terms: impl Iterator<Item = (u32, u32)>) -> (u128, Vec<u64>) {
+    let mut sum = 0u128;
+    let mut limb_sum = vec![0u64; CRT_LIMBS];
+    for (x, w) in terms {
+        sum += x as u128 * w as u128;
+        let conv = convolve(&limbs(x as u128, COEFF_LIMBS), &limbs(w as u128, COEFF_LIMBS), CRT_LIMBS);
+        for (m, c) in conv.into_iter().enumerate() {
+            limb_sum[m] += c;
+        }
+    }
+    (sum, limb_sum)
+}
+
+// The sums of the inverse NTT out[j] = sum_k C[k] * L^{-1} * w^{-jk} with their limb positions, from the pointwise products C
+fn inverse_ntt_sums(c_ntt: &[u32], powers: &[u32], modulus: u32) -> Vec<(u128, Vec<u64>)> {
+    let (l, p) = (powers.len(), modulus as u64);
+    let l_inv = mod_inv(l as u64, p);
+    (0..l).map(|j| weighted_sum((0..l).map(|k| {
+        let w_inv = powers[(l - j * k % l) % l] as u64 * l_inv % p;
+        (c_ntt[k], w_inv as u32)
+    }))).collect()
+}
+
+// Assign the outputs of stage s reduced from their sums, with their quotients, range checks, q bits and carries,
+// returning the reduced outputs. q < L * p <= 2^43, so it fits in u64.
+fn assign_stage<F: Field>(row: &mut [F], layout: &NttMulLayout, n: usize, s: usize, sums: Vec<(u128, Vec<u64>)>, modulus: u32) -> Vec<u32> {
+    sums.iter().enumerate().map(|(k, (sum, limb_sum))| {
+        let (out, q) = ((sum % modulus as u128) as u32, (sum / modulus as u128) as u64);
+        assign_stage_output(row, layout, n, s, k, limb_sum, out, q, modulus);
+        out
+    }).collect()
+}
+
+// Assign the output k of stage s and its quotient, with the range check of out, the bits of q and the carries of the identity 1)
+// for the limb positions limb_sum. A pair that does not reduce the sum still gives a row, which the constraints then reject.
+fn assign_stage_output<F: Field>(row: &mut [F], layout: &NttMulLayout, n: usize, s: usize, k: usize, limb_sum: &[u64], out: u32, q: u64, modulus: u32) {
+    let col = layout.stage(n, s, k);
+    let index = s*layout.l + k;
+    row[col] = F::from_canonical_u32(out);
+    row[col + layout.l] = F::from_wrapped_u64(q);
+
+    let block = layout.stage_range + index*RANGE_CHECK_WIDTH;
+    assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], out, modulus);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    let q_bits = layout.q_bits + index*MUL_CRT_BITS;
+    let carry_bits = index*CRT_LIMBS*MUL_CARRY_BITS;
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+        limb_sum, q, out, modulus
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_field::{PrimeField32, PrimeField64};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference;
+    use crate::gadgets::soundness::{assert_rejected, ROUNDS};
+    use crate::gadgets::utils::mod_exp;
+    use crate::params::{P1, P2, P3};
+
+    #[test]
+    fn test_ntt_mul() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate 2 random input polynomials with n coefficients in the range of [0, P1)
+        let n = 8;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let random_poly2: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let air = NttMulAir::new(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+
+        let trace = generate_nttmul_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+
+        // out matches the reference schoolbook product, and the wrap-around slots stay empty
+        let widen = |poly: &[u32]| poly.iter().map(|&c| c as u128).collect::<Vec<_>>();
+        let expected = reference::mul(&widen(&random_poly1), &widen(&random_poly2), P1 as u128);
+        let l = ntt_size(n);
+        let row = trace.row_slice(0);
+        for j in 0..l {
+            let expected = if j < 2*n-1 { Val::from_canonical_u32(expected[j] as u32) } else { Val::zero() };
+            assert_eq!(row[2*n + 6*l + j], expected);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_ntt_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = NttMulAir::new(a.clone(), b.clone(), P1, n).unwrap();
+        let honest = generate_nttmul_trace::<Val>(a, b, P1, n).unwrap();
+
+        // a wrong output out[k] of the inverse NTT, with its quotient re-solved mod n from the honest pointwise products
+        let layout = NttMulLayout::new(n);
+        let powers = ntt_powers(root_of_unity(P1, layout.l).unwrap(), layout.l, P1);
+        let c_ntt: Vec<u32> = (0..layout.l).map(|k| honest.values[layout.stage(n, 2, k)].as_canonical_u32()).collect();
+        let sums = inverse_ntt_sums(&c_ntt, &powers, P1);
+        for _ in 0..ROUNDS {
+            let (k, delta) = (rng.gen_range(0..layout.l), rng.gen_range(1..P1));
+            let (sum, limb_sum) = &sums[k];
+            let out = ((sum % P1 as u128) as u32 + delta) % P1;
+            let q = (Val::from_wrapped_u64((sum % Val::ORDER_U64 as u128) as u64) - Val::from_canonical_u32(out)) * Val::from_canonical_u32(P1).inverse();
+            let mut forged = honest.clone();
+            assign_stage_output(&mut forged.values[..layout.width], &layout, n, 3, k, limb_sum, out, q.as_canonical_u64(), P1);
+            assert_rejected(&air, forged, &format!("a forged out[{}] + {}", k, delta));
+        }
+    }
+
+    #[test]
+    fn test_ntt_mul_bounds() {
+        let poly = vec![0; NTT_MUL_MAX_N + 1];
+        assert!(matches!(
+            NttMulAir::new(poly.clone(), poly.clone(), P1, NTT_MUL_MAX_N + 1),
+            Err(GadgetError::TooManyCoefficients { max: NTT_MUL_MAX_N, .. })
+        ));
+        assert!(matches!(
+            generate_nttmul_trace::<Val>(poly.clone(), poly, P1, NTT_MUL_MAX_N + 1),
+            Err(GadgetError::TooManyCoefficients { max: NTT_MUL_MAX_N, .. })
+        ));
+        assert!(NttMulAir::new(vec![P1], vec![0], P1, 1).is_err());
+    }
+
+    #[test]
+    fn test_root_of_unity() {
+        for modulus in [P1, P2, P3] {
+            let w = root_of_unity(modulus, 16).unwrap() as u64;
+            assert_eq!(mod_exp(w, 16, modulus as u64), 1);
+            assert_ne!(mod_exp(w, 8, modulus as u64), 1);
+        }
+        // L must divide p - 1 (P1 has 2-adicity 20), and the modulus must have a known generator
+        assert!(root_of_unity(P1, 1 << 21).is_err());
+        assert!(root_of_unity(7, 2).is_err());
+    }
+}
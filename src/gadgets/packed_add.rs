@@ -0,0 +1,243 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field, PrimeField32};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_mersenne_31::Mersenne31;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::pad_trace;
+use crate::params::N;
+
+// Largest modulus with at least 2 coefficients per native element: (2 * 23170)^2 < 2^31 - 1 <= (2 * 23171)^2
+pub const MAX_PACKED_MODULUS: u32 = 23170;
+
+// Define AIR constraint inputs
+pub struct PackedPolyAddAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PackedPolyAddAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+
+    // Number of coefficients packed per column, at least 1 so that the width stays defined
+    fn k(&self) -> usize {
+        packing_factor(self.modulus).max(1)
+    }
+
+    // Number of packed columns per polynomial
+    fn packed(&self) -> usize {
+        self.n.div_ceil(self.k())
+    }
+}
+
+/*
+Packed Polynomial Addition Air
+Input:
+- a, b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus, at most MAX_PACKED_MODULUS
+Output:
+- out = (a + b) % mod, packed k coefficients per column
+- carry: carry[i] = 1 when a[i] + b[i] >= mod
+
+Note:
+- The coefficients i = c*k, ..., c*k + k-1 are packed into the column c as digits in the radix R = 2 * mod,
+    A[c] = sum_j a[c*k+j] * R^j
+and the same for B and OUT, so each polynomial takes ceil(N/k) columns instead of N.
+- The packing factor k = packing_factor(mod) is the largest k with R^k < n = 2^31 - 1 (Mersenne31),
+i.e. floor(log_R(n - 1)). Every digit of A + B is at most 2 * mod - 2 < R, so A[c] + B[c] < R^k never wraps around n,
+and the digits of one column don't carry into each other. k >= 2 needs (2 * mod)^2 < n, that is mod <= MAX_PACKED_MODULUS:
+larger moduli, e.g. the 31-bits P1, P2, P3, are not supported and use PolyAddAir.
+- The unpacking of OUT is constrained through the carry bits, one per coefficient as in CiphertextAddAir:
+    A[c] + B[c] === OUT[c] + mod * sum_j carry[c*k+j] * R^j
+so the digit j of OUT[c] is a[c*k+j] + b[c*k+j] - carry[c*k+j] * mod, and unpack() reads it back on the host.
+- The digits of OUT are not range checked one by one: the digit j is in [0, mod) exactly when carry[c*k+j] is the bit
+a[c*k+j] + b[c*k+j] >= mod, a constant of the inputs, so every carry is pinned to it instead. A flipped carry would
+otherwise shift the digit by mod, below 0 or to mod and above, which borrows from or overflows into the next digit.
+- The modulus is a constant of the constraints instead of a column, since it is the same for every coefficient.
+*/
+impl<F: Field> BaseAir<F> for PackedPolyAddAir {
+    // Air Table looks like this
+    // row:[ a: N/k ][ b: N/k ][ out: N/k ][ carry: N ]
+    //     ^-----inputs-----^^-calculated by generate_packed_polyadd_trace-^
+    //     [0........................................0]
+    //     [0........................................0]
+    //     [0........................................0]
+    fn width(&self) -> usize {
+        3*self.packed() + self.n
+    }
+}
+
+impl GadgetLayout for PackedPolyAddAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.packed())
+            .push("b", self.packed())
+            .push("out", self.packed())
+            .push("carry", self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PackedPolyAddAir {
+    fn eval(&self, builder: &mut AB) {
+        let (n, k, m) = (self.n, self.k(), self.packed());
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce the packed self.a and self.b as the input columns
+        let radix = 2*self.modulus as u64;
+        for (c, (a, b)) in pack(&self.a, radix, k).into_iter().zip(pack(&self.b, radix, k)).enumerate() {
+            builder.when_first_row().assert_eq(row[c], AB::Expr::from_wrapped_u64(a));
+            builder.when_first_row().assert_eq(row[m+c], AB::Expr::from_wrapped_u64(b));
+        }
+
+        // Enforce A[c] + B[c] === OUT[c] + mod * sum_j carry[c*k+j] * R^j, with every carry the bit a + b >= mod
+        let (out, carry) = (2*m, 3*m);
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+        for c in 0..m {
+            let mut carries = AB::Expr::zero();
+            for j in 0..k.min(n - c*k) {
+                let i = c*k+j;
+                builder.when_first_row().assert_eq(row[carry+i], AB::Expr::from_bool(self.a[i] as u64 + self.b[i] as u64 >= self.modulus as u64));
+                carries = carries + row[carry+c*k+j] * AB::F::from_wrapped_u64(radix.pow(j as u32));
+            }
+            builder.assert_eq(row[c] + row[m+c], row[out+c] + carries * modulus);
+        }
+    }
+}
+
+// Number of coefficients mod `modulus` packed per native element: the largest k with (2 * modulus)^k < 2^31 - 1,
+// or 0 when 2 * modulus does not fit a single native element
+pub fn packing_factor(modulus: u32) -> usize {
+    let (order, radix) = (Mersenne31::ORDER_U32 as u64, 2*modulus as u64);
+    let mut k = 0;
+    let mut power = radix;
+    while radix > 1 && power < order {
+        k += 1;
+        power *= radix;
+    }
+    k
+}
+
+// Pack k coefficients per value as digits in `radix`, the last value holding the remaining coefficients
+fn pack(poly: &[u32], radix: u64, k: usize) -> Vec<u64> {
+    poly.chunks(k).map(|chunk| chunk.iter().rev().fold(0, |acc, &c| acc * radix + c as u64)).collect()
+}
+
+// Unpack the n coefficients mod `modulus` of the packed `values`, as laid out by generate_packed_polyadd_trace()
+pub fn unpack<F: PrimeField32>(values: &[F], modulus: u32, n: usize) -> Vec<u32> {
+    let (radix, k) = (2*modulus as u64, packing_factor(modulus).max(1));
+    let mut coeffs = Vec::with_capacity(n);
+    for value in values {
+        let mut x = value.as_canonical_u32() as u64;
+        for _ in 0..k.min(n - coeffs.len()) {
+            coeffs.push((x % radix) as u32);
+            x /= radix;
+        }
+    }
+    coeffs
+}
+
+// Define a function to generate execution trace
+pub fn generate_packed_polyadd_trace<F: Field>(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+    if modulus > MAX_PACKED_MODULUS {
+        return Err(GadgetError::ModulusTooLarge { modulus, max: MAX_PACKED_MODULUS });
+    }
+
+    let (radix, k) = (2*modulus as u64, packing_factor(modulus));
+    let width = 3*n.div_ceil(k) + n;
+    let mut values: Vec<F> = Vec::with_capacity(width);
+
+    // Assign the packed input polynomials and their packed sum, then the carries
+    let out: Vec<u32> = a.iter().zip(b.iter()).map(|(&x, &y)| (x + y) % modulus).collect();
+    for poly in [&a, &b, &out] {
+        values.extend(pack(poly, radix, k).into_iter().map(F::from_wrapped_u64));
+    }
+    values.extend((0..n).map(|i| F::from_bool(a[i] + b[i] >= modulus)));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows: 0 + 0 = 0 with no carry
+    Ok(pad_trace(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::poly_op::PolynomialOpAir;
+    use crate::gadgets::soundness::{assert_rejected, assert_rejects_corrupted};
+
+    #[test]
+    fn test_packed_poly_add() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // q = 12289 packs 2 coefficients per column, q = 257 packs 3 with an incomplete last column
+        let n = 16;
+        let mut rng = thread_rng();
+        for (modulus, k) in [(12289, 2), (257, 3)] {
+            assert_eq!(packing_factor(modulus), k);
+            let mut a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+            let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..modulus)).collect();
+            a[0] = modulus - 1;
+
+            // the unpacked out of the packed trace is the out of the unpacked PolyAddAir trace, in fewer columns
+            let air = PackedPolyAddAir { a:a.clone(), b:b.clone(), modulus, n };
+            let trace = generate_packed_polyadd_trace::<Val>(a.clone(), b.clone(), modulus, n).unwrap();
+            let unpacked_air = PolyAddAir { a:a.clone(), b:b.clone(), modulus, n };
+            let unpacked = generate_polyadd_trace::<Val>(a, b, modulus, n).unwrap();
+            let out = air.layout().get("out").unwrap();
+            let expected: Vec<u32> = unpacked.row_slice(0)[unpacked_air.output_columns()].iter().map(|x| x.as_canonical_u32()).collect();
+            assert_eq!(unpack(&trace.row_slice(0)[out], modulus, n), expected);
+            assert!(trace.width() < unpacked.width());
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace.clone(), &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+            assert_rejects_corrupted(&air, &trace, "out");
+        }
+    }
+
+    #[test]
+    fn test_packed_poly_add_forged_carry() {
+        // a[1] + b[1] = 3 < mod: a carry of 1 with OUT[0] shifted by -mod * R still holds natively,
+        // but OUT[0] = 2 + (3 - mod) * R is then negative and wraps around n
+        let (n, modulus) = (4, 12289);
+        let (a, b) = (vec![1, 1, 2, 2], vec![1, 2, 3, 4]);
+        let air = PackedPolyAddAir { a:a.clone(), b:b.clone(), modulus, n };
+        let mut trace = generate_packed_polyadd_trace::<Val>(a, b, modulus, n).unwrap();
+        let (out, carry) = (air.layout().get("out").unwrap().start, air.layout().get("carry").unwrap().start);
+        trace.values[out] -= Val::from_canonical_u32(modulus) * Val::from_canonical_u32(2*modulus);
+        trace.values[carry+1] = Val::one();
+
+        assert_rejected(&air, trace, "a forged carry[1]");
+    }
+
+    #[test]
+    fn test_packing_factor() {
+        assert_eq!(packing_factor(1), 30);
+        assert_eq!(packing_factor(MAX_PACKED_MODULUS), 2);
+        assert_eq!(packing_factor(MAX_PACKED_MODULUS + 1), 1);
+        assert_eq!(packing_factor(1 << 30), 0);
+
+        // 31-bits moduli don't pack
+        let n = 4;
+        assert_eq!(
+            generate_packed_polyadd_trace::<Val>(vec![0; n], vec![0; n], MAX_PACKED_MODULUS + 1, n).unwrap_err(),
+            GadgetError::ModulusTooLarge { modulus: MAX_PACKED_MODULUS + 1, max: MAX_PACKED_MODULUS }
+        );
+    }
+}
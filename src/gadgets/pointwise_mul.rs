@@ -0,0 +1,242 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::limbs;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_wide_reduction, eval_wide_reduction, range_checked_limbs, CRT_LIMBS, MUL_CARRY_BITS, MUL_CRT_BITS};
+use crate::gadgets::range_check::{assign_range_check, check_range_modulus, eval_range_checks, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct PointwiseMulAir {
+    pub a_ntt: Vec<u32>,
+    pub b_ntt: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PointwiseMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a_ntt: Vec<u32>, b_ntt: Vec<u32>, modulus: u32) -> Self {
+        Self { a_ntt, b_ntt, modulus, n: N }
+    }
+}
+
+/*
+Pointwise (Hadamard) Multiplication Air
+Input:
+- a_ntt = a_ntt[0], ..., a_ntt[N-1]: NTT of a, e.g. the output of NttAir
+- b_ntt = b_ntt[0], ..., b_ntt[N-1]: NTT of b
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0], ..., out[N-1] where out[i] = (a_ntt[i] * b_ntt[i]) % mod
+
+Note:
+- In the NTT domain, the cyclic convolution of a and b is the slotwise product, so this takes N constraints instead of the O(N^2) terms of PolyMulAir.
+- Each product is below mod^2 < 2^62, and is reduced with one quotient column per slot as in PolyMulAir:
+    a_ntt[i] * b_ntt[i] === q[i] * mod + out[i]
+enforced mod n, and mod 2^MUL_CRT_BITS by eval_wide_reduction() from the limbs of the constant product,
+with out[i] range checked into [0, mod) and q[i] < mod decomposed into MUL_CRT_BITS bits,
+so that it holds over the integers as in PolyMulAir.
+*/
+impl<F: Field> BaseAir<F> for PointwiseMulAir {
+    // Air Table looks like this
+    // row:[a_ntt: N][b_ntt: N][mod:1][out: N][q: N][out_range: 62*N][q_bits: 43*N][carry_bits: 138*N]
+    //     ^---------inputs----------^^---------------calculated by generate_pointwise_mul_trace----------------^
+    //     [0...................................................................................0]
+    //     [0...................................................................................0]
+    //     [0...................................................................................0]
+    fn width(&self) -> usize {
+        pointwise_mul_width(self.n)
+    }
+}
+
+// Witness columns of one slot: the range check of out[i], the bits of q[i] and the carries of its reduction
+const SLOT_WIDTH: usize = RANGE_CHECK_WIDTH + MUL_CRT_BITS + CRT_LIMBS*MUL_CARRY_BITS;
+
+fn pointwise_mul_width(n: usize) -> usize {
+    4*n + 1 + SLOT_WIDTH*n
+}
+
+impl GadgetLayout for PointwiseMulAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a_ntt", self.n)
+            .push("b_ntt", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+            .push("q", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*self.n)
+            .push("q_bits", MUL_CRT_BITS*self.n)
+            .push("carry_bits", CRT_LIMBS*MUL_CARRY_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PointwiseMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (a, b, mod_col) = (0, n, 2*n);
+        let (out, q) = (2*n+1, 3*n+1);
+        let (out_range, q_bits, carry_bits) = (4*n+1, 4*n+1 + RANGE_CHECK_WIDTH*n, 4*n+1 + (RANGE_CHECK_WIDTH + MUL_CRT_BITS)*n);
+
+        // Enforce self.a_ntt and self.b_ntt as 2 inputs and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[a+i], AB::Expr::from_canonical_u32(self.a_ntt[i]));
+            builder.when_first_row().assert_eq(row[b+i], AB::Expr::from_canonical_u32(self.b_ntt[i]));
+        }
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce 0 <= out[i] < mod
+        let outputs: Vec<AB::Expr> = (0..n).map(|i| row[out+i].into()).collect();
+        eval_range_checks(builder, &outputs, &row[out_range..q_bits], self.modulus);
+
+        // Enforce a_ntt[i] * b_ntt[i] === q[i] * mod + out[i] (mod n), and mod 2^MUL_CRT_BITS from the limbs of the product
+        for i in 0..n {
+            builder.assert_eq(row[a+i] * row[b+i], row[q+i] * row[mod_col] + row[out+i]);
+
+            let product = self.a_ntt[i] as u128 * self.b_ntt[i] as u128;
+            let sum = limbs(product, CRT_LIMBS).into_iter().map(AB::Expr::from_canonical_u64).collect();
+            let (out_i, q_i, carry_i) = (out_range + i*RANGE_CHECK_WIDTH, q_bits + i*MUL_CRT_BITS, carry_bits + i*CRT_LIMBS*MUL_CARRY_BITS);
+            eval_wide_reduction(
+                builder, sum, row[q+i].into(),
+                range_checked_limbs::<AB>(&row[out_i..out_i + RANGE_CHECK_BITS]),
+                &row[q_i..q_i + MUL_CRT_BITS],
+                &row[carry_i..carry_i + CRT_LIMBS*MUL_CARRY_BITS],
+                self.modulus
+            );
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_pointwise_mul_trace<F: Field>(a_ntt: Vec<u32>, b_ntt: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a_ntt, n, modulus)?;
+    check_poly(&b_ntt, n, modulus)?;
+    check_range_modulus(modulus)?;
+
+    let width = pointwise_mul_width(n);
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * width);
+
+    // Add inputs and modulus to values vector
+    values.extend(a_ntt.iter().map(|&x| F::from_canonical_u32(x)));
+    values.extend(b_ntt.iter().map(|&x| F::from_canonical_u32(x)));
+    values.push(F::from_canonical_u32(modulus));
+
+    // Multiply slot by slot, in u64 since the products are up to 62 bits
+    let products: Vec<u64> = (0..n).map(|i| a_ntt[i] as u64 * b_ntt[i] as u64).collect();
+    values.extend(products.iter().map(|&c| F::from_canonical_u32((c % modulus as u64) as u32)));
+    values.extend(products.iter().map(|&c| F::from_wrapped_u64(c / modulus as u64)));
+
+    // Range checks of out, then the bits of q and the carries of each reduction
+    let mut witness = vec![F::zero(); SLOT_WIDTH*n];
+    let (out_range, reductions) = witness.split_at_mut(RANGE_CHECK_WIDTH*n);
+    let (q_bits, carry_bits) = reductions.split_at_mut(MUL_CRT_BITS*n);
+    for (i, &c) in products.iter().enumerate() {
+        let (out, q) = ((c % modulus as u64) as u32, c / modulus as u64);
+        assign_range_check(&mut out_range[i*RANGE_CHECK_WIDTH..(i+1)*RANGE_CHECK_WIDTH], out, modulus);
+        assign_wide_reduction(
+            &mut q_bits[i*MUL_CRT_BITS..(i+1)*MUL_CRT_BITS],
+            &mut carry_bits[i*CRT_LIMBS*MUL_CARRY_BITS..(i+1)*CRT_LIMBS*MUL_CARRY_BITS],
+            &limbs(c as u128, CRT_LIMBS), q, out, modulus
+        );
+    }
+    values.extend(witness);
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField32;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::ntt_mul::{ntt_powers, root_of_unity};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::gadgets::utils::mod_inv;
+    use crate::params::P1;
+
+    // Dense transform x -> (sum_j x[j] * root^{jk})_k mod p
+    fn transform(x: &[u32], root: u32, modulus: u32) -> Vec<u32> {
+        let n = x.len();
+        let powers = ntt_powers(root, n, modulus);
+        (0..n).map(|k| {
+            let sum: u128 = (0..n).map(|j| x[j] as u128 * powers[j * k % n] as u128).sum();
+            (sum % modulus as u128) as u32
+        }).collect()
+    }
+
+    #[test]
+    fn test_pointwise_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let b: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let root = root_of_unity(P1, n).unwrap();
+        let a_ntt = transform(&a, root, P1);
+        let b_ntt = transform(&b, root, P1);
+
+        let air = PointwiseMulAir { a_ntt:a_ntt.clone(), b_ntt:b_ntt.clone(), modulus:P1, n };
+        let trace = generate_pointwise_mul_trace::<Val>(a_ntt, b_ntt, P1, n).unwrap();
+        let c_ntt: Vec<u32> = trace.row_slice(0)[2*n+1..3*n+1].iter().map(|x| x.as_canonical_u32()).collect();
+
+        // the inverse transform of the pointwise product is the cyclic convolution of a and b
+        let root_inv = mod_inv(root as u64, P1 as u64) as u32;
+        let n_inv = mod_inv(n as u64, P1 as u64) as u128;
+        let c: Vec<u32> = transform(&c_ntt, root_inv, P1).iter().map(|&x| (x as u128 * n_inv % P1 as u128) as u32).collect();
+        for k in 0..n {
+            let sum: u128 = (0..n).map(|j| a[j] as u128 * b[(n + k - j) % n] as u128).sum();
+            assert_eq!(c[k], (sum % P1 as u128) as u32);
+        }
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_pointwise_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a_ntt: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let b_ntt: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = PointwiseMulAir { a_ntt: a_ntt.clone(), b_ntt: b_ntt.clone(), modulus: P1, n };
+        let mut trace = generate_pointwise_mul_trace::<Val>(a_ntt.clone(), b_ntt.clone(), P1, n).unwrap();
+
+        // shift out[i] by delta and re-solve q[i] mod n, with the witness of the forged slot assigned as the generator would
+        let (i, delta) = (rng.gen_range(0..n), rng.gen_range(1..P1));
+        let product = a_ntt[i] as u64 * b_ntt[i] as u64;
+        let out = ((product % P1 as u64 + delta as u64) % P1 as u64) as u32;
+        let order = Val::ORDER_U32 as u64;
+        let q = (product % order + order - out as u64) % order * mod_inv(P1 as u64, order) % order;
+        let row = &mut trace.values[..pointwise_mul_width(n)];
+        row[2*n+1+i] = Val::from_canonical_u32(out);
+        row[3*n+1+i] = Val::from_wrapped_u64(q);
+        let (out_range, q_bits) = (4*n+1, 4*n+1 + RANGE_CHECK_WIDTH*n);
+        let carry_bits = q_bits + MUL_CRT_BITS*n;
+        assign_range_check(&mut row[out_range + i*RANGE_CHECK_WIDTH..out_range + (i+1)*RANGE_CHECK_WIDTH], out, P1);
+        let (head, carries) = row.split_at_mut(carry_bits);
+        assign_wide_reduction(
+            &mut head[q_bits + i*MUL_CRT_BITS..q_bits + (i+1)*MUL_CRT_BITS],
+            &mut carries[i*CRT_LIMBS*MUL_CARRY_BITS..(i+1)*CRT_LIMBS*MUL_CARRY_BITS],
+            &limbs(product as u128, CRT_LIMBS), q, out, P1
+        );
+
+        assert_rejected(&air, trace, &format!("a forged out[{}] + {}", i, delta));
+    }
+}
@@ -0,0 +1,176 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct PolyEvalAir {
+    pub poly: Vec<u32>,
+    // evaluation point in [0, mod)
+    pub x: u32,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PolyEvalAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(poly: Vec<u32>, x: u32, modulus: u32) -> Self {
+        Self { poly, x, modulus, n: N }
+    }
+}
+
+/*
+Polynomial Evaluation Air
+Input:
+- poly = poly[0] + poly[1] * X + ... + poly[N-1] * X^{N-1}
+- x: evaluation point in [0, mod)
+- mod: FHE ciphertext modulus
+Output:
+- out = poly(x) % mod
+- acc = acc[0], ..., acc[N-1]: intermediate values of the Horner evaluation, with out = acc[0]
+- q = q[0], ..., q[N-2]: quotients of the non-native modular reduction
+
+Note:
+- Horner's rule evaluates poly(x) = poly[0] + x * (poly[1] + x * (... + x * poly[N-1])) from the highest coefficient down:
+    acc[N-1] = poly[N-1]
+    acc[i]   = (acc[i+1] * x + poly[i]) % mod    for i = N-2, ..., 0
+so each step is a single constraint of degree 2 between acc[i+1] and acc[i]:
+    acc[i+1] * x + poly[i] === q[i] * mod + acc[i]
+which is N-1 constraints in total, instead of the O(N^2) terms of a Vandermonde row x^0, ..., x^{N-1} times poly.
+- acc[i+1] * x + poly[i] is at most (p-1)^2 + p-1 < 2^62, which overflows n, while q[i] < p fits in the native field,
+as in PolyScalarMulAir.
+- The recurrence is unrolled within one row, so poly is pinned on the first row as in the other single-row gadgets,
+without a selector per coefficient.
+*/
+impl<F: Field> BaseAir<F> for PolyEvalAir {
+    // Air Table looks like this
+    // row:[  poly: N  ][x:1][  acc: N  ][ q: N-1 ]
+    //     ^-----inputs-----^^--calculated by generate_polyeval_trace--^
+    //     [0........................................0]
+    //     [0........................................0]
+    //     [0........................................0]
+    fn width(&self) -> usize {
+        3*self.n
+    }
+}
+
+impl GadgetLayout for PolyEvalAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("poly", self.n)
+            .push("x", 1)
+            .push("acc", self.n)
+            .push("q", self.n-1)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PolyEvalAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.poly as input polynomial and self.x as evaluation point
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.poly[i]));
+        }
+        let x = n;
+        builder.when_first_row().assert_eq(row[x], AB::Expr::from_canonical_u32(self.x));
+
+        // Enforce acc[N-1] === poly[N-1] and every Horner step acc[i+1] * x + poly[i] === q[i] * mod + acc[i]
+        let (acc, q) = (n+1, 2*n+1);
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+        builder.assert_eq(row[acc+n-1], row[n-1]);
+        for i in 0..n-1 {
+            builder.assert_eq(row[acc+i+1] * row[x] + row[i], row[q+i] * modulus + row[acc+i]);
+        }
+    }
+}
+
+// Column of out = acc[0] in the polynomial evaluation trace
+pub fn poly_eval_output(n: usize) -> usize {
+    n+1
+}
+
+// Horner steps (acc[i], q[i]) computed on the host, with acc[0] = poly(x) % mod
+fn horner_steps(poly: &[u32], x: u32, modulus: u32) -> (Vec<u32>, Vec<u32>) {
+    let n = poly.len();
+    let mut acc = vec![0u32; n];
+    let mut q = vec![0u32; n-1];
+    acc[n-1] = poly[n-1];
+    for i in (0..n-1).rev() {
+        let step = acc[i+1] as u64 * x as u64 + poly[i] as u64;
+        acc[i] = (step % modulus as u64) as u32;
+        q[i] = (step / modulus as u64) as u32;
+    }
+    (acc, q)
+}
+
+// poly(x) % mod computed on the host with Horner's rule
+pub fn poly_eval(poly: &[u32], x: u32, modulus: u32) -> u32 {
+    poly.iter().rev().fold(0u64, |acc, &c| (acc * x as u64 + c as u64) % modulus as u64) as u32
+}
+
+// Define a function to generate execution trace
+pub fn generate_polyeval_trace<F: Field>(poly: Vec<u32>, x: u32, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&poly, n, modulus)?;
+    check_poly(&[x], 1, modulus)?;
+
+    let mut values: Vec<F>= Vec::with_capacity(MIN_TRACE_HEIGHT * 3*n);
+
+    // Add input polynomial and evaluation point to values vector
+    values.extend(poly.iter().map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(x));
+
+    // Run Horner's rule and push the intermediate values, then the quotients
+    let (acc, q) = horner_steps(&poly, x, modulus);
+    values.extend(acc.iter().chain(q.iter()).map(|&c| F::from_canonical_u32(c)));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 3*n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::utils::mod_exp;
+    use crate::params::P1;
+
+    #[test]
+    fn test_poly_eval() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate a random input polynomial with N coefficients in the range of [0, P1)
+        let mut rng = thread_rng();
+        let poly: Vec<u32> = (0..N).map(|_| rng.gen_range(0..P1)).collect();
+
+        // x = 0 selects poly[0], x = 1 sums the coefficients, a random x covers the general case
+        for x in [0, 1, rng.gen_range(2..P1)] {
+            // Horner agrees with the sum of poly[i] * x^i
+            let expected = poly_eval(&poly, x, P1);
+            let naive = poly.iter().enumerate().fold(0u64, |sum, (i, &c)| {
+                (sum + c as u64 * mod_exp(x as u64, i as u64, P1 as u64)) % P1 as u64
+            });
+            assert_eq!(expected as u64, naive);
+
+            let air = PolyEvalAir::new(poly.clone(), x, P1);
+            let trace = generate_polyeval_trace::<Val>(poly.clone(), x, P1, N).unwrap();
+            assert_eq!(trace.row_slice(0)[poly_eval_output(N)], Val::from_canonical_u32(expected));
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+}
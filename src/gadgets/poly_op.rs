@@ -0,0 +1,196 @@
+use std::ops::Range;
+use p3_air::{AirBuilder, AirBuilderWithPublicValues};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::GadgetError;
+
+/*
+Shared structure of the gadgets that take 2 input polynomials a and b mod `modulus`.
+Every such gadget lays its row out as [a: N][b: N][operation specific columns...],
+so the input placement is implemented once here and each gadget only provides the operation constraint.
+*/
+pub trait PolynomialOpAir {
+    fn a(&self) -> &[u32];
+    fn b(&self) -> &[u32];
+    fn modulus(&self) -> u32;
+    fn n(&self) -> usize;
+
+    // Enforce self.a and self.b as the 2 input polynomials at row[0..N) and row[N..2N)
+    fn eval_inputs<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n();
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a()[i]));
+            builder.when_first_row().assert_eq(row[i+n], AB::Expr::from_canonical_u32(self.b()[i]));
+        }
+    }
+
+    // Enforce the operation specific constraints over `row`, which starts at the a[0] column
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]);
+
+    // Columns of the output polynomial `out` within the row
+    fn output_columns(&self) -> Range<usize>;
+
+    // Number of output coefficients, i.e. of public values bound by eval_outputs()
+    fn num_outputs(&self) -> usize {
+        self.output_columns().len()
+    }
+
+    /*
+    Bind the output columns to the public values, so that verify() checks the proof against a caller-supplied `out`
+    instead of trusting whatever the prover put in the trace.
+    The public values are either empty (nothing is bound, as before) or exactly the coefficients of `out`.
+    Since the verifier recomputes the constraints from the public values it is given, a proof generated without them
+    never verifies against a non-empty expected output.
+    Public values of any other length are not an output of the AIR: they get an unsatisfiable constraint
+    instead of being indexed past their end, so that eval() never panics on the inputs of the verifier.
+    */
+    fn eval_outputs<AB: AirBuilderWithPublicValues>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let public_values: Vec<AB::Expr> = builder.public_values().iter().map(|&v| v.into()).collect();
+        if public_values.is_empty() {
+            return;
+        }
+        if public_values.len() != self.num_outputs() {
+            builder.when_first_row().assert_one(AB::Expr::zero());
+            return;
+        }
+        for (col, value) in self.output_columns().zip(public_values) {
+            builder.when_first_row().assert_eq(row[col], value);
+        }
+    }
+
+    // Enforce the inputs, the operation and the public outputs over the first row of the main trace
+    fn eval_poly_op<AB: AirBuilderWithPublicValues>(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+        self.eval_inputs(builder, &row);
+        self.eval_op(builder, &row);
+        self.eval_outputs(builder, &row);
+    }
+
+    // Read the output coefficients from the first row of `trace`, to be passed as public values
+    fn public_outputs<F: Field>(&self, trace: &RowMajorMatrix<F>) -> Vec<F> {
+        let row = trace.row_slice(0);
+        self.output_columns().map(|col| row[col]).collect()
+    }
+
+    // Generate the execution trace for self.a and self.b
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::add::PolyAddAir;
+    use crate::gadgets::config::{initialize_config, ZkConfig, ZkAir, Challenger, Val};
+    use crate::gadgets::mul::{MulShape, PolyMulAir};
+    use crate::gadgets::sub::PolySubAir;
+    use crate::params::P1;
+
+    // Prove and verify any 2-input gadget through the shared trait only
+    fn prove_op<A: PolynomialOpAir + ZkAir>(air: &A) -> bool {
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let trace = air.generate_trace::<Val>().unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, air, &mut challenger, &proof, &vec![]).is_ok()
+    }
+
+    #[test]
+    fn test_poly_op_gadgets() {
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let add = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let sub = PolySubAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let mul = PolyMulAir { a:random_poly1, b:random_poly2, modulus:P1, n, shape: MulShape::Row };
+        assert!(prove_op(&add));
+        assert!(prove_op(&sub));
+        assert!(prove_op(&mul));
+    }
+
+    // Prove with the honest output as public values, and verify against `expected`
+    fn verify_output<A: PolynomialOpAir + ZkAir>(air: &A, expected: impl Fn(Vec<Val>) -> Vec<Val>) -> bool {
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let trace = air.generate_trace::<Val>().unwrap();
+        let public_values = air.public_outputs(&trace);
+        let expected = expected(public_values.clone());
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, air, &mut challenger, &proof, &expected).is_ok()
+    }
+
+    #[test]
+    fn test_poly_op_public_outputs() {
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let add = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let mul = PolyMulAir { a:random_poly1, b:random_poly2, modulus:P1, n, shape: MulShape::Row };
+
+        // the verifier passes the correct output
+        assert!(verify_output(&add, |out| out));
+        assert!(verify_output(&mul, |out| out));
+
+        // the verifier passes a wrong output
+        let wrong = |mut out: Vec<Val>| { out[n/2] += Val::one(); out };
+        assert!(!verify_output(&add, wrong));
+        assert!(!verify_output(&mul, wrong));
+
+        // the verifier passes an output of another length, which is rejected instead of indexed past its end
+        let short = |out: Vec<Val>| out[..n-1].to_vec();
+        let long = |out: Vec<Val>| [out, vec![Val::zero()]].concat();
+        assert!(!verify_output(&add, short));
+        assert!(!verify_output(&add, long));
+        assert!(!verify_output(&mul, short));
+
+        // a proof without public outputs does not verify against an expected output
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let trace = add.generate_trace::<Val>().unwrap();
+        let expected = add.public_outputs(&trace);
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &add, &mut challenger, trace, &vec![]);
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        assert!(verify(&config, &add, &mut challenger, &proof, &expected).is_err());
+    }
+
+    #[test]
+    fn test_poly_op_inputs_enforced() {
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // the trace is generated for b, but the AIR claims b + 1 at b[0]
+        let sub = PolySubAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let mut claimed = random_poly2;
+        claimed[0] = (claimed[0] + 1) % P1;
+        let wrong = PolySubAir { a:random_poly1, b:claimed, modulus:P1, n };
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let trace = sub.generate_trace::<Val>().unwrap();
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &wrong, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &wrong, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "proof for different input polynomials was accepted");
+    }
+}
@@ -0,0 +1,143 @@
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::generate_polyadd_trace;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::mul::{generate_polymul_trace, polymul_coeffs};
+use crate::gadgets::negacyclic::{generate_negacyclic_mul_trace, negacyclic_coeffs};
+use crate::gadgets::sub::generate_polysub_trace;
+
+// A polynomial with coefficients in [0, modulus), carrying its modulus along with the coefficients
+// so that operands reduced modulo different moduli, or of different lengths, are caught before proving
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    coeffs: Vec<u32>,
+    modulus: u32
+}
+
+impl Polynomial {
+    // Check that every coefficient is in [0, modulus)
+    pub fn new(coeffs: Vec<u32>, modulus: u32) -> Result<Self, GadgetError> {
+        check_poly(&coeffs, coeffs.len(), modulus)?;
+        Ok(Self { coeffs, modulus })
+    }
+
+    pub fn coeffs(&self) -> &[u32] {
+        &self.coeffs
+    }
+
+    pub fn modulus(&self) -> u32 {
+        self.modulus
+    }
+
+    // Number of coefficients
+    pub fn len(&self) -> usize {
+        self.coeffs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coeffs.is_empty()
+    }
+
+    // Both operands must share the modulus and the number of coefficients
+    fn check_operand(&self, other: &Polynomial) -> Result<(), GadgetError> {
+        if other.modulus != self.modulus {
+            return Err(GadgetError::ModulusMismatch { expected: self.modulus, actual: other.modulus });
+        }
+        if other.len() != self.len() {
+            return Err(GadgetError::LengthMismatch { expected: self.len(), actual: other.len() });
+        }
+        Ok(())
+    }
+
+    // self + other, with the PolyAddAir trace proving it
+    pub fn add<F: Field>(&self, other: &Polynomial) -> Result<(Polynomial, RowMajorMatrix<F>), GadgetError> {
+        self.check_operand(other)?;
+        let modulus = self.modulus as u64;
+        let coeffs = self.coeffs.iter().zip(other.coeffs.iter())
+            .map(|(&a, &b)| ((a as u64 + b as u64) % modulus) as u32)
+            .collect();
+        let trace = generate_polyadd_trace(self.coeffs.clone(), other.coeffs.clone(), self.modulus, self.len())?;
+        Ok((Polynomial { coeffs, modulus: self.modulus }, trace))
+    }
+
+    // self - other, with the PolySubAir trace proving it
+    pub fn sub<F: Field>(&self, other: &Polynomial) -> Result<(Polynomial, RowMajorMatrix<F>), GadgetError> {
+        self.check_operand(other)?;
+        let modulus = self.modulus as u64;
+        let coeffs = self.coeffs.iter().zip(other.coeffs.iter())
+            .map(|(&a, &b)| ((a as u64 + modulus - b as u64) % modulus) as u32)
+            .collect();
+        let trace = generate_polysub_trace(self.coeffs.clone(), other.coeffs.clone(), self.modulus, self.len())?;
+        Ok((Polynomial { coeffs, modulus: self.modulus }, trace))
+    }
+
+    // self * other without reduction by X^N + 1, i.e. 2N-1 coefficients, with the PolyMulAir trace proving it
+    pub fn mul<F: Field>(&self, other: &Polynomial) -> Result<(Polynomial, RowMajorMatrix<F>), GadgetError> {
+        self.check_operand(other)?;
+        let (out, _) = polymul_coeffs(&self.coeffs, &other.coeffs, self.modulus);
+        let coeffs = out.iter().map(|&c| c as u32).collect();
+        let trace = generate_polymul_trace(self.coeffs.clone(), other.coeffs.clone(), self.modulus, self.len())?;
+        Ok((Polynomial { coeffs, modulus: self.modulus }, trace))
+    }
+
+    // self * other in Z_mod[X]/(X^N+1), with the NegacyclicMulAir trace proving it
+    pub fn negacyclic_mul<F: Field>(&self, other: &Polynomial) -> Result<(Polynomial, RowMajorMatrix<F>), GadgetError> {
+        self.check_operand(other)?;
+        let coeffs = negacyclic_coeffs(&self.coeffs, &other.coeffs, self.modulus);
+        let trace = generate_negacyclic_mul_trace(self.coeffs.clone(), other.coeffs.clone(), self.modulus, self.len())?;
+        Ok((Polynomial { coeffs, modulus: self.modulus }, trace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_field::AbstractField;
+    use p3_matrix::Matrix;
+    use crate::gadgets::config::Val;
+    use crate::params::{P1, P2};
+
+    #[test]
+    fn test_polynomial_arithmetic() {
+        // a = 1 + 2X + (P1-1)X^2 + 3X^3, b = (P1-2) + X + 5X^2 + X^3
+        let a = Polynomial::new(vec![1, 2, P1 - 1, 3], P1).unwrap();
+        let b = Polynomial::new(vec![P1 - 2, 1, 5, 1], P1).unwrap();
+
+        let (sum, trace) = a.add::<Val>(&b).unwrap();
+        assert_eq!(sum.coeffs(), &[P1 - 1, 3, 4, 4]);
+        // out starts at 2N+1 in the PolyAddAir trace
+        let row = trace.row_slice(0);
+        for i in 0..4 {
+            assert_eq!(row[9+i], Val::from_canonical_u32(sum.coeffs()[i]));
+        }
+        drop(row);
+
+        let (diff, _) = a.sub::<Val>(&b).unwrap();
+        assert_eq!(diff.coeffs(), &[3, 1, P1 - 6, 2]);
+
+        // small operands keep the convolution easy to check by hand
+        let x = Polynomial::new(vec![1, 2, 0, 1], P1).unwrap();
+        let y = Polynomial::new(vec![3, 0, 1, 1], P1).unwrap();
+
+        // (1 + 2X + X^3)(3 + X^2 + X^3) = 3 + 6X + X^2 + 6X^3 + 2X^4 + X^5 + X^6
+        let (product, _) = x.mul::<Val>(&y).unwrap();
+        assert_eq!(product.coeffs(), &[3, 6, 1, 6, 2, 1, 1]);
+
+        // X^4 = -1: (3 - 2) + (6 - 1)X + (1 - 1)X^2 + 6X^3
+        let (negacyclic, _) = x.negacyclic_mul::<Val>(&y).unwrap();
+        assert_eq!(negacyclic.coeffs(), &[1, 5, 0, 6]);
+    }
+
+    #[test]
+    fn test_polynomial_mismatch() {
+        let a = Polynomial::new(vec![1, 2, 3, 4], P1).unwrap();
+        let b = Polynomial::new(vec![1, 2, 3, 4], P2).unwrap();
+        assert_eq!(a.add::<Val>(&b).unwrap_err(), GadgetError::ModulusMismatch { expected: P1, actual: P2 });
+        assert_eq!(a.negacyclic_mul::<Val>(&b).unwrap_err(), GadgetError::ModulusMismatch { expected: P1, actual: P2 });
+
+        let c = Polynomial::new(vec![1, 2], P1).unwrap();
+        assert_eq!(a.sub::<Val>(&c).unwrap_err(), GadgetError::LengthMismatch { expected: 4, actual: 2 });
+
+        assert_eq!(Polynomial::new(vec![0, P1], P1).unwrap_err(), GadgetError::CoefficientOutOfRange { index: 1, value: P1, modulus: P1 });
+    }
+}
@@ -0,0 +1,172 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::ciphertext::Ciphertext;
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace, negacyclic_layout, negacyclic_reduced, negacyclic_width};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct PtCtMulAir {
+    pub ct: Ciphertext,
+    pub m: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PtCtMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(ct: Ciphertext, m: Vec<u32>, modulus: u32) -> Self {
+        Self { ct, m, modulus, n: N }
+    }
+
+    // The 2 negacyclic multiplications c0 * m and c1 * m, in the order their sub-traces are laid out
+    fn channels(&self) -> [NegacyclicMulAir; 2] {
+        [&self.ct.c0, &self.ct.c1].map(|c| NegacyclicMulAir { a: c.clone(), b: self.m.clone(), modulus: self.modulus, n: self.n })
+    }
+}
+
+/*
+Plaintext-Ciphertext Multiplication Air
+Input:
+- ct = (c0, c1): ciphertext, a pair of polynomials with N coefficients
+- m = m[0] + m[1] * X + ... + m[N-1] * X^{N-1}: plaintext polynomial
+- mod: FHE ciphertext modulus
+Output:
+- out = (out0, out1) where out0 = c0 * m and out1 = c1 * m in Z_mod[X]/(X^N+1)
+
+Note:
+- Each component is an independent NegacyclicMulAir with m as its second input, and the 2 of them are placed side by side in one row.
+- Both b slots are pinned to self.m on the first row, and additionally constrained equal to each other,
+so the 2 multiplications share the same plaintext columns.
+*/
+impl<F: Field> BaseAir<F> for PtCtMulAir {
+    // Air Table looks like this
+    // row:[   NegacyclicMulAir c0 * m   ][   NegacyclicMulAir c1 * m   ]
+    //     [0..........................................................0]
+    //     [0..........................................................0]
+    //     [0..........................................................0]
+    fn width(&self) -> usize {
+        2*negacyclic_width(self.n)
+    }
+}
+
+impl GadgetLayout for PtCtMulAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .nest("c0_m", negacyclic_layout(self.n))
+            .nest("c1_m", negacyclic_layout(self.n))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PtCtMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let channel_width = negacyclic_width(n);
+        for (k, channel) in self.channels().iter().enumerate() {
+            channel.eval_row(builder, &row[k*channel_width..(k+1)*channel_width]);
+        }
+
+        // Enforce the plaintext is shared: b of the c1 channel === b of the c0 channel
+        for i in 0..n {
+            builder.assert_eq(row[channel_width+n+i], row[n+i]);
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_ptctmul_trace<F: Field>(c0: Vec<u32>, c1: Vec<u32>, m: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let channel_traces = [c0, c1].into_iter()
+        .map(|c| generate_negacyclic_mul_trace::<F>(c, m.clone(), modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Concatenate the channel traces row by row
+    let width = 2*negacyclic_width(n);
+    let height = channel_traces[0].height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        for trace in channel_traces.iter() {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_field::AbstractField;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_ptct_mul_monomial() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate a random ciphertext with n coefficients in the range of [0, P1)
+        let n = 16;
+        let mut rng = thread_rng();
+        let c0: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let c1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // m = X^k rotates each component by k, negating the coefficients that wrap past X^N = -1:
+        // (c * X^k)[i+k] = c[i] for i+k < N, and (c * X^k)[i+k-N] = -c[i] otherwise
+        let k = 5;
+        let mut m = vec![0u32; n];
+        m[k] = 1;
+        let rotate = |c: &[u32]| -> Vec<u32> {
+            let mut rotated = vec![0u32; n];
+            for i in 0..n {
+                if i + k < n {
+                    rotated[i+k] = c[i];
+                } else {
+                    rotated[i+k-n] = (P1 - c[i]) % P1;
+                }
+            }
+            rotated
+        };
+
+        let air = PtCtMulAir { ct: Ciphertext::new(c0.clone(), c1.clone()), m: m.clone(), modulus: P1, n };
+
+        let trace = generate_ptctmul_trace::<Val>(c0.clone(), c1.clone(), m, P1, n).unwrap();
+
+        // reduced starts at negacyclic_reduced(N) within each channel
+        let row = trace.row_slice(0);
+        let channel_width = negacyclic_width(n);
+        for (c, expected) in [rotate(&c0), rotate(&c1)].iter().enumerate() {
+            for i in 0..n {
+                assert_eq!(row[c*channel_width + negacyclic_reduced(n) + i], Val::from_canonical_u32(expected[i]));
+            }
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_ptct_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let (c0, c1, m) = (random_poly(), random_poly(), random_poly());
+        let air = PtCtMulAir { ct: Ciphertext::new(c0.clone(), c1.clone()), m: m.clone(), modulus: P1, n };
+
+        // either channel c0 * m or c1 * m with a wrong raw product, and its quotients re-solved
+        assert_rejects_forged_product(&air, &generate_ptctmul_trace(c0, c1, m, P1, n).unwrap(), |_| P1);
+    }
+}
@@ -0,0 +1,292 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::bit_decompose::{bits as to_bits, eval_bit_decompose};
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Number of bits per coefficient: every modulus of this crate is below 2^31
+pub const RANGE_CHECK_BITS: usize = 31;
+
+// Largest modulus whose bits fit the RANGE_CHECK_BITS bits the comparison is done over
+pub const MAX_RANGE_CHECK_MODULUS: u32 = (1 << RANGE_CHECK_BITS) - 1;
+
+// Columns of one value range checked by eval_range_checks(): its bits, then its prefix equality flags
+pub const RANGE_CHECK_WIDTH: usize = 2*RANGE_CHECK_BITS;
+
+// Define AIR constraint inputs
+pub struct RangeCheckAir {
+    pub a: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl RangeCheckAir {
+    // Construct the AIR with the default number of coefficients params::N, for a modulus of at most MAX_RANGE_CHECK_MODULUS
+    pub fn new(a: Vec<u32>, modulus: u32) -> Result<Self, GadgetError> {
+        check_range_modulus(modulus)?;
+        Ok(Self { a, modulus, n: N })
+    }
+}
+
+// Check that the bits of `modulus` fit in RANGE_CHECK_BITS: above that, the comparison would drop its top bits
+// and prove the coefficients below a smaller bound
+pub fn check_range_modulus(modulus: u32) -> Result<(), GadgetError> {
+    if modulus > MAX_RANGE_CHECK_MODULUS {
+        return Err(GadgetError::ModulusTooLarge { modulus, max: MAX_RANGE_CHECK_MODULUS });
+    }
+    Ok(())
+}
+
+/*
+Range Check Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- bits = bits[i][0], ..., bits[i][30]: little-endian bit decomposition of a[i]
+- eq = eq[i][0], ..., eq[i][30]: eq[i][k] = 1 if bits[i][k..31] match the bits of mod at the same positions, otherwise 0
+
+Note:
+- A decomposition into 31 bits alone does not prove a[i] < mod, and cannot even fix a[i] as an integer
+over a 31-bits field such as Mersenne31, where every element has a 31-bits representative.
+Instead, the bits are compared with the (constant) bits m[k] of mod from the MSB down, which is an integer comparison:
+    sum_k bits[i][k] * 2^k < mod  <=>  there is a k with m[k] = 1, bits[i][k] = 0 and bits[i][k+1..31] = m[k+1..31]
+- eq[i][k] carries the "all higher bits are equal" prefix, and the disjoint terms eq[i][k+1] * (1 - bits[i][k]) over the k
+with m[k] = 1 sum to 1 exactly when a[i] < mod.
+- The proven integer is below mod, which check_range_modulus() bounds by MAX_RANGE_CHECK_MODULUS = 2^31 - 1 only.
+It is the canonical representative of a[i], so that 0 <= a[i] < mod, when mod is also at most the order of the native field:
+this holds for every such mod over Mersenne31 (n = 2^31 - 1) and Goldilocks, but not over BabyBear (2^31 - 2^27 + 1),
+where a mod above the order of the field lets two integers below mod decompose the same a[i].
+P1, P2 and P3 are below all three orders.
+*/
+impl<F: Field> BaseAir<F> for RangeCheckAir {
+    // Air Table looks like this
+    // row:[ a: N ][ bits: 31N ][ eq: 31N ]
+    //     ^input^^-calculated by generate_range_check_trace-^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        (2*RANGE_CHECK_BITS+1)*self.n
+    }
+}
+
+impl GadgetLayout for RangeCheckAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("bits", RANGE_CHECK_BITS*self.n)
+            .push("eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for RangeCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+
+        for i in 0..n {
+            let bits = n + i*RANGE_CHECK_BITS;
+            let eq = n + (n+i)*RANGE_CHECK_BITS;
+            eval_range_check(builder, row[i].into(), &row[bits..bits+RANGE_CHECK_BITS], &row[eq..eq+RANGE_CHECK_BITS], self.modulus);
+        }
+    }
+}
+
+// Enforce 0 <= value < modulus, given the bit decomposition `bits` of value and the prefix equality flags `eq`
+// (both little-endian, RANGE_CHECK_BITS long), so other gadgets can range check their own columns
+pub(crate) fn eval_range_check<AB: AirBuilder>(builder: &mut AB, value: AB::Expr, bits: &[AB::Var], eq: &[AB::Var], modulus: u32) {
+    // Enforce value === sum_k bits[k] * 2^k
+    eval_bit_decompose(builder, value, &bits[..RANGE_CHECK_BITS]);
+
+    let bound: Vec<bool> = to_bits(modulus as u64, RANGE_CHECK_BITS).collect();
+    eval_less_than(builder, bits, eq, &bound);
+}
+
+// Enforce 0 <= values[i] < modulus on the first row, where witness[i*RANGE_CHECK_WIDTH..(i+1)*RANGE_CHECK_WIDTH]
+// holds the bits then the prefix equality flags of values[i], as assigned by assign_range_check().
+// Gadgets padded with zero rows range check their outputs this way: an all-zero block fails the comparison
+// for the moduli whose bit 30 is 0, so it cannot be enforced on the padding rows.
+pub(crate) fn eval_range_checks<AB: AirBuilder>(builder: &mut AB, values: &[AB::Expr], witness: &[AB::Var], modulus: u32) {
+    for (i, value) in values.iter().enumerate() {
+        let block = &witness[i*RANGE_CHECK_WIDTH..(i+1)*RANGE_CHECK_WIDTH];
+        eval_range_check(&mut builder.when_first_row(), value.clone(), &block[..RANGE_CHECK_BITS], &block[RANGE_CHECK_BITS..], modulus);
+    }
+}
+
+// Enforce that the integer with little-endian bits `bits` is below the constant with little-endian bits `bound`,
+// given the prefix equality flags `eq` computed by less_than_columns(). `bits` must already be constrained to be boolean.
+pub(crate) fn eval_less_than<AB: AirBuilder>(builder: &mut AB, bits: &[AB::Var], eq: &[AB::Var], bound: &[bool]) {
+    let top = bound.len() - 1;
+
+    // bits[k] when m[k] = 1, and 1 - bits[k] when m[k] = 0: 1 exactly when both bits agree
+    let agree = |k: usize| -> AB::Expr {
+        if bound[k] { bits[k].into() } else { AB::Expr::one() - bits[k] }
+    };
+
+    // Enforce eq[top] = agree(top) and eq[k] = eq[k+1] * agree(k)
+    builder.assert_eq(eq[top], agree(top));
+    for k in (0..top).rev() {
+        builder.assert_eq(eq[k], eq[k+1] * agree(k));
+    }
+
+    // Enforce sum over k with m[k] = 1 of eq[k+1] * (1 - bits[k]) === 1, where the prefix above the MSB is empty (= 1)
+    let mut less_than = AB::Expr::zero();
+    for k in 0..bound.len() {
+        if bound[k] {
+            let prefix: AB::Expr = if k == top { AB::Expr::one() } else { eq[k+1].into() };
+            less_than = less_than + prefix * (AB::Expr::one() - bits[k]);
+        }
+    }
+    builder.assert_one(less_than);
+}
+
+// Little-endian bits of value and the prefix equality flags against modulus, as laid out by eval_range_check
+pub(crate) fn range_check_columns(value: u32, modulus: u32) -> (Vec<bool>, Vec<bool>) {
+    let bits: Vec<bool> = to_bits(value as u64, RANGE_CHECK_BITS).collect();
+    let bound: Vec<bool> = to_bits(modulus as u64, RANGE_CHECK_BITS).collect();
+    let eq = less_than_columns(&bits, &bound);
+    (bits, eq)
+}
+
+// Assign the RANGE_CHECK_WIDTH columns of value checked by eval_range_checks() to block
+pub(crate) fn assign_range_check<F: Field>(block: &mut [F], value: u32, modulus: u32) {
+    let (bits, eq) = range_check_columns(value, modulus);
+    for (col, flag) in block.iter_mut().zip(bits.into_iter().chain(eq)) {
+        *col = F::from_bool(flag);
+    }
+}
+
+// The blocks of assign_range_check() for every value, one after the other
+pub(crate) fn range_check_witness<F: Field>(values: &[u32], modulus: u32) -> Vec<F> {
+    let mut witness = vec![F::zero(); values.len() * RANGE_CHECK_WIDTH];
+    for (block, &value) in witness.chunks_mut(RANGE_CHECK_WIDTH).zip(values) {
+        assign_range_check(block, value, modulus);
+    }
+    witness
+}
+
+// Prefix equality flags eq[k] = (bits[k..] == bound[k..]), as laid out by eval_less_than
+pub(crate) fn less_than_columns(bits: &[bool], bound: &[bool]) -> Vec<bool> {
+    let mut eq = vec![false; bound.len()];
+    let mut prefix = true;
+    for k in (0..bound.len()).rev() {
+        prefix = prefix && bits[k] == bound[k];
+        eq[k] = prefix;
+    }
+    eq
+}
+
+// Define a function to generate execution trace
+// Coefficients are not checked against the modulus here: proving that is the job of this gadget
+pub fn generate_range_check_trace<F: Field>(a:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if a.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: a.len() });
+    }
+    check_range_modulus(modulus)?;
+    let width = (2*RANGE_CHECK_BITS+1)*n;
+
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial to the row
+    for i in 0..n {
+        row.push(F::from_wrapped_u32(a[i]));
+    }
+
+    // Assign bits and prefix equality flags of every coefficient to the row
+    let columns: Vec<(Vec<bool>, Vec<bool>)> = a.iter().map(|&c| range_check_columns(c, modulus)).collect();
+    for (bits, _) in columns.iter() {
+        row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+    }
+    for (_, eq) in columns.iter() {
+        row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows:
+    // the comparison is enforced on every row, and an all-zero row claims 0 is not below the modulus
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use std::panic::{self, AssertUnwindSafe};
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_range_check() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate a random input polynomial with n coefficients in the range of [0, P1), including both ends
+        let n = 16;
+        let mut rng = thread_rng();
+        let mut random_poly: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+        random_poly[0] = 0;
+        random_poly[n-1] = P1 - 1;
+
+        let air = RangeCheckAir { a:random_poly.clone(), modulus:P1, n };
+
+        let trace = generate_range_check_trace::<Val>(random_poly, P1, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_range_check_out_of_range() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        for bad in [P1, P1 + 1, (1 << RANGE_CHECK_BITS) - 2] {
+            let mut random_poly: Vec<u32> = (0..n).map(|_| {
+                rng.gen_range(0..P1)
+            }).collect();
+            random_poly[n/2] = bad;
+
+            let air = RangeCheckAir { a:random_poly.clone(), modulus:P1, n };
+            let trace = generate_range_check_trace::<Val>(random_poly, P1, n).unwrap();
+
+            // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+                let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+                verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+            }));
+            assert!(!matches!(result, Ok(true)), "proof with coefficient {} >= {} was accepted", bad, P1);
+        }
+    }
+
+    #[test]
+    fn test_range_check_modulus_too_large() {
+        // the largest 31-bits modulus is accepted, 2^31 would lose its top bit in the comparison
+        assert!(RangeCheckAir::new(vec![0; N], MAX_RANGE_CHECK_MODULUS).is_ok());
+        for modulus in [MAX_RANGE_CHECK_MODULUS + 1, u32::MAX] {
+            let err = GadgetError::ModulusTooLarge { modulus, max: MAX_RANGE_CHECK_MODULUS };
+            assert_eq!(RangeCheckAir::new(vec![0; N], modulus).err(), Some(err.clone()));
+            assert_eq!(generate_range_check_trace::<Val>(vec![0; 4], modulus, 4).unwrap_err(), err);
+        }
+    }
+}
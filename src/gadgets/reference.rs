@@ -0,0 +1,227 @@
+/*
+Reference FHE arithmetic for differential testing
+
+Note:
+- Plain u128 schoolbook arithmetic over Z_q[X] and Z_q[X]/(X^N+1), written independently of the gadgets
+and their trace generators, so it is an oracle for what the proven outputs should be.
+- q may be any modulus below 2^127, including P = P1 * P2 * P3, so the RNS channels can be checked
+against the product in the original ring.
+- Only compiled for tests: it favours obviousness over speed.
+*/
+
+use crate::params::{P1, P2, P3};
+
+// x * y mod q by double-and-add, for q < 2^127
+pub fn mul_mod(x: u128, y: u128, q: u128) -> u128 {
+    let (mut x, mut y) = (x % q, y % q);
+    let mut result = 0;
+    while y > 0 {
+        if y & 1 == 1 {
+            result = (result + x) % q;
+        }
+        x = (x << 1) % q;
+        y >>= 1;
+    }
+    result
+}
+
+// (a + b) mod q, coefficient-wise
+pub fn add(a: &[u128], b: &[u128], q: u128) -> Vec<u128> {
+    a.iter().zip(b).map(|(&x, &y)| (x % q + y % q) % q).collect()
+}
+
+// (a - b) mod q, coefficient-wise
+pub fn sub(a: &[u128], b: &[u128], q: u128) -> Vec<u128> {
+    a.iter().zip(b).map(|(&x, &y)| (x % q + q - y % q) % q).collect()
+}
+
+// -a mod q, coefficient-wise
+pub fn neg(a: &[u128], q: u128) -> Vec<u128> {
+    a.iter().map(|&x| (q - x % q) % q).collect()
+}
+
+// c * a mod q, coefficient-wise
+pub fn scalar_mul(a: &[u128], c: u128, q: u128) -> Vec<u128> {
+    a.iter().map(|&x| mul_mod(x, c, q)).collect()
+}
+
+// a * b in Z_q[X], with the 2N-1 coefficients of the full product
+pub fn mul(a: &[u128], b: &[u128], q: u128) -> Vec<u128> {
+    let mut out = vec![0u128; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            out[i+j] = (out[i+j] + mul_mod(x, y, q)) % q;
+        }
+    }
+    out
+}
+
+// a * b in Z_q[X]/(X^N+1): X^{i+j} with i + j >= N wraps around to -X^{i+j-N}
+pub fn negacyclic_mul(a: &[u128], b: &[u128], q: u128) -> Vec<u128> {
+    let n = a.len();
+    let mut out = vec![0u128; n];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            let term = mul_mod(x, y, q);
+            if i + j < n {
+                out[i+j] = (out[i+j] + term) % q;
+            } else {
+                out[i+j-n] = (out[i+j-n] + q - term) % q;
+            }
+        }
+    }
+    out
+}
+
+// a * b in Z_modulus[X]/(X^n+1) on the u32 coefficients of the gadgets: the expected reduced output of every
+// negacyclic multiplication gadget, by u128 schoolbook multiplication and X^n = -1, independently of negacyclic_coeffs()
+pub fn negacyclic_mul_ref(a: &[u32], b: &[u32], modulus: u32, n: usize) -> Vec<u32> {
+    assert!(a.len() == n && b.len() == n, "expected 2 polynomials with {} coefficients", n);
+    let q = modulus as u128;
+    let mut out = vec![0u128; n];
+    for i in 0..n {
+        for j in 0..n {
+            let term = a[i] as u128 * b[j] as u128 % q;
+            if i + j < n {
+                out[i+j] = (out[i+j] + term) % q;
+            } else {
+                out[i+j-n] = (out[i+j-n] + q - term) % q;
+            }
+        }
+    }
+    out.into_iter().map(|c| c as u32).collect()
+}
+
+// Residues of every coefficient in the RNS channels P1, P2, P3
+pub fn rns_reduce(a: &[u128]) -> [Vec<u128>; 3] {
+    [P1, P2, P3].map(|p| a.iter().map(|&x| x % p as u128).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField32;
+    use p3_matrix::Matrix;
+    use p3_matrix::dense::RowMajorMatrix;
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::gadgets::config::Val;
+    use crate::gadgets::layout::GadgetLayout;
+    use crate::gadgets::mul::{MulShape, PolyMulAir, generate_polymul_trace};
+    use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace};
+    use crate::gadgets::neg::{PolyNegAir, generate_polyneg_trace};
+    use crate::gadgets::rns::{RnsPolyMulAir, crt_recombine, generate_rns_polymul_trace};
+    use crate::gadgets::scalar_mul::{PolyScalarMulAir, generate_polyscalarmul_trace};
+    use crate::gadgets::sub::{PolySubAir, generate_polysub_trace};
+    use crate::params::P;
+
+    // Number of random inputs every gadget is compared on
+    const ROUNDS: usize = 20;
+
+    // Values of the block `name` of `air` on the first row of `trace`
+    fn block<A: GadgetLayout>(air: &A, trace: &RowMajorMatrix<Val>, name: &str) -> Vec<u128> {
+        let range = air.layout().get(name).unwrap();
+        trace.row_slice(0)[range].iter().map(|x| x.as_canonical_u32() as u128).collect()
+    }
+
+    fn random_poly(n: usize, q: u128) -> Vec<u128> {
+        let mut rng = thread_rng();
+        (0..n).map(|_| rng.gen_range(0..q)).collect()
+    }
+
+    fn to_u32(poly: &[u128]) -> Vec<u32> {
+        poly.iter().map(|&c| c as u32).collect()
+    }
+
+    #[test]
+    fn test_reference_identities() {
+        // X^{N-1} * X = X^N = -1 in Z_q[X]/(X^N+1)
+        let (n, q) = (4, P1 as u128);
+        let (mut x_top, mut x) = (vec![0u128; n], vec![0u128; n]);
+        x_top[n-1] = 1;
+        x[1] = 1;
+        assert_eq!(negacyclic_mul(&x_top, &x, q), vec![q - 1, 0, 0, 0]);
+
+        // the product mod P agrees with the CRT of its residues
+        let a = random_poly(n, P);
+        let b = random_poly(n, P);
+        let product = negacyclic_mul(&a, &b, P);
+        let channels = rns_reduce(&a).into_iter().zip(rns_reduce(&b)).zip([P1, P2, P3])
+            .map(|((a_k, b_k), p)| negacyclic_mul(&a_k, &b_k, p as u128))
+            .collect::<Vec<_>>();
+        for i in 0..n {
+            assert_eq!(crt_recombine([0, 1, 2].map(|k| channels[k][i] as u32)), product[i]);
+        }
+    }
+
+    #[test]
+    fn test_negacyclic_mul_ref() {
+        // (1 + 2X) * (3 + 4X) = 3 + 10X + 8X^2 = (3 - 8) + 10X = 2 + 3X in Z_7[X]/(X^2+1)
+        assert_eq!(negacyclic_mul_ref(&[1, 2], &[3, 4], 7, 2), vec![2, 3]);
+        // (1 + X^3) * X = X + X^4 = -1 + X in Z_17[X]/(X^4+1)
+        assert_eq!(negacyclic_mul_ref(&[1, 0, 0, 1], &[0, 1, 0, 0], 17, 4), vec![16, 1, 0, 0]);
+
+        // it agrees with the u128 negacyclic_mul() for 31-bits moduli
+        let n = 8;
+        for p in [P1, P2, P3] {
+            let (a, b) = (random_poly(n, p as u128), random_poly(n, p as u128));
+            let expected = negacyclic_mul(&a, &b, p as u128);
+            assert_eq!(negacyclic_mul_ref(&to_u32(&a), &to_u32(&b), p, n), to_u32(&expected));
+        }
+    }
+
+    #[test]
+    fn test_differential_linear_gadgets() {
+        let (n, q) = (8, P1 as u128);
+        let mut rng = thread_rng();
+        for _ in 0..ROUNDS {
+            let (a, b) = (random_poly(n, q), random_poly(n, q));
+            let c = rng.gen_range(0..q);
+
+            let air = PolyAddAir { a:to_u32(&a), b:to_u32(&b), modulus:P1, n };
+            let trace = generate_polyadd_trace::<Val>(to_u32(&a), to_u32(&b), P1, n).unwrap();
+            assert_eq!(block(&air, &trace, "out"), add(&a, &b, q));
+
+            let air = PolySubAir { a:to_u32(&a), b:to_u32(&b), modulus:P1, n };
+            let trace = generate_polysub_trace::<Val>(to_u32(&a), to_u32(&b), P1, n).unwrap();
+            assert_eq!(block(&air, &trace, "out"), sub(&a, &b, q));
+
+            let air = PolyNegAir { a:to_u32(&a), modulus:P1, n };
+            let trace = generate_polyneg_trace::<Val>(to_u32(&a), P1, n).unwrap();
+            assert_eq!(block(&air, &trace, "out"), neg(&a, q));
+
+            let air = PolyScalarMulAir::with_n(to_u32(&a), c as u32, P1, n).unwrap();
+            let trace = generate_polyscalarmul_trace::<Val>(to_u32(&a), c as u32, P1, n).unwrap();
+            assert_eq!(block(&air, &trace, "out"), scalar_mul(&a, c, q));
+        }
+    }
+
+    #[test]
+    fn test_differential_mul_gadgets() {
+        let n = 8;
+        for _ in 0..ROUNDS {
+            for p in [P1, P2, P3] {
+                let q = p as u128;
+                let (a, b) = (random_poly(n, q), random_poly(n, q));
+
+                let air = PolyMulAir { a:to_u32(&a), b:to_u32(&b), modulus:p, n, shape: MulShape::Row };
+                let trace = generate_polymul_trace::<Val>(to_u32(&a), to_u32(&b), p, n).unwrap();
+                assert_eq!(block(&air, &trace, "out"), mul(&a, &b, q));
+
+                let air = NegacyclicMulAir { a:to_u32(&a), b:to_u32(&b), modulus:p, n };
+                let trace = generate_negacyclic_mul_trace::<Val>(to_u32(&a), to_u32(&b), p, n).unwrap();
+                assert_eq!(block(&air, &trace, "reduced"), negacyclic_mul(&a, &b, q));
+            }
+
+            // every RNS channel holds the residue of the product mod P
+            let (a, b) = (random_poly(n, P), random_poly(n, P));
+            let air = RnsPolyMulAir::new(&a, &b, n);
+            let trace = generate_rns_polymul_trace::<Val>(&a, &b, n).unwrap();
+            let product = mul(&a, &b, P);
+            let residues = rns_reduce(&product);
+            for k in 0..3 {
+                assert_eq!(block(&air, &trace, &format!("channel[{}].out", k)), residues[k]);
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::ops::Range;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::PolyAddAir;
+use crate::gadgets::error::{check_air_inputs, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::PolyMulAir;
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::sub::PolySubAir;
+
+/*
+Gadget registry: the 2-input polynomial gadgets, selected by operation name at runtime
+
+Note:
+- BoxedAir is a closed enum over the gadgets rather than a Box<dyn ...>: the Air trait is generic over the builder,
+so it is not object safe, while an enum implements Air, PolynomialOpAir and GadgetLayout by delegating to its variant.
+Every caller of a ZkAir (prove(), verify(), io.rs) takes a BoxedAir as it is.
+- A new gadget is registered by adding a variant, its name to OPERATIONS and a branch to every match below.
+*/
+
+// Names of the registered operations, in the order of the variants
+pub const OPERATIONS: [&str; 3] = ["add", "sub", "mul"];
+
+// One of the registered gadgets
+pub enum BoxedAir {
+    Add(PolyAddAir),
+    Sub(PolySubAir),
+    Mul(PolyMulAir),
+}
+
+impl BoxedAir {
+    // Construct the gadget registered as `name` with n coefficients, checked as in PolyAddAir::with_n
+    pub fn from_name(name: &str, a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        match name {
+            "add" => Ok(BoxedAir::Add(PolyAddAir::with_n(a, b, modulus, n)?)),
+            "sub" => {
+                check_air_inputs(&[&a, &b], n, modulus)?;
+                Ok(BoxedAir::Sub(PolySubAir { a, b, modulus, n }))
+            }
+            "mul" => Ok(BoxedAir::Mul(PolyMulAir::with_n(a, b, modulus, n)?)),
+            _ => Err(GadgetError::UnknownOperation { name: name.to_string() }),
+        }
+    }
+
+    // Name of the operation, the one from_name() was called with
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoxedAir::Add(_) => OPERATIONS[0],
+            BoxedAir::Sub(_) => OPERATIONS[1],
+            BoxedAir::Mul(_) => OPERATIONS[2],
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for BoxedAir {
+    fn width(&self) -> usize {
+        match self {
+            BoxedAir::Add(air) => BaseAir::<F>::width(air),
+            BoxedAir::Sub(air) => BaseAir::<F>::width(air),
+            BoxedAir::Mul(air) => BaseAir::<F>::width(air),
+        }
+    }
+}
+
+impl GadgetLayout for BoxedAir {
+    fn layout(&self) -> TraceLayout {
+        match self {
+            BoxedAir::Add(air) => air.layout(),
+            BoxedAir::Sub(air) => air.layout(),
+            BoxedAir::Mul(air) => air.layout(),
+        }
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for BoxedAir {
+    fn eval(&self, builder: &mut AB) {
+        self.eval_poly_op(builder);
+    }
+}
+
+impl PolynomialOpAir for BoxedAir {
+    fn a(&self) -> &[u32] {
+        match self {
+            BoxedAir::Add(air) => air.a(),
+            BoxedAir::Sub(air) => air.a(),
+            BoxedAir::Mul(air) => air.a(),
+        }
+    }
+
+    fn b(&self) -> &[u32] {
+        match self {
+            BoxedAir::Add(air) => air.b(),
+            BoxedAir::Sub(air) => air.b(),
+            BoxedAir::Mul(air) => air.b(),
+        }
+    }
+
+    fn modulus(&self) -> u32 {
+        match self {
+            BoxedAir::Add(air) => air.modulus(),
+            BoxedAir::Sub(air) => air.modulus(),
+            BoxedAir::Mul(air) => air.modulus(),
+        }
+    }
+
+    fn n(&self) -> usize {
+        match self {
+            BoxedAir::Add(air) => air.n(),
+            BoxedAir::Sub(air) => air.n(),
+            BoxedAir::Mul(air) => air.n(),
+        }
+    }
+
+    fn output_columns(&self) -> Range<usize> {
+        match self {
+            BoxedAir::Add(air) => air.output_columns(),
+            BoxedAir::Sub(air) => air.output_columns(),
+            BoxedAir::Mul(air) => air.output_columns(),
+        }
+    }
+
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        match self {
+            BoxedAir::Add(air) => air.eval_op(builder, row),
+            BoxedAir::Sub(air) => air.eval_op(builder, row),
+            BoxedAir::Mul(air) => air.eval_op(builder, row),
+        }
+    }
+
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError> {
+        match self {
+            BoxedAir::Add(air) => air.generate_trace(),
+            BoxedAir::Sub(air) => air.generate_trace(),
+            BoxedAir::Mul(air) => air.generate_trace(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_field::AbstractField;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    #[test]
+    fn test_registry_add_by_name() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = BoxedAir::from_name("add", random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        assert_eq!(air.name(), "add");
+        assert_eq!(BaseAir::<Val>::width(&air), air.layout().width());
+
+        // the output is bound to the public values, as for PolyAddAir itself
+        let trace = air.generate_trace::<Val>().unwrap();
+        let public_values = air.public_outputs(&trace);
+        let expected: Vec<Val> = (0..n).map(|i| Val::from_canonical_u32((random_poly1[i] + random_poly2[i]) % P1)).collect();
+        assert_eq!(public_values, expected);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &public_values).expect("verification failed");
+    }
+
+    #[test]
+    fn test_registry_names() {
+        for name in OPERATIONS {
+            let air = BoxedAir::from_name(name, vec![1, 2], vec![3, 4], P1, 2).unwrap();
+            assert_eq!(air.name(), name);
+        }
+
+        assert_eq!(BoxedAir::from_name("div", vec![1, 2], vec![3, 4], P1, 2).err(), Some(GadgetError::UnknownOperation { name: "div".to_string() }));
+        // the inputs are checked for every operation
+        assert_eq!(BoxedAir::from_name("sub", vec![1, 2], vec![3], P1, 2).err(), Some(GadgetError::LengthMismatch { expected: 2, actual: 1 }));
+    }
+}
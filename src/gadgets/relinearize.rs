@@ -0,0 +1,326 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_low, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::ciphertext::Ciphertext;
+use crate::gadgets::decompose::{GadgetDecomposeAir, decompose_layout, decompose_width, digit_column, digit_polynomials, generate_decompose_trace};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::inner_product::{InnerProductAir, generate_inner_product_trace, inner_product_layout, inner_product_output, inner_product_output_range, inner_product_width};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::negacyclic::{negacyclic_coeffs, negacyclic_width};
+use crate::gadgets::range_check::RANGE_CHECK_WIDTH;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct RelinearizeAir {
+    // (c0, c1, c2): degree-2 ciphertext, e.g. the output of a ciphertext-ciphertext multiplication
+    pub ct: Ciphertext,
+    pub c2: Vec<u32>,
+    // relinearization key: rlk0[l], rlk1[l] encrypt base^l * s^2 for l = [0..levels)
+    pub rlk0: Vec<Vec<u32>>,
+    pub rlk1: Vec<Vec<u32>>,
+    pub base: u32,
+    pub levels: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl RelinearizeAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(ct: Ciphertext, c2: Vec<u32>, rlk0: Vec<Vec<u32>>, rlk1: Vec<Vec<u32>>, base: u32, levels: usize, modulus: u32) -> Self {
+        Self { ct, c2, rlk0, rlk1, base, levels, modulus, n: N }
+    }
+
+    fn decompose_air(&self) -> GadgetDecomposeAir {
+        GadgetDecomposeAir { poly: self.c2.clone(), base: self.base, levels: self.levels, modulus: self.modulus, n: self.n }
+    }
+
+    // The 2 inner products <digits(c2), rlk0> and <digits(c2), rlk1>, in the order their sub-traces are laid out
+    fn inner_products(&self) -> [InnerProductAir; 2] {
+        let digits = digit_polynomials(&self.c2, self.base, self.levels);
+        [&self.rlk0, &self.rlk1].map(|rlk| InnerProductAir { a: digits.clone(), b: rlk.clone(), modulus: self.modulus, n: self.n })
+    }
+}
+
+/*
+Relinearization Air
+Input:
+- (c0, c1, c2): degree-2 ciphertext with N coefficients per polynomial
+- rlk = (rlk0[l], rlk1[l]) for l = [0..L): relinearization key
+- base: B, levels: L of the gadget decomposition of c2
+- mod: FHE ciphertext modulus
+Output:
+- (c0', c1') where c0' = c0 + sum_l d_l * rlk0[l] and c1' = c1 + sum_l d_l * rlk1[l] in Z_mod[X]/(X^N+1),
+with d_l = sum_i digit[i][l] * X^i the l-th base-B digit polynomial of c2
+
+Note:
+- The row is the composition of 3 sub-AIRs, each embedded through its eval_row():
+    1) GadgetDecomposeAir of c2, proving the digits d_l
+    2) InnerProductAir <d, rlk0> and InnerProductAir <d, rlk1>
+and the digit columns of 1) are constrained equal to the a_l inputs of both inner products, so the products use the proven digits.
+- The final additions c0 + <d, rlk0> and c1 + <d, rlk1> carry a bit per coefficient as in CiphertextAddAir, through eval_reduced_sum():
+the outputs are range checked and the sums are also enforced mod 2^8 over the lowest limbs of c and of the range checked inner products.
+- GadgetDecomposeAir repeats its row on every row of the trace, while the other parts are 0 below the first row
+(which passes their constraints), so the digit links and the inputs are only enforced on the first row.
+*/
+impl<F: Field> BaseAir<F> for RelinearizeAir {
+    // Air Table looks like this
+    // row:[ GadgetDecomposeAir c2 ][ InnerProductAir rlk0 ][ InnerProductAir rlk1 ][c0: N][c1: N][out0: N][carry0: N][out1: N][carry1: N][out_range: 124N][low_carry_bits: 46N]
+    //     [ repeated             ][0...............................................................................................................................0]
+    //     [ repeated             ][0...............................................................................................................................0]
+    //     [ repeated             ][0...............................................................................................................................0]
+    fn width(&self) -> usize {
+        relinearize_width(self.base, self.levels, self.n)
+    }
+}
+
+impl GadgetLayout for RelinearizeAir {
+    fn layout(&self) -> TraceLayout {
+        relinearize_layout(self.base, self.levels, self.n)
+    }
+}
+
+// Layout of RelinearizeAir, shared with the gadgets embedding it
+pub(crate) fn relinearize_layout(base: u32, levels: usize, n: usize) -> TraceLayout {
+    TraceLayout::new()
+        .nest("c2", decompose_layout(base, levels, n))
+        .nest("rlk0", inner_product_layout(levels, n))
+        .nest("rlk1", inner_product_layout(levels, n))
+        .push("c0", n)
+        .push("c1", n)
+        .push("out0", n)
+        .push("carry0", n)
+        .push("out1", n)
+        .push("carry1", n)
+        .push("out_range", RANGE_CHECK_WIDTH*2*n)
+        .push("low_carry_bits", MUL_CARRY_BITS*2*n)
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for RelinearizeAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+        self.eval_row(builder, &row);
+    }
+}
+
+impl RelinearizeAir {
+    // Enforce the relinearization constraints over `row`, which starts at the GadgetDecomposeAir columns,
+    // so other gadgets can embed the RelinearizeAir layout at any column offset
+    pub(crate) fn eval_row<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+        let levels = self.levels;
+
+        // 1) decomposition of c2
+        let decompose = self.decompose_air();
+        let decompose_cols = decompose_width(self.base, levels, n);
+        decompose.eval_row(builder, &row[..decompose_cols]);
+
+        // 2) inner products, with a_l[i] === digit[i][l]
+        let ip_width = inner_product_width(levels, n);
+        let channel_width = negacyclic_width(n);
+        for (k, ip) in self.inner_products().iter().enumerate() {
+            let offset = decompose_cols + k*ip_width;
+            ip.eval_row(builder, &row[offset..offset+ip_width]);
+            for l in 0..levels {
+                for i in 0..n {
+                    builder.when_first_row().assert_eq(row[offset + l*channel_width + i], row[digit_column(i, l, levels, n)]);
+                }
+            }
+        }
+
+        // Enforce self.ct as (c0, c1)
+        let c = decompose_cols + 2*ip_width;
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[c+i], AB::Expr::from_canonical_u32(self.ct.c0[i]));
+            builder.when_first_row().assert_eq(row[c+n+i], AB::Expr::from_canonical_u32(self.ct.c1[i]));
+        }
+
+        // Enforce c_k[i] + ip_k[i] === carry_k[i] * mod + out_k[i] for both components k = 0, 1, as 2N sums,
+        // with the lowest limbs of the constant c_k and of the range checked inner products
+        let mask = (1 << LIMB_BITS) - 1;
+        let mut sums = Vec::with_capacity(2*n);
+        let mut sums_low = Vec::with_capacity(2*n);
+        let mut carries = Vec::with_capacity(2*n);
+        let mut outputs = Vec::with_capacity(2*n);
+        for (k, poly) in [&self.ct.c0, &self.ct.c1].into_iter().enumerate() {
+            let ip_out = decompose_cols + k*ip_width + inner_product_output(levels, n);
+            let ip_range = decompose_cols + k*ip_width + inner_product_output_range(levels, n);
+            let out = c + 2*n + k*2*n;
+            let carry = out + n;
+            for i in 0..n {
+                let block = ip_range + i*RANGE_CHECK_WIDTH;
+                sums.push(row[c + k*n + i] + row[ip_out+i]);
+                sums_low.push(reduced_low::<AB>(&row[block..block + RANGE_CHECK_WIDTH]) + AB::F::from_canonical_u32(poly[i] & mask));
+                carries.push(row[carry+i].into());
+                outputs.push(row[out+i]);
+            }
+        }
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, carries, &outputs, &row[c + 6*n..c + 6*n + reduced_sums_width(2*n)], self.modulus);
+    }
+}
+
+pub(crate) fn relinearize_width(base: u32, levels: usize, n: usize) -> usize {
+    relinearize_inputs(base, levels, n) + 6*n + reduced_sums_width(2*n)
+}
+
+// Column of c0[0] in the relinearization trace, after the decomposition and the inner products
+pub(crate) fn relinearize_inputs(base: u32, levels: usize, n: usize) -> usize {
+    decompose_width(base, levels, n) + 2*inner_product_width(levels, n)
+}
+
+// Column of out0[0] in the relinearization trace; out1 starts 2N columns later
+pub fn relinearize_output(base: u32, levels: usize, n: usize) -> usize {
+    relinearize_inputs(base, levels, n) + 2*n
+}
+
+// (c0 + <digits(c2), rlk0>, c1 + <digits(c2), rlk1>) computed on the host
+pub fn relinearize(ct: &Ciphertext, c2: &[u32], rlk0: &[Vec<u32>], rlk1: &[Vec<u32>], base: u32, levels: usize, modulus: u32) -> Ciphertext {
+    let digits = digit_polynomials(c2, base, levels);
+    let apply = |c: &[u32], rlk: &[Vec<u32>]| -> Vec<u32> {
+        let mut acc: Vec<u64> = c.iter().map(|&x| x as u64).collect();
+        for (d, key) in digits.iter().zip(rlk.iter()) {
+            for (a, p) in acc.iter_mut().zip(negacyclic_coeffs(d, key, modulus)) {
+                *a = (*a + p as u64) % modulus as u64;
+            }
+        }
+        acc.iter().map(|&a| a as u32).collect()
+    };
+    Ciphertext::new(apply(&ct.c0, rlk0), apply(&ct.c1, rlk1))
+}
+
+// Define a function to generate execution trace
+pub fn generate_relinearize_trace<F: Field>(ct: Ciphertext, c2: Vec<u32>, rlk0: Vec<Vec<u32>>, rlk1: Vec<Vec<u32>>, base: u32, levels: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    ct.check(n, modulus)?;
+    for rlk in [&rlk0, &rlk1] {
+        if rlk.len() != levels {
+            return Err(GadgetError::LengthMismatch { expected: levels, actual: rlk.len() });
+        }
+        for key in rlk.iter() {
+            check_poly(key, n, modulus)?;
+        }
+    }
+
+    let digits = digit_polynomials(&c2, base, levels);
+    let decompose_trace = generate_decompose_trace::<F>(c2.clone(), base, levels, modulus, n)?;
+    let ip_traces = [&rlk0, &rlk1].into_iter()
+        .map(|rlk| generate_inner_product_trace::<F>(digits.clone(), rlk.clone(), modulus, n))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // c_k + <d, rlk_k> with the carries of the final additions
+    let out = relinearize(&ct, &c2, &rlk0, &rlk1, base, levels, modulus);
+    let components = [(&ct.c0, &out.c0), (&ct.c1, &out.c1)];
+    let ip_outputs = [&rlk0, &rlk1].map(|rlk| {
+        digits.iter().zip(rlk.iter()).fold(vec![0u32; n], |acc, (d, key)| {
+            let product = negacyclic_coeffs(d, key, modulus);
+            (0..n).map(|i| ((acc[i] as u64 + product[i] as u64) % modulus as u64) as u32).collect()
+        })
+    });
+
+    // the sum wrapped around mod exactly when the reduced output is below the input
+    let carries: Vec<Vec<bool>> = components.iter().map(|(c, out)| c.iter().zip(out.iter()).map(|(&x, &y)| y < x).collect()).collect();
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = components.iter().zip(ip_outputs.iter())
+        .flat_map(|((c, _), ip)| (0..n).map(move |i| (c[i] & mask) as i64 + (ip[i] & mask) as i64))
+        .collect();
+
+    let width = relinearize_width(base, levels, n);
+    let height = decompose_trace.height();
+    let mut values: Vec<F> = Vec::with_capacity(height * width);
+    for r in 0..height {
+        values.extend_from_slice(&decompose_trace.row_slice(r));
+        for trace in ip_traces.iter() {
+            values.extend_from_slice(&trace.row_slice(r));
+        }
+        if r == 0 {
+            values.extend(ct.c0.iter().chain(ct.c1.iter()).map(|&c| F::from_canonical_u32(c)));
+            for ((_, out), carry) in components.iter().zip(carries.iter()) {
+                values.extend(out.iter().map(|&x| F::from_canonical_u32(x)));
+                values.extend(carry.iter().map(|&c| F::from_bool(c)));
+            }
+            values.extend(reduced_sums_witness::<F>(&low, &carries.concat(), &[out.c0.clone(), out.c1.clone()].concat(), modulus));
+        } else {
+            values.extend((0..6*n + reduced_sums_width(2*n)).map(|_| F::zero()));
+        }
+    }
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::decompose::decompose;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+    use crate::params::P1;
+
+    #[test]
+    fn test_relinearize_toy_key() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 4;
+        let base = 1 << 8;
+        let levels = 4;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let ct = Ciphertext::new(random_poly(), random_poly());
+        let c2 = random_poly();
+
+        // toy key: rlk0[l] = base^l and rlk1[l] = X, so that c0' = c0 + c2 and c1' = c1 + X * sum_l d_l
+        let rlk0: Vec<Vec<u32>> = (0..levels).map(|l| {
+            let mut key = vec![0u32; n];
+            key[0] = ((base as u64).pow(l as u32) % P1 as u64) as u32;
+            key
+        }).collect();
+        let rlk1: Vec<Vec<u32>> = (0..levels).map(|_| vec![0, 1, 0, 0]).collect();
+
+        let out = relinearize(&ct, &c2, &rlk0, &rlk1, base, levels, P1);
+        let digit_sum: Vec<u64> = c2.iter().map(|&c| decompose(c, base, levels).iter().map(|&d| d as u64).sum()).collect();
+        for i in 0..n {
+            assert_eq!(out.c0[i] as u64, (ct.c0[i] as u64 + c2[i] as u64) % P1 as u64);
+            // X * sum_l d_l shifts the digit sums up by one, with X^N = -1 at the top
+            let shifted = if i == 0 { (P1 as u64 - digit_sum[n-1]) % P1 as u64 } else { digit_sum[i-1] };
+            assert_eq!(out.c1[i] as u64, (ct.c1[i] as u64 + shifted) % P1 as u64);
+        }
+
+        let air = RelinearizeAir { ct: ct.clone(), c2: c2.clone(), rlk0: rlk0.clone(), rlk1: rlk1.clone(), base, levels, modulus: P1, n };
+        let trace = generate_relinearize_trace::<Val>(ct, c2, rlk0, rlk1, base, levels, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        let start = relinearize_output(base, levels, n);
+        for i in 0..n {
+            assert_eq!(row[start+i], Val::from_canonical_u32(out.c0[i]));
+            assert_eq!(row[start+2*n+i], Val::from_canonical_u32(out.c1[i]));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_relinearize_forged_product() {
+        let n = 4;
+        let (base, levels) = (1 << 16, 2);
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let ct = Ciphertext::new(random_poly(), random_poly());
+        let c2 = random_poly();
+        let rlk0: Vec<Vec<u32>> = (0..levels).map(|_| random_poly()).collect();
+        let rlk1: Vec<Vec<u32>> = (0..levels).map(|_| random_poly()).collect();
+        let air = RelinearizeAir { ct: ct.clone(), c2: c2.clone(), rlk0: rlk0.clone(), rlk1: rlk1.clone(), base, levels, modulus: P1, n };
+
+        // every digit-key product with a wrong raw product and re-solved quotients, added into consistent outputs
+        assert_rejects_forged_product(&air, &generate_relinearize_trace(ct, c2, rlk0, rlk1, base, levels, P1, n).unwrap(), |_| P1);
+    }
+}
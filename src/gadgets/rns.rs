@@ -0,0 +1,196 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_nonempty, check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{MulShape, PolyMulAir, check_polymul_bounds, polymul_row, polymul_width};
+use crate::gadgets::trace::pad_trace;
+use crate::gadgets::utils::mod_inv;
+use crate::params::{P, P1, P2, P3};
+
+// The 3 RNS channels, in the order their sub-traces are laid out
+pub const RNS_MODULI: [u32; 3] = [P1, P2, P3];
+
+// Define AIR constraint inputs
+pub struct RnsPolyMulAir {
+    pub channels: Vec<PolyMulAir>,
+    pub n: usize
+}
+
+impl RnsPolyMulAir {
+    // Reduce the input polynomials (coefficients mod P) into each of the 3 RNS channels
+    pub fn new(a: &[u128], b: &[u128], n: usize) -> Self {
+        let channels = RNS_MODULI.iter().map(|&modulus| PolyMulAir {
+            a: reduce_poly(a, modulus),
+            b: reduce_poly(b, modulus),
+            modulus,
+            n,
+            shape: MulShape::Row
+        }).collect();
+        Self { channels, n }
+    }
+}
+
+/*
+RNS Polynomial Multiplication Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1} with coefficients mod P = P1 * P2 * P3
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1} with coefficients mod P
+Output:
+- out_k = (a mod P_k) * (b mod P_k) for each channel k = 1, 2, 3
+
+Note:
+- Each channel is an independent PolyMulAir, and the 3 of them are placed side by side in one row,
+so a single proof covers the multiplication in all 3 residue channels.
+- The full product mod P is recovered from out_1, out_2, out_3 with crt_recombine().
+*/
+impl<F: Field> BaseAir<F> for RnsPolyMulAir {
+    // Air Table looks like this
+    // row:[   PolyMulAir mod P1   ][   PolyMulAir mod P2   ][   PolyMulAir mod P3   ]
+    //     [0.............................................................................0]
+    //     [0.............................................................................0]
+    //     [0.............................................................................0]
+    fn width(&self) -> usize {
+        3*polymul_width(self.n)
+    }
+}
+
+impl GadgetLayout for RnsPolyMulAir {
+    fn layout(&self) -> TraceLayout {
+        self.channels.iter().enumerate().fold(TraceLayout::new(), |layout, (k, channel)| {
+            layout.nest(&format!("channel[{}]", k), channel.layout())
+        })
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for RnsPolyMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let channel_width = polymul_width(self.n);
+        for (k, channel) in self.channels.iter().enumerate() {
+            channel.eval_row(builder, &row[k*channel_width..(k+1)*channel_width]);
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_rns_polymul_trace<F: Field>(a: &[u128], b: &[u128], n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_nonempty(n)?;
+
+    // Concatenate the first rows of the channel traces, as assigned by generate_polymul_trace
+    let width = 3*polymul_width(n);
+    let mut values: Vec<F> = Vec::with_capacity(width);
+    for &modulus in RNS_MODULI.iter() {
+        let (a, b) = (reduce_poly(a, modulus), reduce_poly(b, modulus));
+        check_poly(&a, n, modulus)?;
+        check_poly(&b, n, modulus)?;
+        check_polymul_bounds(modulus, n)?;
+        values.extend(polymul_row::<F>(&a, &b, modulus).0);
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, width))
+}
+
+// Reduce every coefficient of `poly` into the residue channel `modulus`
+pub fn reduce_poly(poly: &[u128], modulus: u32) -> Vec<u32> {
+    poly.iter().map(|&c| (c % modulus as u128) as u32).collect()
+}
+
+/*
+Recombine residues (r_1, r_2, r_3) mod (P1, P2, P3) into the unique x in [0, P) by the CRT:
+    x = sum_k ((r_k * y_k) mod P_k) * M_k mod P
+where M_k = P / P_k and y_k = M_k^{-1} mod P_k.
+Each term is below P_k * M_k = P, so the sum of 3 terms never overflows u128.
+*/
+pub fn crt_recombine(residues: [u32; 3]) -> u128 {
+    let mut x = 0;
+    for (k, &modulus) in RNS_MODULI.iter().enumerate() {
+        let p_k = modulus as u128;
+        let m_k = P / p_k;
+        let y_k = mod_inv((m_k % p_k) as u64, p_k as u64) as u128;
+        x = (x + (residues[k] as u128 * y_k % p_k) * m_k) % P;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_field::PrimeField32;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::mul_mod;
+    use crate::gadgets::soundness::assert_rejects_forged_product;
+
+    #[test]
+    fn test_rns_poly_mul() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate 2 random input polynomials with n coefficients in the range of [0, P)
+        let n = 4;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u128> = (0..n).map(|_| {
+            rng.gen_range(0..P)
+        }).collect();
+
+        let random_poly2: Vec<u128> = (0..n).map(|_| {
+            rng.gen_range(0..P)
+        }).collect();
+
+        let air = RnsPolyMulAir::new(&random_poly1, &random_poly2, n);
+
+        let trace = generate_rns_polymul_trace::<Val>(&random_poly1, &random_poly2, n).unwrap();
+
+        // out[k] of each channel starts right after its 2 input polynomials
+        let row = trace.row_slice(0);
+        let channel_width = polymul_width(n);
+        for k in 0..2*n-1 {
+            let residues = [0, 1, 2].map(|c| row[c*channel_width + 2*n + k].as_canonical_u32());
+
+            // the product coefficient computed directly mod P
+            let mut expected = 0;
+            for i in 0..n {
+                if k >= i && k - i < n {
+                    expected = (expected + mul_mod(random_poly1[i], random_poly2[k-i], P)) % P;
+                }
+            }
+            assert_eq!(crt_recombine(residues), expected);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_crt_recombine() {
+        let x: u128 = 1234567890123456789012345678;
+        let residues = RNS_MODULI.map(|m| (x % m as u128) as u32);
+        assert_eq!(crt_recombine(residues), x);
+        assert_eq!(crt_recombine([0, 0, 0]), 0);
+        assert_eq!(crt_recombine([P1 - 1, P2 - 1, P3 - 1]), P - 1);
+    }
+
+    #[test]
+    fn test_rns_poly_mul_forged_product() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<u128> = (0..n).map(|_| rng.gen_range(0..P)).collect();
+        let b: Vec<u128> = (0..n).map(|_| rng.gen_range(0..P)).collect();
+        let air = RnsPolyMulAir::new(&a, &b, n);
+
+        // the product of any of the 3 channels forged at out[k], with its quotient re-solved mod its own modulus
+        assert_rejects_forged_product(&air, &generate_rns_polymul_trace(&a, &b, n).unwrap(), |k| [P1, P2, P3][k]);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::gadgets::ntt_mul::ntt_powers;
+use crate::gadgets::utils::mod_exp;
+use crate::params::{G1, G2, G3, P1, P2, P3};
+
+// Documented generator of the multiplicative group of P1, P2 or P3
+pub fn generator(p: u32) -> Option<u32> {
+    match p {
+        P1 => Some(G1),
+        P2 => Some(G2),
+        P3 => Some(G3),
+        _ => None,
+    }
+}
+
+// Primitive `order`-th root of unity g^((p-1)/order) mod p, from the documented generator g of p
+// None when p has no documented generator, or `order` does not divide p - 1
+pub fn nth_root_of_unity(p: u32, order: usize) -> Option<u32> {
+    let g = generator(p)?;
+    if order == 0 || (p as usize - 1) % order != 0 {
+        return None;
+    }
+    Some(mod_exp(g as u64, (p as u64 - 1) / order as u64, p as u64) as u32)
+}
+
+/*
+Twiddle factors psi^0, psi^1, ..., psi^{N-1} mod p of the negacyclic NTT over Z_p[X]/(X^N+1)
+
+Note:
+- psi is a primitive 2N-th root of unity, so psi^N = -1: weighting a[j] by psi^j turns the cyclic NTT
+of size N with w = psi^2 into the negacyclic one, since X^N = -1 becomes (psi X)^N = -X^N = 1.
+- The table is empty when 2N does not divide p - 1, e.g. for N = 3500 (see params::validate_rns_params()),
+so callers pad N to a power of two first.
+*/
+pub fn negacyclic_twiddles(p: u32, n: usize) -> Vec<u32> {
+    match nth_root_of_unity(p, 2*n) {
+        Some(psi) => ntt_powers(psi, n, p),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::params::N;
+
+    #[test]
+    fn test_negacyclic_twiddles() {
+        let n = N.next_power_of_two();
+        for p in [P1, P2, P3] {
+            let psi = nth_root_of_unity(p, 2*n).unwrap();
+            let (psi, q) = (psi as u64, p as u64);
+
+            // psi^N = -1, so psi has order exactly 2N
+            assert_eq!(mod_exp(psi, n as u64, q), q - 1);
+            assert_eq!(mod_exp(psi, 2*n as u64, q), 1);
+
+            // the table holds psi^i, and the next power psi^N wraps around to -1
+            let twiddles = negacyclic_twiddles(p, n);
+            assert_eq!(twiddles.len(), n);
+            assert_eq!(twiddles[0], 1);
+            assert_eq!(twiddles[1] as u64, psi);
+            assert_eq!(twiddles[n-1] as u64 * psi % q, q - 1);
+            assert!(twiddles.iter().enumerate().all(|(i, &t)| t as u64 == mod_exp(psi, i as u64, q)));
+        }
+
+        // no root of order 2N for N = 3500, nor for a modulus without a documented generator
+        assert!(negacyclic_twiddles(P1, N).is_empty());
+        assert_eq!(nth_root_of_unity(7, 2), None);
+        assert_eq!(nth_root_of_unity(P1, 0), None);
+    }
+}
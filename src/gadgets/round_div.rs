@@ -0,0 +1,249 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_range_check, range_check_columns, RANGE_CHECK_BITS};
+use crate::gadgets::trace::repeat_row;
+use crate::params::N;
+
+// Rounding of value / divisor to an integer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundMode {
+    // floor(value / d), as in integer schemes
+    Floor,
+    // ceil(value / d)
+    Ceil,
+    // value / d rounded to the nearest integer, with ties rounded half up, as in CKKS rescaling
+    Nearest,
+}
+
+impl RoundMode {
+    // Offset c added to the value before the floor division: out = floor((value + c) / d)
+    pub fn offset(self, divisor: u32) -> u32 {
+        match self {
+            RoundMode::Floor => 0,
+            RoundMode::Ceil => divisor - 1,
+            RoundMode::Nearest => divisor / 2,
+        }
+    }
+}
+
+// Define AIR constraint inputs
+pub struct RoundDivAir {
+    pub value: Vec<u32>,
+    pub divisor: u32,
+    pub modulus: u32,
+    pub mode: RoundMode,
+    pub n: usize
+}
+
+impl RoundDivAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(value: Vec<u32>, divisor: u32, modulus: u32, mode: RoundMode) -> Self {
+        Self { value, divisor, modulus, mode, n: N }
+    }
+}
+
+// Largest quotient floor((mod-1 + c) / d), plus 1: the bound out[i] is range checked against
+fn quotient_bound(divisor: u32, modulus: u32, mode: RoundMode) -> u32 {
+    ((modulus as u64 - 1 + mode.offset(divisor) as u64) / divisor as u64 + 1) as u32
+}
+
+/*
+Rounded Division Air
+Input:
+- value = value[0] + value[1] * X + ... + value[N-1] * X^{N-1} with coefficients in [0, mod)
+- divisor: d > 0
+- mod: FHE ciphertext modulus
+- mode: Floor, Ceil or Nearest
+Output:
+- out[i] = value[i] / d rounded as selected by mode
+- rem[i]: remainder of the rounding
+
+Note:
+- Every mode is a floor division of the value shifted by a constant offset c:
+    Floor:   c = 0         out = floor(value / d)
+    Ceil:    c = d - 1     out = floor((value + d - 1) / d) = ceil(value / d)
+    Nearest: c = floor(d/2) out = floor((value + floor(d/2)) / d)
+so the constraints are value[i] + c === out[i] * d + rem[i] with 0 <= rem[i] < d, where c is baked into the AIR.
+- For Nearest, a tie value = k * d + d/2 only exists for an even d, and the offset d/2 sends it to k + 1: ties round half up.
+Since 0 <= rem[i] < d is constrained, out[i] = k with rem[i] = d is rejected, so the tie-break is enforced and not assumed.
+For an odd d, floor(d/2) = (d-1)/2 rounds up exactly the remainders above d/2.
+- In the native field, out[i] * d + rem[i] could wrap around n. rem[i] < d and out[i] < floor((mod-1 + c)/d) + 1 are range checked
+as in ExactDivAir, and generate_round_div_trace() requires mod + 2d <= 2^31, so both sides stay below n and the identity holds
+over the integers.
+*/
+impl<F: Field> BaseAir<F> for RoundDivAir {
+    // Air Table looks like this
+    // row:[ value: N ][ out: N ][ rem: N ][ out bits: 31N ][ out eq: 31N ][ rem bits: 31N ][ rem eq: 31N ]
+    //     ^--input--^^---------------------calculated by generate_round_div_trace-------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the range checks
+    fn width(&self) -> usize {
+        (3 + 4*RANGE_CHECK_BITS)*self.n
+    }
+}
+
+impl GadgetLayout for RoundDivAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("value", self.n)
+            .push("out", self.n)
+            .push("rem", self.n)
+            .push("out_bits", RANGE_CHECK_BITS*self.n)
+            .push("out_eq", RANGE_CHECK_BITS*self.n)
+            .push("rem_bits", RANGE_CHECK_BITS*self.n)
+            .push("rem_eq", RANGE_CHECK_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for RoundDivAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (value, out, rem) = (0, n, 2*n);
+        let out_bits = 3*n;
+        let out_eq = out_bits + RANGE_CHECK_BITS*n;
+        let rem_bits = out_eq + RANGE_CHECK_BITS*n;
+        let rem_eq = rem_bits + RANGE_CHECK_BITS*n;
+        let divisor = AB::F::from_canonical_u32(self.divisor);
+        let offset = AB::F::from_canonical_u32(self.mode.offset(self.divisor));
+        let bound = quotient_bound(self.divisor, self.modulus, self.mode);
+
+        // Enforce self.value as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[value+i], AB::Expr::from_canonical_u32(self.value[i]));
+        }
+
+        for i in 0..n {
+            // Enforce value[i] + c === out[i] * d + rem[i]
+            builder.assert_eq(row[value+i] + offset, row[out+i] * divisor + row[rem+i]);
+
+            // Enforce 0 <= out[i] < floor((mod-1 + c)/d) + 1 and 0 <= rem[i] < d
+            let (ob, oe) = (out_bits + i*RANGE_CHECK_BITS, out_eq + i*RANGE_CHECK_BITS);
+            eval_range_check(builder, row[out+i].into(), &row[ob..ob+RANGE_CHECK_BITS], &row[oe..oe+RANGE_CHECK_BITS], bound);
+            let (rb, re) = (rem_bits + i*RANGE_CHECK_BITS, rem_eq + i*RANGE_CHECK_BITS);
+            eval_range_check(builder, row[rem+i].into(), &row[rb..rb+RANGE_CHECK_BITS], &row[re..re+RANGE_CHECK_BITS], self.divisor);
+        }
+    }
+}
+
+// value / divisor rounded as selected by mode, computed on the host
+pub fn round_div(value: u32, divisor: u32, mode: RoundMode) -> u32 {
+    ((value as u64 + mode.offset(divisor) as u64) / divisor as u64) as u32
+}
+
+// Define a function to generate execution trace
+pub fn generate_round_div_trace<F: Field>(value: Vec<u32>, divisor: u32, modulus: u32, mode: RoundMode, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&value, n, modulus)?;
+    if divisor == 0 {
+        return Err(GadgetError::ZeroDivisor);
+    }
+    if modulus as u64 + 2 * divisor as u64 > 1 << RANGE_CHECK_BITS {
+        return Err(GadgetError::DivisorTooLarge { divisor, modulus });
+    }
+
+    let width = (3 + 4*RANGE_CHECK_BITS)*n;
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Assign input polynomial, rounded quotients and remainders to the row
+    let offset = mode.offset(divisor) as u64;
+    let out: Vec<u32> = value.iter().map(|&c| round_div(c, divisor, mode)).collect();
+    let rem: Vec<u32> = value.iter().map(|&c| ((c as u64 + offset) % divisor as u64) as u32).collect();
+    row.extend(value.iter().chain(out.iter()).chain(rem.iter()).map(|&c| F::from_canonical_u32(c)));
+
+    // Assign bits and prefix equality flags of every out[i] against its bound, then of every rem[i] against d
+    let bound = quotient_bound(divisor, modulus, mode);
+    for (values, bound) in [(&out, bound), (&rem, divisor)] {
+        let columns: Vec<(Vec<bool>, Vec<bool>)> = values.iter().map(|&c| range_check_columns(c, bound)).collect();
+        for (bits, _) in columns.iter() {
+            row.extend(bits.iter().map(|&bit| F::from_bool(bit)));
+        }
+        for (_, eq) in columns.iter() {
+            row.extend(eq.iter().map(|&flag| F::from_bool(flag)));
+        }
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::params::P1;
+
+    fn prove_and_verify_round_div(value: Vec<u32>, divisor: u32, mode: RoundMode, expected: &[u32]) {
+        let ZkConfig { config, byte_hash } = initialize_config();
+        let n = value.len();
+
+        let air = RoundDivAir { value:value.clone(), divisor, modulus:P1, mode, n };
+        let trace = generate_round_div_trace::<Val>(value, divisor, P1, mode, n).unwrap();
+
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            assert_eq!(row[n+i], Val::from_canonical_u32(expected[i]), "{:?} at index {}", mode, i);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+    }
+
+    #[test]
+    fn test_round_div_modes() {
+        // d = 4: 2 and 6 are ties, and P1 - 1 = 4 * 271319040 is the largest value
+        let value = vec![0, 1, 2, 3, 4, 5, 6, P1 - 1];
+        prove_and_verify_round_div(value.clone(), 4, RoundMode::Floor, &[0, 0, 0, 0, 1, 1, 1, 271319040]);
+        prove_and_verify_round_div(value.clone(), 4, RoundMode::Ceil, &[0, 1, 1, 1, 1, 2, 2, 271319040]);
+        prove_and_verify_round_div(value, 4, RoundMode::Nearest, &[0, 0, 1, 1, 1, 1, 2, 271319040]);
+
+        // d = 5 has no ties: 7 / 5 = 1.4 and 8 / 5 = 1.6
+        let value = vec![2, 3, 7, 8];
+        prove_and_verify_round_div(value.clone(), 5, RoundMode::Floor, &[0, 0, 1, 1]);
+        prove_and_verify_round_div(value.clone(), 5, RoundMode::Ceil, &[1, 1, 2, 2]);
+        prove_and_verify_round_div(value, 5, RoundMode::Nearest, &[0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_round_div_tie_rounded_down() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // under Nearest, 6 / 4 = 1.5 must round half up to 2: rounding it down to 1 leaves rem = 6 + 2 - 4 = 4 = d
+        let (n, divisor) = (4, 4);
+        let value = vec![6, 0, 0, 0];
+        let air = RoundDivAir { value:value.clone(), divisor, modulus:P1, mode:RoundMode::Nearest, n };
+        let mut trace = generate_round_div_trace::<Val>(value, divisor, P1, RoundMode::Nearest, n).unwrap();
+        let width = trace.width();
+        for r in 0..trace.height() {
+            trace.values[r*width + n] = Val::one();
+            trace.values[r*width + 2*n] = Val::from_canonical_u32(divisor);
+        }
+
+        // debug builds panic inside prove() when the constraints don't hold, release builds fail in verify()
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).is_ok()
+        }));
+        assert!(!matches!(result, Ok(true)), "a tie rounded down was accepted");
+
+        assert_eq!(
+            generate_round_div_trace::<Val>(vec![0; 4], P1, P1, RoundMode::Floor, 4).unwrap_err(),
+            GadgetError::DivisorTooLarge { divisor: P1, modulus: P1 }
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct SampleExtractAir {
+    // RLWE ciphertext (a, b) with b = a * s + m + e
+    pub rlwe_a: Vec<u32>,
+    pub rlwe_b: Vec<u32>,
+    // coefficient of the phase to extract
+    pub index: usize,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl SampleExtractAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(rlwe_a: Vec<u32>, rlwe_b: Vec<u32>, index: usize, modulus: u32) -> Self {
+        Self { rlwe_a, rlwe_b, index, modulus, n: N }
+    }
+}
+
+/*
+Sample Extraction Air
+Input:
+- (a, b): RLWE ciphertext with N coefficients per polynomial, under the secret s(X) = s[0] + ... + s[N-1] * X^{N-1}
+- index: h in [0, N)
+- mod: FHE ciphertext modulus
+Output:
+- (mask, body): LWE ciphertext under the secret vector (s[0], ..., s[N-1]), with body - <mask, s> = (b - a * s)[h]
+
+Note:
+- In Z[X]/(X^N+1), the coefficient h of a * s is sum_{j <= h} a[h-j] * s[j] - sum_{j > h} a[N+h-j] * s[j], so
+    body    = b[h]
+    mask[j] = a[h-j]              for j <= h
+    mask[j] = -a[N+h-j] % mod     for j > h
+- As in GaloisAutomorphismAir, the signed permutation only depends on h and N, so it is baked into the constraints,
+and the negations are proven as in PolyNegAir: a[i] + mask[j] === mod, or mask[j] === 0 when a[i] == 0
+- The output constraints are enforced on the first row only, where the inputs are pinned.
+*/
+impl<F: Field> BaseAir<F> for SampleExtractAir {
+    // Air Table looks like this
+    // row:[    a: N    ][    b: N    ][mod:1][    mask: N    ][body:1]
+    //     ^----------------inputs-----------^^-calculated by generate_sample_extract_trace
+    //     [0...........................................................0]
+    //     [0...........................................................0]
+    //     [0...........................................................0]
+    fn width(&self) -> usize {
+        3*self.n+2
+    }
+}
+
+impl GadgetLayout for SampleExtractAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("mod", 1)
+            .push("mask", self.n)
+            .push("body", 1)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for SampleExtractAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.rlwe_a, self.rlwe_b as input ciphertext and self.modulus as mod
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.rlwe_a[i]));
+            builder.when_first_row().assert_eq(row[n+i], AB::Expr::from_canonical_u32(self.rlwe_b[i]));
+        }
+        let mod_col = 2*n;
+        builder.when_first_row().assert_eq(row[mod_col], AB::Expr::from_canonical_u32(self.modulus));
+
+        // Enforce mask[j] === a[i], or the negation of a[i] past the wrap-around of X^N = -1
+        let mask = 2*n+1;
+        for j in 0..n {
+            let (i, negate) = extract_index(j, self.index, n);
+            if !negate {
+                builder.when_first_row().assert_eq(row[mask+j], row[i]);
+            } else if self.rlwe_a[i] == 0 {
+                builder.when_first_row().assert_zero(row[mask+j]);
+            } else {
+                builder.when_first_row().assert_eq(row[i] + row[mask+j], row[mod_col]);
+            }
+        }
+
+        // Enforce body === b[index]
+        builder.when_first_row().assert_eq(row[3*n+1], row[n+self.index]);
+    }
+}
+
+// Index of the coefficient of a read by mask[j] when extracting coefficient h, and whether it is negated
+pub fn extract_index(j: usize, h: usize, n: usize) -> (usize, bool) {
+    if j <= h { (h - j, false) } else { (n + h - j, true) }
+}
+
+// LWE sample (mask, body) of coefficient h, computed on the host
+pub fn sample_extract(rlwe_a: &[u32], rlwe_b: &[u32], h: usize, modulus: u32) -> (Vec<u32>, u32) {
+    let n = rlwe_a.len();
+    let mask = (0..n).map(|j| {
+        let (i, negate) = extract_index(j, h, n);
+        if negate { (modulus - rlwe_a[i]) % modulus } else { rlwe_a[i] }
+    }).collect();
+    (mask, rlwe_b[h])
+}
+
+// Define a function to generate execution trace
+pub fn generate_sample_extract_trace<F: Field>(rlwe_a: Vec<u32>, rlwe_b: Vec<u32>, index: usize, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&rlwe_a, n, modulus)?;
+    check_poly(&rlwe_b, n, modulus)?;
+    if index >= n {
+        return Err(GadgetError::IndexOutOfRange { index, n });
+    }
+
+    let mut values: Vec<F>= Vec::with_capacity(MIN_TRACE_HEIGHT * (3*n+2));
+
+    // Add input ciphertext and modulus to values vector
+    values.extend(rlwe_a.iter().chain(rlwe_b.iter()).map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(modulus));
+
+    // Extract the LWE sample and push it to values vector
+    let (mask, body) = sample_extract(&rlwe_a, &rlwe_b, index, modulus);
+    values.extend(mask.iter().map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(body));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, 3*n+2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::reference::negacyclic_mul_ref;
+    use crate::params::P1;
+
+    #[test]
+    fn test_sample_extract() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let mut random_poly = || -> Vec<u32> { (0..n).map(|_| rng.gen_range(0..P1)).collect() };
+        let (mut rlwe_a, rlwe_b, secret) = (random_poly(), random_poly(), random_poly());
+        // a 0 coefficient that is negated for index 5
+        rlwe_a[7] = 0;
+
+        // phase b - a * s of the RLWE ciphertext
+        let a_s = negacyclic_mul_ref(&rlwe_a, &secret, P1, n);
+        let phase: Vec<u32> = (0..n).map(|i| (rlwe_b[i] + P1 - a_s[i]) % P1).collect();
+
+        for index in [0, 5] {
+            // the host extraction decrypts to the same phase coefficient: body - <mask, s> = phase[index]
+            let (mask, body) = sample_extract(&rlwe_a, &rlwe_b, index, P1);
+            let inner: u64 = mask.iter().zip(secret.iter()).map(|(&m, &s)| m as u64 * s as u64 % P1 as u64).sum();
+            assert_eq!((body as u64 + P1 as u64 - inner % P1 as u64) % P1 as u64, phase[index] as u64);
+
+            let air = SampleExtractAir { rlwe_a:rlwe_a.clone(), rlwe_b:rlwe_b.clone(), index, modulus:P1, n };
+            let trace = generate_sample_extract_trace::<Val>(rlwe_a.clone(), rlwe_b.clone(), index, P1, n).unwrap();
+
+            let row = trace.row_slice(0);
+            for j in 0..n {
+                assert_eq!(row[2*n+1+j], Val::from_canonical_u32(mask[j]));
+            }
+            assert_eq!(row[3*n+1], Val::from_canonical_u32(body));
+            drop(row);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_sample_extract_index_out_of_range() {
+        assert_eq!(
+            generate_sample_extract_trace::<Val>(vec![0; 8], vec![0; 8], 8, P1, 8).unwrap_err(),
+            GadgetError::IndexOutOfRange { index: 8, n: 8 }
+        );
+    }
+}
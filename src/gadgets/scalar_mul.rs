@@ -0,0 +1,274 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_air_inputs, check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, eval_carry_chain, range_checked_limbs, COEFF_LIMBS, MUL_CARRY_BITS};
+use crate::gadgets::range_check::{check_range_modulus, eval_range_checks, range_check_witness, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::params::{FheParams, ParamError, N};
+
+// Define AIR constraint inputs
+// The fields are only set through the checked constructors outside of the crate
+pub struct PolyScalarMulAir {
+    pub(crate) a: Vec<u32>,
+    pub(crate) scalar: u32,
+    pub(crate) modulus: u32,
+    pub(crate) n: usize
+}
+
+impl PolyScalarMulAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, scalar: u32, modulus: u32) -> Result<Self, GadgetError> {
+        Self::with_n(a, scalar, modulus, N)
+    }
+
+    // Construct the AIR with n coefficients, checking that a has n coefficients, that the modulus is nonzero
+    // and of at most 31 bits, and that the scalar is in [0, mod)
+    pub fn with_n(a: Vec<u32>, scalar: u32, modulus: u32, n: usize) -> Result<Self, GadgetError> {
+        check_air_inputs(&[&a], n, modulus)?;
+        check_range_modulus(modulus)?;
+        check_poly(&[scalar], 1, modulus)?;
+        Ok(Self { a, scalar, modulus, n })
+    }
+
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`
+    pub fn with_params(a: Vec<u32>, scalar: u32, params: &FheParams, channel: usize) -> Result<Self, ParamError> {
+        Ok(Self { a, scalar, modulus: params.modulus(channel)?, n: params.n })
+    }
+}
+
+/*
+Polynomial Scalar Multiplication Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- scalar: constant c in [0, mod)
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[N-1] * X^{N-1} where out[i] = (a[i] * c) % mod
+- q = q[0], ..., q[N-1]: quotients of the non-native modular reduction
+
+Note:
+- PolyScalarMulAir does not have a state transition. Values required for constraints are all stored in one row.
+- While `out` and `q` are calculated manually by generate_polyscalarmul_trace(), we prove that the scaling was done correctly,
+by enforcing a[i] * c === q[i] * mod + out[i] for each coefficient, over the integers:
+out[i] and q[i] are range checked into [0, mod), so both sides are below mod^2 < 2^31 * n,
+and the identity is proven 1) mod 2^32 with the carry chain of PolyMulAir and 2) mod n.
+*/
+impl<F: Field> BaseAir<F> for PolyScalarMulAir {
+    // Air Table looks like this
+    // row:[      a: N      ][c:1][     out: N     ][      q: N      ][ out range checks: 62 * N ][ q range checks: 62 * N ][ carry bits: 4 * 23 * N ]
+    //     ^-------inputs--------^^-------------------------------calculated by generate_polyscalarmul_trace-------------------------------^
+    //     [0..............................................................................................................................0]
+    //     [0..............................................................................................................................0]
+    //     [0..............................................................................................................................0]
+    fn width(&self) -> usize {
+        scalar_mul_width(self.n)
+    }
+}
+
+// Bits of the carries of one coefficient, at the COEFF_LIMBS limb positions of the identity 1) mod 2^32
+const CARRY_COLUMNS: usize = COEFF_LIMBS*MUL_CARRY_BITS;
+
+fn scalar_mul_width(n: usize) -> usize {
+    3*n+1 + 2*RANGE_CHECK_WIDTH*n + CARRY_COLUMNS*n
+}
+
+impl GadgetLayout for PolyScalarMulAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("c", 1)
+            .push("out", self.n)
+            .push("q", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*self.n)
+            .push("q_range", RANGE_CHECK_WIDTH*self.n)
+            .push("carry_bits", CARRY_COLUMNS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PolyScalarMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a as input polynomial and self.scalar as c
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+        builder.when_first_row().assert_eq(row[n], AB::Expr::from_canonical_u32(self.scalar));
+
+        /*
+        a[i] * c is at most (p-1)^2 < 2^62, which overflows n, while the quotient q[i] = floor(a[i] * c / p) < p fits in the native field.
+        We enforce a[i] * c === q[i] * p + out[i] (mod n), i.e. the constraint 2) of the CRT argument in add.rs.
+        */
+        let modulus = AB::F::from_wrapped_u32(self.modulus);
+        for i in 0..n {
+            builder.assert_eq(row[i] * row[n], row[i+2*n+1] * modulus + row[i+n+1]);
+        }
+
+        // Enforce 0 <= out[i] < mod and 0 <= q[i] < mod
+        let (out_range, q_range) = (3*n+1, 3*n+1 + RANGE_CHECK_WIDTH*n);
+        let carry_bits = q_range + RANGE_CHECK_WIDTH*n;
+        let outputs: Vec<AB::Expr> = (0..n).map(|i| row[i+n+1].into()).collect();
+        eval_range_checks(builder, &outputs, &row[out_range..q_range], self.modulus);
+        let quotients: Vec<AB::Expr> = (0..n).map(|i| row[i+2*n+1].into()).collect();
+        eval_range_checks(builder, &quotients, &row[q_range..carry_bits], self.modulus);
+
+        // 1) Enforce (a[i] * c)_m - sum_{l+l'=m} q[i]_l * p_l' - out[i]_m + carry_m === 2^8 * carry_{m+1},
+        // where the limbs of a[i] * c are constants since a[i] and c are pinned on the first row
+        let mod_limbs = limbs(self.modulus as u128, COEFF_LIMBS);
+        let mut builder = builder.when_first_row();
+        for i in 0..n {
+            let product = limbs(self.a[i] as u128 * self.scalar as u128, COEFF_LIMBS);
+            let out_limbs = range_checked_limbs::<AB>(&row[out_range + i*RANGE_CHECK_WIDTH..out_range + i*RANGE_CHECK_WIDTH + RANGE_CHECK_BITS]);
+            let q_limbs = range_checked_limbs::<AB>(&row[q_range + i*RANGE_CHECK_WIDTH..q_range + i*RANGE_CHECK_WIDTH + RANGE_CHECK_BITS]);
+            let terms: Vec<AB::Expr> = (0..COEFF_LIMBS).map(|m| {
+                let mut term = AB::Expr::from_canonical_u64(product[m]) - out_limbs[m].clone();
+                for l in 0..=m {
+                    term = term - q_limbs[m-l].clone() * AB::F::from_canonical_u64(mod_limbs[l]);
+                }
+                term
+            }).collect();
+            let block = carry_bits + i*CARRY_COLUMNS;
+            eval_carry_chain(&mut builder, terms, &row[block..block + CARRY_COLUMNS]);
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_polyscalarmul_trace<F: Field>(a:Vec<u32>, scalar: u32, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&[scalar], 1, modulus)?;
+
+    check_range_modulus(modulus)?;
+
+    let width = scalar_mul_width(n);
+    let mut values: Vec<F>= Vec::with_capacity(width);
+
+    // Add input polynomial and scalar to values vector
+    for i in 0..n {
+        values.push(F::from_canonical_u32(a[i]));
+    }
+    values.push(F::from_canonical_u32(scalar));
+
+    // Multiply each coefficient by the scalar in u64 and push the reduced products, then the quotients
+    let products: Vec<u64> = a.iter().map(|&x| x as u64 * scalar as u64).collect();
+    let out: Vec<u32> = products.iter().map(|&x| (x % modulus as u64) as u32).collect();
+    let q: Vec<u32> = products.iter().map(|&x| (x / modulus as u64) as u32).collect();
+    values.extend(out.iter().chain(q.iter()).map(|&x| F::from_canonical_u32(x)));
+
+    // Add the range checks of out and q, and the carries of a[i] * c - q[i] * p - out[i] at the limb positions [0..4)
+    values.extend(range_check_witness::<F>(&out, modulus));
+    values.extend(range_check_witness::<F>(&q, modulus));
+    values.extend(scalar_mul_carries::<F>(&products, &q, &out, modulus));
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(values, width))
+}
+
+// The carry bits of the identity 1) for every coefficient, as assigned by assign_carry_chain()
+fn scalar_mul_carries<F: Field>(products: &[u64], q: &[u32], out: &[u32], modulus: u32) -> Vec<F> {
+    let mut carries = vec![F::zero(); products.len() * CARRY_COLUMNS];
+    let mod_limbs = limbs(modulus as u128, COEFF_LIMBS);
+    for (i, block) in carries.chunks_mut(CARRY_COLUMNS).enumerate() {
+        let product = limbs(products[i] as u128, COEFF_LIMBS);
+        let qp = convolve(&limbs(q[i] as u128, COEFF_LIMBS), &mod_limbs, COEFF_LIMBS);
+        let out_limbs = limbs(out[i] as u128, COEFF_LIMBS);
+        let diff: Vec<i64> = (0..COEFF_LIMBS).map(|m| product[m] as i64 - qp[m] as i64 - out_limbs[m] as i64).collect();
+        assign_carry_chain(block, &diff);
+    }
+    carries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use rand::{thread_rng, Rng};
+    use p3_field::PrimeField64;
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::gadgets::utils::mod_inv;
+    use crate::params::P1;
+
+    fn prove_and_verify_scalar_mul(a: Vec<u32>, scalar: u32, n: usize) -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let air = PolyScalarMulAir::with_n(a.clone(), scalar, P1, n).unwrap();
+
+        let trace = generate_polyscalarmul_trace::<Val>(a.clone(), scalar, P1, n).unwrap();
+
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let expected = (a[i] as u64 * scalar as u64 % P1 as u64) as u32;
+            assert_eq!(row[i+n+1], Val::from_canonical_u32(expected));
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_poly_scalar_mul() {
+
+        // generate a random input polynomial with n coefficients in the range of [0, P1)
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        // scalar = 0 zeroes the polynomial, scalar = 1 keeps it unchanged, a random scalar covers the general case
+        for scalar in [0, 1, rng.gen_range(2..P1)] {
+            prove_and_verify_scalar_mul(random_poly.clone(), scalar, n).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_poly_scalar_mul_forged_output() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let scalar = rng.gen_range(2..P1);
+        let air = PolyScalarMulAir::with_n(a.clone(), scalar, P1, n).unwrap();
+
+        // out[i] + delta with q[i] re-solved mod n, so that a[i] * c === q[i] * p + out[i] still holds mod n,
+        // and every witness column assigned for the forged values
+        let order = Val::ORDER_U64;
+        let products: Vec<u64> = a.iter().map(|&x| x as u64 * scalar as u64).collect();
+        for _ in 0..4 {
+            let (i, delta) = (rng.gen_range(0..n), rng.gen_range(1..P1));
+            let mut out: Vec<u32> = products.iter().map(|&x| (x % P1 as u64) as u32).collect();
+            let mut q: Vec<u32> = products.iter().map(|&x| (x / P1 as u64) as u32).collect();
+            out[i] = ((out[i] as u64 + delta as u64) % P1 as u64) as u32;
+            q[i] = ((products[i] % order + order - out[i] as u64) % order * mod_inv(P1 as u64, order) % order) as u32;
+
+            let mut values: Vec<Val> = a.iter().chain([scalar].iter()).chain(out.iter()).chain(q.iter()).map(|&x| Val::from_canonical_u32(x)).collect();
+            values.extend(range_check_witness::<Val>(&out, P1));
+            values.extend(range_check_witness::<Val>(&q, P1));
+            values.extend(scalar_mul_carries::<Val>(&products, &q, &out, P1));
+            assert_rejected(&air, pad_trace(values, scalar_mul_width(n)), &format!("a forged out[{}] + {}", i, delta));
+        }
+    }
+
+    #[test]
+    fn test_poly_scalar_mul_checked_constructor() {
+        assert!(PolyScalarMulAir::with_n(vec![1, 2], 3, P1, 2).is_ok());
+        assert_eq!(PolyScalarMulAir::with_n(vec![1], 3, P1, 2).err(), Some(GadgetError::LengthMismatch { expected: 2, actual: 1 }));
+        assert_eq!(PolyScalarMulAir::with_n(vec![1, 2], 3, 0, 2).err(), Some(GadgetError::ZeroModulus));
+        assert_eq!(PolyScalarMulAir::with_n(vec![1, 2], P1, P1, 2).err(), Some(GadgetError::CoefficientOutOfRange { index: 0, value: P1, modulus: P1 }));
+        assert!(matches!(PolyScalarMulAir::with_n(vec![1, 2], 3, u32::MAX, 2), Err(GadgetError::ModulusTooLarge { .. })));
+        assert_eq!(PolyScalarMulAir::new(vec![0; 2], 3, P1).err(), Some(GadgetError::LengthMismatch { expected: N, actual: 2 }));
+    }
+}
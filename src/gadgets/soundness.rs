@@ -0,0 +1,405 @@
+/*
+Soundness harness for the gadgets: corrupted witnesses must not verify
+
+Note:
+- Every test starts from an honest trace, adds a random nonzero field element to one random coefficient
+of an output block on the first row, and checks that the corrupted trace is not accepted.
+- A corrupted coefficient next to a stale quotient or borrow is caught by the native constraint alone, so the quotient
+of PolyAddAir and the borrow of PolySubAir are re-solved mod n for the corrupted coefficient first, see assert_rejects_resolved():
+the trace is then only rejected by the bits, range checks and limb identities that the re-solved witness cannot satisfy.
+- The gadgets reducing a sum by a carry or borrow bit through eval_reduced_sum() are also checked against a flipped carry,
+whose out is moved by the modulus the other way so that the sum still holds mod n, see assert_rejects_flipped_carry():
+for large moduli, only the range check of out and the low limb identity mod 2^8 tell it from the honest sum.
+- The gadgets built on PolyMulAir are also checked against a forged product, whose wrong output comes
+with a re-solved quotient instead of a corrupted column, see assert_rejects_forged_product(): the honest trace is post-edited
+at the PolyMulAir rows found in the layout, leaving the columns computed from the product consistent with it.
+- The output block is read from the GadgetLayout of the AIR, so the harness works for any gadget with a layout.
+- Debug builds panic inside prove() when the constraints don't hold, release builds fail in verify(),
+so a round passes unless the proof is produced and verifies.
+- Only compiled for tests, and run at a small n so that it stays fast enough for CI.
+*/
+
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use p3_field::{AbstractField, Field, PrimeField32, PrimeField64};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{prove, verify};
+use rand::{thread_rng, Rng};
+use crate::gadgets::config::{initialize_config, ZkConfig, ZkAir, Challenger, Val};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_carry_chain, assign_output_witness, coeff_limbs, MulLayout, CARRY_OFFSET, MUL_CARRY_BITS};
+use crate::gadgets::range_check::{assign_range_check, RANGE_CHECK_WIDTH};
+
+// Number of corrupted traces proven per block
+pub const ROUNDS: usize = 4;
+
+// Assert that `trace`, an honest trace of `air`, is rejected once any coefficient of `block` is changed
+pub fn assert_rejects_corrupted<A: ZkAir + GadgetLayout>(air: &A, trace: &RowMajorMatrix<Val>, block: &str) {
+    assert_rejects_resolved(air, trace, block, |_, _| {});
+}
+
+// assert_rejects_corrupted() where `resolve` re-solves the other witness columns of the first row
+// for the corrupted coefficient, given with its index in `block`, as a prover forging it would
+pub fn assert_rejects_resolved<A: ZkAir + GadgetLayout>(air: &A, trace: &RowMajorMatrix<Val>, block: &str, resolve: impl Fn(&mut [Val], usize)) {
+    let columns = air.layout().get(block).unwrap_or_else(|| panic!("no block {} in the layout", block));
+    let mut rng = thread_rng();
+
+    for _ in 0..ROUNDS {
+        let col = rng.gen_range(columns.clone());
+        let mut corrupted = trace.clone();
+        corrupted.values[col] += Val::from_canonical_u32(rng.gen_range(1..Val::ORDER_U32));
+        resolve(&mut corrupted.values[..trace.width()], col - columns.start);
+        assert_rejected(air, corrupted, &format!("a corrupted {}[{}]", block, col - columns.start));
+    }
+}
+
+// A PolyMulAir row (MulShape::Row) embedded in the layout of a gadget: the column of its a[0], its number of coefficients,
+// and the column of borrow[0] when it is the raw product of a NegacyclicMulAir
+#[derive(Clone, Debug)]
+pub struct EmbeddedProduct {
+    pub offset: usize,
+    pub n: usize,
+    pub borrow: Option<usize>
+}
+
+// The PolyMulAir rows of `layout`, in column order, found by their blocks "<prefix>input_range" next to "<prefix>a" and "<prefix>q"
+pub fn embedded_products(layout: &TraceLayout) -> Vec<EmbeddedProduct> {
+    layout.columns().iter().filter_map(|(name, _)| {
+        let prefix = name.strip_suffix("input_range")?;
+        let a = layout.get(&format!("{}a", prefix))?;
+        layout.get(&format!("{}q", prefix))?;
+        let borrow = layout.get(&format!("{}borrow", prefix)).map(|borrow| borrow.start);
+        Some(EmbeddedProduct { offset: a.start, n: a.len(), borrow })
+    }).collect()
+}
+
+/*
+Forge out[k] of `product` on `row` as a cheating prover would: out[k] is shifted by a nonzero delta mod p, q[k] re-solved
+as q[k] + (out[k] - out'[k]) / p mod n, so that the identity 2) mod n still holds, and the range check of out'[k], the bits of q'[k]
+and the carries of the identity 1) are re-assigned for them. Only the range checks and the identity 1) can then reject the row.
+The raw product of a NegacyclicMulAir is forged at out[k] and out[k+N] by the same delta, with borrow[k] re-solved, for k < N-1:
+its reduced coefficients, and every column the gadget computes from them, are then the honest ones.
+*/
+pub fn forge_product(row: &mut [Val], product: &EmbeddedProduct, k: usize, delta: u32, modulus: u32) {
+    let (n, layout) = (product.n, MulLayout::new(product.n));
+    let mul = &mut row[product.offset..product.offset + layout.width];
+    let operand = |range: Range<usize>| -> Vec<u32> { mul[range].iter().map(|c| c.as_canonical_u32()).collect() };
+    let (a, b) = (coeff_limbs(&operand(0..n)), coeff_limbs(&operand(n..2*n)));
+
+    let mut shift = |k: usize| -> u32 {
+        let out = mul[layout.out+k].as_canonical_u32();
+        let forged = ((out as u64 + delta as u64) % modulus as u64) as u32;
+        let q = mul[layout.q+k] + (Val::from_canonical_u32(out) - Val::from_canonical_u32(forged)) * Val::from_canonical_u32(modulus).inverse();
+        mul[layout.out+k] = Val::from_canonical_u32(forged);
+        mul[layout.q+k] = q;
+        assign_output_witness(mul, &layout, (&a, &b), k, forged, q.as_canonical_u64(), modulus);
+        forged
+    };
+    let low = shift(k);
+    if let Some(borrow) = product.borrow {
+        assert!(k < n-1, "out[{}] is not folded by the NegacyclicMulAir", k);
+        let high = shift(k+n);
+        row[borrow+k] = Val::from_bool(low < high);
+    }
+}
+
+// Assert that `air` rejects `trace`, an honest trace, once any of the PolyMulAir rows embedded in its layout is forged
+// by forge_product() at a random out[k] by a random delta, where `modulus` gives the modulus of the i-th product in column order
+pub fn assert_rejects_forged_product<A: ZkAir + GadgetLayout>(air: &A, trace: &RowMajorMatrix<Val>, modulus: impl Fn(usize) -> u32) {
+    let products = embedded_products(&air.layout());
+    assert!(!products.is_empty(), "no PolyMulAir in the layout");
+    let mut rng = thread_rng();
+
+    for _ in 0..ROUNDS {
+        let i = rng.gen_range(0..products.len());
+        let (product, modulus) = (&products[i], modulus(i));
+        let outputs = if product.borrow.is_some() { product.n - 1 } else { 2*product.n - 1 };
+        let (k, delta) = (rng.gen_range(0..outputs), rng.gen_range(1..modulus));
+        let mut forged = trace.clone();
+        forge_product(&mut forged.values[..trace.width()], product, k, delta, modulus);
+        assert_rejected(air, forged, &format!("a forged out[{}] + {} of the product at column {}", k, delta, product.offset));
+    }
+}
+
+// Columns of a sum reduced by eval_reduced_sum() on the first row: out, its carry bit (a borrow bit for PolySubAir),
+// the first columns of the range check of out and of its low limb carry, and the public value exposing out, if any
+#[derive(Clone, Debug)]
+pub struct ReducedSumColumns {
+    pub out: usize,
+    pub carry: usize,
+    pub out_range: usize,
+    pub low_carry_bits: usize,
+    pub borrow: bool,
+    pub public: Option<usize>
+}
+
+// The columns of the sums out[i] with the carry bits carry[i] for i = [0..out.len()), whose range checks and low limb carries
+// are the consecutive blocks from out_range and low_carry_bits, as assigned by reduced_sums_witness()
+pub fn reduced_sum_columns(out: Range<usize>, carry: Range<usize>, out_range: usize, low_carry_bits: usize) -> Vec<ReducedSumColumns> {
+    assert_eq!(out.len(), carry.len(), "a carry per sum");
+    out.zip(carry).enumerate().map(|(i, (out, carry))| ReducedSumColumns {
+        out,
+        carry,
+        out_range: out_range + i*RANGE_CHECK_WIDTH,
+        low_carry_bits: low_carry_bits + i*MUL_CARRY_BITS,
+        borrow: false,
+        public: None
+    }).collect()
+}
+
+// Flip the carry bit of `sum` on `row` and move out by the modulus the other way mod n, so that the native identity still holds,
+// then re-assign the range check of the new out and the carry of the low limbs, as a prover forging the sum would. Returns the new out.
+pub fn flip_carry(row: &mut [Val], sum: &ReducedSumColumns, modulus: u32) -> Val {
+    // A carry of 1 takes mod out of the sum and a borrow of 1 adds it: the quotient bit goes down by dq and out up by dq * mod
+    let dq: i64 = if (row[sum.carry] == Val::one()) != sum.borrow { 1 } else { -1 };
+    let (out, p) = (row[sum.out], Val::from_canonical_u32(modulus));
+    let forged = if dq == 1 { out + p } else { out - p };
+    row[sum.carry] = Val::one() - row[sum.carry];
+    row[sum.out] = forged;
+
+    // The honest low limb term is 2^8 times its carry, read back from the bits stored with CARRY_OFFSET
+    let low_carry_bits = sum.low_carry_bits..sum.low_carry_bits + MUL_CARRY_BITS;
+    let carry = row[low_carry_bits.clone()].iter().enumerate().map(|(b, bit)| (bit.as_canonical_u32() as i64) << b).sum::<i64>() - CARRY_OFFSET;
+    let mask = (1 << LIMB_BITS) - 1;
+    let term = (carry << LIMB_BITS) + dq * (modulus & mask) as i64 + (out.as_canonical_u32() & mask) as i64 - (forged.as_canonical_u32() & mask) as i64;
+
+    assign_range_check(&mut row[sum.out_range..sum.out_range + RANGE_CHECK_WIDTH], forged.as_canonical_u32(), modulus);
+    assign_carry_chain(&mut row[low_carry_bits], &[term]);
+    forged
+}
+
+// Assert that `trace`, an honest trace of `air` for `public_values`, is rejected once the carry of any of `sums` is flipped
+// by flip_carry(), with the public value exposing out, if any, following the forged out
+pub fn assert_rejects_flipped_carry<A: ZkAir>(air: &A, trace: &RowMajorMatrix<Val>, public_values: &[Val], sums: &[ReducedSumColumns], modulus: u32) {
+    let mut rng = thread_rng();
+
+    for _ in 0..ROUNDS {
+        let sum = &sums[rng.gen_range(0..sums.len())];
+        let mut forged = trace.clone();
+        let mut public_values = public_values.to_vec();
+        let out = flip_carry(&mut forged.values[..trace.width()], sum, modulus);
+        if let Some(k) = sum.public {
+            public_values[k] = out;
+        }
+        assert_rejected_with(air, forged, &public_values, &format!("a flipped carry at column {}", sum.carry));
+    }
+}
+
+// Assert that `trace` does not prove and verify for `air`
+pub fn assert_rejected<A: ZkAir>(air: &A, trace: RowMajorMatrix<Val>, what: &str) {
+    assert_rejected_with(air, trace, &[], what);
+}
+
+// assert_rejected() for `air` with public values
+pub fn assert_rejected_with<A: ZkAir>(air: &A, trace: RowMajorMatrix<Val>, public_values: &[Val], what: &str) {
+    let ZkConfig { config, byte_hash } = initialize_config();
+    let public_values = public_values.to_vec();
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, air, &mut challenger, trace, &public_values);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, air, &mut challenger, &proof, &public_values).is_ok()
+    }));
+    assert!(!matches!(result, Ok(true)), "proof with {} was accepted", what);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::gadgets::add_then_mul::{AddThenMulAir, generate_add_then_mul_trace};
+    use crate::gadgets::approx_equal::{ApproxEqualAir, generate_approx_equal_trace};
+    use crate::gadgets::base_extend::{BaseExtendAir, generate_base_extend_trace};
+    use crate::gadgets::center::{CenterAir, generate_center_trace};
+    use crate::gadgets::decrypt::{DecryptAir, generate_decrypt_trace};
+    use crate::gadgets::batch_add::{BatchAddAir, generate_batch_add_trace};
+    use crate::gadgets::ciphertext::{Ciphertext, CiphertextAddAir, generate_ciphertext_add_trace};
+    use crate::gadgets::coeff_sum::{CoeffSumAir, generate_coeff_sum_trace};
+    use crate::gadgets::inner_product::{InnerProductAir, generate_inner_product_trace};
+    use crate::gadgets::mul::{MulShape, PolyMulAir, generate_polymul_trace};
+    use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace};
+    use crate::gadgets::neg::{PolyNegAir, generate_polyneg_trace};
+    use crate::gadgets::noise_bound::{NoiseBoundAir, generate_noise_bound_trace};
+    use crate::gadgets::scalar_mul::{PolyScalarMulAir, generate_polyscalarmul_trace};
+    use crate::gadgets::sub::{PolySubAir, generate_polysub_trace};
+    use crate::params::{P1, P2};
+
+    // Small number of coefficients, so that every gadget proves in a few milliseconds
+    const N: usize = 4;
+
+    fn random_poly(modulus: u32) -> Vec<u32> {
+        let mut rng = thread_rng();
+        (0..N).map(|_| rng.gen_range(0..modulus)).collect()
+    }
+
+    // Re-solve the quotient q[i] of a corrupted out[i] of PolyAddAir mod n, with the range check of out[i] and the carry of its low limbs
+    fn resolve_add(air: &PolyAddAir, row: &mut [Val], i: usize) {
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap().start + i;
+        let out = row[col("out")];
+        let q = (row[col("a")] + row[col("b")] - out) * Val::from_canonical_u32(air.modulus).inverse();
+        row[col("q")] = q;
+
+        let range = layout.get("out_range").unwrap().start + i*RANGE_CHECK_WIDTH;
+        assign_range_check(&mut row[range..range + RANGE_CHECK_WIDTH], out.as_canonical_u32(), air.modulus);
+        let mask = (1 << LIMB_BITS) - 1;
+        let low = (air.a[i] & mask) as i64 + (air.b[i] & mask) as i64
+            - q.as_canonical_u32() as i64 * (air.modulus & mask) as i64 - (out.as_canonical_u32() & mask) as i64;
+        let carry = layout.get("low_carry_bits").unwrap().start + i*MUL_CARRY_BITS;
+        assign_carry_chain(&mut row[carry..carry + MUL_CARRY_BITS], &[low]);
+    }
+
+    // Re-solve the borrow of a corrupted out[i] of PolySubAir mod n, with the range check of out[i] and the carry of its low limbs
+    fn resolve_sub(air: &PolySubAir, row: &mut [Val], i: usize) {
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap().start + i;
+        let out = row[col("out")];
+        let borrow = (row[col("b")] + out - row[col("a")]) * Val::from_canonical_u32(air.modulus).inverse();
+        row[col("borrow")] = borrow;
+
+        let range = layout.get("out_range").unwrap().start + i*RANGE_CHECK_WIDTH;
+        assign_range_check(&mut row[range..range + RANGE_CHECK_WIDTH], out.as_canonical_u32(), air.modulus);
+        let mask = (1 << LIMB_BITS) - 1;
+        let low = (air.a[i] & mask) as i64 - (air.b[i] & mask) as i64 + (air.modulus & mask) as i64
+            - (1 - borrow.as_canonical_u32() as i64) * (air.modulus & mask) as i64 - (out.as_canonical_u32() & mask) as i64;
+        let carry = layout.get("low_carry_bits").unwrap().start + i*MUL_CARRY_BITS;
+        assign_carry_chain(&mut row[carry..carry + MUL_CARRY_BITS], &[low]);
+    }
+
+    // The reduced sums of the blocks out and carry of `air`, checked by its blocks out_range and low_carry_bits
+    fn sums_of<A: GadgetLayout>(air: &A, out: &str, carry: &str, out_range: &str, low_carry_bits: &str) -> Vec<ReducedSumColumns> {
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap_or_else(|| panic!("no block {} in the layout", block));
+        reduced_sum_columns(col(out), col(carry), col(out_range).start, col(low_carry_bits).start)
+    }
+
+    #[test]
+    fn test_corrupted_linear_gadgets() {
+        let (a, b) = (random_poly(P1), random_poly(P1));
+        let c = thread_rng().gen_range(0..P1);
+
+        let air = PolyAddAir { a:a.clone(), b:b.clone(), modulus:P1, n:N };
+        assert_rejects_resolved(&air, &generate_polyadd_trace(a.clone(), b.clone(), P1, N).unwrap(), "out", |row, i| resolve_add(&air, row, i));
+
+        let air = PolySubAir { a:a.clone(), b:b.clone(), modulus:P1, n:N };
+        assert_rejects_resolved(&air, &generate_polysub_trace(a.clone(), b.clone(), P1, N).unwrap(), "out", |row, i| resolve_sub(&air, row, i));
+
+        let air = PolyNegAir { a:a.clone(), modulus:P1, n:N };
+        assert_rejects_corrupted(&air, &generate_polyneg_trace(a.clone(), P1, N).unwrap(), "out");
+
+        let air = PolyScalarMulAir::with_n(a.clone(), c, P1, N).unwrap();
+        assert_rejects_corrupted(&air, &generate_polyscalarmul_trace(a, c, P1, N).unwrap(), "out");
+    }
+
+    #[test]
+    fn test_corrupted_mul_gadgets() {
+        let (a, b, c) = (random_poly(P1), random_poly(P1), random_poly(P1));
+
+        let air = PolyMulAir { a:a.clone(), b:b.clone(), modulus:P1, n:N, shape: MulShape::Row };
+        assert_rejects_corrupted(&air, &generate_polymul_trace(a.clone(), b.clone(), P1, N).unwrap(), "out");
+
+        let air = NegacyclicMulAir { a:a.clone(), b:b.clone(), modulus:P1, n:N };
+        assert_rejects_corrupted(&air, &generate_negacyclic_mul_trace(a.clone(), b.clone(), P1, N).unwrap(), "reduced");
+
+        let air = AddThenMulAir { a:a.clone(), b:b.clone(), c:c.clone(), modulus:P1, n:N };
+        let trace = generate_add_then_mul_trace(a, b, c, P1, N).unwrap();
+        assert_rejects_corrupted(&air, &trace, "tmp");
+        assert_rejects_corrupted(&air, &trace, "mul.reduced");
+    }
+
+    #[test]
+    fn test_corrupted_bounded_gadgets() {
+        // small noise e in [-bound, bound], and b = a + e
+        let bound = 1 << 10;
+        let mut rng = thread_rng();
+        let noise: Vec<u32> = (0..N).map(|_| rng.gen_range(-(bound as i64)..=bound as i64).rem_euclid(P1 as i64) as u32).collect();
+        let a = random_poly(P1);
+        let b: Vec<u32> = (0..N).map(|i| ((a[i] as u64 + noise[i] as u64) % P1 as u64) as u32).collect();
+
+        let air = CenterAir { poly:a.clone(), modulus:P1, n:N };
+        assert_rejects_corrupted(&air, &generate_center_trace(a.clone(), P1, N).unwrap(), "out");
+
+        let air = NoiseBoundAir { poly:noise.clone(), bound, modulus:P1, n:N };
+        assert_rejects_corrupted(&air, &generate_noise_bound_trace(noise, bound, P1, N).unwrap(), "mag");
+
+        let air = ApproxEqualAir { a:a.clone(), b:b.clone(), bound, modulus:P1, n:N };
+        assert_rejects_corrupted(&air, &generate_approx_equal_trace(a, b, bound, P1, N).unwrap(), "diff");
+    }
+
+    #[test]
+    fn test_corrupted_base_extend() {
+        let (r1, r2) = (random_poly(P1), random_poly(P2));
+
+        let air = BaseExtendAir { r1:r1.clone(), r2:r2.clone(), n:N };
+        assert_rejects_corrupted(&air, &generate_base_extend_trace(r1, r2, N).unwrap(), "r3");
+    }
+
+    #[test]
+    fn test_corrupted_decrypt() {
+        let (c0, c1) = (random_poly(P1), random_poly(P1));
+        let s: Vec<u32> = (0..N).map(|_| thread_rng().gen_range(0..2)).collect();
+
+        let air = DecryptAir { c0:c0.clone(), c1:c1.clone(), modulus:P1, plain_modulus:16, n:N };
+        let trace = generate_decrypt_trace(c0, c1, s, P1, 16, N).unwrap();
+        assert_rejects_corrupted(&air, &trace, "phase");
+        assert_rejects_corrupted(&air, &trace, "rescale.coeff[0].out");
+    }
+
+    #[test]
+    fn test_flipped_carry_linear_gadgets() {
+        let (a, b) = (random_poly(P1), random_poly(P1));
+
+        let air = PolyAddAir { a:a.clone(), b:b.clone(), modulus:P1, n:N };
+        let sums = sums_of(&air, "out", "q", "out_range", "low_carry_bits");
+        assert_rejects_flipped_carry(&air, &generate_polyadd_trace(a.clone(), b.clone(), P1, N).unwrap(), &[], &sums, P1);
+
+        let air = PolySubAir { a:a.clone(), b:b.clone(), modulus:P1, n:N };
+        let sums: Vec<_> = sums_of(&air, "out", "borrow", "out_range", "low_carry_bits").into_iter().map(|sum| ReducedSumColumns { borrow: true, ..sum }).collect();
+        assert_rejects_flipped_carry(&air, &generate_polysub_trace(a.clone(), b.clone(), P1, N).unwrap(), &[], &sums, P1);
+
+        // out0 and out1 are adjacent, as are carry0 and carry1, and their checks are one block of 2N sums
+        let (x, y) = (Ciphertext::new(a.clone(), b.clone()), Ciphertext::new(b.clone(), a.clone()));
+        let air = CiphertextAddAir { a:x.clone(), b:y.clone(), modulus:P1, n:N };
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap();
+        let sums = reduced_sum_columns(col("out0").start..col("out1").end, col("carry0").start..col("carry1").end, col("out_range").start, col("low_carry_bits").start);
+        assert_rejects_flipped_carry(&air, &generate_ciphertext_add_trace(x, y, P1, N).unwrap(), &[], &sums, P1);
+
+        // row 0 is the addition 0, whose out[i] is the public value i
+        let (batch_a, batch_b) = (vec![a.clone(), b.clone()], vec![b.clone(), a.clone()]);
+        let air = BatchAddAir { a:batch_a.clone(), b:batch_b.clone(), modulus:P1, n:N };
+        let (trace, public_values) = generate_batch_add_trace(batch_a, batch_b, P1, N).unwrap();
+        let sums: Vec<_> = sums_of(&air, "out", "carry", "out_range", "low_carry_bits").into_iter().enumerate()
+            .map(|(i, sum)| ReducedSumColumns { public: Some(i), ..sum }).collect();
+        assert_rejects_flipped_carry(&air, &trace, &public_values, &sums, P1);
+
+        // acc[0] = poly[0] is not a sum
+        let air = CoeffSumAir { poly:a.clone(), modulus:P1, n:N };
+        let layout = air.layout();
+        let col = |block: &str| layout.get(block).unwrap();
+        let sums = reduced_sum_columns(col("acc").start+1..col("acc").end, col("carry"), col("acc_range").start, col("low_carry_bits").start);
+        assert_rejects_flipped_carry(&air, &generate_coeff_sum_trace(a, P1, N).unwrap(), &[], &sums, P1);
+    }
+
+    #[test]
+    fn test_flipped_carry_product_gadgets() {
+        let (a, b, c) = (random_poly(P1), random_poly(P1), random_poly(P1));
+
+        let air = InnerProductAir { a:vec![a.clone(), b.clone()], b:vec![c.clone(), a.clone()], modulus:P1, n:N };
+        let trace = generate_inner_product_trace(vec![a.clone(), b.clone()], vec![c.clone(), a.clone()], P1, N).unwrap();
+        assert_rejects_flipped_carry(&air, &trace, &[], &sums_of(&air, "acc[1]", "carry[1]", "acc_range[1]", "low_carry_bits[1]"), P1);
+
+        let air = AddThenMulAir { a:a.clone(), b:b.clone(), c:c.clone(), modulus:P1, n:N };
+        let trace = generate_add_then_mul_trace(a.clone(), b, c, P1, N).unwrap();
+        assert_rejects_flipped_carry(&air, &trace, &[], &sums_of(&air, "tmp", "carry", "tmp_range", "low_carry_bits"), P1);
+
+        let s: Vec<u32> = (0..N).map(|_| thread_rng().gen_range(0..2)).collect();
+        let c0 = random_poly(P1);
+        let air = DecryptAir { c0:c0.clone(), c1:a.clone(), modulus:P1, plain_modulus:16, n:N };
+        let trace = generate_decrypt_trace(c0, a, s, P1, 16, N).unwrap();
+        assert_rejects_flipped_carry(&air, &trace, &[], &sums_of(&air, "phase", "carry", "phase_range", "low_carry_bits"), P1);
+    }
+}
@@ -0,0 +1,294 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::{assign_wide_reduction, check_polymul_bounds, eval_wide_reduction, power_table, range_checked_limbs, COEFF_LIMBS, CRT_LIMBS, MUL_CARRY_BITS, MUL_CRT_BITS};
+use crate::gadgets::range_check::{assign_range_check, eval_range_checks, RANGE_CHECK_BITS, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::pad_trace;
+use crate::params::N;
+
+// Define AIR constraint inputs
+pub struct PolySquareAir {
+    pub a: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PolySquareAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, modulus: u32) -> Self {
+        Self { a, modulus, n: N }
+    }
+}
+
+/*
+Polynomial Squaring Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[2N-2] * X^{2N-2} where out = a * a
+- q = q[0] + q[1] * X + ... + q[2N-2] * X^{2N-2}: quotients of the non-native modular reduction
+
+Note:
+- Same argument as PolyMulAir with b = a: a(x)^2 === out(x) + mod * q(x) at x = [0..2N-1) for the identity 2) mod n,
+and the identity 1) mod 2^MUL_CRT_BITS with eval_wide_reduction() over the limbs of the range checked a[i] and out[k],
+with the quotients q[k] < N * mod < 2^43 decomposed into MUL_CRT_BITS bits.
+- Compared to PolyMulAir(a, a), the N columns of b and its 2N-1 evaluations are gone:
+a(x) is evaluated once per point and squared.
+- On the host, the convolution is symmetric, out[i] = sum_{j+k=i} a[j] * a[k], so each pair j < k is computed once and doubled,
+and a[i/2]^2 is added when i is even, for about half of the products.
+*/
+impl<F: Field> BaseAir<F> for PolySquareAir {
+    // Air Table looks like this
+    // row:[ a: N ][ out(x): 2N-1 ][ q(x): 2N-1 ][ input_range: 62N ][ out_range: 62(2N-1) ][ q_bits: 43(2N-1) ][ carry_bits: 138(2N-1) ]
+    //     ^input-^^------------------------------------calculated by generate_polysquare_trace--------------------------------------^
+    //     [0.......................................................................................................................0]
+    //     [0.......................................................................................................................0]
+    //     [0.......................................................................................................................0]
+    fn width(&self) -> usize {
+        SquareLayout::new(self.n).width
+    }
+}
+
+// Column offsets of the PolySquareAir row, as MulLayout without b
+struct SquareLayout {
+    out: usize,
+    q: usize,
+    input_range: usize,
+    out_range: usize,
+    q_bits: usize,
+    carry_bits: usize,
+    width: usize
+}
+
+impl SquareLayout {
+    fn new(n: usize) -> Self {
+        let out = n;
+        let q = out + 2*n-1;
+        let input_range = q + 2*n-1;
+        let out_range = input_range + RANGE_CHECK_WIDTH*n;
+        let q_bits = out_range + RANGE_CHECK_WIDTH*(2*n-1);
+        let carry_bits = q_bits + MUL_CRT_BITS*(2*n-1);
+        let width = carry_bits + CRT_LIMBS*MUL_CARRY_BITS*(2*n-1);
+        Self { out, q, input_range, out_range, q_bits, carry_bits, width }
+    }
+}
+
+impl GadgetLayout for PolySquareAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("out", 2*self.n-1)
+            .push("q", 2*self.n-1)
+            .push("input_range", RANGE_CHECK_WIDTH*self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*(2*self.n-1))
+            .push("q_bits", MUL_CRT_BITS*(2*self.n-1))
+            .push("carry_bits", CRT_LIMBS*MUL_CARRY_BITS*(2*self.n-1))
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for PolySquareAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        // Enforce self.a as the input polynomial
+        for i in 0..n {
+            builder.when_first_row().assert_eq(row[i], AB::Expr::from_canonical_u32(self.a[i]));
+        }
+
+        // Evaluate a(x), out(x) and q(x) at x = [0..2N-1), sharing the power table as in PolyMulAir
+        let powers = power_table::<AB::F>(n);
+        let power = |i: usize, j: usize| powers[i * (2*n-1) + j];
+        let layout = SquareLayout::new(n);
+        let (out, q) = (layout.out, layout.q);
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+        for i in 0..2*n-1 {
+            let mut a_eval = AB::Expr::zero();
+            for j in 0..n {
+                a_eval = a_eval + row[j] * power(i, j);
+            }
+            let mut out_eval = AB::Expr::zero();
+            let mut q_eval = AB::Expr::zero();
+            for j in 0..2*n-1 {
+                out_eval = out_eval + row[out+j] * power(i, j);
+                q_eval = q_eval + row[q+j] * power(i, j);
+            }
+
+            // Enforce a[x]^2 === out[x] + mod * q[x]
+            builder.assert_eq(a_eval.clone() * a_eval, out_eval + q_eval * modulus);
+        }
+
+        // Enforce 0 <= a[i] < mod and 0 <= out[k] < mod
+        let inputs: Vec<AB::Expr> = (0..n).map(|i| row[i].into()).collect();
+        eval_range_checks(builder, &inputs, &row[layout.input_range..layout.out_range], self.modulus);
+        let outputs: Vec<AB::Expr> = (0..2*n-1).map(|k| row[out+k].into()).collect();
+        eval_range_checks(builder, &outputs, &row[layout.out_range..layout.q_bits], self.modulus);
+
+        // Enforce sum_{i+j=k} a[i] * a[j] === q[k] * mod + out[k] (mod 2^MUL_CRT_BITS), from the limb convolutions
+        let coeff_limbs = |block: usize| range_checked_limbs::<AB>(&row[block..block + RANGE_CHECK_BITS]);
+        let a_limbs: Vec<Vec<AB::Expr>> = (0..n).map(|i| coeff_limbs(layout.input_range + i*RANGE_CHECK_WIDTH)).collect();
+        for k in 0..2*n-1 {
+            let sum: Vec<AB::Expr> = (0..CRT_LIMBS).map(|m| {
+                let mut sum = AB::Expr::zero();
+                for i in k.saturating_sub(n-1)..=k.min(n-1) {
+                    for l in m.saturating_sub(COEFF_LIMBS-1)..=m.min(COEFF_LIMBS-1) {
+                        sum = sum + a_limbs[i][l].clone() * a_limbs[k-i][m-l].clone();
+                    }
+                }
+                sum
+            }).collect();
+            let (q_bits, carry_bits) = (layout.q_bits + k*MUL_CRT_BITS, layout.carry_bits + k*CRT_LIMBS*MUL_CARRY_BITS);
+            eval_wide_reduction(
+                builder, sum, row[q+k].into(),
+                coeff_limbs(layout.out_range + k*RANGE_CHECK_WIDTH),
+                &row[q_bits..q_bits + MUL_CRT_BITS],
+                &row[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+                self.modulus
+            );
+        }
+    }
+}
+
+// Coefficient of X^i in a * a before reduction, computing each product a[j] * a[i-j] with j < i-j once
+fn square_sum(a: &[u32], i: usize) -> u128 {
+    let n = a.len();
+    let lo = if i < n { 0 } else { i - n + 1 };
+    let mut sum: u128 = 0;
+    for j in lo..(i+1)/2 {
+        sum += a[j] as u128 * a[i-j] as u128;
+    }
+    sum *= 2;
+    if i % 2 == 0 {
+        sum += a[i/2] as u128 * a[i/2] as u128;
+    }
+    sum
+}
+
+// Square the polynomial manually, returning the reduced coefficients out and the quotients q of the reduction
+pub fn polysquare_coeffs(a: &[u32], modulus: u32) -> (Vec<u32>, Vec<u64>) {
+    let sums: Vec<u128> = (0..2*a.len()-1).map(|i| square_sum(a, i)).collect();
+    let out = sums.iter().map(|&sum| (sum % modulus as u128) as u32).collect();
+    // q[i] < N * p < 2^43, so it fits in u64
+    let q = sums.iter().map(|&sum| (sum / modulus as u128) as u64).collect();
+    (out, q)
+}
+
+// Define a function to generate execution trace
+pub fn generate_polysquare_trace<F: Field>(a: Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+
+    check_polymul_bounds(modulus, n)?;
+
+    let layout = SquareLayout::new(n);
+    let mut row = vec![F::zero(); layout.width];
+
+    // Assign input polynomial and its range checks
+    for (i, &c) in a.iter().enumerate() {
+        row[i] = F::from_canonical_u32(c);
+        let block = layout.input_range + i*RANGE_CHECK_WIDTH;
+        assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], c, modulus);
+    }
+
+    // Assign output coefficients and quotients (reduced into the native field) with their witness
+    let (out, q) = polysquare_coeffs(&a, modulus);
+    for k in 0..2*n-1 {
+        assign_square_output(&mut row, &layout, &a, k, out[k], q[k], modulus);
+    }
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(row, layout.width))
+}
+
+// Assign out[k] and q[k] with the range check of out[k], the bits of q[k] and the carries of its reduction
+fn assign_square_output<F: Field>(row: &mut [F], layout: &SquareLayout, a: &[u32], k: usize, out: u32, q: u64, modulus: u32) {
+    let n = a.len();
+    row[layout.out+k] = F::from_canonical_u32(out);
+    row[layout.q+k] = F::from_wrapped_u64(q);
+    let block = layout.out_range + k*RANGE_CHECK_WIDTH;
+    assign_range_check(&mut row[block..block+RANGE_CHECK_WIDTH], out, modulus);
+
+    // limb convolutions of sum_{i+j=k} a[i] * a[j] at positions [0..CRT_LIMBS)
+    let mut sum = vec![0u64; CRT_LIMBS];
+    for i in k.saturating_sub(n-1)..=k.min(n-1) {
+        for (m, x) in convolve(&limbs(a[i] as u128, COEFF_LIMBS), &limbs(a[k-i] as u128, COEFF_LIMBS), CRT_LIMBS).into_iter().enumerate() {
+            sum[m] += x;
+        }
+    }
+
+    let (q_bits, carry_bits) = (layout.q_bits + k*MUL_CRT_BITS, k*CRT_LIMBS*MUL_CARRY_BITS);
+    let (head, carries) = row.split_at_mut(layout.carry_bits);
+    assign_wide_reduction(
+        &mut head[q_bits..q_bits + MUL_CRT_BITS],
+        &mut carries[carry_bits..carry_bits + CRT_LIMBS*MUL_CARRY_BITS],
+        &sum, q, out, modulus
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{get_symbolic_constraints, prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use p3_field::PrimeField32;
+    use crate::gadgets::mul::{MulShape, PolyMulAir, generate_polymul_trace};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::gadgets::utils::mod_inv;
+    use crate::params::P1;
+
+    #[test]
+    fn test_poly_square_matches_poly_mul() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        let mut rng = thread_rng();
+        for n in [1, 4, 16] {
+            let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+            let air = PolySquareAir { a:a.clone(), modulus:P1, n };
+            let trace = generate_polysquare_trace::<Val>(a.clone(), P1, n).unwrap();
+
+            // out and q agree with PolyMulAir(a, a), which has the b columns in between
+            let mul_trace = generate_polymul_trace::<Val>(a.clone(), a.clone(), P1, n).unwrap();
+            let (row, mul_row) = (trace.row_slice(0), mul_trace.row_slice(0));
+            assert_eq!(&row[n..5*n-2], &mul_row[2*n..6*n-2]);
+            drop((row, mul_row));
+
+            // N fewer input constraints, and the 2N-1 identities no longer evaluate b
+            let mul_air = PolyMulAir { a:a.clone(), b:a, modulus:P1, n, shape: MulShape::Row };
+            assert!(get_symbolic_constraints::<Val, _>(&air, 0, 0).len() < get_symbolic_constraints::<Val, _>(&mul_air, 0, 0).len());
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+            let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+            verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn test_poly_square_forged_output() {
+        let n = 4;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = PolySquareAir { a: a.clone(), modulus: P1, n };
+        let mut trace = generate_polysquare_trace::<Val>(a.clone(), P1, n).unwrap();
+
+        // out[k] + delta with q[k] re-solved mod n, so that the evaluations of a(x)^2 === out(x) + mod * q(x) still hold
+        let (k, delta) = (rng.gen_range(0..2*n-1), rng.gen_range(1..P1));
+        let order = Val::ORDER_U32 as u64;
+        let out = ((polysquare_coeffs(&a, P1).0[k] as u64 + delta as u64) % P1 as u64) as u32;
+        let q = ((square_sum(&a, k) % order as u128) as u64 + order - out as u64) % order * mod_inv(P1 as u64, order) % order;
+        let layout = SquareLayout::new(n);
+        assign_square_output(&mut trace.values[..layout.width], &layout, &a, k, out, q, P1);
+
+        assert_rejected(&air, trace, &format!("a forged out[{}] + {}", k, delta));
+    }
+}
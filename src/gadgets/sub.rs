@@ -0,0 +1,234 @@
+use std::ops::Range;
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::add::{eval_reduced_sums, reduced_sums_width, reduced_sums_witness};
+use crate::gadgets::barrett::LIMB_BITS;
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::mul::MUL_CARRY_BITS;
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::gadgets::range_check::{check_range_modulus, RANGE_CHECK_WIDTH};
+use crate::gadgets::trace::{MIN_TRACE_HEIGHT, pad_trace};
+use crate::params::{FheParams, ParamError, N};
+
+// Define AIR constraint inputs
+pub struct PolySubAir {
+    pub a: Vec<u32>,
+    pub b: Vec<u32>,
+    pub modulus: u32,
+    pub n: usize
+}
+
+impl PolySubAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Self {
+        Self { a, b, modulus, n: N }
+    }
+
+    // Construct the AIR with the number of coefficients and the modulus of `channel` of `params`
+    pub fn with_params(a: Vec<u32>, b: Vec<u32>, params: &FheParams, channel: usize) -> Result<Self, ParamError> {
+        Ok(Self { a, b, modulus: params.modulus(channel)?, n: params.n })
+    }
+}
+
+/*
+Polynomial Subtraction Air
+Input:
+- a = a[0] + a[1] * X + ... + a[N-1] * X^{N-1}
+- b = b[0] + b[1] * X + ... + b[N-1] * X^{N-1}
+- mod: FHE ciphertext modulus
+Output:
+- out = out[0] + out[1] * X + ... + out[N-1] * X^{N-1} where out[i] = (a[i] - b[i] + mod) % mod
+- borrow = borrow[0], ..., borrow[N-1]: 1 if a[i] < b[i], otherwise 0
+
+Note:
+- PolySubAir does not have a state transition. Values required for constraints are all stored in one row.
+- While `out` and `borrow` are calculated manually by generate_polysub_trace(), we prove that the subtraction was done correctly,
+by enforcing a[i] + borrow[i] * mod === b[i] + out[i] for each coefficient.
+- As in PolyAddAir, out[i] is range checked into [0, mod) and the identity is also enforced mod 2^8 over the lowest 8-bits limbs,
+through eval_reduced_sum() of a[i] - b[i] + mod with the quotient bit 1 - borrow[i].
+*/
+impl<F: Field> BaseAir<F> for PolySubAir {
+    // Air Table looks like this
+    // row:[  a: N  ][  b: N  ][mod:1][ out: N ][ borrow: N ][ out_range: 62N ][ low_carry_bits: 23N ]
+    //     ^-----------inputs---------^^-----------------calculated by generate_polysub_trace---------------^
+    //     [0...........................................................................................0]
+    //     [0...........................................................................................0]
+    //     [0...........................................................................................0]
+    fn width(&self) -> usize {
+        polysub_width(self.n)
+    }
+}
+
+fn polysub_width(n: usize) -> usize {
+    4*n+1 + reduced_sums_width(n)
+}
+
+impl GadgetLayout for PolySubAir {
+    fn layout(&self) -> TraceLayout {
+        TraceLayout::new()
+            .push("a", self.n)
+            .push("b", self.n)
+            .push("mod", 1)
+            .push("out", self.n)
+            .push("borrow", self.n)
+            .push("out_range", RANGE_CHECK_WIDTH*self.n)
+            .push("low_carry_bits", MUL_CARRY_BITS*self.n)
+    }
+}
+
+// Define constraints
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PolySubAir {
+    fn eval(&self, builder: &mut AB) {
+        self.eval_poly_op(builder);
+    }
+}
+
+impl PolynomialOpAir for PolySubAir {
+    fn a(&self) -> &[u32] {
+        &self.a
+    }
+
+    fn b(&self) -> &[u32] {
+        &self.b
+    }
+
+    fn modulus(&self) -> u32 {
+        self.modulus
+    }
+
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn output_columns(&self) -> Range<usize> {
+        2*self.n+1..3*self.n+1
+    }
+
+    fn eval_op<AB: AirBuilder>(&self, builder: &mut AB, row: &[AB::Var]) {
+        let n = self.n;
+
+        // Enforce self.modulus as mod
+        builder.when_first_row().assert_eq(row[2*n], AB::Expr::from_canonical_u32(self.modulus));
+
+        /*
+        We want to ensure a[i] - b[i] === out[i] mod p.
+        a[i] - b[i] lies in (-p, p), so at most one multiple of p (the borrow) is needed to bring it into [0, p):
+        - a[i] >= b[i]: borrow[i] = 0, out[i] = a[i] - b[i]
+        - a[i] <  b[i]: borrow[i] = 1, out[i] = a[i] - b[i] + p
+        Moving the negative term to the other side gives a[i] + borrow[i] * p === b[i] + out[i],
+        where borrow[i] plays the role of the quotient q in the addition gadget and is constrained to be a bit.
+        With out[i] range checked into [0, mod), both sides are equal over the integers once they are equal mod n and mod 2^8:
+        as a sum, this is a[i] - b[i] + p === (1 - borrow[i]) * p + out[i], reduced by eval_reduced_sum() from the constant limbs of a and b.
+        */
+        let mask = (1 << LIMB_BITS) - 1;
+        let modulus = AB::F::from_canonical_u32(self.modulus);
+        let sums = (0..n).map(|i| row[i] - row[i+n] + modulus).collect();
+        let sums_low = (0..n).map(|i| AB::Expr::from_canonical_u32((self.a[i] & mask) + (self.modulus & mask)) - AB::F::from_canonical_u32(self.b[i] & mask)).collect();
+        let quotients = (0..n).map(|i| AB::Expr::one() - row[i+3*n+1]).collect();
+        eval_reduced_sums(&mut builder.when_first_row(), sums, sums_low, quotients, &row[2*n+1..3*n+1], &row[4*n+1..polysub_width(n)], self.modulus);
+    }
+
+    fn generate_trace<F: Field>(&self) -> Result<RowMajorMatrix<F>, GadgetError> {
+        generate_polysub_trace(self.a.clone(), self.b.clone(), self.modulus, self.n)
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_polysub_trace<F: Field>(a:Vec<u32>, b:Vec<u32>, modulus: u32, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+
+    check_range_modulus(modulus)?;
+
+    // u64 keeps a[i] + modulus from overflowing for 32-bits moduli
+    let out: Vec<u32> = (0..n).map(|i| ((a[i] as u64 + modulus as u64 - b[i] as u64) % modulus as u64) as u32).collect();
+    let borrow: Vec<bool> = (0..n).map(|i| a[i] < b[i]).collect();
+
+    // Pad with zero rows up to trace_height(1) = MIN_TRACE_HEIGHT rows
+    Ok(pad_trace(polysub_row(&a, &b, &out, &borrow, modulus), polysub_width(n)))
+}
+
+// Row of the difference out of a and b with its borrows, the range checks of out and the carries of the low limb sums
+fn polysub_row<F: Field>(a: &[u32], b: &[u32], out: &[u32], borrow: &[bool], modulus: u32) -> Vec<F> {
+    let mut values: Vec<F> = Vec::with_capacity(MIN_TRACE_HEIGHT * polysub_width(a.len()));
+
+    // Add input polynomials, modulus, the difference and the borrows to values vector
+    values.extend(a.iter().chain(b).map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(modulus));
+    values.extend(out.iter().map(|&c| F::from_canonical_u32(c)));
+    values.extend(borrow.iter().map(|&c| F::from_bool(c)));
+
+    // Add the range checks of out and the carries of a[i]_0 - b[i]_0 + mod_0 - (1 - borrow[i]) * mod_0 - out[i]_0
+    let mask = (1 << LIMB_BITS) - 1;
+    let low: Vec<i64> = a.iter().zip(b).map(|(&x, &y)| (x & mask) as i64 - (y & mask) as i64 + (modulus & mask) as i64).collect();
+    let quotients: Vec<bool> = borrow.iter().map(|&c| !c).collect();
+    values.extend(reduced_sums_witness::<F>(&low, &quotients, out, modulus));
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+    use p3_matrix::Matrix;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use p3_field::PrimeField32;
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejected;
+    use crate::params::P1;
+
+    #[test]
+    fn test_poly_sub() -> Result<(), impl Debug> {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // generate 2 random input polynomials with N coefficients in the range of [0, P1)
+        let mut rng = thread_rng();
+        let mut random_poly1: Vec<u32> = (0..N).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let mut random_poly2: Vec<u32> = (0..N).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        // force a[i] < b[i] at a few indices to exercise the borrow path, including the extreme 0 - (P1-1)
+        for i in [0, 1, N/2, N-1] {
+            random_poly1[i] = 0;
+            random_poly2[i] = P1 - 1 - i as u32;
+        }
+
+        let air = PolySubAir::new(random_poly1.clone(), random_poly2.clone(), P1);
+
+        let trace = generate_polysub_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, N).unwrap();
+
+        // out[i] = a[i] - b[i] + P1 where the borrow was taken
+        let row = trace.row_slice(0);
+        for i in [0, 1, N/2, N-1] {
+            assert_eq!(row[i+2*N+1], Val::from_canonical_u32(i as u32 + 1));
+            assert_eq!(row[i+3*N+1], Val::one());
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![])
+    }
+
+    #[test]
+    fn test_poly_sub_forged_borrow() {
+        // (P1-1) - 0 with a borrow of 1 and out = P1-1 + P1 - n still holds mod n with out in [0, P1), but not mod 2^8
+        let (a, b) = (vec![P1 - 1; 4], vec![0; 4]);
+        let air = PolySubAir { a: a.clone(), b: b.clone(), modulus: P1, n: 4 };
+        let forged = (2 * P1 as u64 - 1 - Val::ORDER_U32 as u64) as u32;
+        assert_eq!(forged, 23068674);
+
+        let trace = pad_trace(polysub_row::<Val>(&a, &b, &vec![forged; 4], &vec![true; 4], P1), polysub_width(4));
+        assert_rejected(&air, trace, "a forged borrow");
+    }
+}
@@ -0,0 +1,120 @@
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use tracing::info_span;
+use crate::gadgets::error::{check_poly, GadgetError};
+
+/*
+Trace height (number of rows) shared by the trace generators
+
+Note:
+- The circle PCS of initialize_config() commits to the trace over a domain of 2^k points, so the height must be a power of two.
+Its low-degree extension has height << log_blowup rows, so doubling the height doubles the LDE and the proving work,
+independently of log_blowup.
+- MIN_TRACE_HEIGHT = 4 is the smallest height the gadgets are proven with: it leaves the 1 meaningful row
+of the single-row gadgets followed by zero rows, or repeated when their constraints hold on every row.
+The PCS needs more than 1 row: the circle domain of the trace is a twin coset, which has at least 2 points,
+the quotient of the degree-3 constraints (a selector times a product of 2 columns) is split into 2 chunks
+over the trace domain, and FRI folds away 1 bit of the domain in its first round. Every gadget test runs at 4 rows,
+so 4 is the smallest height they are known to prove and verify with.
+- The height is never assumed to be 4: trace_height() computes it from the number of meaningful rows,
+as the next power of two, and MIN_TRACE_HEIGHT only bounds it from below.
+- The zero-padded gadgets pin their inputs on the first row and hold their other constraints on zero rows as well,
+so their AIRs do not depend on the height: a larger power of two can be passed to the *_with_height generators,
+e.g. to prove several operations at the same height.
+*/
+pub const MIN_TRACE_HEIGHT: usize = 4;
+
+// Height of a trace with `rows` meaningful rows: the next power of two, and at least MIN_TRACE_HEIGHT
+pub fn trace_height(rows: usize) -> usize {
+    rows.next_power_of_two().max(MIN_TRACE_HEIGHT)
+}
+
+// Pad `values`, holding the meaningful rows of a trace with `width` columns, with zero rows up to trace_height()
+pub fn pad_trace<F: Field>(mut values: Vec<F>, width: usize) -> RowMajorMatrix<F> {
+    let rows = values.len() / width;
+    values.resize(trace_height(rows) * width, F::zero());
+    RowMajorMatrix::new(values, width)
+}
+
+// pad_trace() up to `height` rows instead, which must be a power of two of at least trace_height() of the meaningful rows
+pub fn pad_trace_to<F: Field>(mut values: Vec<F>, width: usize, height: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let min = trace_height(values.len() / width);
+    if !height.is_power_of_two() || height < min {
+        return Err(GadgetError::InvalidTraceHeight { height, min });
+    }
+    values.resize(height * width, F::zero());
+    Ok(RowMajorMatrix::new(values, width))
+}
+
+// Repeat `row` trace_height(1) times, for gadgets whose constraints are enforced on every row
+pub fn repeat_row<F: Field>(row: &[F]) -> RowMajorMatrix<F> {
+    let height = trace_height(1);
+    let mut values: Vec<F> = Vec::with_capacity(height * row.len());
+    for _ in 0..height {
+        values.extend_from_slice(row);
+    }
+    RowMajorMatrix::new(values, row.len())
+}
+
+// Trace of an elementwise gadget laid out as [a: N][b: N][mod: 1][out: N] on the first row, as in PolyAddAir,
+// where out[i] = op(a[i], b[i], mod) must be in [0, mod). Padded with zero rows up to trace_height(1).
+pub fn generate_elementwise_trace<F: Field>(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize, op: impl Fn(u64, u64, u32) -> u64) -> Result<RowMajorMatrix<F>, GadgetError> {
+    generate_elementwise_trace_with_height(a, b, modulus, n, trace_height(1), op)
+}
+
+// generate_elementwise_trace() padded with zero rows up to `height`, as in pad_trace_to()
+pub fn generate_elementwise_trace_with_height<F: Field>(a: Vec<u32>, b: Vec<u32>, modulus: u32, n: usize, height: usize, op: impl Fn(u64, u64, u32) -> u64) -> Result<RowMajorMatrix<F>, GadgetError> {
+    let _span = info_span!("trace_generation", n).entered();
+    check_poly(&a, n, modulus)?;
+    check_poly(&b, n, modulus)?;
+
+    let mut values: Vec<F> = Vec::with_capacity(height * (3*n+1));
+
+    // Add input polynomials and modulus to values vector
+    values.extend(a.iter().chain(b.iter()).map(|&c| F::from_canonical_u32(c)));
+    values.push(F::from_canonical_u32(modulus));
+
+    // Apply the operation to every pair of coefficients and push the results to values vector
+    values.extend(a.iter().zip(b.iter()).map(|(&x, &y)| F::from_canonical_u64(op(x as u64, y as u64, modulus))));
+
+    pad_trace_to(values, 3*n+1, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_field::AbstractField;
+    use p3_matrix::Matrix;
+    use crate::gadgets::config::Val;
+
+    #[test]
+    fn test_trace_height() {
+        assert_eq!(trace_height(1), MIN_TRACE_HEIGHT);
+        assert_eq!(trace_height(4), 4);
+        assert_eq!(trace_height(5), 8);
+        assert_eq!(trace_height(2*16-1), 32);
+
+        // 5 meaningful rows of width 3 are padded with 3 zero rows
+        let values: Vec<Val> = (1..=15).map(Val::from_canonical_u32).collect();
+        let trace = pad_trace(values.clone(), 3);
+        assert_eq!(trace.height(), 8);
+        assert_eq!(&trace.values[..15], &values[..]);
+        assert!(trace.values[15..].iter().all(|&v| v == Val::zero()));
+
+        // an explicit height must be a power of two, and cannot drop meaningful rows
+        assert_eq!(pad_trace_to(values.clone(), 3, 16).unwrap().height(), 16);
+        assert_eq!(pad_trace_to(values.clone(), 3, 12).unwrap_err(), GadgetError::InvalidTraceHeight { height: 12, min: 8 });
+        assert_eq!(pad_trace_to(values, 3, 4).unwrap_err(), GadgetError::InvalidTraceHeight { height: 4, min: 8 });
+    }
+
+    #[test]
+    fn test_generated_trace_height() {
+        // the single-row traces, zero-padded or repeated, have exactly MIN_TRACE_HEIGHT rows
+        let n = 4;
+        let trace = generate_elementwise_trace::<Val>(vec![1; n], vec![2; n], 5, n, |x, y, m| (x + y) % m as u64).unwrap();
+        assert_eq!(trace.height(), MIN_TRACE_HEIGHT);
+        assert_eq!(trace.values.len(), MIN_TRACE_HEIGHT * (3*n+1));
+        assert_eq!(repeat_row(&[Val::one(); 7]).height(), MIN_TRACE_HEIGHT);
+        assert_eq!(pad_trace(vec![Val::one(); 7], 7).height(), MIN_TRACE_HEIGHT);
+    }
+}
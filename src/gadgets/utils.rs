@@ -0,0 +1,26 @@
+// Host-side modular arithmetic shared by the gadgets to precompute constraint constants
+
+pub fn mod_exp(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    if modulus == 1 {
+        return 0;
+    }
+    let mut result = 1;
+    base %= modulus; // Initial reduction of base
+
+    // perform exponentiation by iterating exponents in binary representation from the LSB to MSB
+    while exp > 0 {
+        // when the bit is 1: base * result
+        if exp % 2 == 1 {
+            result = (base as u128 * result as u128 % modulus as u128) as u64;
+        }
+        // right shift exponent to the right by 1
+        exp >>= 1;
+        base = (base as u128 * base as u128 % modulus as u128) as u64;
+    }
+    result
+}
+
+// Inverse of x modulo the prime p by Fermat's little theorem: x^(p-2) mod p
+pub fn mod_inv(x: u64, p: u64) -> u64 {
+    mod_exp(x, p - 2, p)
+}
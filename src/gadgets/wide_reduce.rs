@@ -0,0 +1,278 @@
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{AbstractField, Field};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use crate::gadgets::barrett::{convolve, limbs, propagate, CARRY_BITS, LIMB_BITS};
+use crate::gadgets::bit_decompose::{bits, eval_bit_decompose, eval_from_bits};
+use crate::gadgets::error::GadgetError;
+use crate::gadgets::layout::{GadgetLayout, TraceLayout};
+use crate::gadgets::range_check::{eval_less_than, less_than_columns};
+use crate::gadgets::trace::repeat_row;
+use crate::params::{N, P};
+
+// A wide value is held in WIDE_LIMBS little-endian limbs of WIDE_LIMB_BITS bits each (120 bits > the 91 bits of P).
+// 30 bits, and not 31, so that the bits of a limb never sum to limb + n, which is at least 2^31
+pub const WIDE_LIMBS: usize = 4;
+pub const WIDE_LIMB_BITS: usize = 30;
+pub const WIDE_BITS: usize = WIDE_LIMBS * WIDE_LIMB_BITS;
+
+// The quotient floor(value / P) is below 2^120 / P < 2^30, since P > 2^90
+const QUOTIENT_BITS: usize = 30;
+
+// Number of 8-bits limbs of a wide value, of the quotient and of P, which the identity is proven over
+const WIDE_BYTES: usize = WIDE_BITS / LIMB_BITS;
+const QUOTIENT_BYTES: usize = QUOTIENT_BITS.div_ceil(LIMB_BITS);
+const P_BYTES: usize = 12;
+
+// Define AIR constraint inputs
+pub struct WideReduceAir {
+    // wide values below 2^WIDE_BITS, e.g. BFV tensor products before the reduction mod P
+    pub value: Vec<u128>,
+    pub n: usize
+}
+
+impl WideReduceAir {
+    // Construct the AIR with the default number of coefficients params::N
+    pub fn new(value: Vec<u128>) -> Self {
+        Self { value, n: N }
+    }
+}
+
+/*
+Wide Modular Reduction Air
+Input:
+- value[0], ..., value[N-1]: integers below 2^120, each held in 4 limbs of 30 bits: value[i] = sum_l limb[i][l] * 2^{30l}
+Output:
+- out[i] = value[i] % P, with P = P1 * P2 * P3 the 91-bits ciphertext modulus, in the same 4 limbs of 30 bits
+- q[i] = floor(value[i] / P)
+
+Note:
+- value[i] and out[i] do not fit in the native field, so they only exist as limbs, and the limbs as bits:
+limb[i][l] === sum_b bits[i][30l+b] * 2^b, so a limb is a well-defined integer below 2^30 < n.
+- value[i] === q[i] * P + out[i] is proven over the 8-bits limbs of the bits, with a carry chain as in BarrettReduceAir,
+where P_l are the precomputed 8-bits limbs of P and x_m is the 8-bits limb m of x:
+    sum_{j+l=m} q_j * P_l + out_m + carry_m === value_m + 2^8 * carry_{m+1}
+Every term is a bit or a byte times a constant and q has 4 bytes, so each side is below 4 * 2^16 + 2^20 < n
+and the equation holds over the integers. The carries are range checked by their 12 bits, and the last one is 0.
+- out[i] < P: the 120 bits of out[i] are compared with the constant bits of P from the MSB down, as in CrtRecombineAir.
+Together with q[i] >= 0, this makes out[i] the canonical representative of value[i] in [0, P).
+- q[i] < 2^30 is range checked by its bits: value[i] < 2^120 needs no more.
+*/
+impl<F: Field> BaseAir<F> for WideReduceAir {
+    // Air Table looks like this
+    // row:[ value: 4N ][ out: 4N ][ q: N ][ value bits: 120N ][ out bits: 120N ][ out eq: 120N ][ q bits: 30N ][ carry bits: 12 * 14N ]
+    //     ^--input---^^-----------------------------calculated by generate_wide_reduce_trace------------------------------------^
+    //     ... the same row repeated 3 times, since every row must pass the comparison
+    fn width(&self) -> usize {
+        wide_reduce_width(self.n)
+    }
+}
+
+impl GadgetLayout for WideReduceAir {
+    fn layout(&self) -> TraceLayout {
+        let n = self.n;
+        TraceLayout::new()
+            .push("value", WIDE_LIMBS*n)
+            .push("out", WIDE_LIMBS*n)
+            .push("q", n)
+            .push("value_bits", WIDE_BITS*n)
+            .push("out_bits", WIDE_BITS*n)
+            .push("out_eq", WIDE_BITS*n)
+            .push("q_bits", QUOTIENT_BITS*n)
+            .push("carry_bits", CARRY_BITS*(WIDE_BYTES-1)*n)
+    }
+}
+
+fn wide_reduce_width(n: usize) -> usize {
+    (2*WIDE_LIMBS + 1 + 3*WIDE_BITS + QUOTIENT_BITS + CARRY_BITS*(WIDE_BYTES-1))*n
+}
+
+// Little-endian WIDE_LIMB_BITS-bits limbs of x
+pub fn wide_limbs(x: u128) -> [u32; WIDE_LIMBS] {
+    std::array::from_fn(|l| ((x >> (l*WIDE_LIMB_BITS)) & ((1 << WIDE_LIMB_BITS) - 1)) as u32)
+}
+
+// Little-endian bits of P, padded to WIDE_BITS
+fn modulus_bits() -> Vec<bool> {
+    (0..WIDE_BITS).map(|k| (P >> k) & 1 == 1).collect()
+}
+
+// The 8-bits limb m of the integer with little-endian bits `bits`, which are constrained to be boolean elsewhere
+fn byte<AB: AirBuilder>(bits: &[AB::Var], m: usize) -> AB::Expr {
+    let mut sum = AB::Expr::zero();
+    for (b, &bit) in bits.iter().enumerate().skip(m*LIMB_BITS).take(LIMB_BITS) {
+        sum = sum + bit * AB::F::from_canonical_u32(1 << (b - m*LIMB_BITS));
+    }
+    sum
+}
+
+// Define constraints
+impl<AB: AirBuilder> Air<AB> for WideReduceAir {
+    fn eval(&self, builder: &mut AB) {
+        let n = self.n;
+        let main = builder.main();
+        let row = main.row_slice(0);
+
+        let (value, out, q) = (0, WIDE_LIMBS*n, 2*WIDE_LIMBS*n);
+        let value_bits = q + n;
+        let out_bits = value_bits + WIDE_BITS*n;
+        let out_eq = out_bits + WIDE_BITS*n;
+        let q_bits = out_eq + WIDE_BITS*n;
+        let carry_bits = q_bits + QUOTIENT_BITS*n;
+
+        let p_limbs = limbs(P, P_BYTES);
+        let bound = modulus_bits();
+        let limb_base = AB::F::from_canonical_u32(1 << LIMB_BITS);
+
+        for i in 0..n {
+            let (value, out, q) = (value + i*WIDE_LIMBS, out + i*WIDE_LIMBS, q + i);
+            let value_bits = &row[value_bits + i*WIDE_BITS..value_bits + (i+1)*WIDE_BITS];
+            let out_bits = &row[out_bits + i*WIDE_BITS..out_bits + (i+1)*WIDE_BITS];
+            let out_eq = &row[out_eq + i*WIDE_BITS..out_eq + (i+1)*WIDE_BITS];
+            let q_bits = &row[q_bits + i*QUOTIENT_BITS..q_bits + (i+1)*QUOTIENT_BITS];
+
+            // Enforce self.value as the input limbs
+            for (l, limb) in wide_limbs(self.value[i]).into_iter().enumerate() {
+                builder.when_first_row().assert_eq(row[value+l], AB::Expr::from_canonical_u32(limb));
+            }
+
+            // Enforce limb[i][l] === sum_b bits[i][30l+b] * 2^b for value and out, and q === sum_b q_bits[b] * 2^b
+            for l in 0..WIDE_LIMBS {
+                let bits = l*WIDE_LIMB_BITS..(l+1)*WIDE_LIMB_BITS;
+                eval_bit_decompose(builder, row[value+l].into(), &value_bits[bits.clone()]);
+                eval_bit_decompose(builder, row[out+l].into(), &out_bits[bits]);
+            }
+            eval_bit_decompose(builder, row[q].into(), q_bits);
+
+            // Enforce out[i] < P
+            eval_less_than(builder, out_bits, out_eq, &bound);
+
+            // Enforce sum_{j+l=m} q_j * P_l + out_m + carry_m === value_m + 2^8 * carry_{m+1}
+            let carry_bits = carry_bits + i*CARRY_BITS*(WIDE_BYTES-1);
+            let carry: Vec<AB::Expr> = (0..WIDE_BYTES-1).map(|m| {
+                eval_from_bits(builder, &row[carry_bits + m*CARRY_BITS..carry_bits + (m+1)*CARRY_BITS])
+            }).collect();
+            for m in 0..WIDE_BYTES {
+                let mut lhs = if m > 0 { carry[m-1].clone() } else { AB::Expr::zero() };
+                for (l, &p_l) in p_limbs.iter().enumerate() {
+                    if m >= l && m - l < QUOTIENT_BYTES {
+                        lhs = lhs + byte::<AB>(q_bits, m-l) * AB::F::from_canonical_u64(p_l);
+                    }
+                }
+                lhs = lhs + byte::<AB>(out_bits, m);
+                let mut rhs = byte::<AB>(value_bits, m);
+                if m < WIDE_BYTES-1 {
+                    rhs = rhs + carry[m].clone() * limb_base;
+                }
+                builder.assert_eq(lhs, rhs);
+            }
+        }
+    }
+}
+
+// Define a function to generate execution trace
+pub fn generate_wide_reduce_trace<F: Field>(value: Vec<u128>, n: usize) -> Result<RowMajorMatrix<F>, GadgetError> {
+    if value.len() != n {
+        return Err(GadgetError::LengthMismatch { expected: n, actual: value.len() });
+    }
+    if let Some(index) = value.iter().position(|&v| v >> WIDE_BITS != 0) {
+        return Err(GadgetError::ValueOutOfRange { index, bits: WIDE_BITS });
+    }
+
+    let width = wide_reduce_width(n);
+    let mut row: Vec<F> = Vec::with_capacity(width);
+
+    // Reduce every value on the host
+    let (outs, qs): (Vec<u128>, Vec<u128>) = value.iter().map(|&v| (v % P, v / P)).unzip();
+
+    // Assign the limbs of value and out, then the quotients
+    for xs in [&value, &outs] {
+        for &x in xs.iter() {
+            row.extend(wide_limbs(x).into_iter().map(F::from_canonical_u32));
+        }
+    }
+    row.extend(qs.iter().map(|&q| F::from_canonical_u64(q as u64)));
+
+    // Assign the bits of value, the bits and prefix equality flags of out against P, and the bits of q
+    let bound = modulus_bits();
+    let wide_bits = |x: u128| -> Vec<bool> { (0..WIDE_BITS).map(|k| (x >> k) & 1 == 1).collect() };
+    for &v in value.iter() {
+        row.extend(wide_bits(v).into_iter().map(F::from_bool));
+    }
+    for &out in outs.iter() {
+        row.extend(wide_bits(out).into_iter().map(F::from_bool));
+    }
+    for &out in outs.iter() {
+        row.extend(less_than_columns(&wide_bits(out), &bound).into_iter().map(F::from_bool));
+    }
+    for &q in qs.iter() {
+        row.extend(bits(q as u64, QUOTIENT_BITS).map(F::from_bool));
+    }
+
+    // Assign the carries of q * P + out - value, which propagates to 0 limbs
+    let p_limbs = limbs(P, P_BYTES);
+    for i in 0..n {
+        let mut conv = convolve(&limbs(qs[i], QUOTIENT_BYTES), &p_limbs, WIDE_BYTES);
+        for (m, limb) in limbs(outs[i], WIDE_BYTES).into_iter().enumerate() {
+            conv[m] += limb;
+        }
+        let (_, carries) = propagate(&conv, &limbs(value[i], WIDE_BYTES));
+        for carry in carries {
+            row.extend(bits(carry, CARRY_BITS).map(F::from_bool));
+        }
+    }
+
+    // Repeat the row up to trace_height(1) = MIN_TRACE_HEIGHT rows, as in generate_range_check_trace
+    Ok(repeat_row(&row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p3_field::PrimeField32;
+    use rand::{thread_rng, Rng};
+    use p3_uni_stark::{prove, verify};
+    use crate::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+    use crate::gadgets::soundness::assert_rejects_corrupted;
+
+    #[test]
+    fn test_wide_reduce() {
+
+        let ZkConfig { config, byte_hash } = initialize_config();
+
+        // 91-bits values just below, at and above P, and the largest and a random 120-bits value
+        let mut rng = thread_rng();
+        let value: Vec<u128> = vec![P - 1, P, P + 5, (1 << 91) - 1, 0, (1 << WIDE_BITS) - 1, rng.gen_range(0..1 << WIDE_BITS), 7 * P + 3];
+        let n = value.len();
+
+        let air = WideReduceAir { value: value.clone(), n };
+        let trace = generate_wide_reduce_trace::<Val>(value.clone(), n).unwrap();
+
+        // the out limbs put back together match the host-side u128 reduction
+        let out = air.layout().get("out").unwrap();
+        let row = trace.row_slice(0);
+        for i in 0..n {
+            let x = (0..WIDE_LIMBS).fold(0u128, |x, l| {
+                x | (row[out.start + i*WIDE_LIMBS + l].as_canonical_u32() as u128) << (l*WIDE_LIMB_BITS)
+            });
+            assert_eq!(x, value[i] % P);
+        }
+        drop(row);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        let proof = prove(&config, &air, &mut challenger, trace.clone(), &vec![]);
+
+        let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+        verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+        assert_rejects_corrupted(&air, &trace, "out");
+    }
+
+    #[test]
+    fn test_wide_reduce_value_out_of_range() {
+        assert_eq!(
+            generate_wide_reduce_trace::<Val>(vec![1, 1 << WIDE_BITS], 2).unwrap_err(),
+            GadgetError::ValueOutOfRange { index: 1, bits: WIDE_BITS }
+        );
+    }
+}
@@ -0,0 +1,768 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_air::{Air, BaseAir};
+use p3_challenger::CanObserve;
+use p3_field::AbstractField;
+use p3_uni_stark::{prove, verify, PcsError, Proof, SymbolicAirBuilder, VerifierConstraintFolder};
+use tracing::info_span;
+use crate::gadgets::config::{Challenger, MyConfig, Val, VerifierAir, ZkAir, ZkConfig};
+use crate::gadgets::error::{check_poly, GadgetError};
+use crate::gadgets::poly_op::PolynomialOpAir;
+use crate::params::FheParams;
+
+// Proof produced by p3_uni_stark::prove under our ZkConfig
+pub type ZkProof = Proof<MyConfig>;
+
+// Reasons a proof is rejected, independent of the error type of p3_uni_stark::verify
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    // The proof does not have the shape of a proof of the AIR, e.g. a number of opened values other than its width
+    InvalidProofShape,
+    // The constraints evaluated at the out-of-domain point do not match the quotient the prover committed to
+    OutOfDomain,
+    // The opening proof of the committed polynomials at the out-of-domain point was rejected by FRI
+    FriFailed { reason: String },
+    // A number of public values other than the `expected` number of output coefficients of the AIR
+    PublicValueMismatch { expected: usize, actual: usize },
+    // The Merkle opening of the row `index` of committed inputs does not match the commitment
+    InvalidOpening { index: usize, reason: String },
+}
+
+impl From<p3_uni_stark::VerificationError<PcsError<MyConfig>>> for VerificationError {
+    fn from(error: p3_uni_stark::VerificationError<PcsError<MyConfig>>) -> Self {
+        match error {
+            p3_uni_stark::VerificationError::InvalidProofShape => VerificationError::InvalidProofShape,
+            p3_uni_stark::VerificationError::OodEvaluationMismatch => VerificationError::OutOfDomain,
+            p3_uni_stark::VerificationError::InvalidOpeningArgument(e) => VerificationError::FriFailed { reason: format!("{:?}", e) },
+        }
+    }
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::InvalidProofShape => write!(f, "the proof does not have the shape of a proof of this AIR"),
+            VerificationError::OutOfDomain => write!(f, "the constraints do not hold at the out-of-domain point"),
+            VerificationError::FriFailed { reason } => write!(f, "the FRI opening proof was rejected: {}", reason),
+            VerificationError::PublicValueMismatch { expected, actual } => {
+                write!(f, "expected {} public values, got {}", expected, actual)
+            }
+            VerificationError::InvalidOpening { index, reason } => {
+                write!(f, "the opening of row {} of the committed inputs was rejected: {}", index, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+// Errors raised while turning proofs into bytes and back
+#[derive(Debug)]
+pub enum ProofIoError {
+    // The proof could not be encoded to, or decoded from, bytes
+    Encoding(bincode::Error),
+    // The decoded proof was rejected by the verifier
+    Verification(VerificationError),
+    // A file could not be read or written
+    File(std::io::Error),
+    // A polynomial or proof could not be encoded to, or decoded from, JSON
+    Json(serde_json::Error),
+    // A loaded polynomial has a coefficient outside of its stated modulus
+    InvalidPolynomial(GadgetError),
+    // The proof at `index` of a bundle passed to verify_many() was rejected by the verifier
+    Bundle { index: usize, error: VerificationError },
+    // A chunked operation was given a number of proofs other than its `expected` number of chunks
+    ChunkCount { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ProofIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofIoError::Encoding(e) => write!(f, "failed to encode or decode the proof: {}", e),
+            ProofIoError::Verification(e) => write!(f, "proof verification failed: {}", e),
+            ProofIoError::File(e) => write!(f, "failed to access the file: {}", e),
+            ProofIoError::Json(e) => write!(f, "failed to encode or decode JSON: {}", e),
+            ProofIoError::InvalidPolynomial(e) => write!(f, "invalid polynomial: {}", e),
+            ProofIoError::Bundle { index, error } => write!(f, "proof {} of the bundle failed verification: {}", index, error),
+            ProofIoError::ChunkCount { expected, actual } => write!(f, "expected {} chunk proofs, got {}", expected, actual),
+        }
+    }
+}
+
+impl std::error::Error for ProofIoError {}
+
+// Encode a proof with bincode
+pub fn serialize_proof(proof: &ZkProof) -> Result<Vec<u8>, ProofIoError> {
+    bincode::serialize(proof).map_err(ProofIoError::Encoding)
+}
+
+// Decode a proof encoded by serialize_proof()
+pub fn deserialize_proof(bytes: &[u8]) -> Result<ZkProof, ProofIoError> {
+    bincode::deserialize(bytes).map_err(ProofIoError::Encoding)
+}
+
+/*
+Parameters a proof was produced under, embedded in front of it by serialize_proof_with_params()
+Note:
+- The proof itself does not record N, the moduli or the FRI parameters: a verifier with other ones either rejects it
+with an unrelated error, or, for the FHE parameters that only enter the AIR, checks another statement than intended.
+check_proof_params() compares the header with the verifier's own parameters first, and names the first mismatch.
+- The header is not bound to the proof: a prover can write any header, so it only guards against honest confusion,
+and verify() still has to run with the verifier's parameters, which the proof is checked against.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofParams {
+    pub n: usize,
+    pub moduli: Vec<u32>,
+    pub log_blowup: usize,
+    pub num_queries: usize,
+    pub proof_of_work_bits: usize,
+}
+
+impl ProofParams {
+    // Parameters of a proof of `params` under `zk_config`
+    pub fn new(params: &FheParams, zk_config: &ZkConfig) -> Self {
+        let fri_config = &zk_config.config.pcs().fri_config;
+        Self {
+            n: params.n,
+            moduli: params.moduli.clone(),
+            log_blowup: fri_config.log_blowup,
+            num_queries: fri_config.num_queries,
+            proof_of_work_bits: fri_config.proof_of_work_bits,
+        }
+    }
+}
+
+// A proof with the header of the parameters it was produced under
+#[derive(Serialize, Deserialize)]
+pub struct ParamsProof {
+    pub params: ProofParams,
+    pub proof: ZkProof,
+}
+
+// The first parameter of a proof header that differs from the verifier's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamMismatch {
+    // The proof is of polynomials with `actual` coefficients
+    N { expected: usize, actual: usize },
+    // The proof is over other RNS moduli
+    Moduli { expected: Vec<u32>, actual: Vec<u32> },
+    // The proof was produced with another value of the FRI parameter `name`
+    Fri { name: &'static str, expected: usize, actual: usize },
+}
+
+impl fmt::Display for ParamMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamMismatch::N { expected, actual } => write!(f, "expected a proof with N = {}, got N = {}", expected, actual),
+            ParamMismatch::Moduli { expected, actual } => write!(f, "expected a proof over the moduli {:?}, got {:?}", expected, actual),
+            ParamMismatch::Fri { name, expected, actual } => write!(f, "expected a proof with {} = {}, got {}", name, expected, actual),
+        }
+    }
+}
+
+impl std::error::Error for ParamMismatch {}
+
+// Check the header of `proof` against the parameters `expected` of the verifier, before verifying it
+pub fn check_proof_params(proof: &ParamsProof, expected: &ProofParams) -> Result<(), ParamMismatch> {
+    let actual = &proof.params;
+    if actual.n != expected.n {
+        return Err(ParamMismatch::N { expected: expected.n, actual: actual.n });
+    }
+    if actual.moduli != expected.moduli {
+        return Err(ParamMismatch::Moduli { expected: expected.moduli.clone(), actual: actual.moduli.clone() });
+    }
+    let fri = [
+        ("log_blowup", expected.log_blowup, actual.log_blowup),
+        ("num_queries", expected.num_queries, actual.num_queries),
+        ("proof_of_work_bits", expected.proof_of_work_bits, actual.proof_of_work_bits),
+    ];
+    match fri.into_iter().find(|&(_, expected, actual)| expected != actual) {
+        Some((name, expected, actual)) => Err(ParamMismatch::Fri { name, expected, actual }),
+        None => Ok(()),
+    }
+}
+
+// Encode a proof with bincode, preceded by the header of its parameters
+// bincode encodes a struct as the tuple of its fields, so the borrowed pair decodes as a ParamsProof
+pub fn serialize_proof_with_params(proof: &ZkProof, params: &ProofParams) -> Result<Vec<u8>, ProofIoError> {
+    bincode::serialize(&(params, proof)).map_err(ProofIoError::Encoding)
+}
+
+// Decode a proof and its header encoded by serialize_proof_with_params()
+pub fn deserialize_proof_with_params(bytes: &[u8]) -> Result<ParamsProof, ProofIoError> {
+    bincode::deserialize(bytes).map_err(ProofIoError::Encoding)
+}
+
+// Portable JSON form of a polynomial: {"coeffs": [...], "modulus": q}
+#[derive(Serialize, Deserialize)]
+struct PolynomialJson {
+    coeffs: Vec<u32>,
+    modulus: u32,
+}
+
+// Read a polynomial and its modulus from a JSON file, checking every coefficient is in [0, modulus)
+pub fn load_polynomial_json(path: impl AsRef<Path>) -> Result<(Vec<u32>, u32), ProofIoError> {
+    let json = fs::read_to_string(path).map_err(ProofIoError::File)?;
+    let PolynomialJson { coeffs, modulus } = serde_json::from_str(&json).map_err(ProofIoError::Json)?;
+    check_poly(&coeffs, coeffs.len(), modulus).map_err(ProofIoError::InvalidPolynomial)?;
+    Ok((coeffs, modulus))
+}
+
+// Write a polynomial and its modulus in the format read by load_polynomial_json()
+pub fn save_polynomial_json(coeffs: &[u32], modulus: u32, path: impl AsRef<Path>) -> Result<(), ProofIoError> {
+    let json = serde_json::to_string(&PolynomialJson { coeffs: coeffs.to_vec(), modulus }).map_err(ProofIoError::Json)?;
+    fs::write(path, json).map_err(ProofIoError::File)
+}
+
+// Write a proof as JSON, for tooling that cannot read the bincode encoding of serialize_proof()
+pub fn save_proof_json(proof: &ZkProof, path: impl AsRef<Path>) -> Result<(), ProofIoError> {
+    let json = serde_json::to_string(proof).map_err(ProofIoError::Json)?;
+    fs::write(path, json).map_err(ProofIoError::File)
+}
+
+// Read a proof written by save_proof_json()
+pub fn load_proof_json(path: impl AsRef<Path>) -> Result<ZkProof, ProofIoError> {
+    let json = fs::read_to_string(path).map_err(ProofIoError::File)?;
+    serde_json::from_str(&json).map_err(ProofIoError::Json)
+}
+
+/*
+Profiling spans, rendered as a timing tree by the ForestLayer that ZkConfigBuilder installs with the tracing feature
+Note:
+- prove_air*() run p3_uni_stark::prove() in a "prove" span, verify_air*() and verify_many() run verify() in a "verify" span,
+and the trace generators of the elementwise gadgets, PolyMulAir, NegacyclicMulAir and the NTTs run in a "trace_generation" span.
+- The trace and quotient commitments and the FRI opening all happen inside prove(), which emits its own nested spans
+for them, so they show up under "prove" rather than as spans of this crate.
+- With RUST_LOG=info (the default filter), every span is printed with its duration once it closes.
+*/
+
+// Prove `air` over `trace`, creating the challenger from zk_config.byte_hash
+pub fn prove_air<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>) -> ZkProof {
+    prove_air_with_public_values(zk_config, air, trace, &[])
+}
+
+// Verify a proof produced by prove_air() against `air`, with a fresh challenger in the same initial state
+pub fn verify_air<A: ZkAir>(zk_config: &ZkConfig, air: &A, proof: &ZkProof) -> Result<(), VerificationError> {
+    verify_air_with_public_values(zk_config, air, proof, &[])
+}
+
+/*
+Observe the public values into the challenger before anything is sampled from it.
+The values are prefixed with their number, so that a list and the same list with values appended
+never give the same transcript, e.g. when the public outputs of several polynomials are concatenated.
+An empty list observes nothing, so prove_air() keeps the transcript of a plain prove() with no public values.
+prove_air_with_public_values() and verify_air_with_public_values() both call it on a fresh challenger,
+so a verifier observing other values than the prover samples other challenges and rejects the proof.
+*/
+pub(crate) fn observe_public_values(challenger: &mut Challenger, public_values: &[Val]) {
+    if public_values.is_empty() {
+        return;
+    }
+    challenger.observe(Val::from_canonical_usize(public_values.len()));
+    challenger.observe_slice(public_values);
+}
+
+// Prove `air` over `trace` with `public_values`, e.g. PolynomialOpAir::public_outputs(), bound to the transcript
+pub fn prove_air_with_public_values<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>, public_values: &[Val]) -> ZkProof {
+    let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+    observe_public_values(&mut challenger, public_values);
+    info_span!("prove").in_scope(|| prove(&zk_config.config, air, &mut challenger, trace, &public_values.to_vec()))
+}
+
+// Verify a proof produced by prove_air_with_public_values() against the same `public_values`
+pub fn verify_air_with_public_values<A: ZkAir>(zk_config: &ZkConfig, air: &A, proof: &ZkProof, public_values: &[Val]) -> Result<(), VerificationError> {
+    let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+    observe_public_values(&mut challenger, public_values);
+    info_span!("verify").in_scope(|| verify(&zk_config.config, air, &mut challenger, proof, &public_values.to_vec()))
+        .map_err(VerificationError::from)
+}
+
+// verify_air_with_public_values() of a proof of a PolynomialOpAir against its expected output coefficients `out`,
+// which must have one value per output coefficient. eval_outputs() binds nothing for an empty `out` and makes any other
+// wrong length unsatisfiable without indexing it, so the length is checked first: an empty `out` must not verify unbound,
+// and a wrong one is reported as a PublicValueMismatch instead of a failed constraint
+pub fn verify_air_outputs<A: ZkAir + PolynomialOpAir>(zk_config: &ZkConfig, air: &A, proof: &ZkProof, out: &[Val]) -> Result<(), VerificationError> {
+    let expected = air.num_outputs();
+    if out.len() != expected {
+        return Err(VerificationError::PublicValueMismatch { expected, actual: out.len() });
+    }
+    verify_air_with_public_values(zk_config, air, proof, out)
+}
+
+// prove_air() with the challenger transcript starting from `seed`, e.g. a protocol or session identifier,
+// so the proof is bound to it and only verifies through verify_air_seeded() with the same seed
+pub fn prove_air_seeded<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>, seed: &[u8]) -> ZkProof {
+    let mut challenger = Challenger::from_hasher(seed.to_vec(), zk_config.byte_hash);
+    info_span!("prove").in_scope(|| prove(&zk_config.config, air, &mut challenger, trace, &vec![]))
+}
+
+// Verify a proof produced by prove_air_seeded() under the same `seed`
+pub fn verify_air_seeded<A: ZkAir>(zk_config: &ZkConfig, air: &A, proof: &ZkProof, seed: &[u8]) -> Result<(), VerificationError> {
+    let mut challenger = Challenger::from_hasher(seed.to_vec(), zk_config.byte_hash);
+    info_span!("verify").in_scope(|| verify(&zk_config.config, air, &mut challenger, proof, &vec![])).map_err(VerificationError::from)
+}
+
+// Domain separator of the proof at `index` of a bundle of `count` proofs, so each proof is bound to its position
+// and to the size of the bundle, and cannot be replayed standalone, reordered or moved into another bundle
+pub fn bundle_seed(index: usize, count: usize) -> Vec<u8> {
+    let mut seed = b"vfhe/bundle".to_vec();
+    seed.extend_from_slice(&(index as u64).to_le_bytes());
+    seed.extend_from_slice(&(count as u64).to_le_bytes());
+    seed
+}
+
+// Prove the proof at `index` of a bundle of `count` proofs, to be checked together by verify_many()
+pub fn prove_in_bundle<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>, index: usize, count: usize) -> ZkProof {
+    prove_air_seeded(zk_config, air, trace, &bundle_seed(index, count))
+}
+
+// Sized view of a &dyn VerifierAir, since p3_uni_stark::verify takes the AIR by a sized type parameter
+struct DynAir<'b>(&'b dyn VerifierAir);
+
+impl BaseAir<Val> for DynAir<'_> {
+    fn width(&self) -> usize {
+        BaseAir::<Val>::width(self.0)
+    }
+}
+
+impl Air<SymbolicAirBuilder<Val>> for DynAir<'_> {
+    fn eval(&self, builder: &mut SymbolicAirBuilder<Val>) {
+        Air::<SymbolicAirBuilder<Val>>::eval(self.0, builder)
+    }
+}
+
+impl<'a> Air<VerifierConstraintFolder<'a, MyConfig>> for DynAir<'_> {
+    fn eval(&self, builder: &mut VerifierConstraintFolder<'a, MyConfig>) {
+        Air::<VerifierConstraintFolder<'a, MyConfig>>::eval(self.0, builder)
+    }
+}
+
+// Verify a bundle of proofs produced by prove_in_bundle(), the i-th against the i-th AIR, in order
+// Note: this is sequential verification, each proof with a fresh challenger under its bundle_seed(),
+// so the bundle is as large as its proofs together, but it is accepted or rejected as one artifact
+pub fn verify_many(zk_config: &ZkConfig, bundle: &[(&dyn VerifierAir, &ZkProof)]) -> Result<(), ProofIoError> {
+    for (index, &(air, proof)) in bundle.iter().enumerate() {
+        let mut challenger = Challenger::from_hasher(bundle_seed(index, bundle.len()), zk_config.byte_hash);
+        info_span!("verify", index).in_scope(|| verify(&zk_config.config, &DynAir(air), &mut challenger, proof, &vec![]))
+            .map_err(|error| ProofIoError::Bundle { index, error: error.into() })?;
+    }
+    Ok(())
+}
+
+// Size and proving time of a proof, for comparing FRI parameters such as num_queries and log_blowup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProveStats {
+    // Length of the bincode encoding of the proof, as returned by serialize_proof()
+    pub proof_bytes: usize,
+    // Wall time spent in p3_uni_stark::prove, in milliseconds
+    pub prove_ms: u128,
+}
+
+// prove_air() that also reports the size and proving time of the proof
+// Note: std::time::Instant is not available on wasm32-unknown-unknown, so this is for native benchmarks only
+pub fn prove_air_with_stats<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>) -> Result<(ZkProof, ProveStats), ProofIoError> {
+    let start = Instant::now();
+    let proof = prove_air(zk_config, air, trace);
+    let prove_ms = start.elapsed().as_millis();
+    let proof_bytes = serialize_proof(&proof)?.len();
+    Ok((proof, ProveStats { proof_bytes, prove_ms }))
+}
+
+// Prove `air` over `trace` with a fresh challenger and return the encoded proof
+pub fn prove_to_bytes<A: ZkAir>(zk_config: &ZkConfig, air: &A, trace: RowMajorMatrix<Val>) -> Result<Vec<u8>, ProofIoError> {
+    serialize_proof(&prove_air(zk_config, air, trace))
+}
+
+// Decode a proof produced by prove_to_bytes() and verify it against `air` with a fresh challenger
+pub fn verify_from_bytes<A: ZkAir>(zk_config: &ZkConfig, air: &A, bytes: &[u8]) -> Result<(), ProofIoError> {
+    let proof = deserialize_proof(bytes)?;
+    verify_air(zk_config, air, &proof).map_err(ProofIoError::Verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{thread_rng, Rng};
+    use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+    use crate::gadgets::mul::{MulShape, PolyMulAir, generate_polymul_trace};
+    use crate::gadgets::config::{initialize_config, verifier_config, Challenge, ZkConfigBuilder, DEFAULT_NUM_QUERIES};
+    use crate::gadgets::negacyclic::{NegacyclicMulAir, generate_negacyclic_mul_trace};
+    use crate::params::{N, P1, P2};
+
+    #[test]
+    fn test_proof_round_trip() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let random_poly2: Vec<u32> = (0..n).map(|_| {
+            rng.gen_range(0..P1)
+        }).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+        let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+        let proof = prove(&zk_config.config, &air, &mut challenger, trace, &vec![]);
+
+        // serialize, then drop the original so only the bytes remain
+        let bytes = serialize_proof(&proof)?;
+        drop(proof);
+        assert!(!bytes.is_empty());
+
+        let proof = deserialize_proof(&bytes)?;
+        let mut challenger = Challenger::from_hasher(vec![], zk_config.byte_hash);
+        verify(&zk_config.config, &air, &mut challenger, &proof, &vec![]).map_err(|e| ProofIoError::Verification(e.into()))?;
+
+        // the byte-level helpers give the same result
+        let trace = generate_polyadd_trace::<Val>(air.a.clone(), air.b.clone(), P1, n).unwrap();
+        let bytes = prove_to_bytes(&zk_config, &air, trace)?;
+        verify_from_bytes(&zk_config, &air, &bytes)
+    }
+
+    // Names of the spans created while `f` runs, in order, i.e. the spans the ForestLayer prints with RUST_LOG=info
+    #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
+    fn span_names(f: impl FnOnce()) -> Vec<String> {
+        use std::sync::{Arc, Mutex};
+        use tracing::span::{Attributes, Id};
+        use tracing::Subscriber;
+        use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+        use tracing_subscriber::Registry;
+
+        struct SpanNames(Arc<Mutex<Vec<String>>>);
+        impl<S: Subscriber> Layer<S> for SpanNames {
+            fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+                self.0.lock().unwrap().push(attrs.metadata().name().to_string());
+            }
+        }
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(Registry::default().with(SpanNames(names.clone())), f);
+        let names = names.lock().unwrap().clone();
+        names
+    }
+
+    #[test]
+    #[cfg(all(feature = "tracing", not(target_arch = "wasm32")))]
+    fn test_profiling_spans() {
+
+        let zk_config = initialize_config();
+
+        // trace generation, then proving, then verifying
+        let n = 16;
+        let a: Vec<u32> = (0..n as u32).collect();
+        let names = span_names(|| {
+            let air = PolyAddAir { a:a.clone(), b:a.clone(), modulus:P1, n };
+            let proof = prove_air(&zk_config, &air, generate_polyadd_trace::<Val>(a.clone(), a.clone(), P1, n).unwrap());
+            verify_air(&zk_config, &air, &proof).expect("verification failed");
+        });
+        let position = |name: &str| names.iter().position(|span| span == name).unwrap_or_else(|| panic!("no {} span in {:?}", name, names));
+        assert!(position("trace_generation") < position("prove"));
+        assert!(position("prove") < position("verify"));
+    }
+
+    #[test]
+    fn test_check_proof_params() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        // a proof of an addition of 16 coefficients mod P1
+        let n = 16;
+        let mut rng = thread_rng();
+        let a: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let air = PolyAddAir { a:a.clone(), b:a.clone(), modulus:P1, n };
+        let proof = prove_air(&zk_config, &air, generate_polyadd_trace::<Val>(a.clone(), a, P1, n).unwrap());
+        let params = ProofParams::new(&FheParams::new(n, vec![P1]).unwrap(), &zk_config);
+
+        let bytes = serialize_proof_with_params(&proof, &params)?;
+        let proof = deserialize_proof_with_params(&bytes)?;
+        assert_eq!(proof.params, params);
+        check_proof_params(&proof, &params).unwrap();
+        verify_air(&zk_config, &air, &proof.proof).map_err(ProofIoError::Verification)?;
+
+        // a verifier expecting the default N, other moduli or other FRI parameters rejects the header
+        let default_n = ProofParams::new(&FheParams::new(N, vec![P1]).unwrap(), &zk_config);
+        assert_eq!(check_proof_params(&proof, &default_n), Err(ParamMismatch::N { expected: N, actual: n }));
+        let moduli = ProofParams::new(&FheParams::new(n, vec![P1, P2]).unwrap(), &zk_config);
+        assert!(matches!(check_proof_params(&proof, &moduli), Err(ParamMismatch::Moduli { .. })));
+        let queries = ProofParams::new(&FheParams::new(n, vec![P1]).unwrap(), &ZkConfigBuilder::new().num_queries(50).build());
+        assert_eq!(
+            check_proof_params(&proof, &queries),
+            Err(ParamMismatch::Fri { name: "num_queries", expected: 50, actual: DEFAULT_NUM_QUERIES })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_verify_air() {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+        let proof = prove_air(&zk_config, &air, trace);
+        verify_air(&zk_config, &air, &proof).expect("verification failed");
+
+        // the wrappers create their own challengers, so the same proof verifies again
+        verify_air(&zk_config, &air, &proof).expect("second verification failed");
+    }
+
+    #[test]
+    fn test_public_values_observed() {
+
+        let zk_config = initialize_config();
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        // NegacyclicMulAir has no constraint on public values, so only the transcript binds them to the proof
+        let air = NegacyclicMulAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_negacyclic_mul_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        let public_values: Vec<Val> = random_poly1.iter().map(|&c| Val::from_canonical_u32(c)).collect();
+        let proof = prove_air_with_public_values(&zk_config, &air, trace, &public_values);
+        verify_air_with_public_values(&zk_config, &air, &proof, &public_values).expect("verification failed");
+
+        // changing, dropping or appending a public value without proving again changes the challenges
+        let mut changed = public_values.clone();
+        changed[n/2] += Val::one();
+        assert!(verify_air_with_public_values(&zk_config, &air, &proof, &changed).is_err());
+        assert!(verify_air_with_public_values(&zk_config, &air, &proof, &public_values[..n-1]).is_err());
+        assert!(verify_air_with_public_values(&zk_config, &air, &proof, &[public_values.clone(), vec![Val::zero()]].concat()).is_err());
+        assert!(verify_air(&zk_config, &air, &proof).is_err());
+
+        // the public outputs of PolyAddAir are bound both by the transcript and by its output constraints
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+        let public_values = air.public_outputs(&trace);
+        let proof = prove_air_with_public_values(&zk_config, &air, trace, &public_values);
+        verify_air_with_public_values(&zk_config, &air, &proof, &public_values).expect("verification failed");
+
+        let mut changed = public_values;
+        changed[0] += Val::one();
+        assert!(verify_air_with_public_values(&zk_config, &air, &proof, &changed).is_err());
+    }
+
+    #[test]
+    fn test_verify_air_with_verifier_config() -> Result<(), ProofIoError> {
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+        let bytes = prove_to_bytes(&initialize_config(), &air, trace)?;
+
+        // the verifier only sees the bytes, and builds its own config without the prover-side setup
+        let verifier = verifier_config();
+        let proof = deserialize_proof(&bytes)?;
+        verify_air(&verifier, &air, &proof).map_err(ProofIoError::Verification)?;
+
+        // it must use the FRI parameters the proof was generated with: the proof shape no longer matches,
+        // which the FRI verifier may report as an error or as a panic
+        let other = ZkConfigBuilder::new().num_queries(DEFAULT_NUM_QUERIES / 2).build_verifier();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| verify_air(&other, &air, &proof).is_ok()));
+        assert!(!matches!(result, Ok(true)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_verify_air_seeded() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+        let (seed1, seed2): (&[u8], &[u8]) = (b"vfhe/session-1", b"vfhe/session-2");
+
+        let proof1 = prove_air_seeded(&zk_config, &air, trace.clone(), seed1);
+        let proof2 = prove_air_seeded(&zk_config, &air, trace, seed2);
+        assert_ne!(serialize_proof(&proof1)?, serialize_proof(&proof2)?);
+
+        // each proof only verifies under its own seed, and not from the empty transcript of verify_air()
+        verify_air_seeded(&zk_config, &air, &proof1, seed1).map_err(ProofIoError::Verification)?;
+        verify_air_seeded(&zk_config, &air, &proof2, seed2).map_err(ProofIoError::Verification)?;
+        assert!(verify_air_seeded(&zk_config, &air, &proof1, seed2).is_err());
+        assert!(verify_air_seeded(&zk_config, &air, &proof2, seed1).is_err());
+        assert!(verify_air(&zk_config, &air, &proof1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prove_air_with_stats() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+        let (proof, stats) = prove_air_with_stats(&zk_config, &air, trace)?;
+        assert!(stats.proof_bytes > 0);
+        assert_eq!(stats.proof_bytes, serialize_proof(&proof)?.len());
+        verify_air(&zk_config, &air, &proof).map_err(ProofIoError::Verification)
+    }
+
+    #[test]
+    fn test_json_round_trip() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+        let dir = std::env::temp_dir().join(format!("vfhe-json-{}", std::process::id()));
+        fs::create_dir_all(&dir).map_err(ProofIoError::File)?;
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        save_polynomial_json(&random_poly1, P1, dir.join("a.json"))?;
+        save_polynomial_json(&random_poly2, P1, dir.join("b.json"))?;
+
+        // load the inputs back and prove their sum
+        let (a, modulus) = load_polynomial_json(dir.join("a.json"))?;
+        let (b, _) = load_polynomial_json(dir.join("b.json"))?;
+        assert_eq!((a.clone(), modulus), (random_poly1, P1));
+
+        let air = PolyAddAir { a:a.clone(), b:b.clone(), modulus, n };
+        let trace = generate_polyadd_trace::<Val>(a, b, modulus, n).unwrap();
+        let proof = prove_air(&zk_config, &air, trace);
+
+        save_proof_json(&proof, dir.join("proof.json"))?;
+        drop(proof);
+        let proof = load_proof_json(dir.join("proof.json"))?;
+        verify_air(&zk_config, &air, &proof).map_err(ProofIoError::Verification)?;
+
+        // a coefficient outside of the stated modulus is rejected on load
+        fs::write(dir.join("bad.json"), format!("{{\"coeffs\": [0, {}], \"modulus\": {}}}", P1, P1)).map_err(ProofIoError::File)?;
+        assert!(matches!(
+            load_polynomial_json(dir.join("bad.json")),
+            Err(ProofIoError::InvalidPolynomial(GadgetError::CoefficientOutOfRange { index: 1, .. }))
+        ));
+
+        fs::remove_dir_all(&dir).map_err(ProofIoError::File)
+    }
+
+    #[test]
+    fn test_verify_many() -> Result<(), ProofIoError> {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let add_air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let add_trace = generate_polyadd_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        let mul_air = PolyMulAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n, shape: MulShape::Row };
+        let mul_trace = generate_polymul_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+
+        let add_proof = prove_in_bundle(&zk_config, &add_air, add_trace, 0, 2);
+        let mul_proof = prove_in_bundle(&zk_config, &mul_air, mul_trace, 1, 2);
+        verify_many(&zk_config, &[(&add_air, &add_proof), (&mul_air, &mul_proof)])?;
+
+        // each proof is bound to its position and to the size of the bundle
+        assert!(matches!(
+            verify_many(&zk_config, &[(&mul_air, &mul_proof), (&add_air, &add_proof)]),
+            Err(ProofIoError::Bundle { index: 0, .. })
+        ));
+        assert!(matches!(
+            verify_many(&zk_config, &[(&add_air, &add_proof)]),
+            Err(ProofIoError::Bundle { index: 0, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_many_corrupt_proof() {
+
+        let zk_config = initialize_config();
+
+        let n = 16;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let other_poly: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let add_air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let add_trace = generate_polyadd_trace::<Val>(random_poly1.clone(), random_poly2.clone(), P1, n).unwrap();
+        let add_proof = prove_in_bundle(&zk_config, &add_air, add_trace, 0, 2);
+
+        // the mul proof is of a different product than the one its AIR claims
+        let mul_air = PolyMulAir { a:random_poly1.clone(), b:random_poly2, modulus:P1, n, shape: MulShape::Row };
+        let other_air = PolyMulAir { a:random_poly1.clone(), b:other_poly.clone(), modulus:P1, n, shape: MulShape::Row };
+        let other_trace = generate_polymul_trace::<Val>(random_poly1, other_poly, P1, n).unwrap();
+        let corrupt_proof = prove_in_bundle(&zk_config, &other_air, other_trace, 1, 2);
+
+        // the valid add proof does not make up for the corrupt one
+        assert!(matches!(
+            verify_many(&zk_config, &[(&add_air, &add_proof), (&mul_air, &corrupt_proof)]),
+            Err(ProofIoError::Bundle { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_verification_error_variants() {
+
+        let zk_config = initialize_config();
+
+        let n = 8;
+        let mut rng = thread_rng();
+        let random_poly1: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+        let random_poly2: Vec<u32> = (0..n).map(|_| rng.gen_range(0..P1)).collect();
+
+        let air = PolyAddAir { a:random_poly1.clone(), b:random_poly2.clone(), modulus:P1, n };
+        let trace = generate_polyadd_trace::<Val>(random_poly1, random_poly2, P1, n).unwrap();
+        let public_values = air.public_outputs(&trace);
+        let proof = prove_air_with_public_values(&zk_config, &air, trace, &public_values);
+        verify_air_outputs(&zk_config, &air, &proof, &public_values).expect("verification failed");
+        let bytes = serialize_proof(&proof).unwrap();
+
+        // a corrupted opened value no longer matches the committed trace, so its opening proof is rejected
+        let mut corrupted = deserialize_proof(&bytes).unwrap();
+        corrupted.opened_values.trace_local[0] += Challenge::one();
+        assert!(matches!(
+            verify_air_with_public_values(&zk_config, &air, &corrupted, &public_values),
+            Err(VerificationError::FriFailed { .. })
+        ));
+
+        // a missing opened value does not fit the width of the AIR
+        let mut truncated = deserialize_proof(&bytes).unwrap();
+        truncated.opened_values.trace_local.pop();
+        assert_eq!(verify_air_with_public_values(&zk_config, &air, &truncated, &public_values), Err(VerificationError::InvalidProofShape));
+
+        // an expected output with a missing coefficient is rejected before the proof is checked
+        assert_eq!(
+            verify_air_outputs(&zk_config, &air, &proof, &public_values[..n-1]),
+            Err(VerificationError::PublicValueMismatch { expected: n, actual: n-1 })
+        );
+    }
+}
@@ -1,2 +1,5 @@
 pub mod gadgets;
-pub mod params;
\ No newline at end of file
+pub mod io;
+pub mod params;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -1,3 +1,6 @@
+use std::fmt;
+use crate::gadgets::utils::mod_exp;
+
 // N: number of ciphertext polynomial coefficients/terms
 pub const N: usize = 3500;
 
@@ -6,6 +9,155 @@ pub const P1: u32 = 1085276161; // 31-bits, generator: 11
 pub const P2: u32 = 1092616193; // 31-bits, generator: 3
 pub const P3: u32 = 1095761921; // 31-bits, generator: 3
 
+// primitive roots (generators of the multiplicative group) of P1, P2, P3
+pub const G1: u32 = 11;
+pub const G2: u32 = 3;
+pub const G3: u32 = 3;
+
 // P: ciphertext modulus in the original ring
 // 1299343865123888653488095233: 91-bits
 pub const P: u128 = P1 as u128 * P2 as u128 * P3 as u128;
+
+// Reasons the RNS parameters above cannot support a negacyclic NTT of a given size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamError {
+    // The modulus of an RNS channel is not prime
+    NotPrime { modulus: u32 },
+    // The documented generator of a channel does not generate the whole multiplicative group
+    NotPrimitiveRoot { modulus: u32, generator: u32 },
+    // 2N does not divide p - 1, so there is no primitive 2N-th root of unity modulo p
+    UnsupportedRingDegree { n: usize, modulus: u32 },
+    // A parameter set with N = 0 coefficients or no modulus
+    EmptyParams,
+    // A modulus below 2, or of more than 31 bits, which the gadgets cannot reduce in the native field
+    InvalidModulus { modulus: u32 },
+    // A channel index is not in [0, channels)
+    ChannelOutOfRange { channel: usize, channels: usize },
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamError::NotPrime { modulus } => {
+                write!(f, "modulus {} is not prime", modulus)
+            }
+            ParamError::NotPrimitiveRoot { modulus, generator } => {
+                write!(f, "{} is not a primitive root modulo {}", generator, modulus)
+            }
+            ParamError::UnsupportedRingDegree { n, modulus } => {
+                write!(f, "2 * {} does not divide {} - 1, so there is no negacyclic NTT of size {}", n, modulus, n)
+            }
+            ParamError::EmptyParams => {
+                write!(f, "a parameter set needs at least 1 coefficient and 1 modulus")
+            }
+            ParamError::InvalidModulus { modulus } => {
+                write!(f, "modulus {} is not in [2, 2^31)", modulus)
+            }
+            ParamError::ChannelOutOfRange { channel, channels } => {
+                write!(f, "channel {} is not in [0, {})", channel, channels)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+// Public parameters of the proving context: the number of coefficients N and the modulus of every RNS channel,
+// so several parameter sets can be used in one process instead of the N and P1, P2, P3 constants above
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FheParams {
+    pub n: usize,
+    pub moduli: Vec<u32>,
+}
+
+impl Default for FheParams {
+    // N coefficients over the 3 RNS channels P1, P2, P3
+    fn default() -> Self {
+        Self { n: N, moduli: vec![P1, P2, P3] }
+    }
+}
+
+impl FheParams {
+    // Check that there is at least 1 coefficient and 1 modulus, and every modulus fits in 31 bits
+    pub fn new(n: usize, moduli: Vec<u32>) -> Result<Self, ParamError> {
+        if n == 0 || moduli.is_empty() {
+            return Err(ParamError::EmptyParams);
+        }
+        if let Some(&modulus) = moduli.iter().find(|&&m| m < 2 || m >> 31 != 0) {
+            return Err(ParamError::InvalidModulus { modulus });
+        }
+        Ok(Self { n, moduli })
+    }
+
+    // Modulus of the RNS channel `channel`
+    pub fn modulus(&self, channel: usize) -> Result<u32, ParamError> {
+        self.moduli.get(channel).copied().ok_or(ParamError::ChannelOutOfRange { channel, channels: self.moduli.len() })
+    }
+}
+
+// Distinct prime factors of m, by trial division (m < 2^32, so at most 2^16 divisions)
+fn prime_factors(mut m: u64) -> Vec<u64> {
+    let mut factors = Vec::new();
+    let mut d = 2;
+    while d * d <= m {
+        if m % d == 0 {
+            factors.push(d);
+            while m % d == 0 {
+                m /= d;
+            }
+        }
+        d += 1;
+    }
+    if m > 1 {
+        factors.push(m);
+    }
+    factors
+}
+
+// Check that `modulus` is prime, `generator` is a primitive root modulo it, and 2n | modulus - 1
+// Note: g is a primitive root iff g^((p-1)/q) != 1 for every prime factor q of p - 1
+pub fn validate_rns_channel(modulus: u32, generator: u32, n: usize) -> Result<(), ParamError> {
+    let p = modulus as u64;
+    if p < 2 || prime_factors(p) != [p] {
+        return Err(ParamError::NotPrime { modulus });
+    }
+    let primitive = generator as u64 % p != 0
+        && prime_factors(p - 1).iter().all(|&q| mod_exp(generator as u64, (p - 1) / q, p) != 1);
+    if !primitive {
+        return Err(ParamError::NotPrimitiveRoot { modulus, generator });
+    }
+    if n == 0 || (p - 1) % (2 * n as u64) != 0 {
+        return Err(ParamError::UnsupportedRingDegree { n, modulus });
+    }
+    Ok(())
+}
+
+// Check every RNS channel (P1, G1), (P2, G2), (P3, G3) with validate_rns_channel()
+// Note: p - 1 = 2^20 * 3^2 * 5 * 23, 2^21 * 521 and 2^20 * 5 * 11 * 19, so every power of two n <= 2^19 passes,
+// but N = 3500 itself does not: 2N = 2^3 * 5^3 * 7 divides none of them, and the NTT gadgets pad to a power of two instead
+pub fn validate_rns_params(n: usize) -> Result<(), ParamError> {
+    for (modulus, generator) in [(P1, G1), (P2, G2), (P3, G3)] {
+        validate_rns_channel(modulus, generator, n)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_rns_params() {
+        // the negacyclic NTT of N coefficients runs on the next power of two
+        let n = N.next_power_of_two();
+        assert_eq!(validate_rns_params(n), Ok(()));
+        assert_eq!(validate_rns_params(1 << 19), Ok(()));
+        assert_eq!(validate_rns_params(1 << 20), Err(ParamError::UnsupportedRingDegree { n: 1 << 20, modulus: P1 }));
+        assert_eq!(validate_rns_params(N), Err(ParamError::UnsupportedRingDegree { n: N, modulus: P1 }));
+
+        // 2, ..., 10 are not primitive roots of P1, and P1 + 2 is not prime
+        assert_eq!(validate_rns_channel(P1, 2, n), Err(ParamError::NotPrimitiveRoot { modulus: P1, generator: 2 }));
+        assert_eq!(validate_rns_channel(P1, 10, n), Err(ParamError::NotPrimitiveRoot { modulus: P1, generator: 10 }));
+        assert_eq!(validate_rns_channel(P1 + 2, G1, n), Err(ParamError::NotPrime { modulus: P1 + 2 }));
+    }
+}
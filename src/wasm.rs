@@ -0,0 +1,24 @@
+use wasm_bindgen::prelude::*;
+use crate::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+use crate::gadgets::config::{initialize_config, Val};
+use crate::io::{prove_to_bytes, verify_from_bytes};
+
+// Prove a + b mod `modulus` and return the bincode-encoded proof
+// The number of coefficients is a.len(), so the same a and b must be passed to wasm_verify_polyadd()
+#[wasm_bindgen]
+pub fn wasm_prove_polyadd(a: Vec<u32>, b: Vec<u32>, modulus: u32) -> Result<Vec<u8>, JsError> {
+    let n = a.len();
+    let air = PolyAddAir::with_n(a, b, modulus, n)?;
+    let trace = generate_polyadd_trace::<Val>(air.a.clone(), air.b.clone(), modulus, n)?;
+    Ok(prove_to_bytes(&initialize_config(), &air, trace)?)
+}
+
+// Verify a proof produced by wasm_prove_polyadd() for the same inputs
+#[wasm_bindgen]
+pub fn wasm_verify_polyadd(proof_bytes: &[u8], a: Vec<u32>, b: Vec<u32>, modulus: u32) -> bool {
+    let n = a.len();
+    match PolyAddAir::with_n(a, b, modulus, n) {
+        Ok(air) => verify_from_bytes(&initialize_config(), &air, proof_bytes).is_ok(),
+        Err(_) => false,
+    }
+}
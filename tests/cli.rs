@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use verifiable_fhe_plonky3::params::P1;
+
+// Fresh directory under the system temp dir for one test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vfhe-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_json(path: &Path, coeffs: &[u32]) {
+    let coeffs: Vec<String> = coeffs.iter().map(|c| c.to_string()).collect();
+    fs::write(path, format!("[{}]", coeffs.join(", "))).unwrap();
+}
+
+fn vfhe(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_vfhe")).args(args).output().unwrap()
+}
+
+#[test]
+fn test_cli_prove_verify() {
+    let dir = temp_dir("cli");
+    let (a, b, proof) = (dir.join("a.json"), dir.join("b.json"), dir.join("proof.bin"));
+    let (out, wrong, other) = (dir.join("out.json"), dir.join("wrong.json"), dir.join("other.json"));
+    let path = |p: &PathBuf| p.to_str().unwrap().to_string();
+    let (a_arg, b_arg, proof_arg) = (path(&a), path(&b), path(&proof));
+    let (out_arg, wrong_arg, other_arg) = (path(&out), path(&wrong), path(&other));
+
+    write_json(&a, &[1, 2, 3, P1 - 1]);
+    write_json(&b, &[4, 5, 6, 2]);
+
+    // add: out = (5, 7, 9, 1)
+    let output = vfhe(&["prove", "--op", "add", "--input", &a_arg, &b_arg, "--out", &proof_arg]);
+    assert!(output.status.success(), "prove failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "[5,7,9,1]");
+
+    write_json(&out, &[5, 7, 9, 1]);
+    write_json(&wrong, &[5, 7, 9, 2]);
+    let output = vfhe(&["verify", "--op", "add", "--input", &a_arg, &b_arg, "--proof", &proof_arg, "--expected", &out_arg]);
+    assert!(output.status.success(), "verify failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = vfhe(&["verify", "--op", "add", "--input", &a_arg, &b_arg, "--proof", &proof_arg, "--expected", &wrong_arg]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // the verifier's inputs and modulus are the ones the proof is checked for: 0 + b = out does not verify,
+    // and neither does the same addition mod another modulus
+    write_json(&other, &[0, 0, 0, 0]);
+    let output = vfhe(&["verify", "--op", "add", "--input", &other_arg, &b_arg, "--proof", &proof_arg, "--expected", &out_arg]);
+    assert_eq!(output.status.code(), Some(1));
+    let output = vfhe(&["verify", "--op", "add", "--input", &a_arg, &b_arg, "--proof", &proof_arg, "--expected", &out_arg, "--modulus", "12289"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // the inputs are required
+    let output = vfhe(&["verify", "--op", "add", "--proof", &proof_arg, "--expected", &out_arg]);
+    assert_eq!(output.status.code(), Some(2));
+
+    // mul: (1 + 2X)(3 + X) = 3 + 7X + 2X^2
+    write_json(&a, &[1, 2]);
+    write_json(&b, &[3, 1]);
+    write_json(&out, &[3, 7, 2]);
+    let output = vfhe(&["prove", "--op", "mul", "--input", &a_arg, &b_arg, "--out", &proof_arg]);
+    assert!(output.status.success(), "prove failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = vfhe(&["verify", "--op", "mul", "--input", &a_arg, &b_arg, "--proof", &proof_arg, "--expected", &out_arg]);
+    assert!(output.status.success(), "verify failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    // a proof of mul is not a proof of add
+    let output = vfhe(&["verify", "--op", "add", "--input", &a_arg, &b_arg, "--proof", &proof_arg, "--expected", &out_arg]);
+    assert_eq!(output.status.code(), Some(2));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,23 @@
+// Proving and verifying without the tracing subscriber: cargo test --no-default-features --test no_tracing
+#![cfg(not(feature = "tracing"))]
+
+use p3_uni_stark::{prove, verify};
+use verifiable_fhe_plonky3::gadgets::add::{PolyAddAir, generate_polyadd_trace};
+use verifiable_fhe_plonky3::gadgets::config::{initialize_config, ZkConfig, Challenger, Val};
+use verifiable_fhe_plonky3::params::P1;
+
+#[test]
+fn test_poly_add_without_tracing() {
+    let ZkConfig { config, byte_hash } = initialize_config();
+
+    let a: Vec<u32> = vec![1, 2, 3, P1 - 1];
+    let b: Vec<u32> = vec![4, 5, 6, 2];
+    let air = PolyAddAir::with_n(a.clone(), b.clone(), P1, 4).unwrap();
+    let trace = generate_polyadd_trace::<Val>(a, b, P1, 4).unwrap();
+
+    let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+    let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::from_hasher(vec![], byte_hash);
+    verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+}
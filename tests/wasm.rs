@@ -0,0 +1,20 @@
+// Smoke test of the wasm-bindgen entry points: wasm-pack test --node -- --features wasm
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use wasm_bindgen_test::wasm_bindgen_test;
+use verifiable_fhe_plonky3::params::P1;
+use verifiable_fhe_plonky3::wasm::{wasm_prove_polyadd, wasm_verify_polyadd};
+
+#[wasm_bindgen_test]
+fn test_wasm_polyadd() {
+    let a: Vec<u32> = vec![1, 2, 3, P1 - 1];
+    let b: Vec<u32> = vec![4, 5, 6, 2];
+
+    let proof = wasm_prove_polyadd(a.clone(), b.clone(), P1).unwrap();
+    assert!(wasm_verify_polyadd(&proof, a.clone(), b.clone(), P1));
+
+    // the same proof does not verify for other inputs, nor for inputs of another length
+    assert!(!wasm_verify_polyadd(&proof, a.clone(), vec![4, 5, 6, 3], P1));
+    assert!(!wasm_verify_polyadd(&proof, a.clone(), vec![4, 5, 6], P1));
+    assert!(wasm_prove_polyadd(a, vec![4, 5, 6], P1).is_err());
+}